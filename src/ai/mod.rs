@@ -1,14 +1,139 @@
 #![allow(dead_code)]
 
+use bracket_geometry::prelude::Point;
+use serde::{Deserialize, Serialize};
+
 use crate::map::World;
 
+/// Wounded skirmishers break off once HP falls to this share of max.
+pub const FLEE_HP_RATIO: f32 = 0.3;
+/// How close the player must be before a monster bothers running away.
+pub const FLEE_RANGE: f32 = 6.0;
+
+/// What a monster knows when its behavior picks this turn's move.
 #[derive(Clone, Debug)]
 pub struct BehaviorContext {
+    /// Layer the monster is acting on.
     pub focus_world: World,
+    pub hp_ratio: f32,
+    /// Distance from the monster's nearest cell to the player.
+    pub player_distance: f32,
+    /// The player is in sight and within the monster's aggro radius.
+    pub sees_player: bool,
+    /// Where the player was last seen, while the trail is still warm.
+    pub trail: Option<Point>,
+}
+
+/// The move a behavior settles on; `WanderSystem` turns it into a step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decision {
+    /// Run for cover, or at least away from the player.
+    Flee,
+    /// Close in on the player.
+    Chase,
+    /// Head for a remembered tile.
+    Pursue(Point),
+    /// Amble about at the monster's wander chance.
+    Wander,
 }
 
-impl BehaviorContext {
-    pub const fn new(focus_world: World) -> Self {
-        Self { focus_world }
+pub trait Behavior {
+    fn decide(&self, ctx: &BehaviorContext) -> Decision;
+}
+
+/// The default: fights while healthy, flees when badly hurt, and follows
+/// a cold trail for a while once the player slips out of sight.
+pub struct Skirmisher;
+
+impl Behavior for Skirmisher {
+    fn decide(&self, ctx: &BehaviorContext) -> Decision {
+        if ctx.hp_ratio <= FLEE_HP_RATIO && ctx.player_distance < FLEE_RANGE {
+            Decision::Flee
+        } else if ctx.sees_player {
+            Decision::Chase
+        } else if let Some(spot) = ctx.trail {
+            Decision::Pursue(spot)
+        } else {
+            Decision::Wander
+        }
+    }
+}
+
+/// Never fights: flees whenever the player is seen or close, whatever
+/// its HP.
+pub struct Coward;
+
+impl Behavior for Coward {
+    fn decide(&self, ctx: &BehaviorContext) -> Decision {
+        if ctx.sees_player || ctx.player_distance < FLEE_RANGE {
+            Decision::Flee
+        } else {
+            Decision::Wander
+        }
+    }
+}
+
+/// Follows the player across the spectrum: every `cadence` turns, a
+/// monster on another layer of the player's floor that could see the
+/// player from where it stands phases onto the player's layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseShifter {
+    pub cadence: u32,
+}
+
+/// Which [`Behavior`] a monster runs; chosen per template and saved with
+/// the monster's brain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BehaviorKind {
+    #[default]
+    Skirmisher,
+    Coward,
+}
+
+impl BehaviorKind {
+    pub fn behavior(self) -> &'static dyn Behavior {
+        match self {
+            Self::Skirmisher => &Skirmisher,
+            Self::Coward => &Coward,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sighting(hp_ratio: f32, player_distance: f32) -> BehaviorContext {
+        BehaviorContext {
+            focus_world: World::Red,
+            hp_ratio,
+            player_distance,
+            sees_player: true,
+            trail: None,
+        }
+    }
+
+    #[test]
+    fn a_coward_flees_on_sight_whatever_its_hp() {
+        let coward = BehaviorKind::Coward.behavior();
+        let skirmisher = BehaviorKind::Skirmisher.behavior();
+        for hp_ratio in [1.0, 0.75, 0.5, FLEE_HP_RATIO, 0.05] {
+            for distance in [1.0, FLEE_RANGE - 1.0, FLEE_RANGE * 2.0] {
+                let ctx = sighting(hp_ratio, distance);
+                assert_eq!(coward.decide(&ctx), Decision::Flee, "{ctx:?}");
+            }
+        }
+        assert_eq!(skirmisher.decide(&sighting(1.0, 2.0)), Decision::Chase);
+
+        let unseen = BehaviorContext {
+            sees_player: false,
+            ..sighting(1.0, FLEE_RANGE - 1.0)
+        };
+        assert_eq!(coward.decide(&unseen), Decision::Flee, "close is enough");
+        let far = BehaviorContext {
+            sees_player: false,
+            ..sighting(1.0, FLEE_RANGE * 2.0)
+        };
+        assert_eq!(coward.decide(&far), Decision::Wander);
     }
 }
@@ -0,0 +1,209 @@
+use std::time::{Duration, Instant};
+
+use crate::map::{Dungeon, FloorId, SPECTRUM, World};
+
+/// Outcome of generating a batch of floors without a window.
+#[derive(Debug, Default)]
+pub struct MapgenReport {
+    pub floors: u32,
+    pub rooms: usize,
+    pub corridor_tiles: usize,
+    pub walkable_tiles: usize,
+    pub elapsed: Duration,
+    /// `(floor, unreachable tiles)` for every floor whose layers aren't
+    /// fully reachable from the spawn point.
+    pub disconnected: Vec<(u32, usize)>,
+}
+
+impl MapgenReport {
+    pub fn average(&self) -> Duration {
+        if self.floors == 0 {
+            Duration::ZERO
+        } else {
+            self.elapsed / self.floors
+        }
+    }
+
+    pub fn summary(&self) -> Vec<String> {
+        let floors = self.floors.max(1) as f32;
+        let mut lines = vec![
+            format!(
+                "[RR-BENCH] {} floors in {:.2?} ({:.2?} per floor)",
+                self.floors,
+                self.elapsed,
+                self.average()
+            ),
+            format!(
+                "[RR-BENCH] avg {:.1} rooms, {:.1} corridor tiles, {:.1} walkable tiles per floor",
+                self.rooms as f32 / floors,
+                self.corridor_tiles as f32 / floors,
+                self.walkable_tiles as f32 / floors
+            ),
+        ];
+        if self.disconnected.is_empty() {
+            lines.push("[RR-BENCH] every floor is fully connected".to_string());
+        } else {
+            for (floor, unreachable) in &self.disconnected {
+                lines.push(format!(
+                    "[RR-BENCH] floor {floor} is disconnected: {unreachable} tiles unreachable from spawn"
+                ));
+            }
+        }
+        lines
+    }
+}
+
+/// Generates `count` floors of a `width` by `height` dungeon seeded with
+/// `seed`, timing only the generation itself, then checks every world layer
+/// is reachable from spawn. Fails if the dungeon can't be built at that size.
+pub fn run_mapgen_bench(
+    count: u32,
+    seed: u64,
+    width: i32,
+    height: i32,
+) -> Result<MapgenReport, String> {
+    let mut report = MapgenReport::default();
+    if count == 0 {
+        return Ok(report);
+    }
+    let started = Instant::now();
+    let mut dungeon = Dungeon::new(width, height, seed)?;
+    dungeon.ensure_floor(FloorId(count - 1));
+    report.elapsed = started.elapsed();
+    report.floors = count;
+
+    for floor in dungeon.floors.iter().take(count as usize) {
+        let substrate = &floor.substrate;
+        report.rooms += substrate.rooms.len();
+        report.corridor_tiles += substrate.corridors.iter().map(Vec::len).sum::<usize>();
+        report.walkable_tiles += floor.layer(SPECTRUM[0]).walkable_points().len();
+        let unreachable = SPECTRUM
+            .iter()
+            .map(|&world| floor.layer(world).unreachable_from(floor.spawn_point()))
+            .max()
+            .unwrap_or(0);
+        if unreachable > 0 {
+            report.disconnected.push((floor.id.0, unreachable));
+        }
+    }
+    Ok(report)
+}
+
+/// Monsters seeded onto one floor/world layer.
+#[derive(Clone, Debug)]
+pub struct LayerSpawns {
+    pub floor: u32,
+    pub world: World,
+    pub walkable_tiles: usize,
+    pub monsters: usize,
+    /// Sum of max HP plus power over the layer's monsters.
+    pub threat: i32,
+}
+
+impl LayerSpawns {
+    /// Monsters per hundred walkable tiles.
+    pub fn density(&self) -> f32 {
+        self.monsters as f32 * 100.0 / self.walkable_tiles.max(1) as f32
+    }
+}
+
+/// Spawn tallies for a batch of headlessly seeded floors, one row per
+/// layer in floor then spectrum order.
+#[derive(Debug, Default)]
+pub struct SpawnReport {
+    pub layers: Vec<LayerSpawns>,
+}
+
+impl SpawnReport {
+    /// Empty rows for the first `floors` floors of `dungeon`, sized by each
+    /// layer's walkable area.
+    pub fn new(dungeon: &Dungeon, floors: u32) -> Self {
+        let layers = dungeon
+            .floors
+            .iter()
+            .take(floors as usize)
+            .flat_map(|floor| {
+                SPECTRUM.iter().map(|&world| LayerSpawns {
+                    floor: floor.id.0,
+                    world,
+                    walkable_tiles: floor.layer(world).walkable_points().len(),
+                    monsters: 0,
+                    threat: 0,
+                })
+            })
+            .collect();
+        Self { layers }
+    }
+
+    /// Counts one monster; monsters outside the reported floors are ignored.
+    pub fn record(&mut self, floor: FloorId, world: World, threat: i32) {
+        if let Some(row) = self
+            .layers
+            .iter_mut()
+            .find(|row| row.floor == floor.0 && row.world == world)
+        {
+            row.monsters += 1;
+            row.threat += threat;
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = "floor,world,walkable_tiles,monsters,density_per_100,threat\n".to_string();
+        for row in &self.layers {
+            csv.push_str(&format!(
+                "{},{},{},{},{:.2},{}\n",
+                row.floor,
+                row.world.as_str(),
+                row.walkable_tiles,
+                row.monsters,
+                row.density(),
+                row.threat
+            ));
+        }
+        csv
+    }
+
+    /// One line per floor, totalled across its worlds.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for chunk in self.layers.chunks(SPECTRUM.len()) {
+            let Some(first) = chunk.first() else {
+                continue;
+            };
+            let monsters: usize = chunk.iter().map(|row| row.monsters).sum();
+            let threat: i32 = chunk.iter().map(|row| row.threat).sum();
+            let densest = chunk
+                .iter()
+                .max_by(|a, b| a.density().total_cmp(&b.density()))
+                .unwrap_or(first);
+            lines.push(format!(
+                "[RR-BENCH] floor {}: {monsters} monsters, threat {threat}, densest {} ({:.2} per 100 tiles)",
+                first.floor,
+                densest.world.as_str(),
+                densest.density()
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{DEFAULT_MAP_HEIGHT, DEFAULT_MAP_WIDTH};
+
+    #[test]
+    fn a_bench_run_generates_only_connected_floors() {
+        for seed in [1, 7, 0x51eccafe] {
+            let report = run_mapgen_bench(6, seed, DEFAULT_MAP_WIDTH, DEFAULT_MAP_HEIGHT).unwrap();
+            assert_eq!(report.floors, 6);
+            assert!(report.rooms >= 6, "seed {seed} left a floor roomless");
+            assert!(
+                report.disconnected.is_empty(),
+                "seed {seed}: {:?}",
+                report.disconnected
+            );
+        }
+        assert!(run_mapgen_bench(1, 7, 0, DEFAULT_MAP_HEIGHT).is_err());
+    }
+}
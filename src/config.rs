@@ -0,0 +1,438 @@
+use std::{env, path::PathBuf};
+
+use crate::data::loadouts::{Loadout, builtin_loadouts, loadout_named};
+use crate::ecs::components::ACTION_COST;
+use crate::ecs::resources::FovAlgorithm;
+use crate::map::{DEFAULT_MAP_HEIGHT, DEFAULT_MAP_WIDTH, FloorId, World};
+use crate::scripted_input::{ScriptLimits, ScriptSpeed};
+use crate::seed;
+
+/// Narrowest console that still fits the HUD ring's seven world labels.
+pub const MIN_CONSOLE_WIDTH: i32 = 80;
+/// Shortest console that still fits the HUD, a sliver of map, and the log panel.
+pub const MIN_CONSOLE_HEIGHT: i32 = 24;
+
+/// Log entries kept when `--log-history` isn't given.
+pub const DEFAULT_LOG_HISTORY: usize = 8;
+/// Log rows shown when `--log-lines` isn't given.
+pub const DEFAULT_LOG_LINES: usize = 5;
+/// Cap on `--log-history`; the log is a scrollback, not an archive.
+const MAX_LOG_HISTORY: usize = 200;
+
+const DEFAULT_CONSOLE_DIMENSIONS: (i32, i32) = (132, 43);
+const SCRIPTED_CONSOLE_DIMENSIONS: (i32, i32) = (100, 36);
+
+/// Returns the value following `flag` on the command line, if any.
+pub fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|idx| args.get(idx + 1))
+        .map(String::as_str)
+}
+
+/// Looks up a CLI flag first, then falls back to the matching `RR_*` env var.
+pub fn flag_or_env(args: &[String], flag: &str, env_key: &str) -> Option<String> {
+    flag_value(args, flag)
+        .map(str::to_string)
+        .or_else(|| env::var(env_key).ok())
+}
+
+/// Picks the console size from `--width`/`--height` (or `RR_WIDTH`/`RR_HEIGHT`),
+/// defaulting to a smaller window for scripted runs.
+pub fn console_dimensions(args: &[String], is_scripted: bool) -> Result<(i32, i32), String> {
+    let (default_w, default_h) = if is_scripted {
+        SCRIPTED_CONSOLE_DIMENSIONS
+    } else {
+        DEFAULT_CONSOLE_DIMENSIONS
+    };
+    let width = match flag_or_env(args, "--width", "RR_WIDTH") {
+        Some(raw) => parse_dimension("width", &raw)?,
+        None => default_w,
+    };
+    let height = match flag_or_env(args, "--height", "RR_HEIGHT") {
+        Some(raw) => parse_dimension("height", &raw)?,
+        None => default_h,
+    };
+    validate_dimensions(width, height)
+}
+
+fn parse_dimension(label: &str, raw: &str) -> Result<i32, String> {
+    raw.trim()
+        .parse::<i32>()
+        .map_err(|_| format!("console {label} must be a whole number, got '{raw}'"))
+}
+
+pub fn validate_dimensions(width: i32, height: i32) -> Result<(i32, i32), String> {
+    if width < MIN_CONSOLE_WIDTH || height < MIN_CONSOLE_HEIGHT {
+        return Err(format!(
+            "console {width}x{height} is too small; need at least {MIN_CONSOLE_WIDTH}x{MIN_CONSOLE_HEIGHT} to fit the HUD, map, and log"
+        ));
+    }
+    Ok((width, height))
+}
+
+/// How many monsters each world layer of a fresh floor is seeded with:
+/// one per `tiles_per_monster` walkable tiles, clamped to `min..=max`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpawnDensity {
+    pub tiles_per_monster: usize,
+    pub min: usize,
+    pub max: usize,
+}
+
+impl Default for SpawnDensity {
+    fn default() -> Self {
+        Self {
+            tiles_per_monster: 90,
+            min: 2,
+            max: 6,
+        }
+    }
+}
+
+impl SpawnDensity {
+    /// Monsters to seed on a layer with `walkable` open tiles.
+    pub fn target(&self, walkable: usize) -> usize {
+        (walkable / self.tiles_per_monster.max(1)).clamp(self.min, self.max)
+    }
+}
+
+/// Gameplay tunables that stay fixed for a whole session (and survive `R` restarts).
+#[derive(Clone, Debug)]
+pub struct GameOptions {
+    /// Extra energy a world shift costs on top of the turn it takes, letting
+    /// monsters close in while you phase.
+    pub world_switch_cost: i32,
+    /// Percent of max HP restored the first time you reach a new deepest floor.
+    pub descent_heal_percent: i32,
+    /// Master seed from `--seed-code`; every run (including restarts) replays
+    /// it. `None` rolls a fresh seed per run.
+    pub seed: Option<u64>,
+    /// Floor the run begins on; only settable with `--debug`.
+    pub start_floor: FloorId,
+    /// World the run begins attuned to; only settable with `--debug`.
+    pub start_world: World,
+    /// `--debug` was passed, unlocking debug flags and keys.
+    pub debug: bool,
+    /// Player starts invulnerable (`--godmode`, debug only).
+    pub godmode: bool,
+    /// File every log line is appended to as it happens (`--log-file`).
+    pub log_file: Option<PathBuf>,
+    /// Parsing bounds for `--scripted-input` files.
+    pub script_limits: ScriptLimits,
+    /// How fast scripted runs feed keys (`--script-speed`).
+    pub script_speed: ScriptSpeed,
+    /// Turns an empty open doorway waits before swinging shut; 0 leaves
+    /// doors open until closed by hand.
+    pub door_close_turns: u32,
+    /// Log entries kept in memory (`--log-history`).
+    pub log_history: usize,
+    /// Log rows the panel shows at most (`--log-lines`); never more than
+    /// `log_history`.
+    pub log_lines: usize,
+    /// Floor 0 is a safe hub (`--hub`): no monsters, just the Prism
+    /// Merchant's stall.
+    pub hub: bool,
+    /// Endless mode (`--endless`): no victory, just timed monster waves
+    /// and a survival score.
+    pub endless: bool,
+    /// Honour one-way down-stairs (`--one-way-stairs`): the floor above
+    /// seals once you take one.
+    pub one_way_stairs: bool,
+    /// Monsters per layer on fresh floors (`--spawn-divisor`,
+    /// `--spawn-min`, `--spawn-max`).
+    pub spawn_density: SpawnDensity,
+    /// Field-of-view routine (`--fov bracket|shadowcast`).
+    pub fov: FovAlgorithm,
+    /// Floor a speed run races to (`--speedrun <floor>`): the HUD shows
+    /// splits and pace, and the fewest turns to each floor are kept.
+    pub speedrun: Option<u32>,
+    /// Stepping onto an item picks it up; `--no-auto-pickup` starts the
+    /// run with items left where they lie.
+    pub auto_pickup: bool,
+    /// Seconds between `run_stats.json` writes (`--stats-interval`);
+    /// changes in between are coalesced, and 0 writes every change.
+    pub stats_interval: u32,
+    /// Tiles the camera leads the player by along their last step
+    /// (`--look-ahead`); 0 keeps it centred.
+    pub look_ahead: i32,
+    /// Monsters blocked by a monster of another world attack it
+    /// (`--infighting`).
+    pub infighting: bool,
+    /// Novas and detonations also hurt the player caught in the blast
+    /// (`--friendly-fire`).
+    pub friendly_fire: bool,
+    /// Starting stats and pack (`--loadout <name>`); `standard` gives the
+    /// starting world's consumables.
+    pub loadout: Loadout,
+    /// Turns a floor holds after you first arrive before its edges start
+    /// crumbling into the void (`--collapse <turns>`); 0 turns it off.
+    pub collapse_turns: u32,
+    /// Tiles across and down every floor (`--map-width`, `--map-height`).
+    /// Taken as given here; `Dungeon::new` rejects sizes it can't build.
+    pub map_width: i32,
+    pub map_height: i32,
+}
+
+impl Default for GameOptions {
+    fn default() -> Self {
+        Self {
+            world_switch_cost: ACTION_COST / 2,
+            descent_heal_percent: 25,
+            seed: None,
+            start_floor: FloorId(0),
+            start_world: World::Red,
+            debug: false,
+            godmode: false,
+            log_file: None,
+            script_limits: ScriptLimits::default(),
+            script_speed: ScriptSpeed::default(),
+            door_close_turns: 3,
+            log_history: DEFAULT_LOG_HISTORY,
+            log_lines: DEFAULT_LOG_LINES,
+            hub: false,
+            endless: false,
+            one_way_stairs: false,
+            spawn_density: SpawnDensity::default(),
+            fov: FovAlgorithm::default(),
+            speedrun: None,
+            auto_pickup: true,
+            stats_interval: 5,
+            look_ahead: 0,
+            infighting: false,
+            friendly_fire: false,
+            loadout: Loadout::default(),
+            collapse_turns: 0,
+            map_width: DEFAULT_MAP_WIDTH,
+            map_height: DEFAULT_MAP_HEIGHT,
+        }
+    }
+}
+
+impl GameOptions {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        if let Some(raw) = flag_or_env(args, "--switch-cost", "RR_SWITCH_COST") {
+            options.world_switch_cost = parse_non_negative("--switch-cost", &raw)?;
+        }
+        if let Some(raw) = flag_or_env(args, "--descent-heal", "RR_DESCENT_HEAL") {
+            let percent = parse_non_negative("--descent-heal", &raw)?;
+            if percent > 100 {
+                return Err(format!(
+                    "--descent-heal is a percentage of max HP (0-100), got '{raw}'"
+                ));
+            }
+            options.descent_heal_percent = percent;
+        }
+        if let Some(raw) = flag_or_env(args, "--seed-code", "RR_SEED_CODE") {
+            options.seed = Some(seed::decode_seed_code(&raw)?);
+        }
+        options.log_file = flag_or_env(args, "--log-file", "RR_LOG_FILE").map(PathBuf::from);
+        options.script_limits.strict = args.iter().any(|arg| arg == "--strict-script");
+        if let Some(raw) = flag_or_env(args, "--script-max-commands", "RR_SCRIPT_MAX_COMMANDS") {
+            options.script_limits.max_commands =
+                parse_non_negative("--script-max-commands", &raw)? as usize;
+        }
+        if let Some(raw) = flag_or_env(args, "--script-speed", "RR_SCRIPT_SPEED") {
+            options.script_speed = ScriptSpeed::from_arg(&raw)?;
+        }
+        if let Some(raw) = flag_or_env(args, "--door-close-turns", "RR_DOOR_CLOSE_TURNS") {
+            options.door_close_turns = parse_non_negative("--door-close-turns", &raw)? as u32;
+        }
+        if let Some(raw) = flag_or_env(args, "--collapse", "RR_COLLAPSE") {
+            options.collapse_turns = parse_non_negative("--collapse", &raw)? as u32;
+        }
+        if let Some(raw) = flag_or_env(args, "--map-width", "RR_MAP_WIDTH") {
+            options.map_width = parse_map_side("--map-width", &raw)?;
+        }
+        if let Some(raw) = flag_or_env(args, "--map-height", "RR_MAP_HEIGHT") {
+            options.map_height = parse_map_side("--map-height", &raw)?;
+        }
+        if let Some(raw) = flag_or_env(args, "--stats-interval", "RR_STATS_INTERVAL") {
+            options.stats_interval = parse_non_negative("--stats-interval", &raw)? as u32;
+        }
+        if let Some(raw) = flag_or_env(args, "--look-ahead", "RR_LOOK_AHEAD") {
+            options.look_ahead = parse_non_negative("--look-ahead", &raw)?;
+        }
+        if let Some(raw) = flag_or_env(args, "--log-history", "RR_LOG_HISTORY") {
+            let history = parse_non_negative("--log-history", &raw)? as usize;
+            if !(1..=MAX_LOG_HISTORY).contains(&history) {
+                return Err(format!(
+                    "--log-history keeps 1-{MAX_LOG_HISTORY} entries, got '{raw}'"
+                ));
+            }
+            options.log_history = history;
+            options.log_lines = options.log_lines.min(history);
+        }
+        if let Some(raw) = flag_or_env(args, "--log-lines", "RR_LOG_LINES") {
+            let lines = parse_non_negative("--log-lines", &raw)? as usize;
+            if lines == 0 || lines > options.log_history {
+                return Err(format!(
+                    "--log-lines shows 1 to --log-history ({}) entries, got '{raw}'",
+                    options.log_history
+                ));
+            }
+            options.log_lines = lines;
+        }
+        if let Some(raw) = flag_or_env(args, "--spawn-divisor", "RR_SPAWN_DIVISOR") {
+            let divisor = parse_non_negative("--spawn-divisor", &raw)? as usize;
+            if divisor == 0 {
+                return Err(format!(
+                    "--spawn-divisor is walkable tiles per monster and must be at least 1, got '{raw}'"
+                ));
+            }
+            options.spawn_density.tiles_per_monster = divisor;
+        }
+        if let Some(raw) = flag_or_env(args, "--spawn-min", "RR_SPAWN_MIN") {
+            options.spawn_density.min = parse_non_negative("--spawn-min", &raw)? as usize;
+        }
+        if let Some(raw) = flag_or_env(args, "--spawn-max", "RR_SPAWN_MAX") {
+            options.spawn_density.max = parse_non_negative("--spawn-max", &raw)? as usize;
+        }
+        if let Some(raw) = flag_or_env(args, "--speedrun", "RR_SPEEDRUN") {
+            let target = parse_non_negative("--speedrun", &raw)? as u32;
+            if target == 0 {
+                return Err(format!(
+                    "--speedrun is the floor to race to and must be at least 1, got '{raw}'"
+                ));
+            }
+            options.speedrun = Some(target);
+        }
+        let density = options.spawn_density;
+        if density.max < density.min {
+            return Err(format!(
+                "--spawn-max ({}) must be at least --spawn-min ({})",
+                density.max, density.min
+            ));
+        }
+        if let Some(raw) = flag_or_env(args, "--loadout", "RR_LOADOUT") {
+            options.loadout = loadout_named(&raw).ok_or_else(|| {
+                let names: Vec<&str> = builtin_loadouts().iter().map(|l| l.name).collect();
+                format!("--loadout expects one of {}; got '{raw}'", names.join(", "))
+            })?;
+        }
+        if let Some(raw) = flag_or_env(args, "--fov", "RR_FOV") {
+            options.fov = FovAlgorithm::from_name(&raw)
+                .ok_or_else(|| format!("--fov expects bracket or shadowcast, got '{raw}'"))?;
+        }
+        options.hub = args.iter().any(|arg| arg == "--hub")
+            || env::var("RR_HUB")
+                .is_ok_and(|v| ["1", "true", "TRUE", "on", "ON"].contains(&v.as_str()));
+        options.endless = args.iter().any(|arg| arg == "--endless")
+            || env::var("RR_ENDLESS")
+                .is_ok_and(|v| ["1", "true", "TRUE", "on", "ON"].contains(&v.as_str()));
+        options.one_way_stairs = args.iter().any(|arg| arg == "--one-way-stairs")
+            || env::var("RR_ONE_WAY_STAIRS")
+                .is_ok_and(|v| ["1", "true", "TRUE", "on", "ON"].contains(&v.as_str()));
+        options.infighting = args.iter().any(|arg| arg == "--infighting")
+            || env::var("RR_INFIGHTING")
+                .is_ok_and(|v| ["1", "true", "TRUE", "on", "ON"].contains(&v.as_str()));
+        options.friendly_fire = args.iter().any(|arg| arg == "--friendly-fire")
+            || env::var("RR_FRIENDLY_FIRE")
+                .is_ok_and(|v| ["1", "true", "TRUE", "on", "ON"].contains(&v.as_str()));
+        options.auto_pickup = !(args.iter().any(|arg| arg == "--no-auto-pickup")
+            || env::var("RR_NO_AUTO_PICKUP")
+                .is_ok_and(|v| ["1", "true", "TRUE", "on", "ON"].contains(&v.as_str())));
+        let debug = args.iter().any(|arg| arg == "--debug");
+        options.debug = debug;
+        if args.iter().any(|arg| arg == "--godmode") {
+            if !debug {
+                return Err("--godmode is a debug flag; pass --debug as well".to_string());
+            }
+            options.godmode = true;
+        }
+        if let Some(raw) = flag_value(args, "--start-floor") {
+            if !debug {
+                return Err("--start-floor is a debug flag; pass --debug as well".to_string());
+            }
+            options.start_floor = FloorId(parse_non_negative("--start-floor", raw)? as u32);
+        }
+        if let Some(raw) = flag_value(args, "--start-world") {
+            if !debug {
+                return Err("--start-world is a debug flag; pass --debug as well".to_string());
+            }
+            options.start_world = World::from_name(raw).ok_or_else(|| {
+                format!("--start-world expects one of Red, Orange, Yellow, Green, Blue, Indigo, Violet; got '{raw}'")
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+fn parse_map_side(label: &str, raw: &str) -> Result<i32, String> {
+    raw.trim()
+        .parse::<i32>()
+        .map_err(|_| format!("{label} expects a whole number of tiles, got '{raw}'"))
+}
+
+fn parse_non_negative(label: &str, raw: &str) -> Result<i32, String> {
+    match raw.trim().parse::<i32>() {
+        Ok(value) if value >= 0 => Ok(value),
+        _ => Err(format!(
+            "{label} expects a non-negative whole number, got '{raw}'"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn validate_dimensions_accepts_the_minimum_and_up() {
+        assert_eq!(
+            validate_dimensions(MIN_CONSOLE_WIDTH, MIN_CONSOLE_HEIGHT),
+            Ok((MIN_CONSOLE_WIDTH, MIN_CONSOLE_HEIGHT))
+        );
+        assert_eq!(validate_dimensions(200, 60), Ok((200, 60)));
+    }
+
+    #[test]
+    fn validate_dimensions_rejects_either_axis_too_small() {
+        assert!(validate_dimensions(MIN_CONSOLE_WIDTH - 1, MIN_CONSOLE_HEIGHT).is_err());
+        assert!(validate_dimensions(MIN_CONSOLE_WIDTH, MIN_CONSOLE_HEIGHT - 1).is_err());
+        assert!(validate_dimensions(-5, 0).is_err());
+    }
+
+    #[test]
+    fn console_dimensions_reads_flags_and_rejects_garbage() {
+        assert_eq!(
+            console_dimensions(&args(&["--width", "160", "--height", "50"]), false),
+            Ok((160, 50))
+        );
+        assert!(console_dimensions(&args(&["--width", "wide"]), false).is_err());
+        assert!(console_dimensions(&args(&["--height", "10"]), false).is_err());
+    }
+
+    #[test]
+    fn spawn_density_clamps_and_validates() {
+        let density = SpawnDensity::default();
+        assert_eq!(density.target(0), density.min);
+        assert_eq!(density.target(90 * 4), 4);
+        assert_eq!(density.target(usize::MAX), density.max);
+
+        let options =
+            GameOptions::from_args(&args(&["--spawn-divisor", "10", "--spawn-max", "9"])).unwrap();
+        assert_eq!(options.spawn_density.target(500), 9);
+        assert!(GameOptions::from_args(&args(&["--spawn-divisor", "0"])).is_err());
+        assert!(GameOptions::from_args(&args(&["--spawn-min", "5", "--spawn-max", "3"])).is_err());
+    }
+
+    #[test]
+    fn map_size_flags_pass_whole_numbers_through() {
+        let options = GameOptions::default();
+        assert_eq!(
+            (options.map_width, options.map_height),
+            (DEFAULT_MAP_WIDTH, DEFAULT_MAP_HEIGHT)
+        );
+
+        let options =
+            GameOptions::from_args(&args(&["--map-width", "120", "--map-height", "-3"])).unwrap();
+        assert_eq!((options.map_width, options.map_height), (120, -3));
+        assert!(GameOptions::from_args(&args(&["--map-width", "wide"])).is_err());
+    }
+}
@@ -1,5 +1,8 @@
 #![allow(dead_code)]
-use bracket_terminal::prelude::{LIGHT_BLUE, MAGENTA, ORANGE, RED, RGB};
+use bracket_terminal::prelude::{
+    CYAN, GOLD, LAVENDER, LIGHT_BLUE, LIGHT_CYAN, MAGENTA, ORANGE, PINK, RED, RGB, WHITE,
+    YELLOW_GREEN,
+};
 
 use crate::map::World;
 
@@ -13,10 +16,160 @@ pub struct ConsumableTemplate {
 
 #[derive(Clone, Debug)]
 pub enum ConsumableEffect {
-    Heal { amount: i32 },
+    Heal {
+        amount: i32,
+    },
     Cleanse,
-    Blink { range: i32 },
-    Nova { damage: i32, radius: i32 },
+    Blink {
+        range: i32,
+    },
+    Nova {
+        damage: i32,
+        radius: i32,
+        /// Each foe the blast leaves standing may catch fire.
+        burns: bool,
+    },
+    DetectLife {
+        turns: u32,
+    },
+    AcidCloud {
+        turns: u32,
+    },
+    Levitate {
+        turns: u32,
+    },
+    /// Slows every monster within `radius` for `turns`.
+    Freeze {
+        radius: i32,
+        turns: u32,
+    },
+    /// Gives one use back to the emptiest other slot in the pack.
+    Resonate,
+    /// A nova that strikes the same spot on every world layer at once.
+    Detonation {
+        damage: i32,
+        radius: i32,
+    },
+}
+
+/// Percent chance a floor layer's loose consumable is a Resonance Shard
+/// rather than one of its world's own.
+pub const RESONANCE_SHARD_CHANCE: i32 = 8;
+
+/// A weapon the player can wield in place of their blade.
+#[derive(Clone, Debug)]
+pub struct WeaponTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Tiles the weapon strikes in a straight line; 1 is adjacent only.
+    pub reach: i32,
+}
+
+/// What every run starts wielding.
+pub const PRISM_BLADE: WeaponTemplate = WeaponTemplate {
+    name: "Prism Blade",
+    description: "A plain edge of hardened light.",
+    reach: 1,
+};
+
+/// A reach weapon: strikes a foe up to two tiles away in a straight
+/// cardinal line, so long as nothing solid stands between.
+pub const PRISM_SPEAR: WeaponTemplate = WeaponTemplate {
+    name: "Prism Spear",
+    description: "Strikes two tiles straight ahead (Ctrl+direction).",
+    reach: 2,
+};
+
+/// Highest enchant level a piece of gear can reach.
+pub const ENCHANT_CAP: i32 = 5;
+
+/// Prism shards needed to enchant gear currently at `level`.
+pub fn enchant_cost(level: i32) -> u32 {
+    2 + 2 * level.max(0) as u32
+}
+
+/// Percent chance enchanting gear at `level` succeeds: 90% from +0, then
+/// 20 points less per level.
+pub fn enchant_chance(level: i32) -> i32 {
+    (90 - 20 * level).max(10)
+}
+
+/// An effect's family, ignoring its numbers; recipes match on these.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EffectKind {
+    Heal,
+    Cleanse,
+    Blink,
+    Nova,
+    DetectLife,
+    AcidCloud,
+    Levitate,
+    Freeze,
+    Resonate,
+    Detonation,
+}
+
+impl ConsumableEffect {
+    pub fn kind(&self) -> EffectKind {
+        match self {
+            Self::Heal { .. } => EffectKind::Heal,
+            Self::Cleanse => EffectKind::Cleanse,
+            Self::Blink { .. } => EffectKind::Blink,
+            Self::Nova { .. } => EffectKind::Nova,
+            Self::DetectLife { .. } => EffectKind::DetectLife,
+            Self::AcidCloud { .. } => EffectKind::AcidCloud,
+            Self::Levitate { .. } => EffectKind::Levitate,
+            Self::Freeze { .. } => EffectKind::Freeze,
+            Self::Resonate => EffectKind::Resonate,
+            Self::Detonation { .. } => EffectKind::Detonation,
+        }
+    }
+}
+
+/// What two consumables combine into; the order of the inputs doesn't
+/// matter. Pairs without a recipe don't combine.
+pub fn recipe_for(a: EffectKind, b: EffectKind) -> Option<ConsumableTemplate> {
+    let pair = if a <= b { (a, b) } else { (b, a) };
+    match pair {
+        (EffectKind::Heal, EffectKind::Heal) => Some(ConsumableTemplate::new(
+            "Greater Draft",
+            "Restores 18 HP in a single surge.",
+            RGB::named(GOLD),
+            ConsumableEffect::Heal { amount: 18 },
+        )),
+        (EffectKind::Heal, EffectKind::Cleanse) => Some(ConsumableTemplate::new(
+            "Radiant Tonic",
+            "Heals 12 HP with a purifying glow.",
+            RGB::named(PINK),
+            ConsumableEffect::Heal { amount: 12 },
+        )),
+        (EffectKind::Blink, EffectKind::Blink) => Some(ConsumableTemplate::new(
+            "Farstep Lens",
+            "Blinks up to 12 tiles away.",
+            RGB::named(CYAN),
+            ConsumableEffect::Blink { range: 12 },
+        )),
+        (EffectKind::Nova, EffectKind::Nova) => Some(ConsumableTemplate::new(
+            "Prism Storm",
+            "Detonates a 4-tile blast for 10 damage.",
+            RGB::named(MAGENTA),
+            ConsumableEffect::Nova {
+                damage: 10,
+                radius: 4,
+                burns: false,
+            },
+        )),
+        (EffectKind::Nova, EffectKind::Resonate) => Some(ConsumableTemplate::new(
+            "Prismatic Detonation",
+            "Blasts 2 tiles around you for 8 damage on every world at once.",
+            RGB::named(WHITE),
+            ConsumableEffect::Detonation {
+                damage: 8,
+                radius: 2,
+            },
+        )),
+        _ => None,
+    }
 }
 
 pub fn starter_consumables(world: World) -> Vec<ConsumableTemplate> {
@@ -30,20 +183,74 @@ pub fn starter_consumables(world: World) -> Vec<ConsumableTemplate> {
             ),
             ConsumableTemplate::new(
                 "Ember Nova",
-                "Detonates a 3-tile blast for 6 damage.",
+                "Detonates a 3-tile blast for 6 damage that may set foes alight.",
                 RGB::named(RED),
                 ConsumableEffect::Nova {
                     damage: 6,
                     radius: 3,
+                    burns: true,
                 },
             ),
         ],
-        World::Blue => vec![ConsumableTemplate::new(
-            "Stillwater Draught",
-            "Heals 10 HP and purges slowing chills.",
-            RGB::named(LIGHT_BLUE),
-            ConsumableEffect::Heal { amount: 10 },
-        )],
+        World::Blue => vec![
+            ConsumableTemplate::new(
+                "Stillwater Draught",
+                "Heals 10 HP and purges slowing chills.",
+                RGB::named(LIGHT_BLUE),
+                ConsumableEffect::Heal { amount: 10 },
+            ),
+            ConsumableTemplate::new(
+                "Rime Shard",
+                "Chills every foe within 3 tiles to half speed for 5 turns.",
+                RGB::named(LIGHT_CYAN),
+                ConsumableEffect::Freeze {
+                    radius: 3,
+                    turns: 5,
+                },
+            ),
+        ],
+        World::Orange => vec![
+            ConsumableTemplate::new(
+                "Caustic Flask",
+                "Looses acid on every tile around you for 6 turns.",
+                RGB::named(YELLOW_GREEN),
+                ConsumableEffect::AcidCloud { turns: 6 },
+            ),
+            ConsumableTemplate::new(
+                "Prismatic Tonic",
+                "Heals 6 HP and cleanses curse residue.",
+                RGB::named(MAGENTA),
+                ConsumableEffect::Cleanse,
+            ),
+        ],
+        World::Yellow => vec![
+            ConsumableTemplate::new(
+                "Lifesense Lens",
+                "Shows every monster on the floor for 20 turns.",
+                RGB::named(GOLD),
+                ConsumableEffect::DetectLife { turns: 20 },
+            ),
+            ConsumableTemplate::new(
+                "Prismatic Tonic",
+                "Heals 6 HP and cleanses curse residue.",
+                RGB::named(MAGENTA),
+                ConsumableEffect::Cleanse,
+            ),
+        ],
+        World::Indigo => vec![
+            ConsumableTemplate::new(
+                "Updraft Prism",
+                "Lifts you clear of water and lava for 15 turns.",
+                RGB::named(LAVENDER),
+                ConsumableEffect::Levitate { turns: 15 },
+            ),
+            ConsumableTemplate::new(
+                "Prismatic Tonic",
+                "Heals 6 HP and cleanses curse residue.",
+                RGB::named(MAGENTA),
+                ConsumableEffect::Cleanse,
+            ),
+        ],
         _ => vec![ConsumableTemplate::new(
             "Prismatic Tonic",
             "Heals 6 HP and cleanses curse residue.",
@@ -53,6 +260,25 @@ pub fn starter_consumables(world: World) -> Vec<ConsumableTemplate> {
     }
 }
 
+/// The rare shard that tops a spent consumable back up. No world starts
+/// with it; it only turns up loose on the floor.
+pub fn resonance_shard() -> ConsumableTemplate {
+    ConsumableTemplate::new(
+        "Resonance Shard",
+        "Restores one use to your emptiest other consumable.",
+        RGB::named(WHITE),
+        ConsumableEffect::Resonate,
+    )
+}
+
+/// The template behind a consumable named `name` lying on `world`'s layer.
+pub fn floor_item_template(world: World, name: &str) -> Option<ConsumableTemplate> {
+    starter_consumables(world)
+        .into_iter()
+        .chain(std::iter::once(resonance_shard()))
+        .find(|template| template.name == name)
+}
+
 impl ConsumableTemplate {
     pub const fn new(
         name: &'static str,
@@ -0,0 +1,109 @@
+use super::items::{ConsumableTemplate, resonance_shard, starter_consumables};
+use crate::map::{SPECTRUM, World};
+
+/// The kit every run starts with unless `--loadout` picks another.
+pub const STANDARD_LOADOUT: &str = "standard";
+
+/// What goes into the pack at the start of a run.
+#[derive(Clone, Debug)]
+pub enum LoadoutKit {
+    /// One use of each of the starting world's own consumables.
+    WorldStarters,
+    /// Fixed stacks of consumables by name, whatever the starting world.
+    Stacks(&'static [(&'static str, i32)]),
+}
+
+/// A named starting build: the player's opening stats and pack.
+#[derive(Clone, Debug)]
+pub struct Loadout {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub max_hp: i32,
+    pub power: i32,
+    pub defense: i32,
+    pub kit: LoadoutKit,
+}
+
+impl Loadout {
+    /// The pack this loadout opens with for a run starting on `world`, as
+    /// templates and their uses.
+    pub fn pack(&self, world: World) -> Vec<(ConsumableTemplate, i32)> {
+        match self.kit {
+            LoadoutKit::WorldStarters => starter_consumables(world)
+                .into_iter()
+                .map(|template| (template, 1))
+                .collect(),
+            LoadoutKit::Stacks(stacks) => stacks
+                .iter()
+                .filter_map(|&(name, uses)| consumable_named(name).map(|template| (template, uses)))
+                .collect(),
+        }
+    }
+}
+
+/// Any consumable a world starts with, or the Resonance Shard, by name.
+fn consumable_named(name: &str) -> Option<ConsumableTemplate> {
+    SPECTRUM
+        .iter()
+        .flat_map(|&world| starter_consumables(world))
+        .chain(std::iter::once(resonance_shard()))
+        .find(|template| template.name == name)
+}
+
+pub fn builtin_loadouts() -> Vec<Loadout> {
+    vec![
+        Loadout {
+            name: STANDARD_LOADOUT,
+            description: "The starting world's own consumables.",
+            max_hp: 20,
+            power: 5,
+            defense: 1,
+            kit: LoadoutKit::WorldStarters,
+        },
+        Loadout {
+            name: "novas",
+            description: "Five Ember Novas and nothing else.",
+            max_hp: 20,
+            power: 5,
+            defense: 1,
+            kit: LoadoutKit::Stacks(&[("Ember Nova", 5)]),
+        },
+        Loadout {
+            name: "tank",
+            description: "More HP and armor, less bite; the standard pack.",
+            max_hp: 35,
+            power: 4,
+            defense: 3,
+            kit: LoadoutKit::WorldStarters,
+        },
+        Loadout {
+            name: "medic",
+            description: "Three Thermal Drafts and two Prismatic Tonics.",
+            max_hp: 20,
+            power: 5,
+            defense: 1,
+            kit: LoadoutKit::Stacks(&[("Thermal Draft", 3), ("Prismatic Tonic", 2)]),
+        },
+        Loadout {
+            name: "bare",
+            description: "An empty pack.",
+            max_hp: 20,
+            power: 5,
+            defense: 1,
+            kit: LoadoutKit::Stacks(&[]),
+        },
+    ]
+}
+
+/// The builtin loadout called `name`, ignoring case.
+pub fn loadout_named(name: &str) -> Option<Loadout> {
+    builtin_loadouts()
+        .into_iter()
+        .find(|loadout| loadout.name.eq_ignore_ascii_case(name.trim()))
+}
+
+impl Default for Loadout {
+    fn default() -> Self {
+        loadout_named(STANDARD_LOADOUT).expect("the standard loadout is builtin")
+    }
+}
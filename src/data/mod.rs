@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 
 pub mod items;
+pub mod loadouts;
 pub mod monsters;
+pub mod npcs;
 
 use crate::map::World;
 
@@ -9,6 +11,17 @@ use crate::map::World;
 pub struct WorldRuleSet {
     pub world: World,
     pub notes: &'static str,
+    /// Added to the player's base speed while attuned to this world.
+    pub speed_modifier: i32,
+    /// Extra tiles of Blink range while attuned to this world.
+    pub blink_bonus: i32,
+}
+
+pub fn rule_for(world: World) -> WorldRuleSet {
+    builtin_rules()
+        .into_iter()
+        .find(|rule| rule.world == world)
+        .expect("every world has a builtin rule")
 }
 
 pub fn builtin_rules() -> Vec<WorldRuleSet> {
@@ -16,30 +29,44 @@ pub fn builtin_rules() -> Vec<WorldRuleSet> {
         WorldRuleSet {
             world: World::Red,
             notes: "Heat blooms amplify melee damage.",
+            speed_modifier: 5,
+            blink_bonus: 0,
         },
         WorldRuleSet {
             world: World::Orange,
             notes: "Chemical clouds respond to wind tunnels.",
+            speed_modifier: 0,
+            blink_bonus: 0,
         },
         WorldRuleSet {
             world: World::Yellow,
             notes: "Lens-prisms extend FOV and detect traps.",
+            speed_modifier: 10,
+            blink_bonus: 0,
         },
         WorldRuleSet {
             world: World::Green,
             notes: "Regrowth tiles slowly mend allies.",
+            speed_modifier: -5,
+            blink_bonus: 0,
         },
         WorldRuleSet {
             world: World::Blue,
             notes: "Stillwater grants crit bonuses to ranged.",
+            speed_modifier: -10,
+            blink_bonus: 0,
         },
         WorldRuleSet {
             world: World::Indigo,
             notes: "Mindstorms favor teleport talent rolls.",
+            speed_modifier: 5,
+            blink_bonus: 4,
         },
         WorldRuleSet {
             world: World::Violet,
             notes: "Curses thread through unseen resonance.",
+            speed_modifier: 0,
+            blink_bonus: 0,
         },
     ]
 }
@@ -1,8 +1,21 @@
 #![allow(dead_code)]
 
+use bracket_random::prelude::RandomNumberGenerator;
 use bracket_terminal::prelude::RGB;
+use serde::{Deserialize, Serialize};
 
-use crate::map::World;
+use crate::{
+    ai::{BehaviorKind, PhaseShifter},
+    map::{FloorId, World},
+};
+
+/// Chase range for templates that don't set their own.
+pub const DEFAULT_AGGRO_RADIUS: f32 = 8.0;
+/// Tiles a monster sees for templates that don't set their own.
+pub const DEFAULT_SIGHT_RADIUS: i32 = 8;
+/// Turns a monster keeps heading for the player's last-seen tile before it
+/// gives up and goes back to wandering.
+pub const PURSUIT_TURNS: u64 = 8;
 
 #[derive(Clone, Debug)]
 pub struct MonsterTemplate {
@@ -10,9 +23,24 @@ pub struct MonsterTemplate {
     pub glyph: char,
     pub color: RGB,
     pub wander_chance: f32,
+    /// Distance at which the monster notices the player and gives chase.
+    pub aggro_radius: f32,
+    /// Radius of the monster's own field of view.
+    pub sight_radius: i32,
     pub hp: i32,
     pub power: i32,
     pub defense: i32,
+    /// Side length of the square footprint; 1 for ordinary monsters.
+    pub size: i32,
+    /// Leaves an acid cloud where it dies.
+    pub bursts: bool,
+    /// Short-teleports toward the player while the player is attuned to
+    /// Indigo.
+    pub phases: bool,
+    /// How spawned copies pick their moves.
+    pub behavior: BehaviorKind,
+    /// Follows the player into whatever world they shift to.
+    pub phase_shifter: Option<PhaseShifter>,
 }
 
 impl MonsterTemplate {
@@ -28,10 +56,12 @@ impl MonsterTemplate {
                     10,
                     4,
                     1,
-                ),
+                )
+                .aggro(11.0)
+                .sight(11),
             ],
             World::Orange => vec![
-                Self::new("Acid Puff", 'a', RGB::from_u8(255, 180, 90), 0.6, 8, 3, 0),
+                Self::new("Acid Puff", 'a', RGB::from_u8(255, 180, 90), 0.6, 8, 3, 0).bursting(),
                 Self::new(
                     "Flask Golem",
                     'g',
@@ -51,8 +81,10 @@ impl MonsterTemplate {
                     7,
                     2,
                     1,
-                ),
-                Self::new("Sun Mite", 'm', RGB::from_u8(250, 230, 120), 0.5, 5, 2, 0),
+                )
+                .aggro(5.0),
+                Self::new("Sun Mite", 'm', RGB::from_u8(250, 230, 120), 0.5, 5, 2, 0)
+                    .behaving(BehaviorKind::Coward),
             ],
             World::Green => vec![
                 Self::new(
@@ -92,7 +124,8 @@ impl MonsterTemplate {
                     8,
                     4,
                     1,
-                ),
+                )
+                .aggro(4.0),
             ],
             World::Indigo => vec![
                 Self::new("Mindworm", 'n', RGB::from_u8(170, 140, 255), 0.45, 6, 4, 0),
@@ -104,7 +137,8 @@ impl MonsterTemplate {
                     10,
                     5,
                     1,
-                ),
+                )
+                .phasing(),
             ],
             World::Violet => vec![
                 Self::new("Hex Bat", 'x', RGB::from_u8(220, 120, 255), 0.5, 7, 3, 0),
@@ -117,6 +151,8 @@ impl MonsterTemplate {
                     5,
                     2,
                 ),
+                Self::new("Rift Shade", 'r', RGB::from_u8(180, 110, 240), 0.5, 9, 4, 1)
+                    .world_shifting(4),
             ],
         }
     }
@@ -135,9 +171,196 @@ impl MonsterTemplate {
             glyph,
             color,
             wander_chance,
+            aggro_radius: DEFAULT_AGGRO_RADIUS,
+            sight_radius: DEFAULT_SIGHT_RADIUS,
             hp,
             power,
             defense,
+            size: 1,
+            bursts: false,
+            phases: false,
+            behavior: BehaviorKind::Skirmisher,
+            phase_shifter: None,
+        }
+    }
+
+    /// Makes the template a `side`×`side` monster, for boss-scale foes.
+    pub fn large(mut self, side: i32) -> Self {
+        self.size = side.max(1);
+        self
+    }
+
+    /// Sets how far off the template notices the player: small for
+    /// stealthy lurkers, large for hunters.
+    pub fn aggro(mut self, radius: f32) -> Self {
+        self.aggro_radius = radius;
+        self
+    }
+
+    /// Sets how far the template can see; a monster can't notice the player
+    /// past this, whatever its aggro radius.
+    pub fn sight(mut self, radius: i32) -> Self {
+        self.sight_radius = radius.max(1);
+        self
+    }
+
+    /// Makes the template burst into an acid cloud on death.
+    pub fn bursting(mut self) -> Self {
+        self.bursts = true;
+        self
+    }
+
+    /// Lets the template blink toward the player under Indigo mindstorms.
+    pub fn phasing(mut self) -> Self {
+        self.phases = true;
+        self
+    }
+
+    /// Lets the template follow the player across worlds every `cadence`
+    /// turns.
+    pub fn world_shifting(mut self, cadence: u32) -> Self {
+        self.phase_shifter = Some(PhaseShifter {
+            cadence: cadence.max(1),
+        });
+        self
+    }
+
+    /// Gives the template a behavior other than the default skirmisher.
+    pub fn behaving(mut self, behavior: BehaviorKind) -> Self {
+        self.behavior = behavior;
+        self
+    }
+}
+
+/// An elite roll layered on top of a template at spawn. Templates stay
+/// untouched; the modifier renames, tints, and buffs the spawned copy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MonsterModifier {
+    /// Hits harder.
+    Blazing,
+    /// Shrugs off more damage.
+    Armored,
+    /// Earns actions faster than its peers.
+    Swift,
+    /// Carries far more HP.
+    Hulking,
+}
+
+impl MonsterModifier {
+    pub const ALL: [Self; 4] = [Self::Blazing, Self::Armored, Self::Swift, Self::Hulking];
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Self::Blazing => "Blazing",
+            Self::Armored => "Armored",
+            Self::Swift => "Swift",
+            Self::Hulking => "Hulking",
+        }
+    }
+
+    /// Colour the base glyph is pulled toward so elites stand out.
+    pub fn tint(self) -> RGB {
+        match self {
+            Self::Blazing => RGB::from_u8(255, 60, 20),
+            Self::Armored => RGB::from_u8(200, 200, 210),
+            Self::Swift => RGB::from_u8(120, 255, 255),
+            Self::Hulking => RGB::from_u8(255, 215, 0),
+        }
+    }
+
+    pub fn name_for(self, base: &str) -> String {
+        format!("{} {base}", self.prefix())
+    }
+
+    /// Percent chance a monster seeded on `floor` rolls a modifier: 5% on
+    /// the first floor, +3% per floor below, capped at 35%.
+    pub fn chance(floor: FloorId) -> i32 {
+        (5 + floor.0 as i32 * 3).min(35)
+    }
+
+    /// Size of the stat buff; grows by one every three floors.
+    pub fn bonus(floor: FloorId) -> i32 {
+        1 + floor.0 as i32 / 3
+    }
+
+    pub fn roll(rng: &mut RandomNumberGenerator, floor: FloorId) -> Option<Self> {
+        if rng.range(0, 100) >= Self::chance(floor) {
+            return None;
+        }
+        Some(Self::ALL[rng.range(0, Self::ALL.len() as i32) as usize])
+    }
+}
+
+/// Percent chance a monster barks when it first spots the player, and
+/// again when it dies in view.
+pub const BARK_CHANCE: i32 = 30;
+
+/// Flavour lines a world's monsters shout; `{name}` is swapped for the
+/// monster's name.
+#[derive(Clone, Copy, Debug)]
+pub struct Barks {
+    pub aggro: &'static [&'static str],
+    pub death: &'static [&'static str],
+}
+
+impl Barks {
+    pub fn for_world(world: World) -> Self {
+        match world {
+            World::Red => Self {
+                aggro: &[
+                    "The {name} snarls, embers spilling from its jaws!",
+                    "The {name} roars like a forge bellows!",
+                ],
+                death: &["The {name} gutters out with a hiss."],
+            },
+            World::Orange => Self {
+                aggro: &[
+                    "The {name} gurgles and foams!",
+                    "The {name} sloshes toward you!",
+                ],
+                death: &["The {name} bursts with a wet pop."],
+            },
+            World::Yellow => Self {
+                aggro: &[
+                    "The {name} flares blinding-bright!",
+                    "The {name} hums a rising note!",
+                ],
+                death: &["The {name} fades like a sunspot."],
+            },
+            World::Green => Self {
+                aggro: &[
+                    "The {name} rustles and bristles!",
+                    "The {name} creaks awake!",
+                ],
+                death: &["The {name} wilts into mulch."],
+            },
+            World::Blue => Self {
+                aggro: &[
+                    "The {name} clicks, frost crackling on its shell!",
+                    "The {name} rises from the still water!",
+                ],
+                death: &["The {name} shatters like thin ice."],
+            },
+            World::Indigo => Self {
+                aggro: &[
+                    "The {name} whispers inside your skull!",
+                    "The {name} flickers toward you!",
+                ],
+                death: &["The {name} unravels into static."],
+            },
+            World::Violet => Self {
+                aggro: &["The {name} shrieks!", "The {name} hisses a hex at you!"],
+                death: &["The {name} dissolves, whispering your name."],
+            },
+        }
+    }
+
+    /// Rolls [`BARK_CHANCE`] and, on a hit, picks one of `lines` for `name`.
+    pub fn roll(lines: &[&str], name: &str, rng: &mut RandomNumberGenerator) -> Option<String> {
+        if lines.is_empty() || rng.range(0, 100) >= BARK_CHANCE {
+            return None;
         }
+        let line = lines[rng.range(0, lines.len() as i32) as usize];
+        Some(line.replace("{name}", name))
     }
 }
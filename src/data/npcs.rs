@@ -0,0 +1,109 @@
+use bracket_terminal::prelude::{GOLD, RGB};
+use serde::{Deserialize, Serialize};
+
+use super::items::{
+    ConsumableEffect, ConsumableTemplate, PRISM_SPEAR, WeaponTemplate, starter_consumables,
+};
+use crate::map::SPECTRUM;
+
+/// What an NPC does when talked to beyond its flavor lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NpcRole {
+    Shopkeeper,
+}
+
+#[derive(Clone, Debug)]
+pub struct NpcTemplate {
+    pub role: NpcRole,
+    pub name: &'static str,
+    pub glyph: char,
+    pub color: RGB,
+    /// Lines the NPC cycles through when you chat.
+    pub lines: &'static [&'static str],
+}
+
+impl NpcTemplate {
+    pub fn for_role(role: NpcRole) -> Self {
+        match role {
+            NpcRole::Shopkeeper => Self {
+                role,
+                name: "Prism Merchant",
+                glyph: '$',
+                color: RGB::named(GOLD),
+                lines: &[
+                    "Shards for salves, salves for shards. Fair as light.",
+                    "Every world below has a copy of this stall. I don't ask how.",
+                    "Violet curses don't cross my threshold. Neither does credit.",
+                    "Bring back something that glows and we'll talk.",
+                ],
+            },
+        }
+    }
+}
+
+/// Something a shop can sell.
+#[derive(Clone, Debug)]
+pub enum Ware {
+    Consumable(ConsumableTemplate),
+    /// Replaces the wielded weapon, keeping its enchant.
+    Weapon(WeaponTemplate),
+}
+
+impl Ware {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Consumable(template) => template.name,
+            Self::Weapon(template) => template.name,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Consumable(template) => template.description,
+            Self::Weapon(template) => template.description,
+        }
+    }
+}
+
+/// A ware for sale and its price in prism shards.
+#[derive(Clone, Debug)]
+pub struct ShopOffer {
+    pub ware: Ware,
+    pub price: u32,
+}
+
+/// Shards the merchant asks for a Prism Spear.
+const SPEAR_PRICE: u32 = 8;
+
+/// The shopkeeper's wares: one of each distinct starter consumable, priced
+/// by how much it can swing a fight, then a Prism Spear.
+pub fn shop_stock() -> Vec<ShopOffer> {
+    let mut stock: Vec<ShopOffer> = Vec::new();
+    for world in SPECTRUM {
+        for template in starter_consumables(world) {
+            if stock.iter().any(|offer| offer.ware.name() == template.name) {
+                continue;
+            }
+            let price = match template.effect {
+                ConsumableEffect::Heal { .. } | ConsumableEffect::Cleanse => 3,
+                ConsumableEffect::Blink { .. }
+                | ConsumableEffect::DetectLife { .. }
+                | ConsumableEffect::Levitate { .. } => 4,
+                ConsumableEffect::Nova { .. }
+                | ConsumableEffect::AcidCloud { .. }
+                | ConsumableEffect::Freeze { .. }
+                | ConsumableEffect::Resonate
+                | ConsumableEffect::Detonation { .. } => 5,
+            };
+            stock.push(ShopOffer {
+                ware: Ware::Consumable(template),
+                price,
+            });
+        }
+    }
+    stock.push(ShopOffer {
+        ware: Ware::Weapon(PRISM_SPEAR),
+        price: SPEAR_PRICE,
+    });
+    stock
+}
@@ -2,11 +2,20 @@
 
 use bracket_geometry::prelude::Point;
 use bracket_terminal::prelude::RGB;
-use specs::prelude::{Component, NullStorage, VecStorage};
-
-use crate::map::{FloorId, World};
-
-#[derive(Clone, Debug)]
+use serde::{Deserialize, Serialize};
+use specs::prelude::{Component, Entity, NullStorage, VecStorage, WriteStorage};
+
+use crate::{
+    ai::{BehaviorKind, PhaseShifter},
+    data::{
+        items::EffectKind,
+        monsters::{DEFAULT_AGGRO_RADIUS, MonsterModifier},
+        npcs::NpcRole,
+    },
+    map::{FloorId, World},
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Position {
     pub point: Point,
     pub floor: FloorId,
@@ -17,7 +26,56 @@ impl Component for Position {
     type Storage = VecStorage<Self>;
 }
 
-#[derive(Clone, Debug)]
+/// Footprint of an entity wider than one tile. `Position` anchors the
+/// top-left cell; entities without a `Size` occupy only that cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Size {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Size {
+    pub fn square(side: i32) -> Self {
+        Self {
+            width: side.max(1),
+            height: side.max(1),
+        }
+    }
+
+    /// Every cell covered when the top-left cell sits at `anchor`.
+    pub fn cells(&self, anchor: Point) -> Vec<Point> {
+        (0..self.height.max(1))
+            .flat_map(|dy| {
+                (0..self.width.max(1)).map(move |dx| Point::new(anchor.x + dx, anchor.y + dy))
+            })
+            .collect()
+    }
+
+    /// The covered cell closest to `target`, used for reach and distance.
+    pub fn nearest_cell(&self, anchor: Point, target: Point) -> Point {
+        Point::new(
+            target.x.clamp(anchor.x, anchor.x + self.width.max(1) - 1),
+            target.y.clamp(anchor.y, anchor.y + self.height.max(1) - 1),
+        )
+    }
+}
+
+impl Default for Size {
+    fn default() -> Self {
+        Self::square(1)
+    }
+}
+
+impl Component for Size {
+    type Storage = VecStorage<Self>;
+}
+
+/// Cells covered by an entity anchored at `anchor` with an optional `Size`.
+pub fn footprint(anchor: Point, size: Option<&Size>) -> Vec<Point> {
+    size.copied().unwrap_or_default().cells(anchor)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Renderable {
     pub glyph: u16,
     pub color: RGB,
@@ -28,7 +86,7 @@ impl Component for Renderable {
     type Storage = VecStorage<Self>;
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Viewshed {
     pub radius: i32,
     pub dirty: bool,
@@ -40,10 +98,16 @@ impl Component for Viewshed {
     type Storage = VecStorage<Self>;
 }
 
-#[derive(Clone, Debug)]
+/// Energy an actor spends to take one action. An actor moving at this speed
+/// earns exactly one action for every action the player takes at this speed.
+pub const ACTION_COST: i32 = 60;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Actor {
     pub energy: i32,
     pub speed: i32,
+    /// Speed before world and status modifiers; `speed` is derived from it.
+    pub base_speed: i32,
 }
 
 impl Component for Actor {
@@ -62,7 +126,7 @@ impl Component for Portal {
     type Storage = VecStorage<Self>;
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct WorldAffinity {
     pub primary: World,
     pub resist: Option<World>,
@@ -73,7 +137,39 @@ impl Component for WorldAffinity {
     type Storage = VecStorage<Self>;
 }
 
-#[derive(Clone, Debug, Default)]
+/// Defense a resisted world's blows lose against the bearer, and the defense
+/// a vulnerable one's gain.
+pub const AFFINITY_DEFENSE: i32 = 1;
+/// Turns the player must stand in a world before their affinity retunes to
+/// it; until then they answer to the world they came from.
+pub const ATTUNE_TURNS: u32 = 3;
+
+impl WorldAffinity {
+    /// Tuned to `world`: shrugs off some of its blows and suffers more
+    /// from its [opposite](World::opposite). Which applies depends on the
+    /// attacker's own world, not the layer the fight happens on.
+    pub fn attuned(world: World) -> Self {
+        Self {
+            primary: world,
+            resist: Some(world),
+            vulnerable: Some(world.opposite()),
+        }
+    }
+
+    /// Defense against an attacker from `source`, after resistance and
+    /// vulnerability shift it by [`AFFINITY_DEFENSE`].
+    pub fn defense_against(&self, source: World, defense: i32) -> i32 {
+        if self.resist == Some(source) {
+            defense + AFFINITY_DEFENSE
+        } else if self.vulnerable == Some(source) {
+            defense - AFFINITY_DEFENSE
+        } else {
+            defense
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct PlaneAttunements {
     pub unlocked: Vec<World>,
     pub perks: u64,
@@ -121,25 +217,78 @@ impl Component for MonsterTag {
     type Storage = NullStorage<Self>;
 }
 
-#[derive(Clone, Debug)]
+/// Marks non-hostile entities (NPCs, summoned allies). Bumping one swaps
+/// places with it instead of attacking.
+#[derive(Default)]
+pub struct Friendly;
+
+impl Component for Friendly {
+    type Storage = NullStorage<Self>;
+}
+
+/// A character you talk to rather than fight; always paired with
+/// `Friendly`, and never given stats or a brain.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Npc {
+    pub name: String,
+    pub role: NpcRole,
+}
+
+impl Component for Npc {
+    type Storage = VecStorage<Self>;
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Monster {
     pub name: String,
+    /// Elite roll this monster spawned with, if any.
+    #[serde(default)]
+    pub modifier: Option<MonsterModifier>,
+    /// Bursts into an acid cloud on death.
+    #[serde(default)]
+    pub bursts: bool,
+    /// Short-teleports toward the player under Indigo mindstorms.
+    #[serde(default)]
+    pub phases: bool,
 }
 
 impl Component for Monster {
     type Storage = VecStorage<Self>;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MonsterBrain {
     pub wander_chance: f32,
+    /// How close the player must come before the monster gives chase.
+    /// Saves from before it existed get [`DEFAULT_AGGRO_RADIUS`].
+    #[serde(default = "default_aggro_radius")]
+    pub aggro_radius: f32,
+    /// Set the first time the monster gives chase, so it only barks once.
+    #[serde(default)]
+    pub alerted: bool,
+    /// Where and on which turn the monster last saw the player. It keeps
+    /// heading there for [`PURSUIT_TURNS`] turns after losing sight.
+    #[serde(default)]
+    pub last_seen_player: Option<(Point, u64)>,
+    /// How the monster picks its moves; saves from before it existed
+    /// get the default skirmisher.
+    #[serde(default)]
+    pub behavior: BehaviorKind,
+    /// Hops to the player's world on a cadence; `None` for monsters that
+    /// keep to their own layer.
+    #[serde(default)]
+    pub phase_shifter: Option<PhaseShifter>,
+}
+
+fn default_aggro_radius() -> f32 {
+    DEFAULT_AGGRO_RADIUS
 }
 
 impl Component for MonsterBrain {
     type Storage = VecStorage<Self>;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CombatStats {
     pub max_hp: i32,
     pub hp: i32,
@@ -151,12 +300,15 @@ impl Component for CombatStats {
     type Storage = VecStorage<Self>;
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Inventory {
     pub slots: Vec<InventorySlot>,
+    /// Prism shards, earned from kills and spent on enchanting.
+    #[serde(default)]
+    pub shards: u32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InventorySlot {
     pub name: String,
     pub description: String,
@@ -165,12 +317,218 @@ pub struct InventorySlot {
     pub color: RGB,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum InventoryEffect {
-    Heal { amount: i32 },
+    Heal {
+        amount: i32,
+    },
     Cleanse,
-    Blink { range: i32 },
-    Nova { damage: i32, radius: i32 },
+    Blink {
+        range: i32,
+    },
+    Nova {
+        damage: i32,
+        radius: i32,
+        /// Whether the blast can set what it hits alight.
+        #[serde(default)]
+        burns: bool,
+    },
+    DetectLife {
+        turns: u32,
+    },
+    AcidCloud {
+        turns: u32,
+    },
+    Levitate {
+        turns: u32,
+    },
+    Freeze {
+        radius: i32,
+        turns: u32,
+    },
+    Resonate,
+    Detonation {
+        damage: i32,
+        radius: i32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GearSlot {
+    Weapon,
+    Armor,
+}
+
+/// A worn piece of gear. Its enchant bonus is already folded into the
+/// wearer's `CombatStats`; `enchant` records how far it has been pushed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GearPiece {
+    pub name: String,
+    pub enchant: i32,
+    /// Tiles a weapon strikes along a straight line. Armor (and gear
+    /// saved before reach existed) leaves it at 0, which counts as 1.
+    #[serde(default)]
+    pub reach: i32,
+}
+
+impl GearPiece {
+    pub fn reach(&self) -> i32 {
+        self.reach.max(1)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Equipment {
+    pub weapon: Option<GearPiece>,
+    pub armor: Option<GearPiece>,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: GearSlot) -> Option<&GearPiece> {
+        match slot {
+            GearSlot::Weapon => self.weapon.as_ref(),
+            GearSlot::Armor => self.armor.as_ref(),
+        }
+    }
+
+    pub fn get_mut(&mut self, slot: GearSlot) -> Option<&mut GearPiece> {
+        match slot {
+            GearSlot::Weapon => self.weapon.as_mut(),
+            GearSlot::Armor => self.armor.as_mut(),
+        }
+    }
+}
+
+impl Component for Equipment {
+    type Storage = VecStorage<Self>;
+}
+
+/// Turns a Violet curse lasts; further cursing hits refresh it rather
+/// than stacking.
+pub const CURSE_TURNS: u32 = 30;
+/// Percent chance a landed Violet hit curses the player.
+pub const CURSE_CHANCE: i32 = 25;
+
+/// A lingering Violet curse. While it lasts, cursed tiles creep across the
+/// floor wherever the player can't see; Cleanse lifts it early.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Cursed {
+    pub turns_left: u32,
+}
+
+impl Component for Cursed {
+    type Storage = VecStorage<Self>;
+}
+
+/// Turns a fresh burn adds to the timer.
+pub const BURN_TURNS: u32 = 3;
+/// Most turns of burning that can pile up; re-igniting never goes past it.
+pub const BURN_TURN_CAP: u32 = 6;
+/// Damage burning deals each turn, ignoring defense.
+pub const BURN_DAMAGE: i32 = 1;
+/// Percent chance a Red melee hit, or an Ember Nova, sets its target alight.
+pub const BURN_CHANCE: i32 = 30;
+
+/// Flames clinging to an entity. They hurt every turn until they burn
+/// out; water or a Cleanse puts them out early.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Burning {
+    pub turns_left: u32,
+    pub damage: i32,
+}
+
+impl Burning {
+    /// Sets `entity` alight, or stokes flames already on it up to
+    /// [`BURN_TURN_CAP`]. Returns whether it was newly lit.
+    pub fn ignite(storage: &mut WriteStorage<'_, Burning>, entity: Entity) -> bool {
+        if let Some(burning) = storage.get_mut(entity) {
+            burning.turns_left = (burning.turns_left + BURN_TURNS).min(BURN_TURN_CAP);
+            return false;
+        }
+        let _ = storage.insert(
+            entity,
+            Burning {
+                turns_left: BURN_TURNS,
+                damage: BURN_DAMAGE,
+            },
+        );
+        true
+    }
+}
+
+impl Component for Burning {
+    type Storage = VecStorage<Self>;
+}
+
+/// Turns a chill lasts; a fresh one refreshes the timer rather than
+/// stacking.
+pub const SLOW_TURNS: u32 = 4;
+/// Percent of its speed a slowed entity keeps.
+pub const SLOW_SPEED_PERCENT: i32 = 50;
+/// Percent chance a Blue melee hit slows its target.
+pub const SLOW_CHANCE: i32 = 25;
+
+/// A Blue chill. The slowed entity earns energy as if its speed were cut
+/// to [`SLOW_SPEED_PERCENT`], so it acts less often until it thaws.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Slowed {
+    pub turns_left: u32,
+}
+
+impl Slowed {
+    /// `speed` as it counts while slowed.
+    pub fn effective_speed(speed: i32) -> i32 {
+        (speed * SLOW_SPEED_PERCENT / 100).max(1)
+    }
+
+    /// Slows `entity` for at least `turns`. Returns whether it was newly
+    /// slowed.
+    pub fn chill(storage: &mut WriteStorage<'_, Slowed>, entity: Entity, turns: u32) -> bool {
+        if let Some(slowed) = storage.get_mut(entity) {
+            slowed.turns_left = slowed.turns_left.max(turns);
+            return false;
+        }
+        let _ = storage.insert(entity, Slowed { turns_left: turns });
+        true
+    }
+}
+
+impl Component for Slowed {
+    type Storage = VecStorage<Self>;
+}
+
+/// Turns left on every timed status one entity carries, gathered from the
+/// separate status components (and the player's levitation timer); 0 means
+/// the status is absent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StatusEffects {
+    pub burning: u32,
+    pub slowed: u32,
+    pub cursed: u32,
+    pub levitating: u32,
+}
+
+impl StatusEffects {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl InventoryEffect {
+    pub fn kind(&self) -> EffectKind {
+        match self {
+            Self::Heal { .. } => EffectKind::Heal,
+            Self::Cleanse => EffectKind::Cleanse,
+            Self::Blink { .. } => EffectKind::Blink,
+            Self::Nova { .. } => EffectKind::Nova,
+            Self::DetectLife { .. } => EffectKind::DetectLife,
+            Self::AcidCloud { .. } => EffectKind::AcidCloud,
+            Self::Levitate { .. } => EffectKind::Levitate,
+            Self::Freeze { .. } => EffectKind::Freeze,
+            Self::Resonate => EffectKind::Resonate,
+            Self::Detonation { .. } => EffectKind::Detonation,
+        }
+    }
 }
 
 impl Component for Inventory {
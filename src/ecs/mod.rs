@@ -2,43 +2,92 @@
 
 pub mod components;
 pub mod resources;
+pub mod snapshot;
 pub mod systems;
 
-use bracket_geometry::prelude::Point;
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use bracket_geometry::prelude::{Point, line2d_bresenham};
 use bracket_pathfinding::prelude::DistanceAlg;
 use bracket_random::prelude::RandomNumberGenerator;
 use specs::prelude::{
-    Builder, Dispatcher, DispatcherBuilder, Entity, Join, World as SpecsWorld, WorldExt,
+    Builder, Component, Dispatcher, DispatcherBuilder, Entity, Join, World as SpecsWorld, WorldExt,
 };
+use specs::shred::Fetch;
 
 use crate::{
     data::{
-        items::{ConsumableEffect, starter_consumables},
-        monsters::MonsterTemplate,
+        items::{
+            ConsumableEffect, ConsumableTemplate, ENCHANT_CAP, PRISM_BLADE, enchant_chance,
+            enchant_cost, recipe_for, starter_consumables,
+        },
+        loadouts::Loadout,
+        monsters::{Barks, MonsterModifier, MonsterTemplate},
+        npcs::{NpcTemplate, ShopOffer, Ware},
+        rule_for,
     },
-    map::{Dungeon, FloorId, MapLayer, World, world_color},
+    map::{
+        CORPSE_LOOT_CHANCE, Corpse, Dungeon, FloorId, MapLayer, SPECTRUM, Tile, World, WorldFloor,
+        world_color,
+    },
+    seed::RngStream,
 };
 
 use self::{
     components::{
-        Actor, CombatStats, IntentStep, Inventory, InventoryEffect, InventorySlot, Monster,
-        MonsterBrain, MonsterTag, PlaneAttunements, PlayerTag, Position, Renderable, Viewshed,
-        WorldAffinity,
+        ACTION_COST, ATTUNE_TURNS, Actor, BURN_CHANCE, Burning, CombatStats, Cursed, Equipment,
+        Friendly, GearPiece, GearSlot, IntentStep, Inventory, InventoryEffect, InventorySlot,
+        Monster, MonsterBrain, MonsterTag, Npc, PlaneAttunements, PlayerTag, Position, Renderable,
+        SLOW_CHANCE, SLOW_TURNS, Size, Slowed, StatusEffects, Viewshed, WorldAffinity, footprint,
+    },
+    resources::{
+        AiRng, CombatEvent, CombatEventKind, CombatLog, CombatRng, CombatRules, DebugFlags,
+        FovAlgorithm, LogLine, MovementContext, TurnClock,
     },
-    resources::{CombatLog, MovementContext},
     systems::{EnergySystem, FovSystem, MovementSystem, WanderSystem},
 };
 
+const PLAYER_BASE_SPEED: i32 = ACTION_COST;
+/// Source of [`EcsWorld::instance_id`]s.
+static NEXT_INSTANCE: AtomicU64 = AtomicU64::new(0);
+/// Most slots the pack holds; the quickbar shows them a page at a time.
+pub const PACK_SLOTS: usize = 10;
+
 pub struct EcsWorld {
     specs_world: SpecsWorld,
     dispatcher: Dispatcher<'static, 'static>,
     player: Entity,
     pub turn: u64,
+    pending_player_surcharge: i32,
+    instance_id: u64,
+    /// Turns left on a detect-life effect; while non-zero every monster on
+    /// the floor is drawn, seen or not.
+    detect_life_turns: u32,
+    levitate_turns: u32,
+    /// Turns the player has stood in a world their affinity isn't tuned to;
+    /// see [`ATTUNE_TURNS`].
+    attunement_turns: u32,
+    /// Where bursting monsters died since the last
+    /// [`take_cloud_bursts`](Self::take_cloud_bursts); the map owns clouds,
+    /// so the caller releases them.
+    cloud_bursts: Vec<Point>,
+    /// Corpses left by kills since the last
+    /// [`take_fallen`](Self::take_fallen), for the floor to lay down.
+    fallen: Vec<Corpse>,
 }
 
 pub struct AttackReport {
-    pub hit: String,
+    pub hit: CombatEvent,
     pub kill: Option<String>,
+    /// What a slain elite dropped.
+    pub loot: Option<String>,
+    /// The blow set the target alight.
+    pub ignited: bool,
+    /// The blow slowed the target.
+    pub chilled: bool,
 }
 
 #[derive(Clone)]
@@ -46,27 +95,87 @@ pub struct ConsumableMessage {
     pub lines: Vec<String>,
 }
 impl EcsWorld {
-    pub fn new(spawn: Point, floor: FloorId, world: World) -> Self {
+    pub fn new(spawn: Point, floor: FloorId, world: World, seed: u64, loadout: &Loadout) -> Self {
+        let mut specs_world = Self::empty_specs_world(seed);
+        let player = Self::spawn_player(&mut specs_world, spawn, floor, world, loadout);
+        Self::from_parts(specs_world, player, 0, 0)
+    }
+
+    /// A specs world with every component registered and the shared
+    /// resources inserted, but no entities yet.
+    fn empty_specs_world(seed: u64) -> SpecsWorld {
         let mut specs_world = SpecsWorld::new();
         Self::register_components(&mut specs_world);
-        specs_world.insert(RandomNumberGenerator::seeded(0x51ec5ead));
+        specs_world.insert(AiRng(RandomNumberGenerator::seeded(
+            RngStream::Ai.seed(seed),
+        )));
+        specs_world.insert(CombatRng(RandomNumberGenerator::seeded(
+            RngStream::Combat.seed(seed),
+        )));
         specs_world.insert(CombatLog::default());
-        let player = Self::spawn_player(&mut specs_world, spawn, floor, world);
+        specs_world.insert(DebugFlags::default());
+        specs_world.insert(FovAlgorithm::default());
+        specs_world.insert(CombatRules::default());
+        specs_world
+    }
+
+    fn from_parts(
+        specs_world: SpecsWorld,
+        player: Entity,
+        turn: u64,
+        pending_player_surcharge: i32,
+    ) -> Self {
         let dispatcher = DispatcherBuilder::new()
-            .with(EnergySystem::default(), "energy", &[])
-            .with(WanderSystem::default(), "wander", &[])
-            .with(MovementSystem::default(), "movement", &["wander"])
-            .with(FovSystem::default(), "fov", &["movement"])
+            .with(EnergySystem, "energy", &[])
+            // Monsters decide on what they can see now, so sight left dirty
+            // by a door or a spawn catches up before they choose.
+            .with(FovSystem, "sight", &["energy"])
+            .with(WanderSystem, "wander", &["sight"])
+            .with(MovementSystem, "movement", &["wander"])
+            .with(FovSystem, "fov", &["movement"])
             .build();
 
         Self {
             specs_world,
             dispatcher,
             player,
-            turn: 0,
+            turn,
+            pending_player_surcharge,
+            instance_id: NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed),
+            detect_life_turns: 0,
+            levitate_turns: 0,
+            attunement_turns: 0,
+            cloud_bursts: Vec::new(),
+            fallen: Vec::new(),
         }
     }
 
+    /// Distinguishes this world from any rebuilt one (new run, loaded save),
+    /// so RNG checks don't mistake a reseed for drift.
+    pub fn instance_id(&self) -> u64 {
+        self.instance_id
+    }
+
+    /// The next draw of every random stream, read from copies so the real
+    /// streams are left untouched. Equal fingerprints mean nothing has
+    /// drawn since.
+    pub fn rng_fingerprint(&self) -> u64 {
+        let mut ai = AiRng::clone(&self.specs_world.read_resource());
+        let mut combat = CombatRng::clone(&self.specs_world.read_resource());
+        ai.next_u64() ^ combat.next_u64().rotate_left(32)
+    }
+
+    /// Reseeds the combat stream so that `want`, rolling on a copy of it,
+    /// is satisfied; the real rolls that follow then come out the same.
+    #[cfg(test)]
+    pub fn pin_combat_rng(&mut self, want: impl Fn(&mut RandomNumberGenerator) -> bool) {
+        let rng = (0..)
+            .map(RandomNumberGenerator::seeded)
+            .find(|rng| want(&mut rng.clone()))
+            .unwrap();
+        self.specs_world.insert(CombatRng(rng));
+    }
+
     fn register_components(world: &mut SpecsWorld) {
         world.register::<Position>();
         world.register::<Renderable>();
@@ -79,8 +188,15 @@ impl EcsWorld {
         world.register::<Monster>();
         world.register::<MonsterBrain>();
         world.register::<MonsterTag>();
+        world.register::<Friendly>();
+        world.register::<Npc>();
         world.register::<CombatStats>();
         world.register::<Inventory>();
+        world.register::<Size>();
+        world.register::<Equipment>();
+        world.register::<Cursed>();
+        world.register::<Burning>();
+        world.register::<Slowed>();
     }
 
     fn spawn_player(
@@ -88,6 +204,7 @@ impl EcsWorld {
         spawn: Point,
         floor: FloorId,
         world_affinity: World,
+        loadout: &Loadout,
     ) -> Entity {
         world
             .create_entity()
@@ -109,52 +226,761 @@ impl EcsWorld {
             })
             .with(Actor {
                 energy: 0,
-                speed: 60,
+                speed: PLAYER_BASE_SPEED,
+                base_speed: PLAYER_BASE_SPEED,
             })
             .with(CombatStats {
-                max_hp: 20,
-                hp: 20,
-                power: 5,
-                defense: 1,
-            })
-            .with(WorldAffinity {
-                primary: world_affinity,
-                resist: None,
-                vulnerable: None,
+                max_hp: loadout.max_hp,
+                hp: loadout.max_hp,
+                power: loadout.power,
+                defense: loadout.defense,
             })
+            .with(WorldAffinity::attuned(world_affinity))
             .with(PlaneAttunements {
                 unlocked: vec![world_affinity],
                 perks: 0,
             })
             .with(PlayerTag)
             .with(Inventory {
-                slots: starter_consumables(world_affinity)
-                    .into_iter()
-                    .map(|template| InventorySlot {
-                        name: template.name.to_string(),
-                        description: template.description.to_string(),
-                        uses_remaining: 1,
-                        effect: match template.effect {
-                            ConsumableEffect::Heal { amount } => InventoryEffect::Heal { amount },
-                            ConsumableEffect::Cleanse => InventoryEffect::Cleanse,
-                            ConsumableEffect::Blink { range } => InventoryEffect::Blink { range },
-                            ConsumableEffect::Nova { damage, radius } => {
-                                InventoryEffect::Nova { damage, radius }
-                            }
-                        },
-                        color: template.color,
+                slots: loadout
+                    .pack(world_affinity)
+                    .iter()
+                    .take(PACK_SLOTS)
+                    .map(|(template, uses)| InventorySlot {
+                        uses_remaining: *uses,
+                        ..slot_from_template(template)
                     })
                     .collect(),
+                shards: 0,
+            })
+            .with(Equipment {
+                weapon: Some(GearPiece {
+                    name: PRISM_BLADE.name.to_string(),
+                    enchant: 0,
+                    reach: PRISM_BLADE.reach,
+                }),
+                armor: Some(GearPiece {
+                    name: "Woven Mantle".to_string(),
+                    enchant: 0,
+                    reach: 0,
+                }),
             })
             .build()
     }
 
+    /// Adds one use of `template` to the pack, stacking onto a slot with the
+    /// same name. Returns false when a new slot is needed but the quickbar
+    /// is full.
+    pub fn give_player_consumable(&mut self, template: &ConsumableTemplate) -> bool {
+        let mut inventories = self.specs_world.write_component::<Inventory>();
+        let Some(inventory) = inventories.get_mut(self.player) else {
+            return false;
+        };
+        if let Some(slot) = inventory
+            .slots
+            .iter_mut()
+            .find(|slot| slot.name == template.name)
+        {
+            slot.uses_remaining += 1;
+            return true;
+        }
+        if inventory.slots.len() >= PACK_SLOTS {
+            return false;
+        }
+        inventory.slots.push(slot_from_template(template));
+        true
+    }
+
+    /// Trades `offer.price` shards for one use of its consumable. Nothing is
+    /// spent when the player can't afford it or has no room to carry it.
+    pub fn buy_ware(&mut self, offer: &ShopOffer) -> Result<String, String> {
+        let shards = self.player_shards();
+        let name = offer.ware.name();
+        if shards < offer.price {
+            return Err(format!(
+                "The {name} costs {} shards; you hold {shards}.",
+                offer.price
+            ));
+        }
+        match &offer.ware {
+            Ware::Consumable(template) => {
+                if !self.give_player_consumable(template) {
+                    return Err(format!("No room in your pack for the {name}."));
+                }
+            }
+            Ware::Weapon(template) => {
+                let mut equipment = self.specs_world.write_component::<Equipment>();
+                let Some(weapon) = equipment
+                    .get_mut(self.player)
+                    .and_then(|equipment| equipment.weapon.as_mut())
+                else {
+                    return Err("You have nothing to trade in.".to_string());
+                };
+                if weapon.name == template.name {
+                    return Err(format!("You already wield a {name}."));
+                }
+                // The enchant is folded into the player's stats, so it
+                // carries over rather than being refunded.
+                weapon.name = template.name.to_string();
+                weapon.reach = template.reach;
+            }
+        }
+        if let Some(inventory) = self
+            .specs_world
+            .write_component::<Inventory>()
+            .get_mut(self.player)
+        {
+            inventory.shards -= offer.price;
+        }
+        Ok(format!("You buy a {name} for {} shards.", offer.price))
+    }
+
+    /// Spends one use from each of two pack slots (the same slot twice if it
+    /// holds two uses) to make the recipe's result. Nothing is consumed when
+    /// the pair doesn't combine or there's no room for the result.
+    pub fn craft_consumables(&mut self, first: usize, second: usize) -> Result<String, String> {
+        let slots = self
+            .specs_world
+            .read_component::<Inventory>()
+            .get(self.player)
+            .map(|inv| inv.slots.clone())
+            .unwrap_or_default();
+        let (Some(a), Some(b)) = (slots.get(first), slots.get(second)) else {
+            let missing = if slots.get(first).is_none() {
+                first
+            } else {
+                second
+            };
+            return Err(format!("Slot {} is empty.", missing + 1));
+        };
+        if first == second && a.uses_remaining < 2 {
+            return Err(format!("{} needs a second charge to combine.", a.name));
+        }
+        let template = recipe_for(a.effect.kind(), b.effect.kind())
+            .filter(|result| result.name != a.name && result.name != b.name)
+            .ok_or_else(|| "These resonances don't bind.".to_string())?;
+
+        let taken = if first == second { 2 } else { 1 };
+        let mut emptied: Vec<usize> = [first, second]
+            .into_iter()
+            .filter(|&idx| slots[idx].uses_remaining <= taken)
+            .collect();
+        emptied.dedup();
+        let stacks = slots
+            .iter()
+            .enumerate()
+            .any(|(idx, slot)| slot.name == template.name && !emptied.contains(&idx));
+        if !stacks && slots.len() - emptied.len() >= PACK_SLOTS {
+            return Err(format!("No room in your pack for the {}.", template.name));
+        }
+
+        {
+            let mut inventories = self.specs_world.write_component::<Inventory>();
+            if let Some(inventory) = inventories.get_mut(self.player) {
+                inventory.slots[first].uses_remaining -= 1;
+                inventory.slots[second].uses_remaining -= 1;
+                inventory.slots.retain(|slot| slot.uses_remaining > 0);
+            }
+        }
+        let inputs = if a.name == b.name {
+            format!("Two {} charges", a.name)
+        } else {
+            format!("{} and {}", a.name, b.name)
+        };
+        self.give_player_consumable(&template);
+        Ok(format!("{inputs} fuse into a {}.", template.name))
+    }
+
+    /// Every kill yields a prism shard; elites yield three.
+    fn grant_kill_shards(&mut self, elite: bool) {
+        self.grant_shards(if elite { 3 } else { 1 });
+    }
+
+    pub fn player_shards(&self) -> u32 {
+        self.specs_world
+            .read_component::<Inventory>()
+            .get(self.player)
+            .map(|inv| inv.shards)
+            .unwrap_or(0)
+    }
+
+    pub fn player_equipment(&self) -> Equipment {
+        self.specs_world
+            .read_component::<Equipment>()
+            .get(self.player)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Spends shards to try raising a worn piece one enchant level, rolling
+    /// the combat stream against [`enchant_chance`]. A failed roll still eats
+    /// the shards but leaves the gear as it was. `Err` means nothing was
+    /// attempted (no gear, at the cap, or too few shards).
+    pub fn enchant_gear(&mut self, slot: GearSlot) -> Result<String, String> {
+        let piece = self
+            .player_equipment()
+            .get(slot)
+            .cloned()
+            .ok_or_else(|| "Nothing is worn there.".to_string())?;
+        if piece.enchant >= ENCHANT_CAP {
+            return Err(format!("{} is already at +{ENCHANT_CAP}.", piece.name));
+        }
+        let cost = enchant_cost(piece.enchant);
+        let shards = self.player_shards();
+        if shards < cost {
+            return Err(format!(
+                "Enchanting {} needs {cost} shards; you hold {shards}.",
+                piece.name
+            ));
+        }
+        if let Some(inventory) = self
+            .specs_world
+            .write_component::<Inventory>()
+            .get_mut(self.player)
+        {
+            inventory.shards -= cost;
+        }
+        let roll = self.specs_world.write_resource::<CombatRng>().range(0, 100);
+        if roll >= enchant_chance(piece.enchant) {
+            return Ok(format!(
+                "The shards crumble; {} stays +{}.",
+                piece.name, piece.enchant
+            ));
+        }
+        let level = piece.enchant + 1;
+        if let Some(gear) = self
+            .specs_world
+            .write_component::<Equipment>()
+            .get_mut(self.player)
+            .and_then(|equipment| equipment.get_mut(slot))
+        {
+            gear.enchant = level;
+        }
+        if let Some(stats) = self
+            .specs_world
+            .write_component::<CombatStats>()
+            .get_mut(self.player)
+        {
+            match slot {
+                GearSlot::Weapon => stats.power += 1,
+                GearSlot::Armor => stats.defense += 1,
+            }
+        }
+        Ok(format!("{} flares and settles at +{level}.", piece.name))
+    }
+
+    /// Elites drop their layer's signature consumable straight into the pack.
+    fn claim_elite_loot(&mut self, name: &str, world: World) -> String {
+        let Some(template) = starter_consumables(world).into_iter().next() else {
+            return format!("{name} leaves nothing behind.");
+        };
+        if self.give_player_consumable(&template) {
+            format!("{name} leaves behind a {}.", template.name)
+        } else {
+            format!(
+                "{name} leaves behind a {}, but your pack is full.",
+                template.name
+            )
+        }
+    }
+
+    /// Resolves one turn. `WanderSystem` draws from the AI stream, so this
+    /// must only run when a turn actually happens; calling it on idle
+    /// frames would make seeded runs diverge with frame timing.
     pub fn advance(&mut self, layer: &MapLayer, floor: FloorId, world: World) {
+        let player_cost = ACTION_COST + std::mem::take(&mut self.pending_player_surcharge);
         let context = MovementContext::from_layer(layer, floor, world, self.player_point());
         self.specs_world.insert(context);
-        self.dispatcher.dispatch(&mut self.specs_world);
-        self.specs_world.maintain();
+        self.dispatch_pass(floor, world, player_cost);
+        // A slowed player leaves monsters with a banked spare action; spend it
+        // in a follow-up pass that grants no new energy. The map can't change
+        // between passes, so only the player's spot needs refreshing.
+        if self.monsters_ready(floor, world) {
+            let player_point = self.player_point();
+            self.specs_world
+                .write_resource::<MovementContext>()
+                .player_point = player_point;
+            self.dispatch_pass(floor, world, 0);
+        }
         self.turn = self.turn.wrapping_add(1);
+        self.phase_in_shifters(layer, floor, world);
+        self.tick_curse();
+        self.tick_burning(layer, floor, world);
+        self.tick_slow(floor, world);
+        self.tick_attunement(world);
+        if self.detect_life_turns > 0 {
+            self.detect_life_turns -= 1;
+            if self.detect_life_turns == 0 {
+                self.specs_world
+                    .write_resource::<CombatLog>()
+                    .push("Your lifesense fades.");
+            }
+        }
+        // The game announces the landing itself, since what the player
+        // lands in is decided by the map.
+        self.levitate_turns = self.levitate_turns.saturating_sub(1);
+    }
+
+    /// Retunes the player's affinity to `world` once they have stood in it
+    /// for [`ATTUNE_TURNS`]. Until then blows from the world they left are
+    /// still resisted, and its opposite's still cut deeper, so hopping
+    /// straight to the opposite world is a risk.
+    fn tick_attunement(&mut self, world: World) {
+        let attuned = self
+            .specs_world
+            .read_component::<WorldAffinity>()
+            .get(self.player)
+            .map(|affinity| affinity.primary);
+        if attuned == Some(world) {
+            self.attunement_turns = 0;
+            return;
+        }
+        self.attunement_turns += 1;
+        if self.attunement_turns < ATTUNE_TURNS {
+            return;
+        }
+        self.attunement_turns = 0;
+        let _ = self
+            .specs_world
+            .write_component::<WorldAffinity>()
+            .insert(self.player, WorldAffinity::attuned(world));
+        self.specs_world.write_resource::<CombatLog>().push(format!(
+            "You attune to {}; {} blows now cut deeper.",
+            world.as_str(),
+            world.opposite().as_str()
+        ));
+    }
+
+    /// The world the player's affinity is currently tuned to.
+    pub fn player_affinity(&self) -> Option<World> {
+        self.specs_world
+            .read_component::<WorldAffinity>()
+            .get(self.player)
+            .map(|affinity| affinity.primary)
+    }
+
+    pub fn detect_life_active(&self) -> bool {
+        self.detect_life_turns > 0
+    }
+
+    /// Turns of levitation left; zero when the player is on the ground.
+    pub fn levitate_turns(&self) -> u32 {
+        self.levitate_turns
+    }
+
+    /// Turns left on the player's curse, if cursed.
+    pub fn player_curse_turns(&self) -> Option<u32> {
+        self.specs_world
+            .read_component::<Cursed>()
+            .get(self.player)
+            .map(|curse| curse.turns_left)
+    }
+
+    /// Counts the curse down a turn, lifting it when it runs out.
+    fn tick_curse(&mut self) {
+        let mut curses = self.specs_world.write_component::<Cursed>();
+        let Some(curse) = curses.get_mut(self.player) else {
+            return;
+        };
+        curse.turns_left = curse.turns_left.saturating_sub(1);
+        if curse.turns_left == 0 {
+            curses.remove(self.player);
+            self.specs_world
+                .write_resource::<CombatLog>()
+                .push("The curse loosens its grip.");
+        }
+    }
+
+    /// Burns everything alight on the layer, the player included. Flames on
+    /// anything standing in water go out first; the rest tick down and
+    /// gutter out when their turns run out.
+    fn tick_burning(&mut self, layer: &MapLayer, floor: FloorId, world: World) {
+        let godmode = self.godmode();
+        let mut log = Vec::new();
+        let mut elite_kills = Vec::new();
+        let mut kills = 0;
+        {
+            let visible: HashSet<Point> = self.player_visible_tiles().into_iter().collect();
+            let entities = self.specs_world.entities();
+            let positions = self.specs_world.read_component::<Position>();
+            let monsters = self.specs_world.read_component::<Monster>();
+            let sizes = self.specs_world.read_component::<Size>();
+            let mut stats = self.specs_world.write_component::<CombatStats>();
+            let mut burning = self.specs_world.write_component::<Burning>();
+            let mut out = Vec::new();
+            for (entity, pos, burn, stat) in
+                (&entities, &positions, &mut burning, &mut stats).join()
+            {
+                if pos.floor != floor || pos.world != world {
+                    continue;
+                }
+                let is_player = entity == self.player;
+                let cells = footprint(pos.point, sizes.get(entity));
+                let seen = is_player || cells.iter().any(|cell| visible.contains(cell));
+                let name = monsters
+                    .get(entity)
+                    .map(|monster| monster.name.clone())
+                    .unwrap_or_default();
+                let in_water = cells.iter().any(|&cell| {
+                    layer
+                        .tile_at(cell)
+                        .is_some_and(|tile| tile.tag == Tile::TAG_WATER)
+                });
+                if in_water {
+                    out.push(entity);
+                    if is_player {
+                        log.push("The water hisses out your flames.".to_string());
+                    } else if seen {
+                        log.push(format!("{name}'s flames hiss out in the water."));
+                    }
+                    continue;
+                }
+                if !(is_player && godmode) {
+                    stat.hp = (stat.hp - burn.damage).max(0);
+                }
+                burn.turns_left = burn.turns_left.saturating_sub(1);
+                if is_player {
+                    if stat.hp == 0 {
+                        log.push("The flames consume your spectrum.".to_string());
+                    } else {
+                        log.push(format!("You burn for {}.", burn.damage));
+                    }
+                } else if stat.hp == 0 {
+                    if seen {
+                        log.push(format!("{name} burns to cinders."));
+                    }
+                    if let Some(monster) = monsters.get(entity) {
+                        self.fallen.push(Corpse::fresh(pos.point, pos.world, &name));
+                        if monster.bursts {
+                            self.cloud_bursts.push(pos.point);
+                        }
+                        if monster.modifier.is_some() {
+                            elite_kills.push(name.clone());
+                        } else {
+                            kills += 1;
+                        }
+                    }
+                    let _ = entities.delete(entity);
+                    continue;
+                }
+                if burn.turns_left == 0 {
+                    out.push(entity);
+                    if is_player {
+                        log.push("The flames on you burn out.".to_string());
+                    }
+                }
+            }
+            for entity in out {
+                burning.remove(entity);
+            }
+        }
+        self.specs_world.maintain();
+        for _ in 0..kills {
+            self.grant_kill_shards(false);
+        }
+        for name in elite_kills {
+            self.grant_kill_shards(true);
+            log.push(self.claim_elite_loot(&name, world));
+        }
+        let mut combat_log = self.specs_world.write_resource::<CombatLog>();
+        for line in log {
+            combat_log.push(line);
+        }
+    }
+
+    /// Counts every chill on the layer down a turn, thawing the ones that
+    /// run out.
+    fn tick_slow(&mut self, floor: FloorId, world: World) {
+        let visible: HashSet<Point> = self.player_visible_tiles().into_iter().collect();
+        let entities = self.specs_world.entities();
+        let positions = self.specs_world.read_component::<Position>();
+        let monsters = self.specs_world.read_component::<Monster>();
+        let mut slowed = self.specs_world.write_component::<Slowed>();
+        let mut combat_log = self.specs_world.write_resource::<CombatLog>();
+        let mut thawed = Vec::new();
+        for (entity, pos, chill) in (&entities, &positions, &mut slowed).join() {
+            if pos.floor != floor || pos.world != world {
+                continue;
+            }
+            chill.turns_left = chill.turns_left.saturating_sub(1);
+            if chill.turns_left > 0 {
+                continue;
+            }
+            thawed.push(entity);
+            if entity == self.player {
+                combat_log.push("The chill leaves your limbs.");
+            } else if let Some(monster) = monsters.get(entity)
+                && visible.contains(&pos.point)
+            {
+                combat_log.push(format!("{} thaws.", monster.name));
+            }
+        }
+        for entity in thawed {
+            slowed.remove(entity);
+        }
+    }
+
+    /// Chills every monster on the layer with a cell within `radius` of
+    /// `origin`.
+    fn freeze_around(
+        &mut self,
+        origin: Point,
+        radius: i32,
+        turns: u32,
+        floor: FloorId,
+        world: World,
+    ) -> Vec<String> {
+        let entities = self.specs_world.entities();
+        let positions = self.specs_world.read_component::<Position>();
+        let monsters = self.specs_world.read_component::<Monster>();
+        let sizes = self.specs_world.read_component::<Size>();
+        let mut slowed = self.specs_world.write_component::<Slowed>();
+        let mut log = Vec::new();
+        for (entity, pos, monster) in (&entities, &positions, &monsters).join() {
+            if pos.floor != floor || pos.world != world {
+                continue;
+            }
+            let nearest = sizes
+                .get(entity)
+                .copied()
+                .unwrap_or_default()
+                .nearest_cell(pos.point, origin);
+            if DistanceAlg::Pythagoras.distance2d(origin, nearest) > radius as f32 {
+                continue;
+            }
+            Slowed::chill(&mut slowed, entity, turns);
+            log.push(format!("{} is chilled to a crawl.", monster.name));
+        }
+        if log.is_empty() {
+            log.push("Frost rimes the floor around you, catching nothing.".to_string());
+        }
+        log
+    }
+
+    /// Every timed status on `entity`; levitation only ever applies to the
+    /// player.
+    pub fn status_effects(&self, entity: Entity) -> StatusEffects {
+        StatusEffects {
+            burning: self
+                .specs_world
+                .read_component::<Burning>()
+                .get(entity)
+                .map_or(0, |burning| burning.turns_left),
+            slowed: self
+                .specs_world
+                .read_component::<Slowed>()
+                .get(entity)
+                .map_or(0, |slowed| slowed.turns_left),
+            cursed: self
+                .specs_world
+                .read_component::<Cursed>()
+                .get(entity)
+                .map_or(0, |curse| curse.turns_left),
+            levitating: if entity == self.player {
+                self.levitate_turns
+            } else {
+                0
+            },
+        }
+    }
+
+    /// Where each afflicted monster on the layer stands and what ails it.
+    pub fn monster_statuses(&self, floor: FloorId, world: World) -> Vec<(Point, StatusEffects)> {
+        let entities = self.specs_world.entities();
+        let positions = self.specs_world.read_component::<Position>();
+        let tags = self.specs_world.read_component::<MonsterTag>();
+        (&entities, &positions, &tags)
+            .join()
+            .filter(|(_, pos, _)| pos.floor == floor && pos.world == world)
+            .map(|(entity, pos, _)| (pos.point, self.status_effects(entity)))
+            .filter(|(_, effects)| !effects.is_empty())
+            .collect()
+    }
+
+    /// Every timed status on the player, for the HUD.
+    pub fn player_status_effects(&self) -> StatusEffects {
+        self.status_effects(self.player)
+    }
+
+    /// Every cell covered by something burning on the layer, for the
+    /// flame tint.
+    pub fn burning_cells(&self, floor: FloorId, world: World) -> HashSet<Point> {
+        self.cells_with::<Burning>(floor, world)
+    }
+
+    /// Every cell covered by something slowed on the layer, for the chill
+    /// tint.
+    pub fn slowed_cells(&self, floor: FloorId, world: World) -> HashSet<Point> {
+        self.cells_with::<Slowed>(floor, world)
+    }
+
+    fn cells_with<C: Component>(&self, floor: FloorId, world: World) -> HashSet<Point> {
+        let entities = self.specs_world.entities();
+        let positions = self.specs_world.read_component::<Position>();
+        let marked = self.specs_world.read_component::<C>();
+        let sizes = self.specs_world.read_component::<Size>();
+        (&entities, &positions, &marked)
+            .join()
+            .filter(|(_, pos, _)| pos.floor == floor && pos.world == world)
+            .flat_map(|(entity, pos, _)| footprint(pos.point, sizes.get(entity)))
+            .collect()
+    }
+
+    /// One turn of searching beside a hidden door; `chance` is a percent.
+    pub fn roll_search(&mut self, chance: i32) -> bool {
+        self.specs_world.write_resource::<CombatRng>().range(0, 100) < chance
+    }
+
+    /// While the player is cursed, one roll in three claims another tile of
+    /// `floor` the player can't currently see. Returns whether one spread.
+    pub fn spread_curse(&mut self, floor: &mut WorldFloor) -> bool {
+        if self.player_curse_turns().is_none() {
+            return false;
+        }
+        let visible: HashSet<Point> = self.player_visible_tiles().into_iter().collect();
+        let frontier = floor.curse_frontier(&visible);
+        if frontier.is_empty() {
+            return false;
+        }
+        let mut rng = self.specs_world.write_resource::<CombatRng>();
+        if rng.range(0, 3) != 0 {
+            return false;
+        }
+        let idx = rng.range(0, frontier.len() as i32) as usize;
+        floor.cursed.insert(frontier[idx]);
+        true
+    }
+
+    fn dispatch_pass(&mut self, floor: FloorId, world: World, player_cost: i32) {
+        self.specs_world.insert(TurnClock {
+            player_cost,
+            turn: self.turn,
+        });
+        self.dispatcher.dispatch(&self.specs_world);
+        self.clear_slain_monsters(floor, world);
+        self.specs_world.maintain();
+    }
+
+    /// Removes monsters on the layer that other monsters killed this pass
+    /// (see [`CombatRules::infighting`]), leaving their corpses and bursts
+    /// behind. The player earns no shards for them.
+    fn clear_slain_monsters(&mut self, floor: FloorId, world: World) {
+        let entities = self.specs_world.entities();
+        let positions = self.specs_world.read_component::<Position>();
+        let monsters = self.specs_world.read_component::<Monster>();
+        let stats = self.specs_world.read_component::<CombatStats>();
+        for (entity, pos, monster, stat) in (&entities, &positions, &monsters, &stats).join() {
+            if pos.floor != floor || pos.world != world || stat.hp > 0 {
+                continue;
+            }
+            self.fallen
+                .push(Corpse::fresh(pos.point, pos.world, &monster.name));
+            if monster.bursts {
+                self.cloud_bursts.push(pos.point);
+            }
+            let _ = entities.delete(entity);
+        }
+    }
+
+    /// Brings phase shifters on the floor's other layers over to `world`
+    /// when their cadence comes round and the player would be in sight from
+    /// where they stand. Each lands on its own spot, or beside it when that
+    /// is blocked, and fights by the layer's rules from then on.
+    fn phase_in_shifters(&mut self, layer: &MapLayer, floor: FloorId, world: World) {
+        let visible: HashSet<Point> = self.player_visible_tiles().into_iter().collect();
+        let player = self.player_point();
+        let hunters: Vec<(Entity, Position)> = {
+            let entities = self.specs_world.entities();
+            let positions = self.specs_world.read_component::<Position>();
+            let brains = self.specs_world.read_component::<MonsterBrain>();
+            let sizes = self.specs_world.read_component::<Size>();
+            (&entities, &positions, &brains)
+                .join()
+                .filter(|(entity, pos, brain)| {
+                    brain.phase_shifter.is_some_and(|shifter| {
+                        self.turn.is_multiple_of(u64::from(shifter.cadence.max(1)))
+                    }) && pos.floor == floor
+                        && pos.world != world
+                        && !sizes.contains(*entity)
+                        && visible.contains(&pos.point)
+                        && DistanceAlg::Pythagoras.distance2d(pos.point, player)
+                            <= brain.aggro_radius
+                })
+                .map(|(entity, pos, _)| (entity, pos.clone()))
+                .collect()
+        };
+        for (entity, from) in hunters {
+            let landing = std::iter::once(from.point)
+                .chain(
+                    [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                        .map(|(dx, dy)| Point::new(from.point.x + dx, from.point.y + dy)),
+                )
+                .find(|&cell| {
+                    cell != player
+                        && layer.is_walkable(cell)
+                        && self.entity_at(cell, floor, world).is_none()
+                });
+            let Some(landing) = landing else {
+                continue;
+            };
+            if let Some(pos) = self
+                .specs_world
+                .write_component::<Position>()
+                .get_mut(entity)
+            {
+                pos.point = landing;
+                pos.world = world;
+            }
+            if let Some(viewshed) = self
+                .specs_world
+                .write_component::<Viewshed>()
+                .get_mut(entity)
+            {
+                viewshed.dirty = true;
+            }
+            if let Some(monster) = self.specs_world.read_component::<Monster>().get(entity) {
+                self.specs_world.write_resource::<CombatLog>().push(format!(
+                    "{} phases in from {}!",
+                    monster.name,
+                    from.world.as_str()
+                ));
+            }
+        }
+    }
+
+    fn monsters_ready(&self, floor: FloorId, world: World) -> bool {
+        let actors = self.specs_world.read_component::<Actor>();
+        let positions = self.specs_world.read_component::<Position>();
+        let monsters = self.specs_world.read_component::<MonsterTag>();
+        (&actors, &positions, &monsters)
+            .join()
+            .any(|(actor, pos, _)| {
+                pos.floor == floor && pos.world == world && actor.energy >= ACTION_COST
+            })
+    }
+
+    /// Makes the player's next action cost `extra` energy beyond a normal
+    /// action, handing monsters proportionally more time to act.
+    pub fn charge_player_energy(&mut self, extra: i32) {
+        self.pending_player_surcharge += extra.max(0);
+    }
+
+    /// Re-derives the player's speed from its base plus a world modifier.
+    pub fn apply_player_speed_modifier(&mut self, modifier: i32) -> i32 {
+        let mut actors = self.specs_world.write_component::<Actor>();
+        let Some(actor) = actors.get_mut(self.player) else {
+            return PLAYER_BASE_SPEED;
+        };
+        actor.speed = (actor.base_speed + modifier).max(ACTION_COST / 4);
+        actor.speed
+    }
+
+    pub fn player_speed(&self) -> i32 {
+        self.specs_world
+            .read_component::<Actor>()
+            .get(self.player)
+            .map(|actor| actor.speed)
+            .unwrap_or(PLAYER_BASE_SPEED)
     }
 
     pub fn queue_player_step(&mut self, delta: Point) {
@@ -174,23 +1000,7 @@ impl EcsWorld {
         floor: FloorId,
         world: World,
     ) -> Option<Vec<String>> {
-        let (name, effect, remove_slot) = {
-            let mut inventories = self.specs_world.write_component::<Inventory>();
-            let inventory = inventories.get_mut(self.player)?;
-            if slot_index >= inventory.slots.len() {
-                return None;
-            }
-            let slot = &mut inventory.slots[slot_index];
-            if slot.uses_remaining <= 0 {
-                return None;
-            }
-            slot.uses_remaining -= 1;
-            (
-                slot.name.clone(),
-                slot.effect.clone(),
-                slot.uses_remaining <= 0,
-            )
-        };
+        let (name, effect, remove_slot) = self.spend_charge(slot_index)?;
 
         let mut log = vec![format!("Activated {name}")];
         match effect {
@@ -208,79 +1018,447 @@ impl EcsWorld {
                 }
             }
             InventoryEffect::Cleanse => {
-                log.push("Resonance cleansed of spectral grime.".to_string());
+                let lifted = self
+                    .specs_world
+                    .write_component::<Cursed>()
+                    .remove(self.player)
+                    .is_some();
+                let doused = self
+                    .specs_world
+                    .write_component::<Burning>()
+                    .remove(self.player)
+                    .is_some();
+                // The curse's claim on the floor goes with it.
+                let cleared = dungeon
+                    .floor_mut(floor)
+                    .map(|floor| std::mem::take(&mut floor.cursed).len())
+                    .unwrap_or(0);
+                if doused {
+                    log.push("The flames on you gutter out.".to_string());
+                }
+                if lifted {
+                    log.push("The curse unravels; its threads fall still.".to_string());
+                } else {
+                    log.push("Resonance cleansed of spectral grime.".to_string());
+                }
+                if cleared > 0 {
+                    log.push("The violet stain fades from the ground.".to_string());
+                }
             }
             InventoryEffect::Blink { range } => {
-                if let Some(dest) = self.blink_destination(range, dungeon, floor, world) {
+                let bonus = rule_for(world).blink_bonus;
+                if bonus > 0 {
+                    log.push(format!("The mindstorm stretches your blink by {bonus}."));
+                }
+                if let Some(dest) = self.blink_destination(range + bonus, dungeon, floor, world) {
                     self.set_player_position(dest, floor, world);
                     log.push(format!("Blink to {},{}", dest.x, dest.y));
                 } else {
                     log.push("Blink fizzles; nowhere to anchor.".to_string());
                 }
             }
-            InventoryEffect::Nova { damage, radius } => {
-                log.extend(self.spectral_nova(damage, radius, floor, world));
+            InventoryEffect::Nova {
+                damage,
+                radius,
+                burns,
+            } => {
+                let origin = self.player_point();
+                log.extend(self.spectral_nova(origin, damage, radius, burns, floor, &[world]));
+                self.specs_world.maintain();
+            }
+            InventoryEffect::Detonation { damage, radius } => {
+                let origin = self.player_point();
+                log.extend(self.spectral_nova(origin, damage, radius, false, floor, &SPECTRUM));
+                self.specs_world.maintain();
+            }
+            InventoryEffect::DetectLife { turns } => {
+                self.detect_life_turns = self.detect_life_turns.max(turns);
+                log.push(format!(
+                    "Every heartbeat on the floor glows through the walls for {turns} turns."
+                ));
+            }
+            InventoryEffect::Freeze { radius, turns } => {
+                let origin = self.player_point();
+                log.extend(self.freeze_around(origin, radius, turns, floor, world));
+            }
+            InventoryEffect::Levitate { turns } => {
+                self.levitate_turns = self.levitate_turns.max(turns);
+                log.push(format!("You rise off the ground for {turns} turns."));
+            }
+            InventoryEffect::AcidCloud { turns } => {
+                let origin = self.player_point();
+                if release_acid(dungeon, floor, origin, turns, false) > 0 {
+                    log.push("Acid billows out around you.".to_string());
+                } else {
+                    log.push("The acid spatters uselessly against the walls.".to_string());
+                }
             }
+            InventoryEffect::Resonate => log.push(self.resonate()),
         }
 
         if remove_slot {
-            let mut inventories = self.specs_world.write_component::<Inventory>();
-            if let Some(inv) = inventories.get_mut(self.player) {
-                if slot_index < inv.slots.len() {
-                    inv.slots.remove(slot_index);
-                }
-            }
+            self.drop_slot(slot_index);
         }
 
         Some(log)
     }
 
-    pub fn entity_at(&self, point: Point, floor: FloorId, world: World) -> Option<Entity> {
-        let entities = self.specs_world.entities();
-        let positions = self.specs_world.read_component::<Position>();
-        for (entity, pos) in (&entities, &positions).join() {
-            if pos.floor == floor && pos.world == world && pos.point == point {
-                return Some(entity);
-            }
-        }
-        None
-    }
-
-    pub fn player_attack(
+    /// Throws one charge of `slot_index` toward `target`. The flask flies
+    /// along a straight line and stops on the first creature it meets, or
+    /// short of the first wall; area effects (novas, frost, acid) burst
+    /// where it lands and everything else is wasted. `None` when the slot
+    /// is empty.
+    pub fn throw_consumable(
         &mut self,
-        target_point: Point,
+        slot_index: usize,
+        target: Point,
+        dungeon: &mut Dungeon,
         floor: FloorId,
         world: World,
-    ) -> Option<AttackReport> {
-        let target = self.entity_at(target_point, floor, world)?;
-        if target == self.player {
-            return None;
-        }
+    ) -> Option<Vec<String>> {
+        let layer = dungeon.active_layer(floor, world)?;
+        let landing = self.throw_landing(target, layer, floor, world);
+        let (name, effect, remove_slot) = self.spend_charge(slot_index)?;
+
+        let mut log = vec![format!(
+            "You throw the {name}; it lands at {},{}.",
+            landing.x, landing.y
+        )];
+        match effect {
+            InventoryEffect::Nova {
+                damage,
+                radius,
+                burns,
+            } => {
+                log.extend(self.spectral_nova(landing, damage, radius, burns, floor, &[world]));
+                self.specs_world.maintain();
+            }
+            InventoryEffect::Detonation { damage, radius } => {
+                log.extend(self.spectral_nova(landing, damage, radius, false, floor, &SPECTRUM));
+                self.specs_world.maintain();
+            }
+            InventoryEffect::Freeze { radius, turns } => {
+                log.extend(self.freeze_around(landing, radius, turns, floor, world));
+            }
+            InventoryEffect::AcidCloud { turns } => {
+                if release_acid(dungeon, floor, landing, turns, true) > 0 {
+                    log.push("Acid billows out where it shatters.".to_string());
+                } else {
+                    log.push("The acid spatters uselessly against the walls.".to_string());
+                }
+            }
+            _ => log.push(format!("The {name} shatters; its magic is wasted.")),
+        }
+
+        if remove_slot {
+            self.drop_slot(slot_index);
+        }
+
+        Some(log)
+    }
+
+    /// Where a throw at `target` comes down: the first tile along the line
+    /// holding a creature, the last open tile before a wall, or `target`.
+    fn throw_landing(
+        &self,
+        target: Point,
+        layer: &MapLayer,
+        floor: FloorId,
+        world: World,
+    ) -> Point {
+        let origin = self.player_point();
+        let mut landing = origin;
+        for point in line2d_bresenham(origin, target).into_iter().skip(1) {
+            if !layer.is_walkable(point) {
+                break;
+            }
+            landing = point;
+            if self.entity_at(point, floor, world).is_some() {
+                break;
+            }
+        }
+        landing
+    }
+
+    /// Gives one use back to the pack slot with the fewest left, picking at
+    /// random between ties. Other Resonance Shards never count.
+    fn resonate(&mut self) -> String {
+        let mut inventories = self.specs_world.write_component::<Inventory>();
+        let Some(inventory) = inventories.get_mut(self.player) else {
+            return "The shard hums, but finds nothing to resonate with.".to_string();
+        };
+        let emptiest = emptiest_slots(inventory);
+        if emptiest.is_empty() {
+            return "The shard hums, but finds nothing to resonate with.".to_string();
+        }
+        let pick = self
+            .specs_world
+            .write_resource::<CombatRng>()
+            .range(0, emptiest.len() as i32) as usize;
+        let slot = &mut inventory.slots[emptiest[pick]];
+        slot.uses_remaining += 1;
+        format!(
+            "The {} resonates back to {} uses.",
+            slot.name, slot.uses_remaining
+        )
+    }
+
+    /// Gives one use back to the pack's emptiest slot, the first of them on
+    /// a tie, so a new depth always tops something up without a roll.
+    /// Returns the log line, or `None` when nothing could take it.
+    pub fn recharge_emptiest_slot(&mut self) -> Option<String> {
+        let mut inventories = self.specs_world.write_component::<Inventory>();
+        let inventory = inventories.get_mut(self.player)?;
+        let idx = *emptiest_slots(inventory).first()?;
+        let slot = &mut inventory.slots[idx];
+        slot.uses_remaining += 1;
+        Some(format!(
+            "Your {} recharges to {} uses.",
+            slot.name, slot.uses_remaining
+        ))
+    }
+
+    /// Takes one use from `slot_index`, returning the slot's name, its
+    /// effect, and whether that was the last use.
+    fn spend_charge(&mut self, slot_index: usize) -> Option<(String, InventoryEffect, bool)> {
+        let mut inventories = self.specs_world.write_component::<Inventory>();
+        let inventory = inventories.get_mut(self.player)?;
+        let slot = inventory.slots.get_mut(slot_index)?;
+        if slot.uses_remaining <= 0 {
+            return None;
+        }
+        slot.uses_remaining -= 1;
+        Some((
+            slot.name.clone(),
+            slot.effect.clone(),
+            slot.uses_remaining <= 0,
+        ))
+    }
 
+    fn drop_slot(&mut self, slot_index: usize) {
+        let mut inventories = self.specs_world.write_component::<Inventory>();
+        if let Some(inv) = inventories.get_mut(self.player)
+            && slot_index < inv.slots.len()
+        {
+            inv.slots.remove(slot_index);
+        }
+    }
+
+    /// The entity covering `point`, counting every cell of a large
+    /// entity's footprint.
+    pub fn entity_at(&self, point: Point, floor: FloorId, world: World) -> Option<Entity> {
         let entities = self.specs_world.entities();
-        let mut stats = self.specs_world.write_component::<CombatStats>();
-        let monsters = self.specs_world.read_component::<Monster>();
+        let positions = self.specs_world.read_component::<Position>();
+        let sizes = self.specs_world.read_component::<Size>();
+        for (entity, pos) in (&entities, &positions).join() {
+            if pos.floor == floor
+                && pos.world == world
+                && footprint(pos.point, sizes.get(entity)).contains(&point)
+            {
+                return Some(entity);
+            }
+        }
+        None
+    }
 
-        let attacker_stats = stats.get(self.player)?.clone();
-        let target_stats = stats.get_mut(target)?;
-        let damage = (attacker_stats.power - target_stats.defense).max(1);
-        target_stats.hp = target_stats.hp.saturating_sub(damage);
+    pub fn is_friendly(&self, entity: Entity) -> bool {
+        self.specs_world
+            .read_component::<Friendly>()
+            .contains(entity)
+    }
 
-        let name = monsters
-            .get(target)
+    /// Trades tiles between the player and `other`, returning the other's name.
+    pub fn swap_player_with(&mut self, other: Entity) -> Option<String> {
+        let player = self.player_position();
+        let other_point = {
+            let mut positions = self.specs_world.write_component::<Position>();
+            let other_pos = positions.get_mut(other)?;
+            let previous = other_pos.point;
+            other_pos.point = player.point;
+            previous
+        };
+        if let Some(vs) = self
+            .specs_world
+            .write_component::<Viewshed>()
+            .get_mut(other)
+        {
+            vs.dirty = true;
+        }
+        self.set_player_position(other_point, player.floor, player.world);
+        let name = self
+            .specs_world
+            .read_component::<Monster>()
+            .get(other)
             .map(|m| m.name.clone())
-            .unwrap_or_else(|| "foe".to_string());
+            .or_else(|| {
+                self.specs_world
+                    .read_component::<Npc>()
+                    .get(other)
+                    .map(|npc| npc.name.clone())
+            })
+            .unwrap_or_else(|| "your companion".to_string());
+        Some(name)
+    }
 
-        let mut kill = None;
-        if target_stats.hp == 0 {
-            kill = Some(format!("{name} collapses into specter dust."));
-            let _ = entities.delete(target);
+    /// The first NPC standing next to the player on the player's layer.
+    pub fn adjacent_npc(&self) -> Option<Npc> {
+        let player = self.player_position();
+        let positions = self.specs_world.read_component::<Position>();
+        let npcs = self.specs_world.read_component::<Npc>();
+        (&positions, &npcs)
+            .join()
+            .find(|(pos, _)| {
+                pos.floor == player.floor
+                    && pos.world == player.world
+                    && pos.point != player.point
+                    && (pos.point.x - player.point.x).abs() <= 1
+                    && (pos.point.y - player.point.y).abs() <= 1
+            })
+            .map(|(_, npc)| npc.clone())
+    }
+
+    pub fn player_attack(
+        &mut self,
+        target_point: Point,
+        floor: FloorId,
+        world: World,
+    ) -> Option<AttackReport> {
+        let target = self.entity_at(target_point, floor, world)?;
+        if target == self.player {
+            return None;
         }
 
-        Some(AttackReport {
-            hit: format!("You strike {name} for {damage}"),
-            kill,
-        })
+        let (mut report, elite) = {
+            let entities = self.specs_world.entities();
+            let mut stats = self.specs_world.write_component::<CombatStats>();
+            let monsters = self.specs_world.read_component::<Monster>();
+            let positions = self.specs_world.read_component::<Position>();
+
+            let attacker_stats = stats.get(self.player)?.clone();
+            let target_stats = stats.get_mut(target)?;
+            let damage = (attacker_stats.power - target_stats.defense).max(1);
+            target_stats.hp = (target_stats.hp - damage).max(0);
+
+            let name = monsters
+                .get(target)
+                .map(|m| m.name.clone())
+                .unwrap_or_else(|| "foe".to_string());
+            let elite = monsters.get(target).is_some_and(|m| m.modifier.is_some());
+
+            let mut kill = None;
+            let mut ignited = false;
+            if target_stats.hp > 0
+                && world == World::Red
+                && self.specs_world.write_resource::<CombatRng>().range(0, 100) < BURN_CHANCE
+            {
+                ignited = Burning::ignite(&mut self.specs_world.write_component(), target);
+            }
+            let mut chilled = false;
+            if target_stats.hp > 0
+                && world == World::Blue
+                && self.specs_world.write_resource::<CombatRng>().range(0, 100) < SLOW_CHANCE
+            {
+                chilled =
+                    Slowed::chill(&mut self.specs_world.write_component(), target, SLOW_TURNS);
+            }
+            if target_stats.hp == 0 {
+                kill = Some(format!("{name} collapses into specter dust."));
+                if let Some(pos) = positions.get(target) {
+                    self.fallen.push(Corpse::fresh(pos.point, pos.world, &name));
+                    if monsters.get(target).is_some_and(|m| m.bursts) {
+                        self.cloud_bursts.push(pos.point);
+                    }
+                }
+                let _ = entities.delete(target);
+            }
+
+            let report = AttackReport {
+                hit: CombatEvent {
+                    kind: CombatEventKind::PlayerStrike,
+                    other: name,
+                    amount: damage,
+                },
+                kill,
+                loot: None,
+                ignited,
+                chilled,
+            };
+            (report, elite)
+        };
+
+        // Flush deletions now so the corpse stops occupying its tile before
+        // the player steps into it this turn.
+        if report.kill.is_some() {
+            self.specs_world.maintain();
+            self.grant_kill_shards(elite);
+            if elite {
+                report.loot = Some(self.claim_elite_loot(&report.hit.other, world));
+            }
+        }
+        Some(report)
+    }
+
+    /// Thrusts the wielded weapon along cardinal `delta`, striking the
+    /// first monster within its reach. Walls and closed doors stop the
+    /// thrust, and so does any friendly in the way.
+    pub fn attack_along(
+        &mut self,
+        delta: Point,
+        layer: &MapLayer,
+        floor: FloorId,
+        world: World,
+    ) -> Result<AttackReport, String> {
+        let weapon = self.player_equipment().weapon.unwrap_or(GearPiece {
+            name: "bare hands".to_string(),
+            enchant: 0,
+            reach: 1,
+        });
+        if weapon.reach() < 2 {
+            return Err(format!(
+                "Your {} can't reach past arm's length.",
+                weapon.name
+            ));
+        }
+        if delta.x.abs() + delta.y.abs() != 1 {
+            return Err("A reach attack only thrusts straight along a row or column.".to_string());
+        }
+        let origin = self.player_point();
+        for step in 1..=weapon.reach() {
+            let point = Point::new(origin.x + delta.x * step, origin.y + delta.y * step);
+            if !layer.is_walkable(point) {
+                return Err(format!("Your {} jars against the wall.", weapon.name));
+            }
+            if let Some(entity) = self.entity_at(point, floor, world) {
+                if self.is_friendly(entity) {
+                    return Err("You pull the thrust short of a friend.".to_string());
+                }
+                return self
+                    .player_attack(point, floor, world)
+                    .ok_or_else(|| "Your thrust finds nothing to bite.".to_string());
+            }
+        }
+        Err(format!("Your {} thrusts into empty air.", weapon.name))
+    }
+
+    /// Strikes whatever stands on the tile one `delta` away without ever
+    /// stepping there; nothing is queued, so an empty tile just reports
+    /// the miss.
+    pub fn attack_adjacent(
+        &mut self,
+        delta: Point,
+        floor: FloorId,
+        world: World,
+    ) -> Result<AttackReport, String> {
+        let point = self.player_point() + delta;
+        match self.entity_at(point, floor, world) {
+            Some(entity) if self.is_friendly(entity) => {
+                Err("You hold back the blow; that's a friend.".to_string())
+            }
+            Some(_) => self
+                .player_attack(point, floor, world)
+                .ok_or_else(|| "Your blow finds nothing to bite.".to_string()),
+            None => Err("You strike at empty air.".to_string()),
+        }
     }
 
     pub fn player_visible_tiles(&self) -> Vec<Point> {
@@ -291,6 +1469,85 @@ impl EcsWorld {
             .unwrap_or_default()
     }
 
+    /// Restores `percent` of the player's max HP (at least 1 when any is
+    /// granted) and returns the HP actually gained.
+    pub fn restore_player_fraction(&mut self, percent: i32) -> i32 {
+        if percent <= 0 {
+            return 0;
+        }
+        let mut stats = self.specs_world.write_component::<CombatStats>();
+        let Some(player_stats) = stats.get_mut(self.player) else {
+            return 0;
+        };
+        let amount = (player_stats.max_hp * percent / 100).max(1);
+        let before = player_stats.hp;
+        player_stats.hp = (player_stats.hp + amount).min(player_stats.max_hp);
+        player_stats.hp - before
+    }
+
+    /// Deals `amount` to the player outside of melee (traps, hazards) and
+    /// returns the damage taken; godmode blocks it.
+    pub fn damage_player(&mut self, amount: i32) -> i32 {
+        if self.godmode() {
+            return 0;
+        }
+        let mut stats = self.specs_world.write_component::<CombatStats>();
+        let Some(player_stats) = stats.get_mut(self.player) else {
+            return 0;
+        };
+        let before = player_stats.hp;
+        player_stats.hp = (player_stats.hp - amount.max(0)).max(0);
+        before - player_stats.hp
+    }
+
+    pub fn set_godmode(&mut self, enabled: bool) {
+        self.specs_world.write_resource::<DebugFlags>().godmode = enabled;
+    }
+
+    pub fn godmode(&self) -> bool {
+        self.specs_world.read_resource::<DebugFlags>().godmode
+    }
+
+    /// Switches the field-of-view routine; every viewshed recomputes with it
+    /// on the next pass.
+    pub fn set_fov_algorithm(&mut self, algorithm: FovAlgorithm) {
+        *self.specs_world.write_resource::<FovAlgorithm>() = algorithm;
+        self.mark_viewsheds_dirty();
+    }
+
+    pub fn fov_algorithm(&self) -> FovAlgorithm {
+        *self.specs_world.read_resource::<FovAlgorithm>()
+    }
+
+    pub fn set_infighting(&mut self, enabled: bool) {
+        self.specs_world.write_resource::<CombatRules>().infighting = enabled;
+    }
+
+    pub fn infighting(&self) -> bool {
+        self.specs_world.read_resource::<CombatRules>().infighting
+    }
+
+    pub fn set_friendly_fire(&mut self, enabled: bool) {
+        self.specs_world
+            .write_resource::<CombatRules>()
+            .friendly_fire = enabled;
+    }
+
+    pub fn friendly_fire(&self) -> bool {
+        self.specs_world
+            .read_resource::<CombatRules>()
+            .friendly_fire
+    }
+
+    /// Forces every viewshed to recompute next pass, e.g. after a door
+    /// swings and changes what blocks sight.
+    pub fn mark_viewsheds_dirty(&mut self) {
+        let mut viewsheds = self.specs_world.write_component::<Viewshed>();
+        for viewshed in (&mut viewsheds).join() {
+            viewshed.dirty = true;
+        }
+    }
+
     pub fn player_stats(&self) -> Option<CombatStats> {
         let stats = self.specs_world.read_component::<CombatStats>();
         stats.get(self.player).cloned()
@@ -310,20 +1567,59 @@ impl EcsWorld {
             .unwrap_or_default()
     }
 
-    pub fn drain_combat_log(&mut self) -> Vec<String> {
+    pub fn drain_combat_log(&mut self) -> Vec<LogLine> {
         let mut log = self.specs_world.write_resource::<CombatLog>();
         std::mem::take(&mut log.entries)
     }
 
+    /// Spawns `template` with its top-left cell at `point`. Callers must
+    /// check the whole footprint is clear first. An elite `modifier` buffs
+    /// one stat by an amount that scales with `floor`.
     pub fn spawn_monster(
         &mut self,
         template: &MonsterTemplate,
         point: Point,
         floor: FloorId,
         world: World,
+        modifier: Option<MonsterModifier>,
     ) {
-        self.specs_world
-            .create_entity()
+        self.spawn_monster_from(template, point, floor, world, world, modifier);
+    }
+
+    /// Like [`Self::spawn_monster`], for a monster that strayed onto
+    /// `world`'s layer from `home`: it keeps `home` as its affinity, so
+    /// infighting treats it as an outsider there.
+    pub fn spawn_monster_from(
+        &mut self,
+        template: &MonsterTemplate,
+        point: Point,
+        floor: FloorId,
+        world: World,
+        home: World,
+        modifier: Option<MonsterModifier>,
+    ) {
+        let (mut hp, mut power, mut defense, mut speed) =
+            (template.hp, template.power, template.defense, ACTION_COST);
+        let bonus = MonsterModifier::bonus(floor);
+        match modifier {
+            Some(MonsterModifier::Blazing) => power += bonus + 1,
+            Some(MonsterModifier::Armored) => defense += bonus,
+            Some(MonsterModifier::Swift) => speed += ACTION_COST * bonus / 4,
+            Some(MonsterModifier::Hulking) => hp += template.hp * bonus / 2,
+            None => {}
+        }
+        let (name, color) = match modifier {
+            Some(modifier) => (
+                modifier.name_for(template.name),
+                template.color.lerp(modifier.tint(), 0.5),
+            ),
+            None => (template.name.to_string(), template.color),
+        };
+        let mut builder = self.specs_world.create_entity();
+        if template.size > 1 {
+            builder = builder.with(Size::square(template.size));
+        }
+        builder
             .with(Position {
                 point,
                 floor,
@@ -331,30 +1627,81 @@ impl EcsWorld {
             })
             .with(Renderable {
                 glyph: template.glyph as u16,
-                color: template.color,
+                color,
                 order: 1,
             })
             .with(Monster {
-                name: template.name.to_string(),
+                name,
+                modifier,
+                bursts: template.bursts,
+                phases: template.phases,
+            })
+            .with(Viewshed {
+                radius: template.sight_radius,
+                dirty: true,
+                visible: Vec::new(),
+                remembered: Vec::new(),
             })
             .with(MonsterBrain {
                 wander_chance: template.wander_chance,
+                aggro_radius: template.aggro_radius,
+                alerted: false,
+                last_seen_player: None,
+                behavior: template.behavior,
+                phase_shifter: template.phase_shifter,
+            })
+            .with(Actor {
+                energy: 0,
+                speed,
+                base_speed: speed,
             })
             .with(CombatStats {
-                max_hp: template.hp,
-                hp: template.hp,
-                power: template.power,
-                defense: template.defense,
+                max_hp: hp,
+                hp,
+                power,
+                defense,
             })
             .with(WorldAffinity {
-                primary: world,
+                primary: home,
                 resist: None,
                 vulnerable: None,
             })
-            .with(MonsterTag::default())
+            .with(MonsterTag)
+            .build();
+    }
+
+    /// Places a talking, unattackable NPC. It has no stats, brain, or
+    /// energy, so systems that act or fight never pick it up.
+    pub fn spawn_npc(
+        &mut self,
+        template: &NpcTemplate,
+        point: Point,
+        floor: FloorId,
+        world: World,
+    ) {
+        self.specs_world
+            .create_entity()
+            .with(Position {
+                point,
+                floor,
+                world,
+            })
+            .with(Renderable {
+                glyph: template.glyph as u16,
+                color: template.color,
+                order: 1,
+            })
+            .with(Npc {
+                name: template.name.to_string(),
+                role: template.role,
+            })
+            .with(Friendly)
             .build();
     }
 
+    /// Calls `f` for every cell an entity on the layer covers, so large
+    /// entities are drawn across their whole footprint. Cells come in
+    /// ascending `Renderable::order`.
     pub fn each_renderable<F>(&self, floor: FloorId, world: World, include_player: bool, mut f: F)
     where
         F: FnMut(Point, &Renderable),
@@ -363,6 +1710,8 @@ impl EcsWorld {
         let positions = self.specs_world.read_component::<Position>();
         let renderables = self.specs_world.read_component::<Renderable>();
         let players = self.specs_world.read_component::<PlayerTag>();
+        let sizes = self.specs_world.read_component::<Size>();
+        let mut draws: Vec<(Point, &Renderable)> = Vec::new();
         for (entity, pos, renderable) in (&entities, &positions, &renderables).join() {
             if pos.floor != floor || pos.world != world {
                 continue;
@@ -370,10 +1719,61 @@ impl EcsWorld {
             if !include_player && players.contains(entity) {
                 continue;
             }
-            f(pos.point, renderable);
+            for cell in footprint(pos.point, sizes.get(entity)) {
+                draws.push((cell, renderable));
+            }
+        }
+        // Lowest `order` first, so whatever shares a tile with the player
+        // is drawn over by the `@`. The sort is stable, keeping join order
+        // between equals.
+        draws.sort_by_key(|(_, renderable)| renderable.order);
+        for (cell, renderable) in draws {
+            f(cell, renderable);
+        }
+    }
+
+    /// Whether any monster of species `name` (elites included) is still
+    /// alive on the layer.
+    pub fn monster_named_on(&self, name: &str, floor: FloorId, world: World) -> bool {
+        let positions = self.specs_world.read_component::<Position>();
+        let monsters = self.specs_world.read_component::<Monster>();
+        (&positions, &monsters).join().any(|(pos, monster)| {
+            pos.floor == floor && pos.world == world && monster.name.ends_with(name)
+        })
+    }
+
+    /// Every monster's layer and combat stats, for headless tallies.
+    pub fn monster_census(&self) -> Vec<(FloorId, World, CombatStats)> {
+        let positions = self.specs_world.read_component::<Position>();
+        let monsters = self.specs_world.read_component::<MonsterTag>();
+        let stats = self.specs_world.read_component::<CombatStats>();
+        (&positions, &monsters, &stats)
+            .join()
+            .map(|(pos, _, stats)| (pos.floor, pos.world, stats.clone()))
+            .collect()
+    }
+
+    /// Adds `amount` prism shards to the player's pouch.
+    pub fn grant_shards(&mut self, amount: u32) {
+        let mut inventories = self.specs_world.write_component::<Inventory>();
+        if let Some(inventory) = inventories.get_mut(self.player) {
+            inventory.shards += amount;
         }
     }
 
+    /// The cells each monster on the layer covers, one entry per monster.
+    pub fn monster_footprints(&self, floor: FloorId, world: World) -> Vec<Vec<Point>> {
+        let entities = self.specs_world.entities();
+        let positions = self.specs_world.read_component::<Position>();
+        let monsters = self.specs_world.read_component::<MonsterTag>();
+        let sizes = self.specs_world.read_component::<Size>();
+        (&entities, &positions, &monsters)
+            .join()
+            .filter(|(_, pos, _)| pos.floor == floor && pos.world == world)
+            .map(|(entity, pos, _)| footprint(pos.point, sizes.get(entity)))
+            .collect()
+    }
+
     pub fn player_position(&self) -> Position {
         let storage = self.specs_world.read_component::<Position>();
         storage.get(self.player).cloned().unwrap_or(Position {
@@ -391,15 +1791,28 @@ impl EcsWorld {
         self.player
     }
 
+    /// Moves the player, possibly onto another floor or world layer.
+    ///
+    /// The player is a single entity: `CombatStats`, `Inventory`, and
+    /// attunements travel with it unchanged. Monsters keep their own
+    /// per-layer `Position`s and are untouched. Sight is the only per-layer
+    /// player state, so changing layers drops the old layer's visible and
+    /// remembered tiles; otherwise they would be revealed on the new layer
+    /// before FOV reruns.
     pub fn set_player_position(&mut self, point: Point, floor: FloorId, world: World) {
-        {
+        let changed_layer = {
             let mut positions = self.specs_world.write_component::<Position>();
-            if let Some(pos) = positions.get_mut(self.player) {
-                pos.point = point;
-                pos.floor = floor;
-                pos.world = world;
+            match positions.get_mut(self.player) {
+                Some(pos) => {
+                    let changed = pos.floor != floor || pos.world != world;
+                    pos.point = point;
+                    pos.floor = floor;
+                    pos.world = world;
+                    changed
+                }
+                None => false,
             }
-        }
+        };
 
         {
             let mut renderables = self.specs_world.write_component::<Renderable>();
@@ -408,10 +1821,20 @@ impl EcsWorld {
             }
         }
 
+        // Affinity catches up with a new world over a few turns; see
+        // `tick_attunement`.
+        if changed_layer {
+            self.attunement_turns = 0;
+        }
+
         {
             let mut viewsheds = self.specs_world.write_component::<Viewshed>();
             if let Some(vs) = viewsheds.get_mut(self.player) {
                 vs.dirty = true;
+                if changed_layer {
+                    vs.visible.clear();
+                    vs.remembered.clear();
+                }
             }
         }
     }
@@ -438,57 +1861,1160 @@ impl EcsWorld {
         if candidates.is_empty() {
             return None;
         }
-        let mut rng = self.specs_world.write_resource::<RandomNumberGenerator>();
+        let mut rng = self.specs_world.write_resource::<CombatRng>();
         let idx = rng.range(0, candidates.len() as i32) as usize;
         Some(candidates[idx])
     }
 
+    /// Blasts every monster within `radius` of `origin` on each of
+    /// `worlds`' layers. A single-layer blast names each monster it hits;
+    /// one spanning several layers sums up each layer instead.
     fn spectral_nova(
         &mut self,
+        origin: Point,
         damage: i32,
         radius: i32,
+        burns: bool,
         floor: FloorId,
-        world: World,
+        worlds: &[World],
     ) -> Vec<String> {
         let mut log = Vec::new();
-        let mut stats = self.specs_world.write_component::<CombatStats>();
-        let positions = self.specs_world.read_component::<Position>();
-        let monsters = self.specs_world.read_component::<Monster>();
-        let entities = self.specs_world.entities();
-        let mut deaths = Vec::new();
-        let origin = self.player_point();
-        let mut affected = 0;
+        let mut elite_kills = Vec::new();
+        let mut kills = 0;
+        let detailed = worlds.len() == 1;
+        // Only with friendly fire on does the blast reach the player.
+        let caught = self.friendly_fire()
+            && self
+                .specs_world
+                .read_component::<Position>()
+                .get(self.player)
+                .is_some_and(|pos| {
+                    pos.floor == floor
+                        && worlds.contains(&pos.world)
+                        && DistanceAlg::Pythagoras.distance2d(origin, pos.point) <= radius as f32
+                });
+        {
+            let mut stats = self.specs_world.write_component::<CombatStats>();
+            let positions = self.specs_world.read_component::<Position>();
+            let monsters = self.specs_world.read_component::<Monster>();
+            let sizes = self.specs_world.read_component::<Size>();
+            let mut burning = self.specs_world.write_component::<Burning>();
+            let mut rng = self.specs_world.write_resource::<CombatRng>();
+            let entities = self.specs_world.entities();
+            let mut deaths = Vec::new();
+            let mut affected = 0;
+            // Hits and kills per layer, in `worlds` order.
+            let mut impact = vec![(0, 0); worlds.len()];
 
-        for (entity, pos, stat, monster) in (&entities, &positions, &mut stats, &monsters).join() {
-            if pos.floor != floor || pos.world != world {
-                continue;
+            for (entity, pos, stat, monster) in
+                (&entities, &positions, &mut stats, &monsters).join()
+            {
+                if pos.floor != floor {
+                    continue;
+                }
+                let Some(layer) = worlds.iter().position(|&world| world == pos.world) else {
+                    continue;
+                };
+                let nearest = sizes
+                    .get(entity)
+                    .copied()
+                    .unwrap_or_default()
+                    .nearest_cell(pos.point, origin);
+                let dist = DistanceAlg::Pythagoras.distance2d(origin, nearest);
+                if dist <= radius as f32 {
+                    affected += 1;
+                    impact[layer].0 += 1;
+                    stat.hp = (stat.hp - damage).max(0);
+                    if detailed {
+                        log.push(format!("{} sears for {} damage.", monster.name, damage));
+                    }
+                    if burns
+                        && stat.hp > 0
+                        && rng.range(0, 100) < BURN_CHANCE
+                        && Burning::ignite(&mut burning, entity)
+                    {
+                        log.push(format!("{} catches fire!", monster.name));
+                    }
+                    if stat.hp == 0 {
+                        impact[layer].1 += 1;
+                        deaths.push((entity, monster.name.clone()));
+                        self.fallen
+                            .push(Corpse::fresh(pos.point, pos.world, &monster.name));
+                        if monster.bursts {
+                            self.cloud_bursts.push(pos.point);
+                        }
+                        if monster.modifier.is_some() {
+                            elite_kills.push((monster.name.clone(), pos.world));
+                        } else {
+                            kills += 1;
+                        }
+                    }
+                }
+            }
+
+            for (entity, name) in deaths {
+                if detailed {
+                    log.push(format!("{name} disintegrates in prismatic fire."));
+                }
+                let _ = entities.delete(entity);
             }
-            let dist = DistanceAlg::Pythagoras.distance2d(origin, pos.point);
-            if dist <= radius as f32 {
-                affected += 1;
-                stat.hp = stat.hp.saturating_sub(damage);
-                log.push(format!("{} sears for {} damage.", monster.name, damage));
-                if stat.hp == 0 {
-                    deaths.push((entity, monster.name.clone()));
+
+            if detailed {
+                if affected == 0 && !caught {
+                    log.push("Nova crackles harmlessly.".to_string());
+                }
+            } else {
+                for (world, (hit, slain)) in worlds.iter().zip(impact) {
+                    if hit > 0 {
+                        log.push(format!(
+                            "{}: {hit} seared for {damage}, {slain} slain.",
+                            world.as_str()
+                        ));
+                    }
+                }
+                if affected == 0 && !caught {
+                    log.push("The detonation rings through empty worlds.".to_string());
                 }
             }
         }
 
-        for (entity, name) in deaths {
-            log.push(format!("{name} disintegrates in prismatic fire."));
-            let _ = entities.delete(entity);
+        if caught {
+            let taken = self.damage_player(damage);
+            if taken > 0 {
+                log.push(format!("The blast sears you for {taken} damage."));
+            }
         }
 
-        if affected == 0 {
-            log.push("Nova crackles harmlessly.".to_string());
+        for _ in 0..kills {
+            self.grant_kill_shards(false);
+        }
+        for (name, world) in elite_kills {
+            self.grant_kill_shards(true);
+            log.push(self.claim_elite_loot(&name, world));
+        }
+        log
+    }
+
+    /// Burns every monster on the layer with a cell under one of `clouds`,
+    /// ignoring defense. Returns log lines for the ones the player can see.
+    pub fn hurt_monsters_in_clouds(
+        &mut self,
+        clouds: &[Point],
+        damage: i32,
+        floor: FloorId,
+        world: World,
+    ) -> Vec<String> {
+        let mut log = Vec::new();
+        let mut elite_kills = Vec::new();
+        let mut kills = 0;
+        {
+            let visible: HashSet<Point> = self.player_visible_tiles().into_iter().collect();
+            let mut stats = self.specs_world.write_component::<CombatStats>();
+            let positions = self.specs_world.read_component::<Position>();
+            let monsters = self.specs_world.read_component::<Monster>();
+            let sizes = self.specs_world.read_component::<Size>();
+            let entities = self.specs_world.entities();
+
+            for (entity, pos, stat, monster) in
+                (&entities, &positions, &mut stats, &monsters).join()
+            {
+                if pos.floor != floor || pos.world != world {
+                    continue;
+                }
+                let cells = footprint(pos.point, sizes.get(entity));
+                if !cells.iter().any(|cell| clouds.contains(cell)) {
+                    continue;
+                }
+                let seen = cells.iter().any(|cell| visible.contains(cell));
+                stat.hp = (stat.hp - damage).max(0);
+                if stat.hp > 0 {
+                    if seen {
+                        log.push(format!("{} sizzles in the acid.", monster.name));
+                    }
+                    continue;
+                }
+                if seen {
+                    log.push(format!("{} dissolves in the acid.", monster.name));
+                }
+                self.fallen
+                    .push(Corpse::fresh(pos.point, pos.world, &monster.name));
+                if monster.bursts {
+                    self.cloud_bursts.push(pos.point);
+                }
+                if monster.modifier.is_some() {
+                    elite_kills.push(monster.name.clone());
+                } else {
+                    kills += 1;
+                }
+                let _ = entities.delete(entity);
+            }
         }
+        self.specs_world.maintain();
 
+        for _ in 0..kills {
+            self.grant_kill_shards(false);
+        }
+        for name in elite_kills {
+            self.grant_kill_shards(true);
+            log.push(self.claim_elite_loot(&name, world));
+        }
         log
     }
 
+    /// Points where bursting monsters died, emptied on read.
+    pub fn take_cloud_bursts(&mut self) -> Vec<Point> {
+        std::mem::take(&mut self.cloud_bursts)
+    }
+
+    /// Corpses left since the last call, emptied on read.
+    pub fn take_fallen(&mut self) -> Vec<Corpse> {
+        std::mem::take(&mut self.fallen)
+    }
+
+    /// Gives each corpse in `fallen` that the player saw drop on `world` a
+    /// chance at a dying bark.
+    pub fn bark_deaths(&mut self, fallen: &[Corpse], world: World) {
+        let visible = self.player_visible_tiles();
+        let mut rng = self.specs_world.write_resource::<CombatRng>();
+        let mut log = self.specs_world.write_resource::<CombatLog>();
+        for corpse in fallen {
+            if corpse.world != world || !visible.contains(&corpse.point) {
+                continue;
+            }
+            if let Some(bark) =
+                Barks::roll(Barks::for_world(corpse.world).death, &corpse.name, &mut rng)
+            {
+                log.push_bark(bark);
+            }
+        }
+    }
+
+    /// Searches `corpse`, with a [`CORPSE_LOOT_CHANCE`] percent chance of
+    /// one of its world's consumables.
+    pub fn loot_corpse(&mut self, corpse: &Corpse) -> String {
+        let templates = starter_consumables(corpse.world);
+        let found = {
+            let mut rng = self.specs_world.write_resource::<CombatRng>();
+            (rng.range(0, 100) < CORPSE_LOOT_CHANCE && !templates.is_empty())
+                .then(|| templates[rng.range(0, templates.len() as i32) as usize].clone())
+        };
+        let name = &corpse.name;
+        match found {
+            None => format!("You search the {name}'s remains and find nothing."),
+            Some(template) if self.give_player_consumable(&template) => {
+                format!("You pull a {} from the {name}'s remains.", template.name)
+            }
+            Some(template) => format!(
+                "The {name}'s remains hold a {}, but your pack is full.",
+                template.name
+            ),
+        }
+    }
+
+    /// The movement grid from the most recent turn, if it matches `floor`/`world`.
+    pub fn movement_context(
+        &self,
+        floor: FloorId,
+        world: World,
+    ) -> Option<Fetch<'_, MovementContext>> {
+        let context = self.specs_world.try_fetch::<MovementContext>()?;
+        (context.floor == floor && context.world == world).then_some(context)
+    }
+
+    /// Every tile currently seen by a non-player viewshed on the given layer.
+    pub fn monster_sight(&self, floor: FloorId, world: World) -> HashSet<Point> {
+        let entities = self.specs_world.entities();
+        let positions = self.specs_world.read_component::<Position>();
+        let viewsheds = self.specs_world.read_component::<Viewshed>();
+        let mut seen = HashSet::new();
+        for (entity, pos, viewshed) in (&entities, &positions, &viewsheds).join() {
+            if entity == self.player || pos.floor != floor || pos.world != world {
+                continue;
+            }
+            seen.extend(viewshed.visible.iter().copied());
+        }
+        seen
+    }
+
     pub fn has_monster_intent(&self) -> bool {
         let intents = self.specs_world.read_component::<IntentStep>();
         let monsters = self.specs_world.read_component::<MonsterTag>();
         (&intents, &monsters).join().next().is_some()
     }
+
+    pub fn has_player_intent(&self) -> bool {
+        self.specs_world
+            .read_component::<IntentStep>()
+            .contains(self.player)
+    }
+
+    /// Drops every queued step, the player's and every monster's.
+    pub fn clear_all_intents(&mut self) {
+        self.specs_world.write_component::<IntentStep>().clear();
+    }
+}
+
+/// Releases acid clouds on the tiles around `origin` (and on `origin`
+/// itself with `centre`), returning how many took.
+fn release_acid(
+    dungeon: &mut Dungeon,
+    floor: FloorId,
+    origin: Point,
+    turns: u32,
+    centre: bool,
+) -> usize {
+    dungeon.floor_mut(floor).map_or(0, |target| {
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| Point::new(dx, dy)))
+            .filter(|&delta| centre || delta != Point::zero())
+            .filter(|&delta| target.add_cloud(origin + delta, turns))
+            .count()
+    })
+}
+
+/// Indices of the pack slots with the fewest uses left, in pack order.
+/// Resonance Shards never count.
+fn emptiest_slots(inventory: &Inventory) -> Vec<usize> {
+    let candidates = || {
+        inventory
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| !matches!(slot.effect, InventoryEffect::Resonate))
+    };
+    let Some(fewest) = candidates().map(|(_, slot)| slot.uses_remaining).min() else {
+        return Vec::new();
+    };
+    candidates()
+        .filter(|(_, slot)| slot.uses_remaining == fewest)
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// A single-use pack slot built from a consumable template.
+fn slot_from_template(template: &ConsumableTemplate) -> InventorySlot {
+    InventorySlot {
+        name: template.name.to_string(),
+        description: template.description.to_string(),
+        uses_remaining: 1,
+        effect: match template.effect {
+            ConsumableEffect::Heal { amount } => InventoryEffect::Heal { amount },
+            ConsumableEffect::Cleanse => InventoryEffect::Cleanse,
+            ConsumableEffect::Blink { range } => InventoryEffect::Blink { range },
+            ConsumableEffect::Nova {
+                damage,
+                radius,
+                burns,
+            } => InventoryEffect::Nova {
+                damage,
+                radius,
+                burns,
+            },
+            ConsumableEffect::DetectLife { turns } => InventoryEffect::DetectLife { turns },
+            ConsumableEffect::AcidCloud { turns } => InventoryEffect::AcidCloud { turns },
+            ConsumableEffect::Levitate { turns } => InventoryEffect::Levitate { turns },
+            ConsumableEffect::Freeze { radius, turns } => InventoryEffect::Freeze { radius, turns },
+            ConsumableEffect::Resonate => InventoryEffect::Resonate,
+            ConsumableEffect::Detonation { damage, radius } => {
+                InventoryEffect::Detonation { damage, radius }
+            }
+        },
+        color: template.color,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::{
+            items::{EffectKind, PRISM_SPEAR, recipe_for, resonance_shard},
+            loadouts::loadout_named,
+            monsters::{BARK_CHANCE, DEFAULT_AGGRO_RADIUS, PURSUIT_TURNS},
+        },
+        ecs::components::AFFINITY_DEFENSE,
+        map::Tile,
+    };
+    use specs::RunNow;
+
+    /// An open `width` by `height` room walled around its rim.
+    fn room(width: i32, height: i32) -> MapLayer {
+        let mut layer = MapLayer::empty(World::Red, width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let edge = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+                let tile = if edge {
+                    Tile::wall()
+                } else {
+                    Tile::floor(World::Red)
+                };
+                layer.set_tile(Point::new(x, y), tile);
+            }
+        }
+        layer
+    }
+
+    fn world_with_player_at(point: Point) -> EcsWorld {
+        EcsWorld::new(point, FloorId(0), World::Red, 7, &Loadout::default())
+    }
+
+    #[test]
+    fn idle_queries_leave_the_rng_streams_alone() {
+        let layer = room(40, 40);
+        let mut ecs = world_with_player_at(Point::new(2, 2));
+        let imp = &MonsterTemplate::for_world(World::Red)[0];
+        ecs.spawn_monster(imp, Point::new(30, 30), FloorId(0), World::Red, None);
+
+        let before = ecs.rng_fingerprint();
+        assert_eq!(ecs.rng_fingerprint(), before);
+        let _ = ecs.monster_sight(FloorId(0), World::Red);
+        let _ = ecs.movement_context(FloorId(0), World::Red);
+        let _ = ecs.player_point();
+        assert_eq!(ecs.rng_fingerprint(), before);
+
+        for _ in 0..10 {
+            ecs.advance(&layer, FloorId(0), World::Red);
+        }
+        assert_ne!(ecs.rng_fingerprint(), before, "a wandering monster rolls");
+    }
+
+    fn monster_hp(ecs: &EcsWorld) -> Vec<i32> {
+        let monsters = ecs.specs_world.read_component::<Monster>();
+        let stats = ecs.specs_world.read_component::<CombatStats>();
+        (&monsters, &stats)
+            .join()
+            .map(|(_, stat)| stat.hp)
+            .collect()
+    }
+
+    #[test]
+    fn acid_clouds_wear_monsters_down_to_zero_and_no_further() {
+        let mut ecs = world_with_player_at(Point::new(2, 2));
+        let imp = &MonsterTemplate::for_world(World::Red)[0];
+        let cloud = Point::new(10, 10);
+        ecs.spawn_monster(imp, cloud, FloorId(0), World::Red, None);
+
+        ecs.hurt_monsters_in_clouds(&[cloud], 2, FloorId(0), World::Red);
+        assert_eq!(monster_hp(&ecs), vec![imp.hp - 2]);
+
+        ecs.hurt_monsters_in_clouds(&[cloud], 100, FloorId(0), World::Red);
+        assert!(monster_hp(&ecs).is_empty(), "the dissolved imp is removed");
+        assert_eq!(ecs.fallen.len(), 1);
+    }
+
+    /// Total hp of the monsters still standing after `turns` turns in a
+    /// one-tile corridor, with a Red imp between the player and a Blue
+    /// stray that wants to reach them.
+    fn corridor_brawl(infighting: bool, turns: usize) -> (i32, i32) {
+        let layer = room(9, 3);
+        let mut ecs = world_with_player_at(Point::new(1, 1));
+        ecs.set_godmode(true);
+        ecs.set_infighting(infighting);
+        let imp = &MonsterTemplate::for_world(World::Red)[0];
+        let stray = &MonsterTemplate::for_world(World::Blue)[0];
+        ecs.spawn_monster(imp, Point::new(3, 1), FloorId(0), World::Red, None);
+        ecs.spawn_monster_from(
+            stray,
+            Point::new(4, 1),
+            FloorId(0),
+            World::Red,
+            World::Blue,
+            None,
+        );
+        let full = imp.hp + stray.hp;
+        for _ in 0..turns {
+            ecs.advance(&layer, FloorId(0), World::Red);
+        }
+        (full, monster_hp(&ecs).iter().sum())
+    }
+
+    #[test]
+    fn blocked_monsters_of_different_worlds_fight_only_with_infighting() {
+        let (full, left) = corridor_brawl(false, 12);
+        assert_eq!(left, full, "without infighting the pack waits its turn");
+        let (full, left) = corridor_brawl(true, 12);
+        assert!(left < full, "the stray should turn on the imp blocking it");
+    }
+
+    #[test]
+    fn affinity_lags_behind_a_shift_and_then_retunes() {
+        let green = room(12, 12);
+        let mut ecs = world_with_player_at(Point::new(3, 3));
+        let affinity = |ecs: &EcsWorld| {
+            ecs.specs_world
+                .read_component::<WorldAffinity>()
+                .get(ecs.player)
+                .cloned()
+                .unwrap()
+        };
+        let red = affinity(&ecs);
+        assert_eq!(red.defense_against(World::Red, 2), 2 + AFFINITY_DEFENSE);
+        assert_eq!(red.defense_against(World::Orange, 2), 2);
+
+        // Jumping straight to Red's opposite leaves the player exposed to
+        // the natives until the new attunement settles.
+        ecs.set_player_position(Point::new(3, 3), FloorId(0), World::Green);
+        assert_eq!(
+            affinity(&ecs).defense_against(World::Green, 2),
+            2 - AFFINITY_DEFENSE
+        );
+        for _ in 1..ATTUNE_TURNS {
+            ecs.advance(&green, FloorId(0), World::Green);
+        }
+        assert_eq!(ecs.player_affinity(), Some(World::Red));
+        ecs.advance(&green, FloorId(0), World::Green);
+        assert_eq!(ecs.player_affinity(), Some(World::Green));
+        let tuned = affinity(&ecs);
+        assert_eq!(tuned.defense_against(World::Green, 2), 2 + AFFINITY_DEFENSE);
+        assert_eq!(
+            tuned.defense_against(World::Green.opposite(), 2),
+            2 - AFFINITY_DEFENSE
+        );
+    }
+
+    /// Energy a Red imp banks in one energy pass while the player stands
+    /// in `world` at that world's pace.
+    fn imp_energy_while_player_in(world: World) -> i32 {
+        let layer = room(12, 12);
+        let mut ecs = world_with_player_at(Point::new(2, 2));
+        ecs.set_player_position(Point::new(2, 2), FloorId(0), world);
+        ecs.apply_player_speed_modifier(rule_for(world).speed_modifier);
+        let imp = &MonsterTemplate::for_world(World::Red)[0];
+        ecs.spawn_monster(imp, Point::new(9, 9), FloorId(0), world, None);
+        ecs.specs_world.insert(MovementContext::from_layer(
+            &layer,
+            FloorId(0),
+            world,
+            Point::new(2, 2),
+        ));
+        ecs.specs_world.insert(TurnClock {
+            player_cost: ACTION_COST,
+            turn: 0,
+        });
+        EnergySystem.run_now(&ecs.specs_world);
+        let monsters = ecs.specs_world.read_component::<Monster>();
+        let actors = ecs.specs_world.read_component::<Actor>();
+        (&monsters, &actors).join().next().unwrap().1.energy
+    }
+
+    #[test]
+    fn world_pace_changes_how_much_time_monsters_get() {
+        let yellow = rule_for(World::Yellow).speed_modifier;
+        let blue = rule_for(World::Blue).speed_modifier;
+        assert!(yellow > blue);
+        let mut ecs = world_with_player_at(Point::new(2, 2));
+        assert_eq!(
+            ecs.apply_player_speed_modifier(yellow) - ecs.apply_player_speed_modifier(blue),
+            yellow - blue
+        );
+
+        // The same imp earns less per player action while the player is
+        // quick in Yellow than while they wade through Blue.
+        assert!(
+            imp_energy_while_player_in(World::Yellow) < imp_energy_while_player_in(World::Blue)
+        );
+    }
+
+    #[test]
+    fn a_forced_elite_roll_spawns_a_renamed_buffed_monster() {
+        let depth = FloorId(6);
+        let mut rng = (0..)
+            .map(RandomNumberGenerator::seeded)
+            .find(|rng| {
+                MonsterModifier::roll(&mut rng.clone(), depth) == Some(MonsterModifier::Blazing)
+            })
+            .unwrap();
+        let modifier = MonsterModifier::roll(&mut rng, depth);
+        assert_eq!(modifier, Some(MonsterModifier::Blazing));
+
+        let mut ecs = world_with_player_at(Point::new(2, 2));
+        let imp = &MonsterTemplate::for_world(World::Red)[0];
+        ecs.spawn_monster(imp, Point::new(8, 8), depth, World::Red, modifier);
+
+        let monsters = ecs.specs_world.read_component::<Monster>();
+        let stats = ecs.specs_world.read_component::<CombatStats>();
+        let (monster, stat) = (&monsters, &stats).join().next().unwrap();
+        assert_eq!(monster.name, format!("Blazing {}", imp.name));
+        assert_eq!(stat.power, imp.power + MonsterModifier::bonus(depth) + 1);
+        assert_eq!((stat.hp, stat.defense), (imp.hp, imp.defense));
+    }
+
+    /// A player whose pack holds one use of each of `names`, in order,
+    /// taken from the worlds' starter consumables.
+    fn player_with_pack(names: &[&str]) -> EcsWorld {
+        let mut ecs = world_with_player_at(Point::new(2, 2));
+        if let Some(inventory) = ecs
+            .specs_world
+            .write_component::<Inventory>()
+            .get_mut(ecs.player)
+        {
+            inventory.slots.clear();
+        }
+        for name in names {
+            let template = SPECTRUM
+                .iter()
+                .flat_map(|&world| starter_consumables(world))
+                .find(|template| template.name == *name)
+                .unwrap();
+            ecs.give_player_consumable(&template);
+        }
+        ecs
+    }
+
+    #[test]
+    fn two_healing_drafts_fuse_and_a_mismatched_pair_does_not() {
+        let mut ecs = player_with_pack(&["Thermal Draft", "Stillwater Draught", "Ember Nova"]);
+
+        assert_eq!(
+            ecs.craft_consumables(0, 2),
+            Err("These resonances don't bind.".to_string())
+        );
+        assert_eq!(ecs.player_inventory().len(), 3, "nothing is consumed");
+
+        assert!(ecs.craft_consumables(0, 1).is_ok());
+        let pack = ecs.player_inventory();
+        let names: Vec<&str> = pack.iter().map(|(_, slot)| slot.name.as_str()).collect();
+        assert_eq!(names, ["Ember Nova", "Greater Draft"]);
+        assert!(matches!(
+            pack[1].1.effect,
+            InventoryEffect::Heal { amount: 18 }
+        ));
+    }
+
+    #[test]
+    fn enchanting_succeeds_under_the_odds_and_fails_over_them() {
+        let mut ecs = world_with_player_at(Point::new(2, 2));
+        let power = ecs.player_stats().unwrap().power;
+        let chance = enchant_chance(0);
+        ecs.grant_shards(enchant_cost(0) + enchant_cost(1));
+
+        ecs.pin_combat_rng(|rng| rng.range(0, 100) < chance);
+        let message = ecs.enchant_gear(GearSlot::Weapon).unwrap();
+        assert!(message.ends_with("settles at +1."), "{message}");
+        assert_eq!(ecs.player_stats().unwrap().power, power + 1);
+
+        let chance = enchant_chance(1);
+        ecs.pin_combat_rng(|rng| rng.range(0, 100) >= chance);
+        let message = ecs.enchant_gear(GearSlot::Weapon).unwrap();
+        assert!(message.starts_with("The shards crumble"), "{message}");
+        let weapon = ecs.player_equipment().weapon.unwrap();
+        assert_eq!(weapon.enchant, 1, "a failure keeps the gear");
+        assert_eq!(ecs.player_stats().unwrap().power, power + 1);
+        assert_eq!(ecs.player_shards(), 0, "but still eats the shards");
+    }
+
+    #[test]
+    fn the_curse_creeps_only_out_of_sight_and_cleanse_wipes_it() {
+        let mut layer = room(20, 8);
+        for y in 0..8 {
+            layer.set_tile(Point::new(10, y), Tile::wall());
+        }
+        let mut dungeon = Dungeon::new(20, 8, 7).unwrap();
+        dungeon.ensure_floor(FloorId(0));
+        dungeon.floors[0] = WorldFloor::empty(FloorId(0), 20, 8);
+        dungeon.floors[0].layers[0] = layer.clone();
+
+        let mut ecs = player_with_pack(&["Prismatic Tonic"]);
+        ecs.advance(&layer, FloorId(0), World::Red);
+        ecs.specs_world
+            .write_component::<Cursed>()
+            .insert(ecs.player, Cursed { turns_left: 30 })
+            .unwrap();
+        for _ in 0..60 {
+            ecs.spread_curse(&mut dungeon.floors[0]);
+        }
+        let visible: HashSet<Point> = ecs.player_visible_tiles().into_iter().collect();
+        let cursed = &dungeon.floors[0].cursed;
+        assert!(!cursed.is_empty());
+        assert!(cursed.iter().all(|point| !visible.contains(point)));
+        assert!(cursed.iter().all(|point| point.x > 10), "{cursed:?}");
+
+        ecs.use_consumable(0, &mut dungeon, FloorId(0), World::Red)
+            .unwrap();
+        assert_eq!(ecs.player_curse_turns(), None);
+        assert!(dungeon.floors[0].cursed.is_empty());
+        assert!(!ecs.spread_curse(&mut dungeon.floors[0]));
+    }
+
+    /// Where a Farstep Lens carries a player at the west end of a long
+    /// corridor while attuned to `world`, with the landing roll pinned to
+    /// the farthest tile in reach.
+    fn farthest_blink_in(world: World) -> Point {
+        let mut dungeon = Dungeon::new(40, 8, 7).unwrap();
+        dungeon.ensure_floor(FloorId(0));
+        let mut floor = WorldFloor::empty(FloorId(0), 40, 8);
+        for layer in floor.layers.iter_mut() {
+            for x in 1..39 {
+                layer.set_tile(Point::new(x, 1), Tile::floor(layer.world));
+            }
+        }
+        dungeon.floors[0] = floor;
+        let mut ecs = player_with_pack(&[]);
+        ecs.set_player_position(Point::new(1, 1), FloorId(0), world);
+        let lens = recipe_for(EffectKind::Blink, EffectKind::Blink).unwrap();
+        ecs.give_player_consumable(&lens);
+        let ConsumableEffect::Blink { range } = lens.effect else {
+            unreachable!()
+        };
+        let reach = range + rule_for(world).blink_bonus;
+        ecs.pin_combat_rng(|rng| rng.range(0, reach) == reach - 1);
+        ecs.use_consumable(0, &mut dungeon, FloorId(0), world)
+            .unwrap();
+        ecs.player_point()
+    }
+
+    #[test]
+    fn indigo_stretches_blink_and_a_pinned_roll_picks_the_landing() {
+        assert_eq!(farthest_blink_in(World::Red), Point::new(13, 1));
+        let bonus = rule_for(World::Indigo).blink_bonus;
+        assert!(bonus > 0);
+        assert_eq!(farthest_blink_in(World::Indigo), Point::new(13 + bonus, 1));
+    }
+
+    #[test]
+    fn a_spear_reaches_two_tiles_but_never_through_a_wall() {
+        let mut layer = room(9, 9);
+        layer.set_tile(Point::new(4, 5), Tile::wall());
+        let mut ecs = world_with_player_at(Point::new(4, 4));
+        if let Some(equipment) = ecs
+            .specs_world
+            .write_component::<Equipment>()
+            .get_mut(ecs.player)
+        {
+            equipment.weapon = Some(GearPiece {
+                name: PRISM_SPEAR.name.to_string(),
+                enchant: 0,
+                reach: PRISM_SPEAR.reach,
+            });
+        }
+        let imp = &MonsterTemplate::for_world(World::Red)[0];
+        ecs.spawn_monster(imp, Point::new(6, 4), FloorId(0), World::Red, None);
+        ecs.spawn_monster(imp, Point::new(4, 6), FloorId(0), World::Red, None);
+
+        let thrust = ecs.attack_along(Point::new(1, 0), &layer, FloorId(0), World::Red);
+        assert_eq!(thrust.unwrap().hit.other, imp.name);
+        assert_eq!(
+            ecs.attack_along(Point::new(0, 1), &layer, FloorId(0), World::Red)
+                .err(),
+            Some("Your Prism Spear jars against the wall.".to_string())
+        );
+        let mut hp = monster_hp(&ecs);
+        hp.sort();
+        assert!(hp[0] < imp.hp, "the open-line imp is hit");
+        assert_eq!(hp[1], imp.hp, "the walled-off imp is untouched");
+    }
+
+    /// A dungeon whose floor 0 is `layer`'s tiles on every world layer.
+    fn dungeon_over(layer: &MapLayer) -> Dungeon {
+        let mut dungeon = Dungeon::new(layer.width, layer.height, 7).unwrap();
+        dungeon.ensure_floor(FloorId(0));
+        let mut floor = WorldFloor::empty(FloorId(0), layer.width, layer.height);
+        for world_layer in floor.layers.iter_mut() {
+            world_layer.tiles = layer.tiles.clone();
+        }
+        dungeon.floors[0] = floor;
+        dungeon
+    }
+
+    #[test]
+    fn an_ember_nova_sets_foes_alight_and_water_douses_them() {
+        let mut layer = room(12, 12);
+        let wader = Point::new(4, 2);
+        layer.set_tile(wader, Tile::water());
+        let mut dungeon = dungeon_over(&layer);
+        let mut ecs = player_with_pack(&["Ember Nova"]);
+        let brute = MonsterTemplate::for_world(World::Red)
+            .into_iter()
+            .filter(|template| template.size == 1)
+            .max_by_key(|template| template.hp)
+            .unwrap();
+        ecs.spawn_monster(&brute, wader, FloorId(0), World::Red, None);
+
+        ecs.pin_combat_rng(|rng| rng.range(0, 100) < BURN_CHANCE);
+        ecs.use_consumable(0, &mut dungeon, FloorId(0), World::Red)
+            .unwrap();
+        assert_eq!(monster_hp(&ecs).len(), 1, "the nova leaves it standing");
+        assert!(ecs.burning_cells(FloorId(0), World::Red).contains(&wader));
+
+        ecs.tick_burning(&layer, FloorId(0), World::Red);
+        assert!(ecs.burning_cells(FloorId(0), World::Red).is_empty());
+    }
+
+    /// How far a Red imp closes on the player down a corridor over `turns`
+    /// turns, chilled for the whole stretch or not.
+    fn imp_progress(slowed: bool, turns: usize) -> i32 {
+        let layer = room(16, 3);
+        let mut ecs = world_with_player_at(Point::new(1, 1));
+        ecs.set_godmode(true);
+        let imp = &MonsterTemplate::for_world(World::Red)[0];
+        let start = Point::new(9, 1);
+        ecs.spawn_monster(imp, start, FloorId(0), World::Red, None);
+        if slowed {
+            let entity = ecs.entity_at(start, FloorId(0), World::Red).unwrap();
+            ecs.specs_world
+                .write_component::<Slowed>()
+                .insert(entity, Slowed { turns_left: 100 })
+                .unwrap();
+        }
+        for _ in 0..turns {
+            ecs.advance(&layer, FloorId(0), World::Red);
+        }
+        let footprints = ecs.monster_footprints(FloorId(0), World::Red);
+        start.x - footprints[0][0].x
+    }
+
+    #[test]
+    fn a_slowed_monster_acts_less_often() {
+        let brisk = imp_progress(false, 6);
+        let chilled = imp_progress(true, 6);
+        assert!(brisk > 0);
+        assert!(chilled < brisk, "chilled {chilled} vs brisk {brisk}");
+    }
+
+    /// Whether an imp noticing at `radius` spots a player six tiles down an
+    /// open corridor.
+    fn imp_spots_player_with_aggro(radius: f32) -> bool {
+        let layer = room(16, 3);
+        let mut ecs = world_with_player_at(Point::new(1, 1));
+        let imp = MonsterTemplate::for_world(World::Red)[0]
+            .clone()
+            .aggro(radius);
+        ecs.spawn_monster(&imp, Point::new(7, 1), FloorId(0), World::Red, None);
+        ecs.advance(&layer, FloorId(0), World::Red);
+        let brains = ecs.specs_world.read_component::<MonsterBrain>();
+        let brain = brains.join().next().unwrap();
+        brain.last_seen_player.is_some()
+    }
+
+    #[test]
+    fn monsters_notice_the_player_only_inside_their_aggro_radius() {
+        assert!(imp_spots_player_with_aggro(DEFAULT_AGGRO_RADIUS));
+        assert!(imp_spots_player_with_aggro(6.5));
+        assert!(!imp_spots_player_with_aggro(5.5));
+    }
+
+    #[test]
+    fn a_thrown_nova_detonates_where_it_lands_not_on_the_player() {
+        let layer = room(14, 7);
+        let mut dungeon = dungeon_over(&layer);
+        let mut ecs = player_with_pack(&["Ember Nova"]);
+        let brute = MonsterTemplate::for_world(World::Red)
+            .into_iter()
+            .filter(|template| template.size == 1)
+            .max_by_key(|template| template.hp)
+            .unwrap();
+        let (near, far) = (Point::new(3, 4), Point::new(10, 3));
+        ecs.spawn_monster(&brute, near, FloorId(0), World::Red, None);
+        ecs.spawn_monster(&brute, far, FloorId(0), World::Red, None);
+        let hp_at = |ecs: &EcsWorld, point| {
+            let entity = ecs.entity_at(point, FloorId(0), World::Red).unwrap();
+            ecs.specs_world
+                .read_component::<CombatStats>()
+                .get(entity)
+                .unwrap()
+                .hp
+        };
+        let player_hp = ecs.player_stats().unwrap().hp;
+
+        let log = ecs
+            .throw_consumable(0, Point::new(10, 2), &mut dungeon, FloorId(0), World::Red)
+            .unwrap();
+        assert!(log[0].ends_with("lands at 10,2."), "{log:?}");
+        assert!(hp_at(&ecs, far) < brute.hp, "the blast catches the far foe");
+        assert_eq!(hp_at(&ecs, near), brute.hp, "the foe beside you is spared");
+        assert_eq!(ecs.player_stats().unwrap().hp, player_hp);
+        assert!(ecs.player_inventory().is_empty());
+    }
+
+    #[test]
+    fn a_forced_roll_makes_a_monster_bark_from_its_worlds_aggro_table() {
+        let layer = room(12, 3);
+        let mut ecs = EcsWorld::new(
+            Point::new(1, 1),
+            FloorId(0),
+            World::Violet,
+            7,
+            &Loadout::default(),
+        );
+        let hexer = &MonsterTemplate::for_world(World::Violet)[0];
+        ecs.spawn_monster(hexer, Point::new(5, 1), FloorId(0), World::Violet, None);
+        let rng = (0..)
+            .map(RandomNumberGenerator::seeded)
+            .find(|rng| rng.clone().range(0, 100) < BARK_CHANCE)
+            .unwrap();
+        ecs.specs_world.insert(AiRng(rng));
+
+        ecs.advance(&layer, FloorId(0), World::Violet);
+        let barks: Vec<String> = ecs
+            .drain_combat_log()
+            .into_iter()
+            .filter_map(|line| match line {
+                LogLine::Bark(bark) => Some(bark),
+                _ => None,
+            })
+            .collect();
+        let table = Barks::for_world(World::Violet).aggro;
+        let expected: Vec<String> = table
+            .iter()
+            .map(|line| line.replace("{name}", hexer.name))
+            .collect();
+        assert_eq!(barks.len(), 1, "{barks:?}");
+        assert!(
+            expected.contains(&barks[0]),
+            "{} isn't a Violet shout",
+            barks[0]
+        );
+
+        ecs.advance(&layer, FloorId(0), World::Violet);
+        assert!(
+            !ecs.drain_combat_log()
+                .iter()
+                .any(|line| matches!(line, LogLine::Bark(_))),
+            "it only shouts on the first chase"
+        );
+    }
+
+    #[test]
+    fn a_monster_chases_a_lost_player_then_gives_up_on_the_trail() {
+        let mut layer = room(40, 7);
+        for x in 1..39 {
+            layer.set_tile(Point::new(x, 3), Tile::wall());
+        }
+        let mut ecs = world_with_player_at(Point::new(1, 1));
+        ecs.set_godmode(true);
+        let hound = MonsterTemplate::for_world(World::Red)[0]
+            .clone()
+            .aggro(40.0)
+            .sight(40);
+        ecs.spawn_monster(&hound, Point::new(30, 1), FloorId(0), World::Red, None);
+        let brain = |ecs: &EcsWorld| {
+            let brains = ecs.specs_world.read_component::<MonsterBrain>();
+            brains.join().next().unwrap().last_seen_player
+        };
+        let at = |ecs: &EcsWorld| ecs.monster_footprints(FloorId(0), World::Red)[0][0];
+
+        ecs.advance(&layer, FloorId(0), World::Red);
+        let (spot, seen) = brain(&ecs).expect("it spots the player down the hall");
+        assert_eq!(spot, Point::new(1, 1));
+
+        ecs.set_player_position(Point::new(1, 5), FloorId(0), World::Red);
+        let start = at(&ecs);
+        let mut turns = 0;
+        while brain(&ecs).is_some() {
+            ecs.advance(&layer, FloorId(0), World::Red);
+            turns += 1;
+            assert!(turns <= PURSUIT_TURNS + 1, "it never gave up");
+        }
+        // The trail is dropped on the first turn it is older than
+        // PURSUIT_TURNS.
+        assert_eq!(seen + turns, PURSUIT_TURNS + 1);
+        let gave_up = at(&ecs);
+        assert!(gave_up.x < start.x, "it followed the trail toward {spot:?}");
+        assert!(
+            gave_up.x > spot.x,
+            "the trail went cold before it got there"
+        );
+
+        for _ in 0..4 {
+            ecs.advance(&layer, FloorId(0), World::Red);
+            assert_eq!(brain(&ecs), None, "out of sight it stays a wanderer");
+        }
+    }
+
+    #[test]
+    fn a_resonance_shard_gives_the_emptiest_slot_one_use_back() {
+        let layer = room(8, 8);
+        let mut dungeon = dungeon_over(&layer);
+        let mut ecs = player_with_pack(&["Ember Nova", "Ember Nova", "Thermal Draft"]);
+        assert!(ecs.give_player_consumable(&resonance_shard()));
+        let uses = |ecs: &EcsWorld| -> Vec<(String, i32)> {
+            ecs.player_inventory()
+                .into_iter()
+                .map(|(_, slot)| (slot.name, slot.uses_remaining))
+                .collect()
+        };
+        assert_eq!(uses(&ecs)[1], ("Thermal Draft".to_string(), 1));
+
+        let log = ecs
+            .use_consumable(2, &mut dungeon, FloorId(0), World::Red)
+            .unwrap();
+        assert!(log.contains(&"The Thermal Draft resonates back to 2 uses.".to_string()));
+        assert_eq!(
+            uses(&ecs),
+            [
+                ("Ember Nova".to_string(), 2),
+                ("Thermal Draft".to_string(), 2)
+            ],
+            "the shard is spent and the nova untouched"
+        );
+    }
+
+    #[test]
+    fn a_prismatic_detonation_hits_the_same_spot_on_every_layer() {
+        let layer = room(10, 10);
+        let mut dungeon = dungeon_over(&layer);
+        let mut ecs = player_with_pack(&[]);
+        let detonation = recipe_for(EffectKind::Nova, EffectKind::Resonate).unwrap();
+        assert!(ecs.give_player_consumable(&detonation));
+        let spot = Point::new(3, 3);
+        let far = Point::new(7, 7);
+        let mut brutes = Vec::new();
+        for world in [World::Red, World::Blue] {
+            let brute = MonsterTemplate::for_world(world)
+                .into_iter()
+                .filter(|template| template.size == 1)
+                .max_by_key(|template| template.hp)
+                .unwrap();
+            ecs.spawn_monster(&brute, spot, FloorId(0), world, None);
+            ecs.spawn_monster(&brute, far, FloorId(0), world, None);
+            brutes.push((world, brute.hp));
+        }
+        let hp_at = |ecs: &EcsWorld, point, world| {
+            ecs.entity_at(point, FloorId(0), world).map(|entity| {
+                ecs.specs_world
+                    .read_component::<CombatStats>()
+                    .get(entity)
+                    .unwrap()
+                    .hp
+            })
+        };
+
+        ecs.use_consumable(0, &mut dungeon, FloorId(0), World::Red)
+            .unwrap();
+        for (world, full) in brutes {
+            let hit = hp_at(&ecs, spot, world);
+            assert!(hit.is_none_or(|hp| hp < full), "{world:?} was spared");
+            assert_eq!(
+                hp_at(&ecs, far, world),
+                Some(full),
+                "{world:?} out of range"
+            );
+        }
+    }
+
+    #[test]
+    fn the_player_draws_over_a_monster_sharing_its_tile() {
+        let mut ecs = world_with_player_at(Point::new(2, 2));
+        let imp = &MonsterTemplate::for_world(World::Red)[0];
+        ecs.spawn_monster(imp, Point::new(2, 2), FloorId(0), World::Red, None);
+
+        let mut drawn = Vec::new();
+        ecs.each_renderable(FloorId(0), World::Red, true, |point, renderable| {
+            drawn.push((point, renderable.order, renderable.glyph));
+        });
+        let orders: Vec<i32> = drawn.iter().map(|&(_, order, _)| order).collect();
+        assert!(orders.is_sorted(), "drawn out of order: {orders:?}");
+        let on_top = drawn
+            .iter()
+            .rev()
+            .find(|(point, _, _)| *point == Point::new(2, 2))
+            .unwrap();
+        assert_eq!(on_top.2, b'@' as u16);
+    }
+
+    #[test]
+    fn a_named_loadout_fills_the_pack_and_stats_it_lists() {
+        let pack = |ecs: &EcsWorld| {
+            ecs.specs_world
+                .read_component::<Inventory>()
+                .get(ecs.player)
+                .unwrap()
+                .slots
+                .iter()
+                .map(|slot| (slot.name.clone(), slot.uses_remaining))
+                .collect::<Vec<_>>()
+        };
+        let spawn = Point::new(2, 2);
+
+        let medic = loadout_named("Medic").unwrap();
+        let ecs = EcsWorld::new(spawn, FloorId(0), World::Red, 7, &medic);
+        assert_eq!(
+            pack(&ecs),
+            [
+                ("Thermal Draft".to_string(), 3),
+                ("Prismatic Tonic".to_string(), 2)
+            ]
+        );
+
+        let tank = loadout_named("tank").unwrap();
+        let ecs = EcsWorld::new(spawn, FloorId(0), World::Blue, 7, &tank);
+        let starters = starter_consumables(World::Blue)
+            .into_iter()
+            .map(|template| (template.name.to_string(), 1))
+            .collect::<Vec<_>>();
+        assert_eq!(pack(&ecs), starters);
+        let stats = ecs.player_stats().unwrap();
+        assert_eq!((stats.max_hp, stats.hp), (35, 35));
+        assert_eq!((stats.power, stats.defense), (4, 3));
+
+        let bare = loadout_named("bare").unwrap();
+        let ecs = EcsWorld::new(spawn, FloorId(0), World::Red, 7, &bare);
+        assert!(pack(&ecs).is_empty());
+    }
+
+    #[test]
+    fn a_point_blank_nova_sears_the_player_only_with_friendly_fire_on() {
+        let layer = room(12, 7);
+        let mut dungeon = dungeon_over(&layer);
+        let mut ecs = player_with_pack(&["Ember Nova", "Ember Nova"]);
+        let hp = ecs.player_stats().unwrap().hp;
+
+        assert!(!ecs.friendly_fire());
+        let log = ecs
+            .use_consumable(0, &mut dungeon, FloorId(0), World::Red)
+            .unwrap();
+        assert_eq!(ecs.player_stats().unwrap().hp, hp);
+        assert!(
+            !log.iter().any(|line| line.contains("sears you")),
+            "{log:?}"
+        );
+
+        ecs.set_friendly_fire(true);
+        let log = ecs
+            .use_consumable(0, &mut dungeon, FloorId(0), World::Red)
+            .unwrap();
+        assert!(ecs.player_stats().unwrap().hp < hp);
+        assert!(log.iter().any(|line| line.contains("sears you")), "{log:?}");
+    }
+
+    #[test]
+    fn a_monsters_sight_stops_at_a_wall_between_it_and_the_far_side() {
+        let mut layer = room(13, 7);
+        for y in 1..6 {
+            layer.set_tile(Point::new(6, y), Tile::wall());
+        }
+        let mut ecs = world_with_player_at(Point::new(10, 3));
+        let imp = &MonsterTemplate::for_world(World::Red)[0];
+        let lurker = Point::new(3, 3);
+        ecs.spawn_monster(imp, lurker, FloorId(0), World::Red, None);
+        ecs.specs_world.insert(MovementContext::from_layer(
+            &layer,
+            FloorId(0),
+            World::Red,
+            ecs.player_point(),
+        ));
+
+        FovSystem.run_now(&ecs.specs_world);
+
+        let monster = ecs.entity_at(lurker, FloorId(0), World::Red).unwrap();
+        let viewsheds = ecs.specs_world.read_component::<Viewshed>();
+        let sight = viewsheds.get(monster).unwrap();
+        assert!(!sight.dirty);
+        assert!(sight.visible.contains(&Point::new(5, 3)));
+        assert!(
+            sight.visible.contains(&Point::new(6, 3)),
+            "the wall is seen"
+        );
+        for y in 1..6 {
+            for x in 7..12 {
+                let hidden = Point::new(x, y);
+                assert!(!sight.visible.contains(&hidden), "sees {hidden:?}");
+            }
+        }
+        drop(viewsheds);
+        assert!(
+            !ecs.monster_sight(FloorId(0), World::Red)
+                .contains(&ecs.player_point())
+        );
+    }
 }
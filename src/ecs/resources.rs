@@ -1,6 +1,9 @@
 #![allow(dead_code)]
 
+use std::ops::{Deref, DerefMut};
+
 use bracket_geometry::prelude::Point;
+use bracket_random::prelude::RandomNumberGenerator;
 
 use crate::map::{FloorId, MapLayer, World};
 
@@ -13,20 +16,26 @@ pub struct MovementContext {
     pub height: i32,
     walkable: Vec<bool>,
     blocks_sight: Vec<bool>,
+    lava: Vec<bool>,
 }
 
 impl MovementContext {
     pub fn from_layer(layer: &MapLayer, floor: FloorId, world: World, player_point: Point) -> Self {
+        // Closed doors stay passable here so monsters push through them; the
+        // game opens any door a monster ends up standing in. Lava isn't
+        // walkable either, so monsters route around it; only the player's
+        // own steps may cross it.
         let walkable = layer
             .tiles
             .iter()
-            .map(|tile| !tile.blocks_move)
+            .map(|tile| (!tile.blocks_move || tile.is_door()) && !tile.is_lava())
             .collect::<Vec<bool>>();
         let blocks_sight = layer
             .tiles
             .iter()
             .map(|tile| tile.blocks_sight)
             .collect::<Vec<bool>>();
+        let lava = layer.tiles.iter().map(|tile| tile.is_lava()).collect();
 
         Self {
             floor,
@@ -36,6 +45,7 @@ impl MovementContext {
             height: layer.height,
             walkable,
             blocks_sight,
+            lava,
         }
     }
 
@@ -47,6 +57,14 @@ impl MovementContext {
         self.walkable.get(idx).copied().unwrap_or(false)
     }
 
+    pub fn is_lava(&self, point: Point) -> bool {
+        if !self.in_bounds(point) {
+            return false;
+        }
+        let idx = (point.y * self.width + point.x) as usize;
+        self.lava.get(idx).copied().unwrap_or(false)
+    }
+
     pub fn blocks_sight(&self, point: Point) -> bool {
         if point.x < 0 || point.x >= self.width || point.y < 0 || point.y >= self.height {
             return true;
@@ -60,13 +78,158 @@ impl MovementContext {
     }
 }
 
+/// The [`Ai`](crate::seed::RngStream::Ai) stream: monster wander and
+/// phase rolls.
+#[derive(Clone)]
+pub struct AiRng(pub RandomNumberGenerator);
+
+/// The [`Combat`](crate::seed::RngStream::Combat) stream: curses, blink
+/// landings, enchant rolls, curse spread, and searches.
+#[derive(Clone)]
+pub struct CombatRng(pub RandomNumberGenerator);
+
+impl Deref for AiRng {
+    type Target = RandomNumberGenerator;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for AiRng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Deref for CombatRng {
+    type Target = RandomNumberGenerator;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for CombatRng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Energy the player's last action cost. Other actors on the layer earn
+/// energy in proportion to it, scaled by their speed against the player's.
+/// `turn` is the turn being resolved, for anything that times itself.
+#[derive(Clone, Copy)]
+pub struct TurnClock {
+    pub player_cost: i32,
+    pub turn: u64,
+}
+
+/// Which field-of-view routine `FovSystem` runs (`--fov`). Both fill a
+/// `Viewshed` the same way; only the shape of the lit area differs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FovAlgorithm {
+    /// bracket-pathfinding's `field_of_view`.
+    #[default]
+    Bracket,
+    /// Recursive shadowcasting, octant by octant.
+    Shadowcast,
+}
+
+impl FovAlgorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "bracket" => Some(Self::Bracket),
+            "shadowcast" => Some(Self::Shadowcast),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Bracket => "bracket",
+            Self::Shadowcast => "shadowcast",
+        }
+    }
+}
+
+/// Debug-only switches systems consult; all off in normal play.
+#[derive(Clone, Copy, Default)]
+pub struct DebugFlags {
+    /// Player takes no damage; blocked hits are logged instead.
+    pub godmode: bool,
+}
+
+/// Optional combat rules the monster systems follow.
+#[derive(Clone, Copy, Default)]
+pub struct CombatRules {
+    /// A monster whose way is blocked by a monster of another world
+    /// attacks it (`--infighting`).
+    pub infighting: bool,
+    /// Novas and detonations also sear the player when they land within
+    /// the blast (`--friendly-fire`).
+    pub friendly_fire: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CombatEventKind {
+    /// The player hits `other`.
+    PlayerStrike,
+    /// `other` hits the player.
+    FoeClaw,
+}
+
+/// A damage event the log can fold into a running tally when the same kind
+/// of hit against the same party repeats.
+#[derive(Clone, Debug)]
+pub struct CombatEvent {
+    pub kind: CombatEventKind,
+    pub other: String,
+    pub amount: i32,
+}
+
+impl CombatEvent {
+    /// Describes `count` consecutive events of this kind totalling `total`.
+    pub fn describe(&self, count: u32, total: i32) -> String {
+        let name = &self.other;
+        match (self.kind, count) {
+            (CombatEventKind::PlayerStrike, 1) => format!("You strike {name} for {total}"),
+            (CombatEventKind::PlayerStrike, _) => {
+                format!("You strike {name} ×{count} for {total}")
+            }
+            (CombatEventKind::FoeClaw, 1) => format!("{name} claws you for {total}"),
+            (CombatEventKind::FoeClaw, _) => format!("{name} claws you ×{count} for {total}"),
+        }
+    }
+
+    pub fn same_streak(&self, other: &CombatEvent) -> bool {
+        self.kind == other.kind && self.other == other.other
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum LogLine {
+    Text(String),
+    Event(CombatEvent),
+    /// Monster flavour chatter, kept apart so the log can style it.
+    Bark(String),
+}
+
 #[derive(Default)]
 pub struct CombatLog {
-    pub entries: Vec<String>,
+    pub entries: Vec<LogLine>,
 }
 
 impl CombatLog {
     pub fn push<S: Into<String>>(&mut self, entry: S) {
-        self.entries.push(entry.into());
+        self.entries.push(LogLine::Text(entry.into()));
+    }
+
+    pub fn push_event(&mut self, event: CombatEvent) {
+        self.entries.push(LogLine::Event(event));
+    }
+
+    pub fn push_bark(&mut self, bark: String) {
+        self.entries.push(LogLine::Bark(bark));
     }
 }
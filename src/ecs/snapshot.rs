@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+use specs::prelude::{Builder, Join, WorldExt};
+
+use super::{
+    EcsWorld,
+    components::{
+        Actor, Burning, CombatStats, Cursed, Equipment, Friendly, Inventory, Monster, MonsterBrain,
+        MonsterTag, Npc, PlaneAttunements, PlayerTag, Position, Renderable, Size, Slowed, Viewshed,
+        WorldAffinity,
+    },
+};
+
+/// One entity's persistent components. Transient state (pending intents,
+/// the combat log) is deliberately left out and rebuilt on the next turn.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EntitySnapshot {
+    pub position: Option<Position>,
+    pub renderable: Option<Renderable>,
+    pub viewshed: Option<Viewshed>,
+    pub actor: Option<Actor>,
+    pub affinity: Option<WorldAffinity>,
+    pub attunements: Option<PlaneAttunements>,
+    pub monster: Option<Monster>,
+    pub brain: Option<MonsterBrain>,
+    pub stats: Option<CombatStats>,
+    pub inventory: Option<Inventory>,
+    #[serde(default)]
+    pub equipment: Option<Equipment>,
+    #[serde(default)]
+    pub size: Option<Size>,
+    #[serde(default)]
+    pub cursed: Option<Cursed>,
+    #[serde(default)]
+    pub npc: Option<Npc>,
+    #[serde(default)]
+    pub burning: Option<Burning>,
+    #[serde(default)]
+    pub slowed: Option<Slowed>,
+    #[serde(default)]
+    pub player: bool,
+    #[serde(default)]
+    pub monster_tag: bool,
+    #[serde(default)]
+    pub friendly: bool,
+}
+
+/// Everything needed to rebuild an `EcsWorld`: every entity on every layer,
+/// plus the turn counter and any energy surcharge owed by the player.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EcsSnapshot {
+    pub turn: u64,
+    pub pending_player_surcharge: i32,
+    #[serde(default)]
+    pub detect_life_turns: u32,
+    #[serde(default)]
+    pub levitate_turns: u32,
+    #[serde(default)]
+    pub attunement_turns: u32,
+    pub entities: Vec<EntitySnapshot>,
+}
+
+impl EcsWorld {
+    pub fn snapshot(&self) -> EcsSnapshot {
+        let world = &self.specs_world;
+        let positions = world.read_component::<Position>();
+        let renderables = world.read_component::<Renderable>();
+        let viewsheds = world.read_component::<Viewshed>();
+        let actors = world.read_component::<Actor>();
+        let affinities = world.read_component::<WorldAffinity>();
+        let attunements = world.read_component::<PlaneAttunements>();
+        let monsters = world.read_component::<Monster>();
+        let brains = world.read_component::<MonsterBrain>();
+        let stats = world.read_component::<CombatStats>();
+        let inventories = world.read_component::<Inventory>();
+        let equipment = world.read_component::<Equipment>();
+        let sizes = world.read_component::<Size>();
+        let curses = world.read_component::<Cursed>();
+        let npcs = world.read_component::<Npc>();
+        let burning = world.read_component::<Burning>();
+        let slowed = world.read_component::<Slowed>();
+        let players = world.read_component::<PlayerTag>();
+        let monster_tags = world.read_component::<MonsterTag>();
+        let friendlies = world.read_component::<Friendly>();
+
+        let entities = world
+            .entities()
+            .join()
+            .map(|entity| EntitySnapshot {
+                position: positions.get(entity).cloned(),
+                renderable: renderables.get(entity).cloned(),
+                viewshed: viewsheds.get(entity).cloned(),
+                actor: actors.get(entity).cloned(),
+                affinity: affinities.get(entity).cloned(),
+                attunements: attunements.get(entity).cloned(),
+                monster: monsters.get(entity).cloned(),
+                brain: brains.get(entity).cloned(),
+                stats: stats.get(entity).cloned(),
+                inventory: inventories.get(entity).cloned(),
+                equipment: equipment.get(entity).cloned(),
+                size: sizes.get(entity).copied(),
+                cursed: curses.get(entity).cloned(),
+                npc: npcs.get(entity).cloned(),
+                burning: burning.get(entity).cloned(),
+                slowed: slowed.get(entity).cloned(),
+                player: players.contains(entity),
+                monster_tag: monster_tags.contains(entity),
+                friendly: friendlies.contains(entity),
+            })
+            .collect();
+
+        EcsSnapshot {
+            turn: self.turn,
+            pending_player_surcharge: self.pending_player_surcharge,
+            detect_life_turns: self.detect_life_turns,
+            levitate_turns: self.levitate_turns,
+            attunement_turns: self.attunement_turns,
+            entities,
+        }
+    }
+
+    /// Rebuilds a world from a snapshot. The RNG is reseeded from `seed` and
+    /// the saved turn, so rolls after a load are reproducible but not a
+    /// continuation of the original stream.
+    pub fn from_snapshot(snapshot: &EcsSnapshot, seed: u64) -> Result<Self, String> {
+        let mut specs_world = Self::empty_specs_world(seed ^ snapshot.turn);
+        let mut player = None;
+        for saved in &snapshot.entities {
+            let mut builder = specs_world.create_entity();
+            if let Some(position) = saved.position.clone() {
+                builder = builder.with(position);
+            }
+            if let Some(renderable) = saved.renderable.clone() {
+                builder = builder.with(renderable);
+            }
+            if let Some(mut viewshed) = saved.viewshed.clone() {
+                viewshed.dirty = true;
+                builder = builder.with(viewshed);
+            }
+            if let Some(actor) = saved.actor.clone() {
+                builder = builder.with(actor);
+            }
+            if let Some(affinity) = saved.affinity.clone() {
+                builder = builder.with(affinity);
+            }
+            if let Some(attunements) = saved.attunements.clone() {
+                builder = builder.with(attunements);
+            }
+            if let Some(monster) = saved.monster.clone() {
+                builder = builder.with(monster);
+            }
+            if let Some(brain) = saved.brain.clone() {
+                builder = builder.with(brain);
+            }
+            if let Some(stats) = saved.stats.clone() {
+                builder = builder.with(stats);
+            }
+            if let Some(inventory) = saved.inventory.clone() {
+                builder = builder.with(inventory);
+            }
+            if let Some(equipment) = saved.equipment.clone() {
+                builder = builder.with(equipment);
+            }
+            if let Some(size) = saved.size {
+                builder = builder.with(size);
+            }
+            if let Some(curse) = saved.cursed.clone() {
+                builder = builder.with(curse);
+            }
+            if let Some(npc) = saved.npc.clone() {
+                builder = builder.with(npc);
+            }
+            if let Some(burning) = saved.burning.clone() {
+                builder = builder.with(burning);
+            }
+            if let Some(slowed) = saved.slowed.clone() {
+                builder = builder.with(slowed);
+            }
+            if saved.player {
+                builder = builder.with(PlayerTag);
+            }
+            if saved.monster_tag {
+                builder = builder.with(MonsterTag);
+            }
+            if saved.friendly {
+                builder = builder.with(Friendly);
+            }
+            let entity = builder.build();
+            if saved.player {
+                if player.is_some() {
+                    return Err("save holds more than one player".to_string());
+                }
+                if saved.position.is_none() || saved.stats.is_none() {
+                    return Err("saved player is missing its position or stats".to_string());
+                }
+                player = Some(entity);
+            }
+        }
+        let player = player.ok_or_else(|| "save holds no player".to_string())?;
+        let mut world = Self::from_parts(
+            specs_world,
+            player,
+            snapshot.turn,
+            snapshot.pending_player_surcharge,
+        );
+        world.detect_life_turns = snapshot.detect_life_turns;
+        world.levitate_turns = snapshot.levitate_turns;
+        world.attunement_turns = snapshot.attunement_turns;
+        Ok(world)
+    }
+}
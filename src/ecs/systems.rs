@@ -1,29 +1,114 @@
 #![allow(dead_code)]
 
-use bracket_geometry::prelude::Point;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bracket_geometry::prelude::{Point, line2d_bresenham};
 use bracket_pathfinding::prelude::{Algorithm2D, BaseMap, DistanceAlg, field_of_view};
-use bracket_random::prelude::RandomNumberGenerator;
 use smallvec::SmallVec;
 use specs::prelude::*;
 
+use crate::{
+    ai::{BehaviorContext, Decision},
+    data::monsters::{Barks, PURSUIT_TURNS},
+    map::World,
+};
+
 use super::{
     components::{
-        Actor, CombatStats, IntentStep, Monster, MonsterBrain, MonsterTag, PlayerTag, Position,
-        Viewshed,
+        ACTION_COST, Actor, BURN_CHANCE, Burning, CURSE_CHANCE, CURSE_TURNS, CombatStats, Cursed,
+        IntentStep, Monster, MonsterBrain, MonsterTag, PlayerTag, Position, SLOW_CHANCE,
+        SLOW_TURNS, Size, Slowed, Viewshed, WorldAffinity, footprint,
+    },
+    resources::{
+        AiRng, CombatEvent, CombatEventKind, CombatLog, CombatRng, CombatRules, DebugFlags,
+        FovAlgorithm, MovementContext, TurnClock,
     },
-    resources::{CombatLog, MovementContext},
 };
 
+/// Percent chance each turn a phasing monster blinks toward the player.
+pub const PHASE_CHANCE: i32 = 20;
+/// Farthest a phasing monster's blink carries it.
+const PHASE_RANGE: usize = 3;
+/// Steps a fleeing monster will search for a tile out of the player's sight.
+const COVER_SEARCH_STEPS: usize = 8;
+
 #[derive(Default)]
 pub struct EnergySystem;
 
 impl<'a> System<'a> for EnergySystem {
-    type SystemData = WriteStorage<'a, Actor>;
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Actor>,
+        ReadStorage<'a, PlayerTag>,
+        ReadStorage<'a, Position>,
+        ReadExpect<'a, MovementContext>,
+        ReadExpect<'a, TurnClock>,
+        ReadStorage<'a, Slowed>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut actors, players, positions, movement, clock, slowed): Self::SystemData,
+    ) {
+        // A chill cuts speed only for earning energy; `Actor.speed` itself
+        // keeps its world and modifier bonuses.
+        let effective = |entity: Entity, actor: &Actor| {
+            if slowed.contains(entity) {
+                Slowed::effective_speed(actor.speed)
+            } else {
+                actor.speed
+            }
+        };
+        let player_speed = (&entities, &actors, &players)
+            .join()
+            .next()
+            .map(|(entity, actor, _)| effective(entity, actor))
+            .unwrap_or(ACTION_COST)
+            .max(1);
+        for (entity, actor, pos, _) in (&entities, &mut actors, &positions, !&players).join() {
+            if pos.floor != movement.floor || pos.world != movement.world {
+                continue;
+            }
+            let earned = effective(entity, actor) * clock.player_cost / player_speed;
+            // Cap the bank at one spare action so idle monsters can't hoard turns.
+            actor.energy = (actor.energy + earned).min(ACTION_COST * 2);
+        }
+    }
+}
+
+/// Tiles spoken for during the current monster turn. Seeded with every
+/// non-player occupant, then updated as each monster commits to a step so
+/// followers route around the pack instead of chasing the same tile.
+#[derive(Default)]
+pub struct StepReservations {
+    claimed: HashSet<Point>,
+}
+
+impl StepReservations {
+    pub fn from_occupants(points: impl IntoIterator<Item = Point>) -> Self {
+        Self {
+            claimed: points.into_iter().collect(),
+        }
+    }
+
+    pub fn is_free(&self, point: Point) -> bool {
+        !self.claimed.contains(&point)
+    }
+
+    /// True when every cell in `cells` is unclaimed or already held by the
+    /// mover itself (`own`), so large monsters can shuffle into their own
+    /// footprint.
+    pub fn fits(&self, cells: &[Point], own: &[Point]) -> bool {
+        cells
+            .iter()
+            .all(|cell| own.contains(cell) || self.is_free(*cell))
+    }
 
-    fn run(&mut self, mut actors: Self::SystemData) {
-        for actor in (&mut actors).join() {
-            actor.energy = actor.energy.saturating_add(actor.speed);
+    pub fn claim(&mut self, from: &[Point], to: &[Point]) {
+        for cell in from {
+            self.claimed.remove(cell);
         }
+        self.claimed.extend(to.iter().copied());
     }
 }
 
@@ -36,10 +121,19 @@ impl<'a> System<'a> for WanderSystem {
         WriteStorage<'a, IntentStep>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, MonsterTag>,
-        ReadStorage<'a, MonsterBrain>,
+        WriteStorage<'a, MonsterBrain>,
         ReadExpect<'a, MovementContext>,
         ReadStorage<'a, CombatStats>,
-        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, AiRng>,
+        ReadStorage<'a, PlayerTag>,
+        WriteStorage<'a, Actor>,
+        ReadStorage<'a, Size>,
+        ReadStorage<'a, Monster>,
+        WriteExpect<'a, CombatLog>,
+        ReadExpect<'a, TurnClock>,
+        ReadStorage<'a, WorldAffinity>,
+        Read<'a, CombatRules>,
+        ReadStorage<'a, Viewshed>,
     );
 
     fn run(
@@ -49,10 +143,19 @@ impl<'a> System<'a> for WanderSystem {
             mut intents,
             positions,
             monsters,
-            brains,
+            mut brains,
             movement,
             stats,
             mut rng,
+            players,
+            mut actors,
+            sizes,
+            kinds,
+            mut log,
+            clock,
+            affinities,
+            rules,
+            viewsheds,
         ): Self::SystemData,
     ) {
         let dirs = [
@@ -61,31 +164,149 @@ impl<'a> System<'a> for WanderSystem {
             Point::new(0, 1),
             Point::new(0, -1),
         ];
-        for (entity, pos, _, brain) in (&entities, &positions, &monsters, &brains).join() {
+        let mut reservations = StepReservations::from_occupants(
+            (&entities, &positions, !&players)
+                .join()
+                .filter(|(_, pos, _)| pos.floor == movement.floor && pos.world == movement.world)
+                .flat_map(|(entity, pos, _)| footprint(pos.point, sizes.get(entity))),
+        );
+        // Which world each monster-held cell answers to, for infighting.
+        let allegiance: HashMap<Point, World> = if rules.infighting {
+            (&entities, &positions, &monsters, &affinities)
+                .join()
+                .filter(|(_, pos, _, _)| pos.floor == movement.floor && pos.world == movement.world)
+                .flat_map(|(entity, pos, _, affinity)| {
+                    footprint(pos.point, sizes.get(entity))
+                        .into_iter()
+                        .map(move |cell| (cell, affinity.primary))
+                })
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        for (entity, pos, _, brain) in (&entities, &positions, &monsters, &mut brains).join() {
             if pos.floor != movement.floor || pos.world != movement.world {
                 continue;
             }
+            if let Some(actor) = actors.get_mut(entity) {
+                if actor.energy < ACTION_COST {
+                    continue;
+                }
+                actor.energy -= ACTION_COST;
+            }
 
             let mut acted = false;
+            let size = sizes.get(entity).copied().unwrap_or_default();
+            let own = size.cells(pos.point);
+
+            // Indigo mindstorms let phasing monsters close the gap in one
+            // jump. The roll only happens for monsters that could use it.
+            let phases = kinds.get(entity).is_some_and(|kind| kind.phases)
+                && movement.world == World::Indigo
+                && size == Size::default();
+            if phases {
+                let distance = DistanceAlg::Pythagoras.distance2d(pos.point, movement.player_point);
+                if distance > 1.5
+                    && distance <= brain.aggro_radius
+                    && rng.range(0, 100) < PHASE_CHANCE
+                    && let Some(landing) =
+                        phase_landing(pos.point, movement.player_point, &movement, &reservations)
+                {
+                    reservations.claim(&own, &[landing]);
+                    let delta = Point::new(landing.x - pos.point.x, landing.y - pos.point.y);
+                    let _ = intents.insert(entity, IntentStep { delta });
+                    continue;
+                }
+            }
 
             if let Some(stat) = stats.get(entity) {
+                let nearest = size.nearest_cell(pos.point, movement.player_point);
                 let player_distance =
-                    DistanceAlg::Pythagoras.distance2d(pos.point, movement.player_point);
-                let hp_ratio = stat.hp as f32 / stat.max_hp as f32;
-                if pos.floor == movement.floor && pos.world == movement.world {
-                    if hp_ratio <= 0.3 && player_distance < 6.0 {
-                        if let Some(step) = step_away(pos.point, movement.player_point, &movement) {
-                            let _ = intents.insert(entity, IntentStep { delta: step });
-                            acted = true;
-                        }
-                    } else if player_distance <= 8.0 {
-                        if let Some(step) =
-                            step_towards(pos.point, movement.player_point, &movement)
+                    DistanceAlg::Pythagoras.distance2d(nearest, movement.player_point);
+                let mover = Mover {
+                    anchor: pos.point,
+                    size,
+                };
+                // A monster with its own field of view goes by that; ones
+                // loaded from older saves fall back to a straight sightline.
+                let sees_player = player_distance <= brain.aggro_radius
+                    && match viewsheds.get(entity) {
+                        Some(viewshed) => viewshed.visible.contains(&movement.player_point),
+                        None => in_line_of_sight(movement.player_point, nearest, &movement),
+                    };
+                if sees_player {
+                    brain.last_seen_player = Some((movement.player_point, clock.turn));
+                }
+                // The trail goes cold once it is reached or too old.
+                let trail = brain
+                    .last_seen_player
+                    .filter(|(spot, seen)| {
+                        !own.contains(spot) && clock.turn.saturating_sub(*seen) <= PURSUIT_TURNS
+                    })
+                    .map(|(spot, _)| spot);
+                if trail.is_none() {
+                    brain.last_seen_player = None;
+                }
+                let context = BehaviorContext {
+                    focus_world: pos.world,
+                    hp_ratio: stat.hp as f32 / stat.max_hp as f32,
+                    player_distance,
+                    sees_player,
+                    trail,
+                };
+                let decision = brain.behavior.behavior().decide(&context);
+                let goal = match decision {
+                    Decision::Chase => Some(movement.player_point),
+                    Decision::Pursue(spot) => Some(spot),
+                    Decision::Flee | Decision::Wander => None,
+                };
+                let step = match decision {
+                    Decision::Flee => {
+                        // A fleeing monster already out of sight stays hidden.
+                        if size == Size::default()
+                            && !in_line_of_sight(movement.player_point, pos.point, &movement)
                         {
-                            let _ = intents.insert(entity, IntentStep { delta: step });
-                            acted = true;
+                            continue;
+                        }
+                        step_to_cover(mover, movement.player_point, &movement, &reservations)
+                            .or_else(|| {
+                                step_away(mover, movement.player_point, &movement, &reservations)
+                            })
+                    }
+                    Decision::Chase => {
+                        // The first chase may come with a shout.
+                        if !brain.alerted {
+                            brain.alerted = true;
+                            if let Some(kind) = kinds.get(entity)
+                                && let Some(bark) = Barks::roll(
+                                    Barks::for_world(pos.world).aggro,
+                                    &kind.name,
+                                    &mut rng,
+                                )
+                            {
+                                log.push_bark(bark);
+                            }
                         }
+                        step_towards(mover, movement.player_point, &movement, &reservations)
                     }
+                    Decision::Pursue(spot) => step_towards(mover, spot, &movement, &reservations),
+                    Decision::Wander => None,
+                };
+                // Boxed in by a monster of another world: go through it.
+                if step.is_none()
+                    && let Some(goal) = goal
+                    && let Some(affinity) = affinities.get(entity)
+                    && let Some(dir) =
+                        rival_in_way(mover, goal, affinity.primary, &allegiance, &movement)
+                {
+                    let _ = intents.insert(entity, IntentStep { delta: dir });
+                    continue;
+                }
+                if let Some(step) = step {
+                    let target = Point::new(pos.point.x + step.x, pos.point.y + step.y);
+                    reservations.claim(&own, &size.cells(target));
+                    let _ = intents.insert(entity, IntentStep { delta: step });
+                    acted = true;
                 }
             }
 
@@ -98,8 +319,11 @@ impl<'a> System<'a> for WanderSystem {
                 continue;
             }
             let dir = dirs[rng.range(0, dirs.len() as i32) as usize];
-            let target = Point::new(pos.point.x + dir.x, pos.point.y + dir.y);
-            if movement.is_walkable(target) {
+            let cells = size.cells(Point::new(pos.point.x + dir.x, pos.point.y + dir.y));
+            if cells.iter().all(|cell| movement.is_walkable(*cell))
+                && reservations.fits(&cells, &own)
+            {
+                reservations.claim(&own, &cells);
                 let _ = intents.insert(entity, IntentStep { delta: dir });
             }
         }
@@ -120,6 +344,14 @@ impl<'a> System<'a> for MovementSystem {
         WriteStorage<'a, CombatStats>,
         ReadStorage<'a, Monster>,
         WriteExpect<'a, CombatLog>,
+        Read<'a, DebugFlags>,
+        ReadStorage<'a, Size>,
+        WriteStorage<'a, Cursed>,
+        WriteExpect<'a, CombatRng>,
+        WriteStorage<'a, Burning>,
+        WriteStorage<'a, Slowed>,
+        ReadStorage<'a, WorldAffinity>,
+        Read<'a, CombatRules>,
     );
 
     fn run(
@@ -134,6 +366,14 @@ impl<'a> System<'a> for MovementSystem {
             mut stats,
             monsters,
             mut combat_log,
+            debug_flags,
+            sizes,
+            mut curses,
+            mut rng,
+            mut burning,
+            mut slowed,
+            affinities,
+            rules,
         ): Self::SystemData,
     ) {
         let mut player_snapshot = {
@@ -144,49 +384,183 @@ impl<'a> System<'a> for MovementSystem {
                 .map(|(entity, pos, _)| (entity, pos.clone()))
         };
 
+        let mut occupied: HashSet<Point> = (&entities, &positions)
+            .join()
+            .filter(|(_, pos)| pos.floor == movement.floor && pos.world == movement.world)
+            .flat_map(|(entity, pos)| footprint(pos.point, sizes.get(entity)))
+            .collect();
+        // Which monster holds each cell, kept only while infighting is on.
+        let mut holders: HashMap<Point, Entity> = if rules.infighting {
+            (&entities, &positions, &monsters)
+                .join()
+                .filter(|(_, pos, _)| pos.floor == movement.floor && pos.world == movement.world)
+                .flat_map(|(entity, pos, _)| {
+                    footprint(pos.point, sizes.get(entity))
+                        .into_iter()
+                        .map(move |cell| (cell, entity))
+                })
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let player_sees: HashSet<Point> = player_snapshot
+            .as_ref()
+            .filter(|_| rules.infighting)
+            .and_then(|(player, _)| viewsheds.get(*player))
+            .map(|vs| vs.visible.iter().copied().collect())
+            .unwrap_or_default();
+        let mut infighting_log = Vec::new();
+
         let mut to_clear = Vec::new();
         for (entity, pos, intent) in (&entities, &mut positions, &intents).join() {
             if pos.floor != movement.floor || pos.world != movement.world {
                 continue;
             }
             let target = Point::new(pos.point.x + intent.delta.x, pos.point.y + intent.delta.y);
+            let own = footprint(pos.point, sizes.get(entity));
+            let cells = footprint(target, sizes.get(entity));
 
-            if let Some((player_entity_id, player_pos)) = player_snapshot.as_mut() {
-                if target == player_pos.point
-                    && pos.floor == player_pos.floor
-                    && pos.world == player_pos.world
-                    && entity != *player_entity_id
+            // A monster felled earlier this pass doesn't get to act.
+            if monsters.contains(entity) && stats.get(entity).is_some_and(|stat| stat.hp == 0) {
+                to_clear.push(entity);
+                continue;
+            }
+
+            if let Some((player_entity_id, player_pos)) = player_snapshot.as_mut()
+                && cells.contains(&player_pos.point)
+                && pos.floor == player_pos.floor
+                && pos.world == player_pos.world
+                && entity != *player_entity_id
+            {
+                if let (Some(attacker_stats), Some(player_stats)) =
+                    (stats.get(entity).cloned(), stats.get_mut(*player_entity_id))
                 {
-                    if let (Some(attacker_stats), Some(player_stats)) =
-                        (stats.get(entity).cloned(), stats.get_mut(*player_entity_id))
+                    let defense = match (affinities.get(entity), affinities.get(*player_entity_id))
                     {
-                        let damage = (attacker_stats.power - player_stats.defense).max(1);
-                        player_stats.hp = player_stats.hp.saturating_sub(damage);
-                        let name = monsters
-                            .get(entity)
-                            .map(|m| m.name.clone())
-                            .unwrap_or_else(|| "foe".to_string());
-                        combat_log.push(format!("{name} claws you for {damage}"));
-                        if player_stats.hp == 0 {
-                            combat_log.push("You feel your spectrum shatter.".to_string());
+                        (Some(attacker), Some(player)) => {
+                            player.defense_against(attacker.primary, player_stats.defense)
                         }
+                        _ => player_stats.defense,
+                    };
+                    let damage = (attacker_stats.power - defense).max(1);
+                    let name = monsters
+                        .get(entity)
+                        .map(|m| m.name.clone())
+                        .unwrap_or_else(|| "foe".to_string());
+                    if debug_flags.godmode {
+                        combat_log.push(format!("{name} claws at you."));
+                        combat_log.push(format!("(godmode) blocked {damage} damage."));
+                        continue;
+                    }
+                    player_stats.hp = (player_stats.hp - damage).max(0);
+                    combat_log.push_event(CombatEvent {
+                        kind: CombatEventKind::FoeClaw,
+                        other: name.clone(),
+                        amount: damage,
+                    });
+                    if player_stats.hp == 0 {
+                        combat_log.push("You feel your spectrum shatter.".to_string());
+                    } else if pos.world == World::Violet && rng.range(0, 100) < CURSE_CHANCE {
+                        if !curses.contains(*player_entity_id) {
+                            combat_log.push(format!("{name}'s touch leaves a curse on you."));
+                        }
+                        let _ = curses.insert(
+                            *player_entity_id,
+                            Cursed {
+                                turns_left: CURSE_TURNS,
+                            },
+                        );
+                    } else if pos.world == World::Red
+                        && rng.range(0, 100) < BURN_CHANCE
+                        && Burning::ignite(&mut burning, *player_entity_id)
+                    {
+                        combat_log.push(format!("{name}'s blow sets you alight!"));
+                    } else if pos.world == World::Blue
+                        && rng.range(0, 100) < SLOW_CHANCE
+                        && Slowed::chill(&mut slowed, *player_entity_id, SLOW_TURNS)
+                    {
+                        combat_log.push(format!("{name}'s frost slows you to a crawl!"));
                     }
-                    continue;
                 }
+                continue;
+            }
+
+            // Infighting: walking into a monster of another world is a blow.
+            let rival = monsters
+                .get(entity)
+                .and(affinities.get(entity))
+                .and_then(|affinity| {
+                    cells
+                        .iter()
+                        .filter(|cell| !own.contains(cell))
+                        .filter_map(|cell| holders.get(cell).map(|&other| (*cell, other)))
+                        .find(|&(_, other)| {
+                            affinities
+                                .get(other)
+                                .is_some_and(|theirs| theirs.primary != affinity.primary)
+                                && stats.get(other).is_some_and(|stat| stat.hp > 0)
+                        })
+                });
+            if let Some((cell, victim)) = rival {
+                if let Some(attacker_stats) = stats.get(entity).cloned()
+                    && let Some(victim_stats) = stats.get_mut(victim)
+                {
+                    let defense = match (affinities.get(entity), affinities.get(victim)) {
+                        (Some(attacker), Some(defender)) => {
+                            defender.defense_against(attacker.primary, victim_stats.defense)
+                        }
+                        _ => victim_stats.defense,
+                    };
+                    let damage = (attacker_stats.power - defense).max(1);
+                    victim_stats.hp = (victim_stats.hp - damage).max(0);
+                    if player_sees.contains(&cell) {
+                        let name = |who: Entity| {
+                            monsters
+                                .get(who)
+                                .map(|m| m.name.clone())
+                                .unwrap_or_else(|| "foe".to_string())
+                        };
+                        let (attacker, defender) = (name(entity), name(victim));
+                        infighting_log
+                            .push(format!("{attacker} turns on {defender} for {damage}."));
+                        if victim_stats.hp == 0 {
+                            infighting_log.push(format!("{defender} falls to {attacker}."));
+                        }
+                    }
+                }
+                to_clear.push(entity);
+                continue;
             }
 
-            if movement.is_walkable(target) {
+            // Every destination cell must be open; a large body can't squeeze
+            // through a gap narrower than itself. The player may walk into
+            // lava, which nothing else will.
+            let is_player = players.contains(entity);
+            if cells.iter().all(|cell| {
+                (movement.is_walkable(*cell) || (is_player && movement.is_lava(*cell)))
+                    && (own.contains(cell) || !occupied.contains(cell))
+            }) {
+                for cell in &own {
+                    occupied.remove(cell);
+                }
+                occupied.extend(cells.iter().copied());
+                if rules.infighting && monsters.contains(entity) {
+                    for cell in &own {
+                        holders.remove(cell);
+                    }
+                    holders.extend(cells.iter().map(|&cell| (cell, entity)));
+                }
                 pos.point = target;
                 if let Some(vs) = viewsheds.get_mut(entity) {
                     vs.dirty = true;
                 }
 
-                if let Some((player_entity_id, player_pos)) = player_snapshot.as_mut() {
-                    if entity == *player_entity_id {
-                        player_pos.point = pos.point;
-                        player_pos.floor = pos.floor;
-                        player_pos.world = pos.world;
-                    }
+                if let Some((player_entity_id, player_pos)) = player_snapshot.as_mut()
+                    && entity == *player_entity_id
+                {
+                    player_pos.point = pos.point;
+                    player_pos.floor = pos.floor;
+                    player_pos.world = pos.world;
                 }
             }
             to_clear.push(entity);
@@ -195,6 +569,9 @@ impl<'a> System<'a> for MovementSystem {
         for entity in to_clear {
             intents.remove(entity);
         }
+        for line in infighting_log {
+            combat_log.push(line);
+        }
     }
 }
 
@@ -204,23 +581,24 @@ pub struct FovSystem;
 impl<'a> System<'a> for FovSystem {
     type SystemData = (
         ReadExpect<'a, MovementContext>,
+        Read<'a, FovAlgorithm>,
         WriteStorage<'a, Viewshed>,
         ReadStorage<'a, Position>,
+        ReadStorage<'a, PlayerTag>,
     );
 
-    fn run(&mut self, (movement, mut viewsheds, positions): Self::SystemData) {
-        let map = MovementFov { ctx: &*movement };
-        for (viewshed, pos) in (&mut viewsheds, &positions).join() {
+    fn run(&mut self, (movement, algorithm, mut viewsheds, positions, players): Self::SystemData) {
+        for (viewshed, pos, player) in (&mut viewsheds, &positions, players.maybe()).join() {
             if !viewshed.dirty || pos.floor != movement.floor || pos.world != movement.world {
                 continue;
             }
-            viewshed.visible = field_of_view(pos.point, viewshed.radius, &map)
-                .into_iter()
-                .filter(|point| movement.in_bounds(*point))
-                .collect();
-            for point in &viewshed.visible {
-                if !viewshed.remembered.contains(point) {
-                    viewshed.remembered.push(*point);
+            viewshed.visible = visible_from(pos.point, viewshed.radius, *algorithm, &movement);
+            // Only the player's memory feeds the map; monsters just look.
+            if player.is_some() {
+                for point in &viewshed.visible {
+                    if !viewshed.remembered.contains(point) {
+                        viewshed.remembered.push(*point);
+                    }
                 }
             }
             viewshed.dirty = false;
@@ -228,6 +606,123 @@ impl<'a> System<'a> for FovSystem {
     }
 }
 
+/// Tiles in sight from `origin` within `radius` under `algorithm`, minus
+/// any whose sightline pinches between two diagonal walls.
+fn visible_from(
+    origin: Point,
+    radius: i32,
+    algorithm: FovAlgorithm,
+    movement: &MovementContext,
+) -> Vec<Point> {
+    let lit = match algorithm {
+        FovAlgorithm::Bracket => field_of_view(origin, radius, &MovementFov { ctx: movement }),
+        FovAlgorithm::Shadowcast => shadowcast(origin, radius, movement),
+    };
+    lit.into_iter()
+        .filter(|point| movement.in_bounds(*point))
+        .filter(|point| !squeezes_through_corner(origin, *point, movement))
+        .collect()
+}
+
+/// Row/column transforms mapping the first octant onto each of the eight.
+const OCTANTS: [(i32, i32, i32, i32); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
+/// Every tile lit from `origin` within `radius`, by recursive
+/// shadowcasting. Walls are lit themselves but shade what lies behind.
+fn shadowcast(origin: Point, radius: i32, movement: &MovementContext) -> Vec<Point> {
+    let mut lit = vec![origin];
+    for octant in OCTANTS {
+        cast_light(origin, radius, 1, 1.0, 0.0, octant, movement, &mut lit);
+    }
+    lit.sort_by_key(|point| (point.y, point.x));
+    lit.dedup();
+    lit
+}
+
+/// Scans one octant row by row from `row`, between the `start` and `end`
+/// slopes, recursing past every wall run to light around it.
+#[allow(clippy::too_many_arguments)]
+fn cast_light(
+    origin: Point,
+    radius: i32,
+    row: i32,
+    mut start: f32,
+    end: f32,
+    (xx, xy, yx, yy): (i32, i32, i32, i32),
+    movement: &MovementContext,
+    lit: &mut Vec<Point>,
+) {
+    if start < end {
+        return;
+    }
+    for distance in row..=radius {
+        let dy = -distance;
+        let mut blocked = false;
+        let mut next_start = start;
+        for dx in -distance..=0 {
+            let left = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+            let right = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+            if start < right {
+                continue;
+            }
+            if end > left {
+                break;
+            }
+            let point = Point::new(origin.x + dx * xx + dy * xy, origin.y + dx * yx + dy * yy);
+            if dx * dx + dy * dy <= radius * radius {
+                lit.push(point);
+            }
+            let opaque = movement.blocks_sight(point);
+            if blocked {
+                if opaque {
+                    next_start = right;
+                } else {
+                    blocked = false;
+                    start = next_start;
+                }
+            } else if opaque && distance < radius {
+                blocked = true;
+                cast_light(
+                    origin,
+                    radius,
+                    distance + 1,
+                    start,
+                    left,
+                    (xx, xy, yx, yy),
+                    movement,
+                    lit,
+                );
+                next_start = right;
+            }
+        }
+        if blocked {
+            break;
+        }
+    }
+}
+
+/// True when the straight sightline from `from` to `to` steps diagonally
+/// between two opaque tiles. Shadowcasting lets light leak through such a
+/// pinch, showing tiles no one could see past a wall corner.
+fn squeezes_through_corner(from: Point, to: Point, movement: &MovementContext) -> bool {
+    line2d_bresenham(from, to).windows(2).any(|step| {
+        let (a, b) = (step[0], step[1]);
+        a.x != b.x
+            && a.y != b.y
+            && movement.blocks_sight(Point::new(b.x, a.y))
+            && movement.blocks_sight(Point::new(a.x, b.y))
+    })
+}
+
 struct MovementFov<'a> {
     ctx: &'a MovementContext,
 }
@@ -273,32 +768,351 @@ impl<'a> Algorithm2D for MovementFov<'a> {
     }
 }
 
-fn step_towards(from: Point, to: Point, movement: &MovementContext) -> Option<Point> {
+/// A monster weighing up a step: where its top-left cell is and how much
+/// ground it covers.
+#[derive(Clone, Copy)]
+struct Mover {
+    anchor: Point,
+    size: Size,
+}
+
+impl Mover {
+    fn cells_after(&self, dir: Point) -> Vec<Point> {
+        self.size
+            .cells(Point::new(self.anchor.x + dir.x, self.anchor.y + dir.y))
+    }
+
+    fn can_walk(&self, dir: Point, movement: &MovementContext) -> bool {
+        self.cells_after(dir)
+            .iter()
+            .all(|cell| movement.is_walkable(*cell))
+    }
+
+    fn is_free(&self, dir: Point, reservations: &StepReservations) -> bool {
+        reservations.fits(&self.cells_after(dir), &self.size.cells(self.anchor))
+    }
+}
+
+/// The farthest open tile, up to [`PHASE_RANGE`] along the line toward
+/// `to`, that a one-tile monster at `from` can blink onto. Walls between
+/// don't matter; the landing itself must be walkable and unclaimed, and
+/// never the player's tile.
+fn phase_landing(
+    from: Point,
+    to: Point,
+    movement: &MovementContext,
+    reservations: &StepReservations,
+) -> Option<Point> {
+    line2d_bresenham(from, to)
+        .into_iter()
+        .filter(|&point| point != from && point != to)
+        .take(PHASE_RANGE)
+        .filter(|&point| movement.is_walkable(point) && reservations.is_free(point))
+        .last()
+}
+
+/// True when nothing opaque stands between `from` and `to`, by the same
+/// rules the FOV uses.
+fn in_line_of_sight(from: Point, to: Point, movement: &MovementContext) -> bool {
+    let line = line2d_bresenham(from, to);
+    let interior = line.len().saturating_sub(1);
+    line.iter()
+        .take(interior)
+        .skip(1)
+        .all(|point| !movement.blocks_sight(*point))
+        && !squeezes_through_corner(from, to, movement)
+}
+
+/// First step along the shortest walk to the nearest tile the player at
+/// `threat` can't see, searching [`COVER_SEARCH_STEPS`] deep. `None` when
+/// no cover is in reach or the way there is blocked by another monster;
+/// only one-tile monsters look for cover.
+fn step_to_cover(
+    mover: Mover,
+    threat: Point,
+    movement: &MovementContext,
+    reservations: &StepReservations,
+) -> Option<Point> {
+    if mover.size != Size::default() {
+        return None;
+    }
+    let dirs = [
+        Point::new(1, 0),
+        Point::new(-1, 0),
+        Point::new(0, 1),
+        Point::new(0, -1),
+    ];
+    // Each reached tile remembers the first step that led there.
+    let mut first_step: HashMap<Point, Point> = HashMap::new();
+    let mut frontier = VecDeque::from([(mover.anchor, 0)]);
+    while let Some((point, depth)) = frontier.pop_front() {
+        if depth >= COVER_SEARCH_STEPS {
+            continue;
+        }
+        for dir in dirs {
+            let next = Point::new(point.x + dir.x, point.y + dir.y);
+            if next == mover.anchor
+                || next == threat
+                || first_step.contains_key(&next)
+                || !movement.is_walkable(next)
+            {
+                continue;
+            }
+            let step = first_step.get(&point).copied().unwrap_or(dir);
+            if depth == 0 && !reservations.is_free(next) {
+                continue;
+            }
+            first_step.insert(next, step);
+            if !in_line_of_sight(threat, next, movement) {
+                return Some(step);
+            }
+            frontier.push_back((next, depth + 1));
+        }
+    }
+    None
+}
+
+fn step_towards(
+    mover: Mover,
+    to: Point,
+    movement: &MovementContext,
+    reservations: &StepReservations,
+) -> Option<Point> {
+    let from = mover.size.nearest_cell(mover.anchor, to);
     let dx = (to.x - from.x).clamp(-1, 1);
     let dy = (to.y - from.y).clamp(-1, 1);
-    try_steps(from, dx, dy, movement)
+    try_steps(mover, dx, dy, movement, reservations)
 }
 
-fn step_away(from: Point, to: Point, movement: &MovementContext) -> Option<Point> {
+/// The step toward `to` that runs into a monster of a world other than
+/// `affinity`, if one stands in the way. `allegiance` is empty unless
+/// infighting is on.
+fn rival_in_way(
+    mover: Mover,
+    to: Point,
+    affinity: World,
+    allegiance: &HashMap<Point, World>,
+    movement: &MovementContext,
+) -> Option<Point> {
+    let from = mover.size.nearest_cell(mover.anchor, to);
+    let own = mover.size.cells(mover.anchor);
+    [
+        Point::new((to.x - from.x).clamp(-1, 1), 0),
+        Point::new(0, (to.y - from.y).clamp(-1, 1)),
+    ]
+    .into_iter()
+    .filter(|&dir| dir != Point::new(0, 0) && mover.can_walk(dir, movement))
+    .find(|&dir| {
+        mover.cells_after(dir).iter().any(|cell| {
+            !own.contains(cell) && allegiance.get(cell).is_some_and(|&world| world != affinity)
+        })
+    })
+}
+
+fn step_away(
+    mover: Mover,
+    to: Point,
+    movement: &MovementContext,
+    reservations: &StepReservations,
+) -> Option<Point> {
+    let from = mover.size.nearest_cell(mover.anchor, to);
     let dx = (from.x - to.x).clamp(-1, 1);
     let dy = (from.y - to.y).clamp(-1, 1);
-    try_steps(from, dx, dy, movement)
+    try_steps(mover, dx, dy, movement, reservations)
 }
 
-fn try_steps(from: Point, dx: i32, dy: i32, movement: &MovementContext) -> Option<Point> {
+fn try_steps(
+    mover: Mover,
+    dx: i32,
+    dy: i32,
+    movement: &MovementContext,
+    reservations: &StepReservations,
+) -> Option<Point> {
     let axes = if dx.abs() >= dy.abs() {
         [Point::new(dx, 0), Point::new(0, dy)]
     } else {
         [Point::new(0, dy), Point::new(dx, 0)]
     };
+    let mut crowded = false;
     for dir in axes {
         if dir == Point::new(0, 0) {
             continue;
         }
-        let target = Point::new(from.x + dir.x, from.y + dir.y);
-        if movement.is_walkable(target) {
+        if !mover.can_walk(dir, movement) {
+            continue;
+        }
+        if mover.is_free(dir, reservations) {
+            return Some(dir);
+        }
+        crowded = true;
+    }
+    // Only another monster stands in the way: sidestep so the follower slides
+    // around it instead of stalling. Walls alone never trigger a sidestep.
+    if !crowded {
+        return None;
+    }
+    let sidesteps = if dx.abs() >= dy.abs() {
+        [Point::new(0, 1), Point::new(0, -1)]
+    } else {
+        [Point::new(1, 0), Point::new(-1, 0)]
+    };
+    for dir in sidesteps {
+        if (dir.x != 0 && dir.x == -dx) || (dir.y != 0 && dir.y == -dy) {
+            continue;
+        }
+        if mover.can_walk(dir, movement) && mover.is_free(dir, reservations) {
             return Some(dir);
         }
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{FloorId, MapLayer, Tile};
+
+    /// A one-tile-wide east-west corridor along y = 1.
+    fn corridor(length: i32) -> MovementContext {
+        let mut layer = MapLayer::empty(World::Red, length, 3);
+        layer.tiles.iter_mut().for_each(|tile| *tile = Tile::wall());
+        for x in 0..length {
+            layer.set_tile(Point::new(x, 1), Tile::floor(World::Red));
+        }
+        MovementContext::from_layer(&layer, FloorId(0), World::Red, Point::new(length - 1, 1))
+    }
+
+    fn mover(x: i32) -> Mover {
+        Mover {
+            anchor: Point::new(x, 1),
+            size: Size::default(),
+        }
+    }
+
+    #[test]
+    fn follower_waits_behind_a_reserved_corridor_tile() {
+        let movement = corridor(10);
+        let leader = Point::new(2, 1);
+        let follower = Point::new(1, 1);
+        let mut reservations = StepReservations::from_occupants([leader, follower]);
+
+        assert_eq!(
+            step_towards(mover(1), movement.player_point, &movement, &reservations),
+            None
+        );
+
+        let step = step_towards(mover(2), movement.player_point, &movement, &reservations)
+            .expect("the leader's way is clear");
+        let target = Point::new(leader.x + step.x, leader.y + step.y);
+        reservations.claim(&[leader], &[target]);
+
+        assert_eq!(
+            step_towards(mover(1), movement.player_point, &movement, &reservations),
+            Some(Point::new(1, 0))
+        );
+    }
+
+    /// A context over `rows`: `#` wall, anything else open floor.
+    fn plan(rows: &[&str]) -> MovementContext {
+        let width = rows[0].len() as i32;
+        let mut layer = MapLayer::empty(World::Red, width, rows.len() as i32);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, glyph) in row.chars().enumerate() {
+                let tile = if glyph == '#' {
+                    Tile::wall()
+                } else {
+                    Tile::floor(World::Red)
+                };
+                layer.set_tile(Point::new(x as i32, y as i32), tile);
+            }
+        }
+        MovementContext::from_layer(&layer, FloorId(0), World::Red, Point::new(1, 1))
+    }
+
+    #[test]
+    fn a_diagonal_pair_of_walls_blocks_the_sightline_between_them() {
+        let movement = plan(&[
+            "#######", "#.....#", "#.@#..#", "#.#...#", "#.....#", "#######",
+        ]);
+        for algorithm in [FovAlgorithm::Bracket, FovAlgorithm::Shadowcast] {
+            let visible = visible_from(Point::new(2, 2), 8, algorithm, &movement);
+            assert!(visible.contains(&Point::new(1, 1)), "{algorithm:?}");
+            for hidden in [Point::new(3, 3), Point::new(4, 4)] {
+                assert!(!visible.contains(&hidden), "{algorithm:?} sees {hidden:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn a_fleeing_monster_ducks_behind_a_pillar() {
+        let movement = plan(&[
+            "###########",
+            "#.........#",
+            "#.....#...#",
+            "#.........#",
+            "###########",
+        ]);
+        let player = Point::new(1, 2);
+        let mut at = Point::new(5, 2);
+        assert!(in_line_of_sight(player, at, &movement));
+        for _ in 0..COVER_SEARCH_STEPS {
+            if !in_line_of_sight(player, at, &movement) {
+                break;
+            }
+            let reservations = StepReservations::from_occupants([player, at]);
+            let step = step_to_cover(
+                Mover {
+                    anchor: at,
+                    size: Size::default(),
+                },
+                player,
+                &movement,
+                &reservations,
+            )
+            .expect("the pillar offers cover");
+            at = Point::new(at.x + step.x, at.y + step.y);
+        }
+        assert!(!in_line_of_sight(player, at, &movement));
+        assert!(at.x > 6, "it hides in the pillar's shadow, not at {at:?}");
+    }
+
+    #[test]
+    fn reservations_let_large_monsters_shuffle_into_their_own_cells() {
+        let own = Size::square(2).cells(Point::new(0, 0));
+        let shifted = Size::square(2).cells(Point::new(1, 0));
+        let reservations = StepReservations::from_occupants(own.clone());
+        assert!(reservations.fits(&shifted, &own));
+        assert!(!reservations.fits(&shifted, &[]));
+    }
+
+    #[test]
+    fn both_fov_algorithms_light_the_room_and_stop_at_its_walls() {
+        let movement = plan(&[
+            "###########",
+            "#.....#...#",
+            "#.....#...#",
+            "#.....#...#",
+            "###########",
+        ]);
+        let origin = Point::new(3, 2);
+        let sights = [FovAlgorithm::Bracket, FovAlgorithm::Shadowcast]
+            .map(|algorithm| visible_from(origin, 8, algorithm, &movement));
+        for (algorithm, visible) in [FovAlgorithm::Bracket, FovAlgorithm::Shadowcast]
+            .iter()
+            .zip(&sights)
+        {
+            for y in 1..4 {
+                for x in 1..6 {
+                    let floor = Point::new(x, y);
+                    assert!(visible.contains(&floor), "{algorithm:?} misses {floor:?}");
+                }
+                let beyond = Point::new(8, y);
+                assert!(!visible.contains(&beyond), "{algorithm:?} sees {beyond:?}");
+            }
+            assert!(
+                visible.contains(&Point::new(6, 2)),
+                "the wall itself is lit"
+            );
+        }
+    }
+}
@@ -1,35 +1,102 @@
 mod ai;
+mod bench;
+mod config;
 mod data;
 mod ecs;
 mod map;
+mod objective;
 mod render;
+mod save;
+mod screenshot;
+mod script_condition;
 mod scripted_input;
+mod seed;
 
-use ai::BehaviorContext;
 use bracket_geometry::prelude::Point;
 use bracket_random::prelude::RandomNumberGenerator;
 use bracket_terminal::prelude::*;
-use chrono;
 
-use data::monsters::MonsterTemplate;
-use ecs::EcsWorld;
-use map::{Dungeon, FloorId, SPECTRUM, Tile, World};
-use render::{HudRing, draw_log, draw_map};
-use scripted_input::ScriptedInput;
+use config::GameOptions;
+use data::{
+    items::{
+        ENCHANT_CAP, RESONANCE_SHARD_CHANCE, enchant_chance, enchant_cost, floor_item_template,
+        resonance_shard, starter_consumables,
+    },
+    monsters::{MonsterModifier, MonsterTemplate},
+    npcs::{NpcRole, NpcTemplate, ShopOffer, shop_stock},
+};
+use ecs::{
+    AttackReport, EcsWorld, PACK_SLOTS,
+    components::{GearSlot, Size},
+    resources::{CombatEvent, CombatEventKind, LogLine},
+};
+use map::{
+    ACID_CLOUD_DAMAGE, ACID_CLOUD_TURNS, ALL_FRAGMENTS, COLLAPSE_DAMAGE, CURSE_DAMAGE, Corpse,
+    Dungeon, EntitySnapshot, FLOOR_ITEMS_PER_LAYER, FloorId, FloorItem, LAVA_DAMAGE, MapSnapshot,
+    SEARCH_CHANCE, SPECTRUM, Tile, WATER_SURCHARGE, World, collapse_rings, fragment_bit, wind_name,
+    world_color,
+};
+use objective::{OBJECTIVE_REWARD_SHARDS, ObjectiveKind, ObjectiveProbe};
+use render::{
+    BURNING_TINT, CHILLED_TINT, CRITICAL_HP_RATIO, EntityVisibility, FRAGMENT_GLYPH, HudRing,
+    Layout, MonsterMemory, critical_pulse, draw_critical_border, draw_debug_overlay,
+    draw_layer_unavailable, draw_log, draw_map, draw_status_row, ease_look_ahead,
+    entity_draw_style, status_indicators, status_row_width, world_rule_line,
+};
+use save::{
+    CloudMark, CorpseMark, DepthSplit, ItemMark, OpenDoor, RevealedLayer, SAVE_SLOTS, SAVE_VERSION,
+    SaveGame, SlotSummary, TileMark,
+};
+use script_condition::ScriptProbe;
+use scripted_input::{PendingWait, ScriptStep, ScriptedInput};
+use seed::RngStream;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, env, fs, io, path::Path};
+use specs::Entity;
+use std::{
+    collections::{BTreeMap, HashSet},
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, LineWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
-const MAP_ORIGIN_X: i32 = 2;
-const MAP_ORIGIN_Y: i32 = 7;
-const LOG_RESERVED_ROWS: i32 = 7;
-const LOG_MAX_ENTRIES: usize = 8;
 const RUN_STATS_PATH: &str = "run_stats.json";
 const RESET_CONFIRM_WINDOW_FRAMES: u64 = 300; // ~5 seconds at 60 FPS
+/// Pack slots shown, and keyed `1`–`5`, on one quickbar page.
+const QUICKBAR_PAGE_SLOTS: usize = 5;
+/// Turns between endless-mode waves.
+const ENDLESS_WAVE_TURNS: u64 = 40;
+/// Most monsters a single endless wave can bring.
+const ENDLESS_WAVE_CAP: usize = 10;
+/// Waves never land closer to the player than this.
+const ENDLESS_WAVE_MIN_DISTANCE: f32 = 8.0;
+/// Percent of spawns that, under `--infighting`, stray in from a
+/// neighbouring world and keep its allegiance.
+const STRAY_SPAWN_PERCENT: i32 = 25;
+/// Most turns one `Shift+.` rest waits out.
+const REST_TURNS: u32 = 20;
+/// Furthest a consumable can be thrown.
+const THROW_RANGE: f32 = 6.0;
+/// Most state-machine steps one frame may take; a full player turn and the
+/// monster follow-up need three.
+const TURN_LOOP_LIMIT: u32 = 4;
+/// Turns left on a collapsing floor's clock at which the log warns you.
+const COLLAPSE_WARNINGS: [u64; 5] = [10, 5, 3, 2, 1];
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct RunStats {
     run_number: u32,
     best_depth: u32,
+    /// Runs ended by restoring the spectrum.
+    #[serde(default)]
+    wins: u32,
+    /// Most turns survived in an endless run.
+    #[serde(default)]
+    best_endless_score: u64,
+    /// Fewest turns a speed run has taken to first reach each floor.
+    #[serde(default)]
+    best_splits: BTreeMap<u32, u64>,
 }
 
 impl Default for RunStats {
@@ -37,6 +104,9 @@ impl Default for RunStats {
         Self {
             run_number: 1,
             best_depth: 0,
+            wins: 0,
+            best_endless_score: 0,
+            best_splits: BTreeMap::new(),
         }
     }
 }
@@ -52,12 +122,44 @@ impl RunStats {
     }
 
     fn persist_to_disk(&self) -> io::Result<()> {
-        let bytes = serde_json::to_vec_pretty(self)
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        let bytes = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
         fs::write(RUN_STATS_PATH, bytes)
     }
 }
 
+/// Coalesces `run_stats.json` writes: changes only mark the stats dirty,
+/// and a dirty file is written at most once per `interval` (the first
+/// change writes straight away). Death, victory, and quitting flush
+/// regardless.
+struct StatsThrottle {
+    interval: Duration,
+    last_write: Option<Instant>,
+    dirty: bool,
+}
+
+impl StatsThrottle {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_write: None,
+            dirty: false,
+        }
+    }
+
+    /// Whether a dirty write is due at `now`.
+    fn due(&self, now: Instant) -> bool {
+        self.dirty
+            && self
+                .last_write
+                .is_none_or(|at| now.duration_since(at) >= self.interval)
+    }
+
+    fn wrote(&mut self, now: Instant) {
+        self.dirty = false;
+        self.last_write = Some(now);
+    }
+}
+
 #[derive(Clone)]
 struct StairCue {
     icon: &'static str,
@@ -70,56 +172,270 @@ enum InputSource {
     Scripted,
 }
 
+/// What a directional step runs into; see [`RainbowRogueState::bump_at`].
+enum Bump {
+    /// A closed door, which swings open.
+    Door,
+    /// A friendly occupant, who trades places.
+    Friend(Entity),
+    /// Anything else standing there, which takes a blow.
+    Foe,
+    /// Nothing; the player walks in and interacts with what lies there.
+    Open,
+}
+
+/// Something the player asks for in play, whatever key or script sent it.
+/// [`RainbowRogueState::action_for_key`] maps keys onto these and
+/// [`RainbowRogueState::apply_action`] carries them out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PlayerAction {
+    /// Step one tile, bumping whatever stands there.
+    Move(i32, i32),
+    /// Shift+direction: keep stepping until something interesting happens.
+    Run(i32, i32),
+    /// Ctrl+direction: attack without moving.
+    Strike(i32, i32),
+    Wait,
+    /// Shift+`.`: keep waiting until disturbed or [`REST_TURNS`] pass.
+    Rest,
+    /// Force a turn to resolve (`T`).
+    StepTurn,
+    Search,
+    CloseDoor,
+    PickUp,
+    Talk,
+    CycleWorld(i32),
+    OpenWorldMenu,
+    ShiftFloor(i32),
+    /// Use the consumable in pack slot `n` (0-based, across pages).
+    UseSlot(usize),
+    FlipQuickbar,
+    StartCraft,
+    StartThrow,
+    OpenEnchant,
+    ToggleAutoPickup,
+    OpenSlotMenu(SlotMenuMode),
+    Screenshot,
+    DumpState,
+    ToggleOverlay,
+    ToggleGodmode,
+    /// `R`: asks for confirmation before abandoning the run.
+    Reset,
+    /// Save and quit.
+    Quit,
+}
+
+/// A Shift+direction run in progress. `sides` records whether the tiles
+/// flanking the run direction were open on the last step, so a change in
+/// that shape (a branch, a doorway, a room edge) can end the run.
+#[derive(Clone, Copy)]
+struct AutoRun {
+    delta: Point,
+    sides: (bool, bool),
+    last_point: Point,
+    /// Player HP when the last step was taken.
+    hp: i32,
+}
+
+/// A Shift+`.` rest in progress.
+#[derive(Clone, Copy)]
+struct AutoRest {
+    turns_left: u32,
+    /// Player HP when the last turn was waited out.
+    hp: i32,
+}
+
+/// How long a log line holds on once the log is full: the oldest line of
+/// the lowest priority present is dropped first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+enum LogPriority {
+    /// Exploration notes and monster barks.
+    Chatter,
+    #[default]
+    Normal,
+    /// Hits taken, critical vitality, and death.
+    Alert,
+}
+
+/// The newest log line, when it is a tally of repeated combat events.
+struct LogStreak {
+    event: CombatEvent,
+    count: u32,
+    total: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SlotMenuMode {
+    Save,
+    Load,
+}
+
+/// The F6/F7 slot picker. `confirm` holds a slot awaiting Y/N before an
+/// occupied save slot is overwritten.
+struct SlotMenu {
+    mode: SlotMenuMode,
+    summaries: Vec<Option<SlotSummary>>,
+    confirm: Option<usize>,
+}
+
+/// Crafting in progress after `X`: `first` is the slot already chosen.
+struct CraftPick {
+    first: Option<usize>,
+}
+
+/// Throwing after `F`: `slot` is the consumable picked, then `target` is
+/// the tile aimed at, nudged with the direction keys.
+struct ThrowPick {
+    slot: Option<usize>,
+    target: Point,
+}
+
+/// Talking to an NPC after `I`. `line` is the flavor line on show;
+/// `shop` holds the wares while the shop page is open.
+struct Dialog {
+    speaker: String,
+    role: NpcRole,
+    line: usize,
+    shop: Option<Vec<ShopOffer>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum RunState {
     AwaitingInput,
+    /// A turn was taken; [`RainbowRogueState::advance_turns`] resolves it.
     PlayerTurn,
-    MonsterTurn,
 }
 
 struct RainbowRogueState {
     dungeon: Dungeon,
     ecs: EcsWorld,
-    behavior: BehaviorContext,
     hud: HudRing,
     active_world: World,
     active_floor: FloorId,
     frame: u64,
     message_log: Vec<String>,
+    /// Priority of each `message_log` line, index for index.
+    log_priorities: Vec<LogPriority>,
     last_move_attempt: Option<(Point, Point)>,
     visible_tiles: HashSet<Point>,
+    monster_memory: MonsterMemory,
     hp_alerted: bool,
     hp_ratio: f32,
     seeded_floors: HashSet<u32>,
     run_stats: RunStats,
     run_max_floor: u32,
+    /// Spectrum fragments collected this run, one bit per world.
+    fragments: u8,
     is_dead: bool,
+    /// All seven fragments were brought back to floor 0.
+    has_won: bool,
+    /// Endless-mode waves spawned so far this run.
+    endless_waves: u32,
+    /// Turn each new depth was first reached this run, deepest last.
+    depth_turns: Vec<DepthSplit>,
+    /// Turn each floor was first arrived on, for `--collapse`.
+    collapse_clocks: BTreeMap<u32, u64>,
+    /// Turns the latest speed-run split gained or lost against the best
+    /// that stood before it.
+    split_delta: Option<i64>,
     reset_prompt_frame: Option<u64>,
     needs_prime_tick: bool,
     verbose: bool,
     play_history: Vec<String>,
+    /// Append-mode `--log-file` sink; line buffered so a crash loses at most
+    /// the line being written.
+    log_file: Option<LineWriter<File>>,
     input_source: InputSource,
     scripted_input: Option<ScriptedInput>,
     last_player_point: Option<Point>,
     run_state: RunState,
-}
-
-impl Default for RainbowRogueState {
-    fn default() -> Self {
-        Self::bootstrap(RunStats::load_from_disk())
-    }
+    debug_overlay: bool,
+    auto_run: Option<AutoRun>,
+    auto_rest: Option<AutoRest>,
+    options: GameOptions,
+    /// Seed behind every floor layout, monster roll, and combat roll this run.
+    master_seed: u64,
+    slot_menu: Option<SlotMenu>,
+    craft_pick: Option<CraftPick>,
+    throw_pick: Option<ThrowPick>,
+    /// The `E` enchant picker is open.
+    enchant_menu: bool,
+    /// The `V` world picker is open.
+    world_menu: bool,
+    /// Which five pack slots the `1`–`5` keys reach; flipped with `Q`.
+    quickbar_page: usize,
+    dialog: Option<Dialog>,
+    /// Startup prompt offering to resume from the autosave.
+    resume_prompt: bool,
+    log_streak: Option<LogStreak>,
+    /// Stepping onto an item collects it; toggled with `O`.
+    auto_pickup: bool,
+    /// What the last handled key that took no turn said, so even a no-op
+    /// leaves a trace that the key registered.
+    last_action: Option<String>,
+    stats_throttle: StatsThrottle,
+    /// Whether `run_stats.json` may be written at all; a debug
+    /// `--start-floor` jump or a headless tool run isn't a real run, so it
+    /// leaves the records alone.
+    persist_stats: bool,
+    /// Directory holding the autosave and the manual slots.
+    save_dir: PathBuf,
+    /// How far the camera currently leads the player (`--look-ahead`).
+    camera_lead: Point,
 }
 
 impl GameState for RainbowRogueState {
     fn tick(&mut self, ctx: &mut BTerm) {
+        self.recover_missing_layer();
         self.expire_reset_prompt();
+        self.write_run_stats_if_due();
+        let rng_before = (self.ecs.instance_id(), self.ecs.rng_fingerprint());
+        if let Some(script) = self.scripted_input.as_mut() {
+            script.start_frame(ctx.frame_time_ms);
+        }
+        // A fast script feeds several keys a frame, each resolved in full;
+        // every round but the last gets its own verbose dump here.
+        let (mut player_acted, mut monsters_acted) = self.resolve_turns(ctx);
+        let mut any_turn = player_acted || monsters_acted;
+        while !ctx.quitting && self.scripted_key_due() {
+            if self.verbose && (player_acted || monsters_acted) {
+                self.dump_verbose_frame(player_acted);
+            }
+            (player_acted, monsters_acted) = self.resolve_turns(ctx);
+            any_turn |= player_acted || monsters_acted;
+        }
+
+        // Frames where no turn resolved must leave every RNG stream alone, or a
+        // replayed seed would depend on how long the player idled.
+        if !any_turn && self.ecs.instance_id() == rng_before.0 {
+            debug_assert_eq!(
+                self.ecs.rng_fingerprint(),
+                rng_before.1,
+                "an RNG stream advanced on a frame where no turn resolved"
+            );
+        }
+
+        ctx.cls_bg(BLACK);
+        self.draw_scene(ctx);
+
+        if self.verbose && (player_acted || monsters_acted) {
+            self.dump_verbose_frame(player_acted);
+        }
+    }
+}
+
+impl RainbowRogueState {
+    /// Runs the turn state machine from `AwaitingInput` until it waits on
+    /// input again, returning whether the player and the monsters acted.
+    fn resolve_turns(&mut self, ctx: &mut BTerm) -> (bool, bool) {
         let mut player_acted = false;
         let mut monsters_acted = false;
         let mut guard = 0;
 
         loop {
             guard += 1;
-            if guard > 4 {
-                debug_assert!(false, "turn state machine exceeded expected iterations");
+            if guard > TURN_LOOP_LIMIT {
+                self.recover_stalled_turn();
                 break;
             }
 
@@ -134,33 +450,119 @@ impl GameState for RainbowRogueState {
                     break;
                 }
                 RunState::PlayerTurn => {
-                    self.run_turn(true);
-                    self.run_state = RunState::MonsterTurn;
-                    continue;
-                }
-                RunState::MonsterTurn => {
-                    let has_monster_intent = self.ecs.has_monster_intent();
-                    if has_monster_intent {
-                        self.run_turn(false);
-                        monsters_acted = true;
-                    }
-                    self.run_state = RunState::AwaitingInput;
+                    monsters_acted = self.advance_turns(1) > 0;
                     break;
                 }
             }
         }
+        (player_acted, monsters_acted)
+    }
 
-        ctx.cls_bg(BLACK);
-        self.draw_scene(ctx);
+    /// Resolves `turns` whole turns with no input and no drawing: each is
+    /// the player's turn (acting on whatever intent is queued, so a wait
+    /// when none is) followed by the monsters' reply. Live play runs every
+    /// turn through here, so tests can fast-forward monster moves, status
+    /// ticks, and spawns exactly as they happen in a run. Returns how many
+    /// of the turns left monster intents over for a pass of their own.
+    pub(crate) fn advance_turns(&mut self, turns: u32) -> u32 {
+        let mut monster_turns = 0;
+        for _ in 0..turns {
+            self.run_turn(true);
+            if self.ecs.has_monster_intent() {
+                self.run_turn(false);
+                monster_turns += 1;
+            }
+        }
+        self.run_state = RunState::AwaitingInput;
+        monster_turns
+    }
 
-        if self.verbose && (player_acted || monsters_acted) {
-            self.dump_verbose_frame(player_acted);
+    /// Snaps the player back to floor 0's spawn point when the active floor
+    /// has no layer to stand on (after a bad load, say), logging why rather
+    /// than leaving the map blank.
+    fn recover_missing_layer(&mut self) {
+        if self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+            .is_some()
+        {
+            return;
         }
+        let lost = self.active_floor;
+        eprintln!(
+            "[RR-ERROR] Floor {} has no {} layer ({} floors generated); recovering to floor 0",
+            lost.0,
+            self.active_world.as_str(),
+            self.dungeon.floors.len()
+        );
+        self.dungeon.ensure_floor(FloorId(0));
+        self.active_floor = FloorId(0);
+        let spawn = self.dungeon.spawn_point(self.active_floor);
+        self.ecs
+            .set_player_position(spawn, self.active_floor, self.active_world);
+        self.auto_run = None;
+        self.auto_rest = None;
+        self.last_move_attempt = None;
+        self.push_log_with(
+            format!(
+                "Floor {} couldn't be found; you are back at floor 0's spawn.",
+                lost.0
+            ),
+            LogPriority::Alert,
+        );
+        self.start_collapse_clock();
+        self.update_visibility();
     }
-}
 
-impl RainbowRogueState {
-    fn bootstrap(meta: RunStats) -> Self {
+    /// A scripted run still has a key to feed this frame. Auto-runs and
+    /// rests pace themselves, one turn a frame, so they hold the script
+    /// back.
+    fn scripted_key_due(&self) -> bool {
+        self.auto_run.is_none()
+            && self.auto_rest.is_none()
+            && self
+                .scripted_input
+                .as_ref()
+                .is_some_and(ScriptedInput::key_due)
+    }
+
+    /// Called when the turn loop runs past [`TURN_LOOP_LIMIT`]: reports the
+    /// state it was stuck in, in release builds too, then drops every
+    /// pending intent and any auto-run or rest so the next frame starts
+    /// from a clean `AwaitingInput`.
+    fn recover_stalled_turn(&mut self) {
+        eprintln!(
+            "[RR-ERROR] Turn loop stalled in {:?} (player intent pending: {}, monster intent pending: {}); resetting to AwaitingInput",
+            self.run_state,
+            self.ecs.has_player_intent(),
+            self.ecs.has_monster_intent()
+        );
+        self.ecs.clear_all_intents();
+        self.auto_run = None;
+        self.auto_rest = None;
+        self.last_move_attempt = None;
+        self.run_state = RunState::AwaitingInput;
+    }
+
+    fn new(options: GameOptions) -> Result<Self, String> {
+        let mut state = Self::bootstrap(RunStats::load_from_disk(), options, true)?;
+        if state.autosave_enabled() && save::autosave_path(&state.save_dir).exists() {
+            state.resume_prompt = true;
+            state.push_log_entry("An autosave waits. C continues it, N starts fresh.");
+        }
+        Ok(state)
+    }
+
+    /// A run for the headless tools (`--bench-spawns`, `--export-map`) and
+    /// tests: built exactly like a live one, but it never touches
+    /// `run_stats.json`.
+    fn headless(options: GameOptions) -> Result<Self, String> {
+        Self::bootstrap(RunStats::default(), options, false)
+    }
+
+    /// Starts a fresh run; fails only when the dungeon can't be built at
+    /// the requested map size.
+    fn bootstrap(meta: RunStats, options: GameOptions, persist: bool) -> Result<Self, String> {
         let args: Vec<String> = env::args().collect();
         let verbose = env::var("RR_VERBOSE")
             .map(|v| ["1", "true", "TRUE", "on", "ON"].contains(&v.as_str()))
@@ -172,9 +574,9 @@ impl RainbowRogueState {
 
         if let Some(script_path_idx) = args.iter().position(|arg| arg == "--scripted-input") {
             if let Some(path) = args.get(script_path_idx + 1) {
-                match ScriptedInput::from_file(path) {
+                match ScriptedInput::from_file(path, options.script_limits) {
                     Ok(si) => {
-                        scripted_input = Some(si);
+                        scripted_input = Some(si.with_speed(options.script_speed));
                         input_source = InputSource::Scripted;
                         println!("[RR-SCRIPT] Running with scripted input from: {}", path);
                     }
@@ -189,51 +591,128 @@ impl RainbowRogueState {
             }
         }
 
-        let dungeon = Dungeon::scaffolding_demo();
-        let active_world = World::Red;
-        let active_floor = FloorId(0);
+        let log_file = options.log_file.as_deref().and_then(|path| {
+            match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => Some(LineWriter::new(file)),
+                Err(err) => {
+                    eprintln!("[RR-ERROR] Cannot open log file {}: {err}", path.display());
+                    None
+                }
+            }
+        });
+        let master_seed = options.seed.unwrap_or_else(seed::random_master_seed);
+        let seed_code = seed::encode_seed_code(master_seed);
+        println!("[RR] Seed code: {seed_code}");
+        let mut dungeon = Dungeon::new(options.map_width, options.map_height, master_seed)?;
+        let active_world = options.start_world;
+        let active_floor = options.start_floor;
+        dungeon.ensure_floor(active_floor);
         let mut message_log: Vec<String> = data::builtin_rules()
             .into_iter()
             .map(|rule| format!("{} focus: {}", rule.world.as_str(), rule.notes))
             .collect();
-        message_log.truncate(LOG_MAX_ENTRIES);
+        message_log.truncate(options.log_history.saturating_sub(1));
+        message_log.push(format!("Seed code {seed_code}"));
         let player_pos = dungeon.spawn_point(active_floor);
-        let ecs = EcsWorld::new(player_pos, active_floor, active_world);
+        let mut ecs = EcsWorld::new(
+            player_pos,
+            active_floor,
+            active_world,
+            master_seed,
+            &options.loadout,
+        );
+        ecs.set_godmode(options.godmode);
+        ecs.set_fov_algorithm(options.fov);
+        ecs.set_infighting(options.infighting);
+        ecs.set_friendly_fire(options.friendly_fire);
+        let auto_pickup = options.auto_pickup;
+        let stats_interval = options.stats_interval;
+        let persist_stats = persist && active_floor == FloorId(0);
 
         let mut state = Self {
             dungeon,
             ecs,
-            behavior: BehaviorContext::new(active_world),
             hud: HudRing::new(),
             active_world,
             active_floor,
             frame: 0,
+            log_priorities: vec![LogPriority::Normal; message_log.len()],
             message_log,
             last_move_attempt: None,
             visible_tiles: HashSet::new(),
+            monster_memory: MonsterMemory::default(),
             hp_alerted: false,
             hp_ratio: 1.0,
             seeded_floors: HashSet::new(),
             run_stats: meta,
             run_max_floor: active_floor.0,
+            fragments: 0,
             is_dead: false,
+            has_won: false,
+            endless_waves: 0,
+            depth_turns: Vec::new(),
+            collapse_clocks: BTreeMap::new(),
+            split_delta: None,
             reset_prompt_frame: None,
             needs_prime_tick: true,
             verbose,
             play_history: Vec::new(),
+            log_file,
             input_source,
             scripted_input,
             last_player_point: Some(player_pos),
             run_state: RunState::AwaitingInput,
+            debug_overlay: false,
+            auto_run: None,
+            auto_rest: None,
+            options,
+            master_seed,
+            slot_menu: None,
+            craft_pick: None,
+            throw_pick: None,
+            enchant_menu: false,
+            world_menu: false,
+            quickbar_page: 0,
+            dialog: None,
+            resume_prompt: false,
+            log_streak: None,
+            auto_pickup,
+            last_action: None,
+            stats_throttle: StatsThrottle::new(Duration::from_secs(stats_interval.into())),
+            persist_stats,
+            save_dir: PathBuf::from(save::SAVE_DIR),
+            camera_lead: Point::zero(),
         };
-        state.seed_floor_monsters(state.active_floor);
+        state.apply_world_pace();
+        for floor in 0..=state.active_floor.0 {
+            state.seed_floor_monsters(FloorId(floor));
+        }
         state.record_depth(state.active_floor);
+        state.start_collapse_clock();
         state.update_visibility();
-        state
+        Ok(state)
     }
 
     fn handle_input(&mut self, ctx: &mut BTerm) -> bool {
+        if self.auto_run.is_some() {
+            if matches!(self.input_source, InputSource::Keyboard) && ctx.key.take().is_some() {
+                self.stop_running("You stop running.");
+                return false;
+            }
+            return self.continue_run();
+        }
+        if self.auto_rest.is_some() {
+            if matches!(self.input_source, InputSource::Keyboard) && ctx.key.take().is_some() {
+                self.stop_resting("You stop resting.");
+                return false;
+            }
+            return self.continue_rest();
+        }
+
         let mut consumed_turn = false;
+        let history_before = self.play_history.len();
+        let shift = matches!(self.input_source, InputSource::Keyboard) && ctx.shift;
+        let control = matches!(self.input_source, InputSource::Keyboard) && ctx.control;
         let key = match self.input_source {
             InputSource::Keyboard => {
                 let k = ctx.key;
@@ -241,7 +720,15 @@ impl RainbowRogueState {
                 k
             }
             InputSource::Scripted => {
-                let k = self.scripted_input.as_mut().and_then(|si| si.next_key());
+                // Out of keys for this frame under `--script-speed`.
+                if !self
+                    .scripted_input
+                    .as_mut()
+                    .is_some_and(ScriptedInput::take_key)
+                {
+                    return false;
+                }
+                let k = self.next_scripted_key();
                 if k.is_none() {
                     // If script is exhausted, signal to quit the game
                     // by returning VirtualKeyCode::Escape, which will be handled below.
@@ -253,12 +740,40 @@ impl RainbowRogueState {
         };
 
         if let Some(key) = key {
-            if self.is_dead {
+            if self.resume_prompt {
+                self.handle_resume_key(key);
+                return false;
+            }
+            if self.slot_menu.is_some() {
+                self.handle_slot_menu_key(key);
+                return false;
+            }
+            if self.craft_pick.is_some() && !self.run_over() {
+                return self.handle_craft_key(key);
+            }
+            if self.throw_pick.is_some() && !self.run_over() {
+                return self.handle_throw_key(key);
+            }
+            if self.enchant_menu && !self.run_over() {
+                return self.handle_enchant_key(key);
+            }
+            if self.world_menu && !self.run_over() {
+                return self.handle_world_key(key);
+            }
+            if self.dialog.is_some() && !self.run_over() {
+                self.handle_dialog_key(key);
+                return false;
+            }
+            if self.run_over() {
                 match key {
                     VirtualKeyCode::R => {
                         self.reset_run();
                         return false;
                     }
+                    VirtualKeyCode::F7 => {
+                        self.open_slot_menu(SlotMenuMode::Load);
+                        return false;
+                    }
                     VirtualKeyCode::Escape => {
                         ctx.quit();
                         return false;
@@ -267,67 +782,194 @@ impl RainbowRogueState {
                 }
             }
 
-            consumed_turn = match key {
-                VirtualKeyCode::Left | VirtualKeyCode::A | VirtualKeyCode::H | VirtualKeyCode::Numpad4 => {
-                    self.try_step(-1, 0)
-                }
-                VirtualKeyCode::Right | VirtualKeyCode::D | VirtualKeyCode::L | VirtualKeyCode::Numpad6 => {
-                    self.try_step(1, 0)
-                }
-                VirtualKeyCode::Up | VirtualKeyCode::W | VirtualKeyCode::K | VirtualKeyCode::Numpad8 => {
-                    self.try_step(0, -1)
-                }
-                VirtualKeyCode::Down | VirtualKeyCode::S | VirtualKeyCode::J | VirtualKeyCode::Numpad2 => {
-                    self.try_step(0, 1)
-                }
+            let Some(action) = self.action_for_key(key, shift, control) else {
+                return false;
+            };
+            consumed_turn = self.apply_action(ctx, action);
+            if !consumed_turn {
+                self.echo_idle_key(key, history_before);
+            }
+        }
+        consumed_turn
+    }
 
-                // Diagonals
-                VirtualKeyCode::Y | VirtualKeyCode::Numpad7 => self.try_step(-1, -1),
-                VirtualKeyCode::U | VirtualKeyCode::Numpad9 => self.try_step(1, -1),
-                VirtualKeyCode::B | VirtualKeyCode::Numpad1 => self.try_step(-1, 1),
-                VirtualKeyCode::N | VirtualKeyCode::Numpad3 => self.try_step(1, 1),
+    /// The action `key` asks for during play, or `None` for unbound keys.
+    /// `shift` and `control` turn direction keys into runs and strikes.
+    fn action_for_key(
+        &self,
+        key: VirtualKeyCode,
+        shift: bool,
+        control: bool,
+    ) -> Option<PlayerAction> {
+        let toward = |dx, dy| {
+            if control {
+                PlayerAction::Strike(dx, dy)
+            } else if shift {
+                PlayerAction::Run(dx, dy)
+            } else {
+                PlayerAction::Move(dx, dy)
+            }
+        };
+        let action = match key {
+            VirtualKeyCode::Left
+            | VirtualKeyCode::A
+            | VirtualKeyCode::H
+            | VirtualKeyCode::Numpad4 => toward(-1, 0),
+            VirtualKeyCode::Right
+            | VirtualKeyCode::D
+            | VirtualKeyCode::L
+            | VirtualKeyCode::Numpad6 => toward(1, 0),
+            VirtualKeyCode::Up
+            | VirtualKeyCode::W
+            | VirtualKeyCode::K
+            | VirtualKeyCode::Numpad8 => toward(0, -1),
+            VirtualKeyCode::Down
+            | VirtualKeyCode::S
+            | VirtualKeyCode::J
+            | VirtualKeyCode::Numpad2 => toward(0, 1),
 
-                VirtualKeyCode::Tab => self.cycle_world(1),
-                VirtualKeyCode::Back => self.cycle_world(-1),
-                VirtualKeyCode::PageUp => self.shift_floor(1),
-                VirtualKeyCode::PageDown => self.shift_floor(-1),
-                VirtualKeyCode::Key1 => self.activate_consumable(0),
-                VirtualKeyCode::Key2 => self.activate_consumable(1),
-                VirtualKeyCode::Key3 => self.activate_consumable(2),
-                VirtualKeyCode::Key4 => self.activate_consumable(3),
-                VirtualKeyCode::R => {
-                    self.handle_reset_request();
-                    false
-                }
-                VirtualKeyCode::Escape => {
-                    ctx.quit();
-                    if matches!(self.input_source, InputSource::Scripted) {
-                        std::process::exit(0); // Force exit for scripted runs
-                    }
-                    false
-                }
-                VirtualKeyCode::Period => {
-                    // This is a "wait" command, consumes a turn but does nothing
-                    true
-                }
-                VirtualKeyCode::T => {
-                    // Step Turn command: forces a turn advancement
-                    self.run_state = RunState::PlayerTurn; // Force player turn to trigger run_turn
-                    true
-                }
-                VirtualKeyCode::P => {
-                    // Dump State command: dumps current game state to verbose log
-                    self.dump_current_state();
-                    false // Does not consume a turn
+            // Diagonals
+            VirtualKeyCode::Y | VirtualKeyCode::Numpad7 => toward(-1, -1),
+            VirtualKeyCode::U | VirtualKeyCode::Numpad9 => toward(1, -1),
+            VirtualKeyCode::B | VirtualKeyCode::Numpad1 => toward(-1, 1),
+            VirtualKeyCode::N | VirtualKeyCode::Numpad3 => toward(1, 1),
+
+            VirtualKeyCode::C => PlayerAction::CloseDoor,
+            VirtualKeyCode::Numpad5 | VirtualKeyCode::Z => PlayerAction::Search,
+            VirtualKeyCode::X => PlayerAction::StartCraft,
+            VirtualKeyCode::F => PlayerAction::StartThrow,
+            VirtualKeyCode::E => PlayerAction::OpenEnchant,
+            VirtualKeyCode::I => PlayerAction::Talk,
+            VirtualKeyCode::O => PlayerAction::ToggleAutoPickup,
+            VirtualKeyCode::Comma | VirtualKeyCode::G => PlayerAction::PickUp,
+            VirtualKeyCode::V => PlayerAction::OpenWorldMenu,
+            VirtualKeyCode::Tab => PlayerAction::CycleWorld(1),
+            VirtualKeyCode::Back => PlayerAction::CycleWorld(-1),
+            VirtualKeyCode::PageUp => PlayerAction::ShiftFloor(1),
+            VirtualKeyCode::PageDown => PlayerAction::ShiftFloor(-1),
+            VirtualKeyCode::Key1
+            | VirtualKeyCode::Key2
+            | VirtualKeyCode::Key3
+            | VirtualKeyCode::Key4
+            | VirtualKeyCode::Key5 => PlayerAction::UseSlot(self.quickbar_slot(key)?),
+            VirtualKeyCode::Q => PlayerAction::FlipQuickbar,
+            VirtualKeyCode::R => PlayerAction::Reset,
+            VirtualKeyCode::Escape => PlayerAction::Quit,
+            VirtualKeyCode::Period if shift => PlayerAction::Rest,
+            VirtualKeyCode::Period => PlayerAction::Wait,
+            VirtualKeyCode::T => PlayerAction::StepTurn,
+            VirtualKeyCode::P => PlayerAction::DumpState,
+            VirtualKeyCode::F3 => PlayerAction::ToggleOverlay,
+            VirtualKeyCode::F4 => PlayerAction::ToggleGodmode,
+            VirtualKeyCode::F12 => PlayerAction::Screenshot,
+            VirtualKeyCode::F6 => PlayerAction::OpenSlotMenu(SlotMenuMode::Save),
+            VirtualKeyCode::F7 => PlayerAction::OpenSlotMenu(SlotMenuMode::Load),
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    /// Carries out `action`; true when it took a turn.
+    fn apply_action(&mut self, ctx: &mut BTerm, action: PlayerAction) -> bool {
+        match action {
+            PlayerAction::Move(dx, dy) => self.try_step(dx, dy),
+            PlayerAction::Run(dx, dy) => self.start_run(dx, dy),
+            PlayerAction::Strike(dx, dy) => self.attack_in_place(dx, dy),
+            // Waiting takes a turn and does nothing else.
+            PlayerAction::Wait => true,
+            PlayerAction::Rest => self.start_rest(),
+            PlayerAction::StepTurn => {
+                self.run_state = RunState::PlayerTurn;
+                true
+            }
+            PlayerAction::Search => self.search(),
+            PlayerAction::CloseDoor => self.close_adjacent_door(),
+            PlayerAction::PickUp => self.pick_up_here(),
+            PlayerAction::Talk => {
+                self.talk_to_adjacent_npc();
+                false
+            }
+            PlayerAction::CycleWorld(delta) => self.cycle_world(delta),
+            PlayerAction::OpenWorldMenu => {
+                self.auto_run = None;
+                self.world_menu = true;
+                false
+            }
+            PlayerAction::ShiftFloor(delta) => self.shift_floor(delta),
+            PlayerAction::UseSlot(slot) => self.activate_consumable(slot),
+            PlayerAction::FlipQuickbar => {
+                self.flip_quickbar_page();
+                false
+            }
+            PlayerAction::StartCraft => {
+                self.start_crafting();
+                false
+            }
+            PlayerAction::StartThrow => {
+                self.start_throw();
+                false
+            }
+            PlayerAction::OpenEnchant => {
+                self.auto_run = None;
+                self.enchant_menu = true;
+                false
+            }
+            PlayerAction::ToggleAutoPickup => {
+                self.toggle_auto_pickup();
+                false
+            }
+            PlayerAction::OpenSlotMenu(mode) => {
+                self.open_slot_menu(mode);
+                false
+            }
+            PlayerAction::Screenshot => {
+                self.take_screenshot();
+                false
+            }
+            PlayerAction::DumpState => {
+                // Dumps to the console under --verbose; takes no turn.
+                self.dump_current_state();
+                false
+            }
+            PlayerAction::ToggleOverlay => {
+                // Tints walkability and sightlines, verbose runs only.
+                self.toggle_debug_overlay();
+                false
+            }
+            PlayerAction::ToggleGodmode => {
+                // --debug runs only.
+                self.toggle_godmode();
+                false
+            }
+            PlayerAction::Reset => {
+                self.handle_reset_request();
+                false
+            }
+            PlayerAction::Quit => {
+                self.autosave();
+                self.flush_run_stats();
+                ctx.quit();
+                if matches!(self.input_source, InputSource::Scripted) {
+                    std::process::exit(0); // Force exit for scripted runs
                 }
-                _ => false,
-            };
+                false
+            }
         }
-        consumed_turn
     }
 
-    fn dump_current_state(&self) {
+    /// Makes sure a handled key that took no turn said something: if
+    /// nothing was logged since `history_before`, a short echo is. Either
+    /// way the newest line becomes the state's `last_action`.
+    fn echo_idle_key(&mut self, key: VirtualKeyCode, history_before: usize) {
+        if self.play_history.len() == history_before {
+            self.push_log_entry(format!("{key:?}: nothing to do."));
+        }
+        self.last_action = self.message_log.first().cloned();
+    }
+
+    fn dump_current_state(&mut self) {
         if !self.verbose {
+            self.push_log_entry("Nothing to dump (needs --verbose).");
             return;
         }
         let player_pos = self.ecs.player_point();
@@ -363,11 +1005,15 @@ impl RainbowRogueState {
         } else {
             println!("[RR-DEBUG] No visible monsters.");
         }
+        if let Some(action) = &self.last_action {
+            println!("[RR-DEBUG] Last idle key: {action}");
+        }
         println!("[RR-DEBUG] Message Log (last 3):");
         for entry in self.message_log.iter().take(3) {
             println!("[RR-DEBUG]   {}", entry);
         }
         println!("[RR-DEBUG] --------------------------");
+        self.push_log_entry("State dumped to console.");
     }
 
     fn run_turn(&mut self, action_taken: bool) {
@@ -376,6 +1022,8 @@ impl RainbowRogueState {
         }
         self.last_player_point = Some(self.ecs.player_point()); // Store previous player point
         let previous_point = self.ecs.player_point();
+        let was_levitating = self.ecs.levitate_turns() > 0;
+        self.tick_doors();
         if let Some(layer) = self
             .dungeon
             .active_layer(self.active_floor, self.active_world)
@@ -385,28 +1033,107 @@ impl RainbowRogueState {
         } else {
             self.ecs.clear_player_intent();
         }
+        self.open_occupied_doors();
+        self.tick_acid_clouds();
+        self.tick_corpses();
+        if let Some(floor) = self.dungeon.floor_mut(self.active_floor) {
+            self.ecs.spread_curse(floor);
+        }
         self.resolve_move_attempt(previous_point);
+        let moved = self.ecs.player_point() != previous_point;
+        let heading = moved.then(|| self.ecs.player_point() - previous_point);
+        self.camera_lead = ease_look_ahead(self.camera_lead, heading, self.options.look_ahead);
+        let landed = was_levitating && self.ecs.levitate_turns() == 0;
+        if landed {
+            self.push_log_entry("You drift back down.");
+        }
+        if moved {
+            self.arrive();
+        }
+        // Landing counts as entering whatever is underfoot.
+        if moved || landed {
+            self.enter_hazard(previous_point);
+        }
+        self.tick_collapse();
+        self.collect_fragment();
         self.update_visibility();
+        self.check_objective();
         self.flush_combat_log();
         self.check_health_warning();
+        self.check_victory();
+        self.spawn_endless_waves();
         self.needs_prime_tick = false;
     }
 
     fn draw_scene(&mut self, ctx: &mut BTerm) {
+        let (screen_w, screen_h) = ctx.get_char_size();
+        let mut layout = Layout::compute(screen_w as i32, screen_h as i32, self.options.log_lines);
+        if let Some(layer) = self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+        {
+            layout.follow(
+                self.ecs.player_point() + self.camera_lead,
+                layer.width,
+                layer.height,
+            );
+        }
         let stair_cue = self.stair_cue();
         let header = format!(
             "RainbowRogue pre-alpha · Frame {} · Turn {}",
             self.frame, self.ecs.turn
         );
         ctx.print_color_centered(1, RGB::named(YELLOW), RGB::named(BLACK), &header);
-        let meta_line = format!(
+        let mut meta_line = format!(
             "Run {} · Deepest cleared floor {}",
             self.run_stats.run_number, self.run_stats.best_depth
         );
+        if self.run_stats.wins > 0 {
+            meta_line.push_str(&format!(" · Spectrums restored {}", self.run_stats.wins));
+        }
+        if self.options.endless {
+            meta_line.push_str(&format!(
+                " · Wave {} · Score {} (best {})",
+                self.endless_waves, self.ecs.turn, self.run_stats.best_endless_score
+            ));
+        }
+        if let Some(splits) = self.speedrun_summary() {
+            meta_line.push_str(&format!(" · {splits}"));
+        }
         ctx.print_color_centered(2, RGB::named(LIGHT_GREEN), RGB::named(BLACK), &meta_line);
 
+        let wind = match self.dungeon.active_floor(self.active_floor) {
+            Some(floor) if self.active_world == World::Orange => {
+                format!(" · Wind {}", wind_name(floor.wind))
+            }
+            _ => String::new(),
+        };
+        let objective = match self
+            .dungeon
+            .active_floor(self.active_floor)
+            .and_then(|floor| floor.objective)
+        {
+            Some(objective) if objective.complete => " · Objective done".to_string(),
+            Some(objective) => format!(" · {}", objective.describe()),
+            None => String::new(),
+        };
+        let explored = self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+            .map(|layer| format!(" ({}% explored)", layer.charted_percent()))
+            .unwrap_or_default();
+        let collapse = match self.collapse_elapsed() {
+            Some(elapsed) if elapsed >= u64::from(self.options.collapse_turns) => {
+                " · Collapsing!".to_string()
+            }
+            Some(elapsed) => format!(
+                " · Collapse in {}",
+                u64::from(self.options.collapse_turns) - elapsed
+            ),
+            None => String::new(),
+        };
         let info = format!(
-            "Active world: {} · Floor {}{}",
+            "Active world: {} · Floor {}{explored}{objective}{wind}{collapse}{}",
             self.active_world.as_str(),
             self.active_floor.0,
             stair_cue
@@ -416,46 +1143,148 @@ impl RainbowRogueState {
         );
         ctx.print_color_centered(3, RGB::named(LIGHT_CYAN), RGB::named(BLACK), &info);
         if let Some(stats) = self.ecs.player_stats() {
-            let vitality = format!("HP {}/{}", stats.hp, stats.max_hp);
-            let hp_color = if self.hp_ratio <= 0.3 {
+            let equipment = self.ecs.player_equipment();
+            let mut vitality = format!("HP {}/{}", stats.hp, stats.max_hp);
+            for piece in [&equipment.weapon, &equipment.armor].into_iter().flatten() {
+                vitality.push_str(&format!(" · {} +{}", piece.name, piece.enchant));
+            }
+            vitality.push_str(&format!(" · {} shards", self.ecs.player_shards()));
+            let indicators = status_indicators(&self.ecs.player_status_effects());
+            let hp_color = if self.hp_ratio <= CRITICAL_HP_RATIO {
                 RGB::named(ORANGE)
             } else if self.hp_ratio <= 0.6 {
                 RGB::from_u8(255, 120, 120)
             } else {
                 RGB::named(RED)
             };
-            ctx.print_color_centered(4, hp_color, RGB::named(BLACK), &vitality);
+            // Centre the vitality text and the status row together, as one
+            // line.
+            let text_width = vitality.chars().count() as i32;
+            let row_width = status_row_width(&indicators);
+            let gap = if indicators.is_empty() { 0 } else { 1 };
+            let start = ((ctx.get_char_size().0 as i32 - text_width - gap - row_width) / 2).max(0);
+            ctx.print_color(start, 4, hp_color, RGB::named(BLACK), &vitality);
+            draw_status_row(ctx, start + text_width + gap, 4, &indicators);
         }
 
-        self.hud
-            .draw(ctx, self.active_world, self.active_floor, self.frame);
-        self.draw_quickbar(ctx);
+        self.hud.draw(
+            ctx,
+            layout.hud,
+            self.active_world,
+            self.active_floor,
+            self.frame,
+            self.fragments,
+        );
+        self.draw_quickbar(ctx, &layout);
         if let Some(cue) = stair_cue {
             let label = format!("{} {}", cue.icon, cue.description);
-            ctx.print_color(2, 6, cue.color, RGB::named(BLACK), &label);
+            ctx.print_color(
+                layout.hud.x1 + 2,
+                layout.hud.y1 + 6,
+                cue.color,
+                RGB::named(BLACK),
+                &label,
+            );
+        } else if let Some(prompt) = self.pickup_prompt() {
+            ctx.print_color(
+                layout.hud.x1 + 2,
+                layout.hud.y1 + 6,
+                RGB::named(WHITE),
+                RGB::named(BLACK),
+                &prompt,
+            );
         }
 
         if let Some(layer) = self
             .dungeon
             .active_layer(self.active_floor, self.active_world)
         {
-            draw_map(
-                ctx,
-                layer,
-                Point::new(MAP_ORIGIN_X, MAP_ORIGIN_Y),
-                LOG_RESERVED_ROWS,
-                &self.visible_tiles,
-            );
+            draw_map(ctx, layer, &layout, &self.visible_tiles);
+
+            // Acid clouds hang over the tiles beneath them and cursed tiles
+            // glow violet underneath; entities drawn below sit on top.
+            if let Some(floor) = self.dungeon.active_floor(self.active_floor) {
+                for &point in &floor.cursed {
+                    if self.visible_tiles.contains(&point)
+                        && let Some(tile) = layer.tile_at(point)
+                        && let Some(screen) = layout.map_to_screen(point)
+                    {
+                        ctx.set(
+                            screen.x,
+                            screen.y,
+                            tile.fg,
+                            RGB::from_u8(70, 20, 90),
+                            tile.glyph,
+                        );
+                    }
+                }
+                if let Some(fragment) = floor.fragment
+                    && fragment.world == self.active_world
+                    && self.fragments & fragment_bit(fragment.world) == 0
+                    && self.visible_tiles.contains(&fragment.point)
+                    && let Some(screen) = layout.map_to_screen(fragment.point)
+                {
+                    ctx.set(
+                        screen.x,
+                        screen.y,
+                        world_color(fragment.world),
+                        RGB::named(BLACK),
+                        FRAGMENT_GLYPH,
+                    );
+                }
+                for corpse in &floor.corpses {
+                    if corpse.world == self.active_world
+                        && self.visible_tiles.contains(&corpse.point)
+                        && let Some(screen) = layout.map_to_screen(corpse.point)
+                    {
+                        ctx.set(
+                            screen.x,
+                            screen.y,
+                            RGB::from_u8(150, 100, 90),
+                            RGB::named(BLACK),
+                            to_cp437('%'),
+                        );
+                    }
+                }
+                let shard = resonance_shard();
+                for item in &floor.items {
+                    if item.world == self.active_world
+                        && self.visible_tiles.contains(&item.point)
+                        && let Some(screen) = layout.map_to_screen(item.point)
+                    {
+                        // Resonance Shards keep their own colour so they
+                        // stand out from the world's usual finds.
+                        let color = if item.name == shard.name {
+                            shard.color
+                        } else {
+                            world_color(item.world)
+                        };
+                        ctx.set(screen.x, screen.y, color, RGB::named(BLACK), to_cp437('!'));
+                    }
+                }
+                for point in floor.cloud_points() {
+                    if self.visible_tiles.contains(&point)
+                        && let Some(screen) = layout.map_to_screen(point)
+                    {
+                        ctx.set(
+                            screen.x,
+                            screen.y,
+                            RGB::named(YELLOW_GREEN),
+                            RGB::from_u8(40, 60, 10),
+                            to_cp437('▒'),
+                        );
+                    }
+                }
+            }
 
             // Clear player's old position if they moved (this is now redundant with the below, but kept for clarity)
             if let Some(last_point) = self.last_player_point {
                 let current_point = self.ecs.player_point();
-                if last_point != current_point {
-                    if let Some(tile) = layer.tile_at(last_point) {
-                        let screen_x = MAP_ORIGIN_X + last_point.x;
-                        let screen_y = MAP_ORIGIN_Y + last_point.y;
-                        ctx.set(screen_x, screen_y, tile.fg, RGB::named(BLACK), tile.glyph);
-                    }
+                if last_point != current_point
+                    && let Some(tile) = layer.tile_at(last_point)
+                    && let Some(screen) = layout.map_to_screen(last_point)
+                {
+                    ctx.set(screen.x, screen.y, tile.fg, RGB::named(BLACK), tile.glyph);
                 }
             }
 
@@ -464,81 +1293,929 @@ impl RainbowRogueState {
                 self.active_floor,
                 self.active_world,
                 true, // Include player for this pass
-                |point, _|
-                 {
-                    if self.visible_tiles.contains(&point) {
-                        if let Some(tile) = layer.tile_at(point) {
-                            let screen_x = MAP_ORIGIN_X + point.x;
-                            let screen_y = MAP_ORIGIN_Y + point.y;
-                            ctx.set(screen_x, screen_y, tile.fg, RGB::named(BLACK), tile.glyph);
-                        }
+                |point, _| {
+                    if self.visible_tiles.contains(&point)
+                        && let Some(tile) = layer.tile_at(point)
+                        && let Some(screen) = layout.map_to_screen(point)
+                    {
+                        ctx.set(screen.x, screen.y, tile.fg, RGB::named(BLACK), tile.glyph);
                     }
                 },
             );
 
+            // Ghosts of monsters last seen out of sight go down first, so a
+            // detect-life heartbeat at the same spot draws over them.
+            for (point, glyph, color) in self.monster_memory.ghosts(&self.visible_tiles) {
+                if let Some(screen) = layout.map_to_screen(point) {
+                    let (fg, glyph_override) =
+                        entity_draw_style(EntityVisibility::Remembered, color);
+                    ctx.set(
+                        screen.x,
+                        screen.y,
+                        fg,
+                        RGB::named(BLACK),
+                        glyph_override.unwrap_or(glyph),
+                    );
+                }
+            }
+
+            let burning = self.ecs.burning_cells(self.active_floor, self.active_world);
+            let slowed = self.ecs.slowed_cells(self.active_floor, self.active_world);
+            let mut occupied = HashSet::new();
             self.ecs.each_renderable(
                 self.active_floor,
                 self.active_world,
                 true,
                 |point, renderable| {
-                    if !self.visible_tiles.contains(&point) {
+                    let Some(state) = self.entity_visibility(point) else {
                         return;
-                    }
-                    let screen_x = MAP_ORIGIN_X + point.x;
-                    let screen_y = MAP_ORIGIN_Y + point.y;
+                    };
+                    let Some(screen) = layout.map_to_screen(point) else {
+                        return;
+                    };
+                    let (fg, glyph_override) = entity_draw_style(state, renderable.color);
+                    let visible = state == EntityVisibility::Visible;
+                    occupied.insert(point);
+                    let bg = if visible && burning.contains(&point) {
+                        BURNING_TINT
+                    } else if visible && slowed.contains(&point) {
+                        CHILLED_TINT
+                    } else {
+                        RGB::named(BLACK)
+                    };
                     ctx.set(
-                        screen_x,
-                        screen_y,
-                        renderable.color,
-                        RGB::named(BLACK),
-                        renderable.glyph,
+                        screen.x,
+                        screen.y,
+                        fg,
+                        bg,
+                        glyph_override.unwrap_or(renderable.glyph),
                     );
                 },
             );
+            // Afflicted monsters in view carry their most pressing status
+            // letter on the open tile above them.
+            for (point, effects) in self
+                .ecs
+                .monster_statuses(self.active_floor, self.active_world)
+            {
+                let above = Point::new(point.x, point.y - 1);
+                if !self.visible_tiles.contains(&point)
+                    || !self.visible_tiles.contains(&above)
+                    || occupied.contains(&above)
+                {
+                    continue;
+                }
+                let (Some(indicator), Some(screen)) = (
+                    status_indicators(&effects).first().copied(),
+                    layout.map_to_screen(above),
+                ) else {
+                    continue;
+                };
+                ctx.set(
+                    screen.x,
+                    screen.y,
+                    indicator.color,
+                    RGB::named(BLACK),
+                    to_cp437(indicator.symbol),
+                );
+            }
+        } else {
+            draw_layer_unavailable(
+                ctx,
+                &layout,
+                self.active_floor.0,
+                self.active_world.as_str(),
+            );
         }
 
-        let (_, screen_h_raw) = ctx.get_char_size();
-        let screen_h = screen_h_raw as i32;
-        let log_panel_start = self.calculate_log_start(screen_h);
-        draw_log(ctx, &self.message_log, log_panel_start);
-        if self.is_dead {
-            self.draw_game_over(ctx);
+        // Godmode runs skip the vitality warning, so they skip its flash too.
+        if !self.ecs.godmode() {
+            draw_critical_border(ctx, &layout, critical_pulse(self.frame, self.hp_ratio));
         }
-    }
-
-    fn calculate_log_start(&self, screen_height: i32) -> i32 {
-        if screen_height <= 0 {
-            return 0;
+        self.draw_threat_arrows(ctx, &layout);
+        if let Some(ThrowPick {
+            slot: Some(_),
+            target,
+        }) = self.throw_pick
+        {
+            for point in line2d_bresenham(self.ecs.player_point(), target)
+                .into_iter()
+                .skip(1)
+            {
+                if let Some(screen) = layout.map_to_screen(point) {
+                    let tint = if point == target {
+                        RGB::from_u8(150, 120, 20)
+                    } else {
+                        RGB::from_u8(60, 50, 10)
+                    };
+                    ctx.set_bg(screen.x, screen.y, tint);
+                }
+            }
         }
-        let min_anchor = MAP_ORIGIN_Y + 5;
-        let mut start = screen_height.saturating_sub(LOG_RESERVED_ROWS + 1);
-        if start < min_anchor {
-            start = min_anchor;
+        if self.debug_overlay {
+            self.draw_debug_overlay(ctx, &layout);
         }
-        start = start.min(screen_height.saturating_sub(2));
-        start.max(1)
-    }
 
-    fn dump_verbose_frame(&self, action_taken: bool) {
-        let pos = self.ecs.player_point();
-        println!(
-            "[RR-VERBOSE] frame={} turn={} acted={} world={} floor={} pos=({}, {})",
-            self.frame,
-            self.ecs.turn,
-            action_taken,
-            self.active_world.as_str(),
-            self.active_floor.0,
-            pos.x,
-            pos.y
-        );
-        for entry in &self.message_log {
-            println!("  log> {entry}");
+        draw_log(ctx, &self.message_log, layout.log);
+        if self.run_over() {
+            self.draw_game_over(ctx, &layout);
+        }
+        if let Some(menu) = &self.slot_menu {
+            Self::draw_slot_menu(ctx, &layout, menu);
+        }
+        if self.enchant_menu {
+            self.draw_enchant_menu(ctx, &layout);
+        }
+        if self.world_menu {
+            self.draw_world_menu(ctx, &layout);
+        }
+        if let Some(dialog) = &self.dialog {
+            self.draw_dialog(ctx, &layout, dialog);
         }
-        println!("--");
     }
 
-    fn handle_reset_request(&mut self) {
+    fn toggle_debug_overlay(&mut self) {
+        if !self.verbose {
+            self.push_log_entry("Debug overlay needs --verbose (or RR_VERBOSE=1).");
+            return;
+        }
+        self.debug_overlay = !self.debug_overlay;
+        if self.debug_overlay {
+            self.push_log_entry(
+                "Debug overlay on: green walkable, cyan your sight, red monster sight.",
+            );
+        } else {
+            self.push_log_entry("Debug overlay off.");
+        }
+    }
+
+    fn open_slot_menu(&mut self, mode: SlotMenuMode) {
+        self.auto_run = None;
+        self.slot_menu = Some(SlotMenu {
+            mode,
+            summaries: save::slot_summaries(&self.save_dir),
+            confirm: None,
+        });
+    }
+
+    fn handle_slot_menu_key(&mut self, key: VirtualKeyCode) {
+        let Some(menu) = self.slot_menu.as_mut() else {
+            return;
+        };
+        if let Some(slot) = menu.confirm {
+            match key {
+                VirtualKeyCode::Y => {
+                    self.slot_menu = None;
+                    self.save_to_slot(slot);
+                }
+                VirtualKeyCode::N | VirtualKeyCode::Escape => menu.confirm = None,
+                _ => {}
+            }
+            return;
+        }
+        let slot = match key {
+            VirtualKeyCode::Key1 => 0,
+            VirtualKeyCode::Key2 => 1,
+            VirtualKeyCode::Key3 => 2,
+            VirtualKeyCode::Escape => {
+                self.slot_menu = None;
+                return;
+            }
+            _ => return,
+        };
+        let occupied = menu.summaries.get(slot).is_some_and(Option::is_some);
+        match menu.mode {
+            SlotMenuMode::Save if occupied => menu.confirm = Some(slot),
+            SlotMenuMode::Save => {
+                self.slot_menu = None;
+                self.save_to_slot(slot);
+            }
+            SlotMenuMode::Load if occupied => {
+                self.slot_menu = None;
+                self.load_from_slot(slot);
+            }
+            SlotMenuMode::Load => {
+                self.push_log_entry(format!("Slot {} is empty.", slot + 1));
+            }
+        }
+    }
+
+    /// The pack slot a `1`–`5` key stands for on the current quickbar page.
+    fn quickbar_slot(&self, key: VirtualKeyCode) -> Option<usize> {
+        let offset = match key {
+            VirtualKeyCode::Key1 => 0,
+            VirtualKeyCode::Key2 => 1,
+            VirtualKeyCode::Key3 => 2,
+            VirtualKeyCode::Key4 => 3,
+            VirtualKeyCode::Key5 => 4,
+            _ => return None,
+        };
+        Some(self.quickbar_page * QUICKBAR_PAGE_SLOTS + offset)
+    }
+
+    fn flip_quickbar_page(&mut self) {
+        let pages = PACK_SLOTS.div_ceil(QUICKBAR_PAGE_SLOTS);
+        self.quickbar_page = (self.quickbar_page + 1) % pages;
+        self.push_log_entry(format!("Quickbar page {}/{pages}.", self.quickbar_page + 1));
+    }
+
+    fn start_crafting(&mut self) {
+        if self.ecs.player_inventory().is_empty() {
+            self.push_log_entry("Your pack is empty; nothing to combine.");
+            return;
+        }
+        self.auto_run = None;
+        self.craft_pick = Some(CraftPick { first: None });
+        self.push_log_entry("Combine which slot? 1-5 picks from this page, Esc cancels.");
+    }
+
+    /// Picks the two slots to combine; returns true when a craft happened,
+    /// which costs a turn.
+    fn handle_craft_key(&mut self, key: VirtualKeyCode) -> bool {
+        if key == VirtualKeyCode::Escape {
+            self.craft_pick = None;
+            self.push_log_entry("You set the resonances aside.");
+            return false;
+        }
+        let Some(slot) = self.quickbar_slot(key) else {
+            return false;
+        };
+        let Some(first) = self.craft_pick.as_ref().and_then(|pick| pick.first) else {
+            let Some((_, picked)) = self
+                .ecs
+                .player_inventory()
+                .into_iter()
+                .find(|(idx, _)| *idx == slot)
+            else {
+                self.push_log_entry(format!("Slot {} is empty.", slot + 1));
+                return false;
+            };
+            self.craft_pick = Some(CraftPick { first: Some(slot) });
+            self.push_log_entry(format!("Combine {} with which slot?", picked.name));
+            return false;
+        };
+        self.craft_pick = None;
+        match self.ecs.craft_consumables(first, slot) {
+            Ok(message) => {
+                self.push_log_entry(message);
+                true
+            }
+            Err(message) => {
+                self.push_log_entry(message);
+                false
+            }
+        }
+    }
+
+    fn start_throw(&mut self) {
+        if self.ecs.player_inventory().is_empty() {
+            self.push_log_entry("Your pack is empty; nothing to throw.");
+            return;
+        }
+        self.auto_run = None;
+        self.throw_pick = Some(ThrowPick {
+            slot: None,
+            target: self.ecs.player_point(),
+        });
+        self.push_log_entry("Throw which slot? 1-5 picks from this page, Esc cancels.");
+    }
+
+    /// Picks the slot to throw, then steers the aim; returns true once a
+    /// throw happened, which costs a turn.
+    fn handle_throw_key(&mut self, key: VirtualKeyCode) -> bool {
+        let Some(pick) = self.throw_pick.as_ref() else {
+            return false;
+        };
+        if key == VirtualKeyCode::Escape {
+            self.throw_pick = None;
+            self.push_log_entry("You keep hold of it.");
+            return false;
+        }
+        let Some(slot) = pick.slot else {
+            let Some(slot) = self.quickbar_slot(key) else {
+                return false;
+            };
+            let Some((_, picked)) = self
+                .ecs
+                .player_inventory()
+                .into_iter()
+                .find(|(idx, _)| *idx == slot)
+            else {
+                self.push_log_entry(format!("Slot {} is empty.", slot + 1));
+                return false;
+            };
+            self.throw_pick = Some(ThrowPick {
+                slot: Some(slot),
+                target: self.default_throw_target(),
+            });
+            self.push_log_entry(format!(
+                "Aim the {}: direction keys move the mark, F or Enter throws.",
+                picked.name
+            ));
+            return false;
+        };
+        let target = pick.target;
+        if matches!(key, VirtualKeyCode::F | VirtualKeyCode::Return) {
+            if target == self.ecs.player_point() {
+                self.push_log_entry("Aim somewhere other than your own feet.");
+                return false;
+            }
+            self.throw_pick = None;
+            return match self.ecs.throw_consumable(
+                slot,
+                target,
+                &mut self.dungeon,
+                self.active_floor,
+                self.active_world,
+            ) {
+                Some(messages) => {
+                    for message in messages {
+                        self.push_log_entry(message);
+                    }
+                    self.last_move_attempt = None;
+                    true
+                }
+                None => {
+                    self.push_log_entry(format!("Slot {} is empty.", slot + 1));
+                    false
+                }
+            };
+        }
+        if let Some(delta) = direction_of(key) {
+            let next = target + delta;
+            if self.in_throw_range(next)
+                && let Some(pick) = self.throw_pick.as_mut()
+            {
+                pick.target = next;
+            }
+        }
+        false
+    }
+
+    /// The closest monster in sight and in range, else the player's tile.
+    fn default_throw_target(&self) -> Point {
+        let player = self.ecs.player_point();
+        self.ecs
+            .monster_footprints(self.active_floor, self.active_world)
+            .into_iter()
+            .flatten()
+            .filter(|&cell| self.in_throw_range(cell))
+            .min_by_key(|cell| (cell.x - player.x).pow(2) + (cell.y - player.y).pow(2))
+            .unwrap_or(player)
+    }
+
+    /// Throws can only be aimed at tiles in sight, [`THROW_RANGE`] away at
+    /// most.
+    fn in_throw_range(&self, point: Point) -> bool {
+        let player = self.ecs.player_point();
+        self.visible_tiles.contains(&point)
+            && DistanceAlg::Pythagoras.distance2d(player, point) <= THROW_RANGE
+    }
+
+    /// `1` enchants the weapon and `2` the armour; any attempt, won or lost,
+    /// costs a turn.
+    fn handle_enchant_key(&mut self, key: VirtualKeyCode) -> bool {
+        let slot = match key {
+            VirtualKeyCode::Key1 => GearSlot::Weapon,
+            VirtualKeyCode::Key2 => GearSlot::Armor,
+            VirtualKeyCode::Escape => {
+                self.enchant_menu = false;
+                return false;
+            }
+            _ => return false,
+        };
+        self.enchant_menu = false;
+        match self.ecs.enchant_gear(slot) {
+            Ok(message) => {
+                self.push_log_entry(message);
+                true
+            }
+            Err(message) => {
+                self.push_log_entry(message);
+                false
+            }
+        }
+    }
+
+    /// `1`–`7` in the world picker attune straight to that world, in
+    /// spectrum order; picking the current world closes it for free.
+    fn handle_world_key(&mut self, key: VirtualKeyCode) -> bool {
+        let idx = match key {
+            VirtualKeyCode::Key1 => 0,
+            VirtualKeyCode::Key2 => 1,
+            VirtualKeyCode::Key3 => 2,
+            VirtualKeyCode::Key4 => 3,
+            VirtualKeyCode::Key5 => 4,
+            VirtualKeyCode::Key6 => 5,
+            VirtualKeyCode::Key7 => 6,
+            VirtualKeyCode::Escape => {
+                self.world_menu = false;
+                return false;
+            }
+            _ => return false,
+        };
+        self.world_menu = false;
+        let target = SPECTRUM[idx];
+        if target == self.active_world {
+            self.push_log_entry(format!("You are already attuned to {}.", target.as_str()));
+            return false;
+        }
+        self.set_world(target)
+    }
+
+    fn talk_to_adjacent_npc(&mut self) {
+        let Some(npc) = self.ecs.adjacent_npc() else {
+            self.push_log_entry("There's no one here to talk to.");
+            return;
+        };
+        self.auto_run = None;
+        self.dialog = Some(Dialog {
+            speaker: npc.name,
+            role: npc.role,
+            line: 0,
+            shop: None,
+        });
+    }
+
+    /// On the talk page `1` opens the shop and `2` moves to the next line;
+    /// on the shop page number keys buy and Esc goes back. Talking and
+    /// buying never cost a turn.
+    fn handle_dialog_key(&mut self, key: VirtualKeyCode) {
+        let Some(dialog) = self.dialog.as_mut() else {
+            return;
+        };
+        let Some(stock) = dialog.shop.as_ref() else {
+            match key {
+                VirtualKeyCode::Key1 if dialog.role == NpcRole::Shopkeeper => {
+                    dialog.shop = Some(shop_stock());
+                }
+                VirtualKeyCode::Key2 => {
+                    let lines = NpcTemplate::for_role(dialog.role).lines;
+                    dialog.line = (dialog.line + 1) % lines.len();
+                }
+                VirtualKeyCode::Escape => self.dialog = None,
+                _ => {}
+            }
+            return;
+        };
+        let pick = match key {
+            VirtualKeyCode::Escape => {
+                dialog.shop = None;
+                return;
+            }
+            VirtualKeyCode::Key1 => 0,
+            VirtualKeyCode::Key2 => 1,
+            VirtualKeyCode::Key3 => 2,
+            VirtualKeyCode::Key4 => 3,
+            VirtualKeyCode::Key5 => 4,
+            VirtualKeyCode::Key6 => 5,
+            VirtualKeyCode::Key7 => 6,
+            VirtualKeyCode::Key8 => 7,
+            VirtualKeyCode::Key9 => 8,
+            _ => return,
+        };
+        let Some(offer) = stock.get(pick).cloned() else {
+            return;
+        };
+        let message = match self.ecs.buy_ware(&offer) {
+            Ok(message) | Err(message) => message,
+        };
+        self.push_log_entry(message);
+    }
+
+    fn handle_resume_key(&mut self, key: VirtualKeyCode) {
+        match key {
+            VirtualKeyCode::C | VirtualKeyCode::Return => {
+                self.resume_prompt = false;
+                let result = save::read_save(&save::autosave_path(&self.save_dir))
+                    .and_then(|snapshot| self.restore_save(snapshot));
+                match result {
+                    Ok(()) => self.push_log_entry("Resumed from autosave."),
+                    Err(err) => self.push_log_entry(format!("Autosave unusable: {err}")),
+                }
+            }
+            VirtualKeyCode::N | VirtualKeyCode::Escape => {
+                self.resume_prompt = false;
+                self.push_log_entry("Starting fresh; the autosave will be replaced.");
+            }
+            _ => {}
+        }
+    }
+
+    /// Scripted playtests never touch the autosave so they can't clobber a
+    /// real run.
+    fn autosave_enabled(&self) -> bool {
+        matches!(self.input_source, InputSource::Keyboard)
+    }
+
+    fn autosave(&mut self) {
+        if self.run_over() || self.resume_prompt || !self.autosave_enabled() {
+            return;
+        }
+        let snapshot = self.capture_save();
+        if let Err(err) = save::write_save(&save::autosave_path(&self.save_dir), &snapshot) {
+            self.push_log_entry(format!("Autosave failed: {err}"));
+        }
+    }
+
+    fn save_to_slot(&mut self, slot: usize) {
         if self.is_dead {
+            self.push_log_entry("The shattered spectrum cannot be saved.");
+            return;
+        }
+        if self.has_won {
+            self.push_log_entry("The restored spectrum needs no saving. Press R for a new run.");
+            return;
+        }
+        let snapshot = self.capture_save();
+        match save::write_save(&save::slot_path(&self.save_dir, slot), &snapshot) {
+            Ok(()) => self.push_log_entry(format!("Saved to slot {}.", slot + 1)),
+            Err(err) => self.push_log_entry(format!("Save failed: {err}")),
+        }
+    }
+
+    fn load_from_slot(&mut self, slot: usize) {
+        let result = save::read_save(&save::slot_path(&self.save_dir, slot))
+            .and_then(|snapshot| self.restore_save(snapshot));
+        match result {
+            Ok(()) => self.push_log_entry(format!("Loaded slot {}.", slot + 1)),
+            Err(err) => self.push_log_entry(format!("Load failed: {err}")),
+        }
+    }
+
+    fn capture_save(&self) -> SaveGame {
+        let mut revealed = Vec::new();
+        for (idx, floor) in self.dungeon.floors.iter().enumerate() {
+            for &world in SPECTRUM.iter() {
+                let tiles = floor.layer(world).revealed_indices();
+                if !tiles.is_empty() {
+                    revealed.push(RevealedLayer {
+                        floor: idx as u32,
+                        world,
+                        tiles,
+                    });
+                }
+            }
+        }
+        let mut open_doors = Vec::new();
+        for (idx, floor) in self.dungeon.floors.iter().enumerate() {
+            for (point, turns_left) in &floor.open_doors {
+                open_doors.push(OpenDoor {
+                    floor: idx as u32,
+                    x: point.x,
+                    y: point.y,
+                    turns_left: *turns_left,
+                });
+            }
+        }
+        let mut detected_traps = Vec::new();
+        for (idx, floor) in self.dungeon.floors.iter().enumerate() {
+            for point in floor.detected_traps() {
+                detected_traps.push(TileMark {
+                    floor: idx as u32,
+                    x: point.x,
+                    y: point.y,
+                });
+            }
+        }
+        let mut acid_clouds = Vec::new();
+        for (idx, floor) in self.dungeon.floors.iter().enumerate() {
+            for cloud in &floor.clouds {
+                acid_clouds.push(CloudMark {
+                    floor: idx as u32,
+                    x: cloud.point.x,
+                    y: cloud.point.y,
+                    turns_left: cloud.turns_left,
+                });
+            }
+        }
+        let mut cursed_tiles = Vec::new();
+        for (idx, floor) in self.dungeon.floors.iter().enumerate() {
+            for point in &floor.cursed {
+                cursed_tiles.push(TileMark {
+                    floor: idx as u32,
+                    x: point.x,
+                    y: point.y,
+                });
+            }
+        }
+        let mut found_secret_doors = Vec::new();
+        for (idx, floor) in self.dungeon.floors.iter().enumerate() {
+            for point in floor.found_secret_doors() {
+                found_secret_doors.push(TileMark {
+                    floor: idx as u32,
+                    x: point.x,
+                    y: point.y,
+                });
+            }
+        }
+        let mut corpses = Vec::new();
+        for (idx, floor) in self.dungeon.floors.iter().enumerate() {
+            for corpse in &floor.corpses {
+                corpses.push(CorpseMark {
+                    floor: idx as u32,
+                    world: corpse.world,
+                    x: corpse.point.x,
+                    y: corpse.point.y,
+                    name: corpse.name.clone(),
+                    turns_left: corpse.turns_left,
+                });
+            }
+        }
+        let mut floor_items = Vec::new();
+        for (idx, floor) in self.dungeon.floors.iter().enumerate() {
+            for item in &floor.items {
+                floor_items.push(ItemMark {
+                    floor: idx as u32,
+                    world: item.world,
+                    x: item.point.x,
+                    y: item.point.y,
+                    name: item.name.clone(),
+                });
+            }
+        }
+        let mut seeded_floors: Vec<u32> = self.seeded_floors.iter().copied().collect();
+        seeded_floors.sort_unstable();
+        SaveGame {
+            version: SAVE_VERSION,
+            saved_at: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            run_number: self.run_stats.run_number,
+            master_seed: self.master_seed,
+            map_width: self.dungeon.size().0,
+            map_height: self.dungeon.size().1,
+            floors_generated: self.dungeon.floors.len() as u32,
+            active_floor: self.active_floor,
+            active_world: self.active_world,
+            frame: self.frame,
+            run_max_floor: self.run_max_floor,
+            seeded_floors,
+            revealed,
+            open_doors,
+            detected_traps,
+            acid_clouds,
+            cursed_tiles,
+            fragments: self.fragments,
+            endless_waves: self.endless_waves,
+            depth_turns: self.depth_turns.clone(),
+            collapse_clocks: self
+                .collapse_clocks
+                .iter()
+                .map(|(&floor, &turn)| DepthSplit { floor, turn })
+                .collect(),
+            found_secret_doors,
+            corpses,
+            floor_items,
+            completed_objectives: self
+                .dungeon
+                .floors
+                .iter()
+                .filter(|floor| floor.objective.is_some_and(|objective| objective.complete))
+                .map(|floor| floor.id.0)
+                .collect(),
+            message_log: self.message_log.clone(),
+            ecs: self.ecs.snapshot(),
+        }
+    }
+
+    /// Replaces the current run with `snapshot`. Session settings (options,
+    /// verbosity, input source, meta stats) are kept as they are.
+    fn restore_save(&mut self, snapshot: SaveGame) -> Result<(), String> {
+        let mut ecs = EcsWorld::from_snapshot(&snapshot.ecs, snapshot.master_seed)?;
+        ecs.set_godmode(self.ecs.godmode());
+        ecs.set_fov_algorithm(self.ecs.fov_algorithm());
+        ecs.set_infighting(self.ecs.infighting());
+        ecs.set_friendly_fire(self.ecs.friendly_fire());
+        let mut dungeon = Dungeon::new(
+            snapshot.map_width,
+            snapshot.map_height,
+            snapshot.master_seed,
+        )?;
+        if snapshot.floors_generated > 0 {
+            dungeon.ensure_floor(FloorId(snapshot.floors_generated - 1));
+        }
+        dungeon.ensure_floor(snapshot.active_floor);
+        for layer in &snapshot.revealed {
+            if let Some(map) = dungeon.active_layer_mut(FloorId(layer.floor), layer.world) {
+                map.reveal_indices(&layer.tiles);
+            }
+        }
+        for trap in &snapshot.detected_traps {
+            if let Some(floor) = dungeon.floor_mut(FloorId(trap.floor)) {
+                floor.detect_trap(Point::new(trap.x, trap.y));
+            }
+        }
+        for mark in &snapshot.found_secret_doors {
+            if let Some(floor) = dungeon.floor_mut(FloorId(mark.floor)) {
+                floor.reveal_secret_door(Point::new(mark.x, mark.y));
+            }
+        }
+        for door in &snapshot.open_doors {
+            if let Some(floor) = dungeon.floor_mut(FloorId(door.floor)) {
+                floor.set_door_open(Point::new(door.x, door.y), true, door.turns_left);
+            }
+        }
+        for mark in &snapshot.corpses {
+            if let Some(floor) = dungeon.floor_mut(FloorId(mark.floor)) {
+                floor.add_corpse(Corpse {
+                    point: Point::new(mark.x, mark.y),
+                    world: mark.world,
+                    name: mark.name.clone(),
+                    turns_left: mark.turns_left,
+                });
+            }
+        }
+        for mark in &snapshot.floor_items {
+            if let Some(floor) = dungeon.floor_mut(FloorId(mark.floor)) {
+                floor.add_item(FloorItem {
+                    point: Point::new(mark.x, mark.y),
+                    world: mark.world,
+                    name: mark.name.clone(),
+                });
+            }
+        }
+        for mark in &snapshot.cursed_tiles {
+            if let Some(floor) = dungeon.floor_mut(FloorId(mark.floor)) {
+                floor.cursed.insert(Point::new(mark.x, mark.y));
+            }
+        }
+        for &floor_id in &snapshot.completed_objectives {
+            if let Some(objective) = dungeon
+                .floor_mut(FloorId(floor_id))
+                .and_then(|floor| floor.objective.as_mut())
+            {
+                objective.complete = true;
+            }
+        }
+        for cloud in &snapshot.acid_clouds {
+            if let Some(floor) = dungeon.floor_mut(FloorId(cloud.floor)) {
+                floor.add_cloud(Point::new(cloud.x, cloud.y), cloud.turns_left);
+            }
+        }
+
+        self.dungeon = dungeon;
+        self.ecs = ecs;
+        self.master_seed = snapshot.master_seed;
+        self.active_floor = snapshot.active_floor;
+        self.active_world = snapshot.active_world;
+        self.frame = snapshot.frame;
+        self.run_max_floor = snapshot.run_max_floor;
+        self.fragments = snapshot.fragments;
+        self.endless_waves = snapshot.endless_waves;
+        self.depth_turns = snapshot.depth_turns;
+        self.collapse_clocks = snapshot
+            .collapse_clocks
+            .into_iter()
+            .map(|clock| (clock.floor, clock.turn))
+            .collect();
+        self.start_collapse_clock();
+        self.collapse_edges();
+        self.split_delta = None;
+        self.seeded_floors = snapshot.seeded_floors.into_iter().collect();
+        self.message_log = snapshot.message_log;
+        self.message_log.truncate(self.options.log_history);
+        self.log_priorities = vec![LogPriority::Normal; self.message_log.len()];
+        self.log_streak = None;
+        self.craft_pick = None;
+        self.throw_pick = None;
+        self.enchant_menu = false;
+        self.world_menu = false;
+        self.last_action = None;
+        self.camera_lead = Point::zero();
+        self.dialog = None;
+        self.is_dead = false;
+        self.has_won = false;
+        self.hp_alerted = false;
+        self.auto_run = None;
+        self.auto_rest = None;
+        self.last_move_attempt = None;
+        self.reset_prompt_frame = None;
+        self.last_player_point = Some(self.ecs.player_point());
+        self.run_state = RunState::AwaitingInput;
+        self.visible_tiles.clear();
+        self.monster_memory.clear();
+        self.update_visibility();
+        Ok(())
+    }
+
+    /// Dumps the HUD, active map, and log as plain text plus a JSON state
+    /// dump for bug reports, rebuilt from game state rather than the screen.
+    fn take_screenshot(&mut self) {
+        let Some(layer) = self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+        else {
+            return;
+        };
+        let mut entities = Vec::new();
+        self.ecs
+            .each_renderable(self.active_floor, self.active_world, true, |point, r| {
+                entities.push(screenshot::EntityGlyph {
+                    x: point.x,
+                    y: point.y,
+                    glyph: to_char(r.glyph as u8),
+                    order: r.order,
+                });
+            });
+        let map = screenshot::ascii_map(layer, &self.visible_tiles, &entities);
+        let vitality = self
+            .ecs
+            .player_stats()
+            .map(|stats| format!("HP {}/{}", stats.hp, stats.max_hp))
+            .unwrap_or_default();
+        let hud = vec![
+            format!(
+                "RainbowRogue · Run {} · Seed code {}",
+                self.run_stats.run_number,
+                seed::encode_seed_code(self.master_seed)
+            ),
+            format!(
+                "Active world: {} · Floor {} · Turn {} · Frame {} · {vitality}",
+                self.active_world.as_str(),
+                self.active_floor.0,
+                self.ecs.turn,
+                self.frame
+            ),
+            world_rule_line(self.active_world),
+        ];
+        let dump = screenshot::ScreenDump {
+            floor: self.active_floor.0,
+            world: self.active_world.as_str().to_string(),
+            turn: self.ecs.turn,
+            frame: self.frame,
+            hud,
+            log: self.message_log.clone(),
+            tiles: screenshot::tile_dump(layer, &self.visible_tiles),
+            entities,
+        };
+        let stem = format!(
+            "screenshot_{}",
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        );
+        match screenshot::write_screenshot(&stem, &map, &dump) {
+            Ok(path) => self.push_log_entry(format!("Screenshot saved to {}", path.display())),
+            Err(err) => self.push_log_entry(format!("Screenshot failed: {err}")),
+        }
+    }
+
+    fn toggle_godmode(&mut self) {
+        if !self.options.debug {
+            self.push_log_entry("Godmode needs --debug.");
+            return;
+        }
+        let enabled = !self.ecs.godmode();
+        self.ecs.set_godmode(enabled);
+        self.push_log_entry(if enabled {
+            "(godmode) on: incoming damage is blocked."
+        } else {
+            "(godmode) off."
+        });
+    }
+
+    fn draw_debug_overlay(&self, ctx: &mut BTerm, layout: &Layout) {
+        let Some(layer) = self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+        else {
+            return;
+        };
+        let movement = self
+            .ecs
+            .movement_context(self.active_floor, self.active_world);
+        let monster_sight = self.ecs.monster_sight(self.active_floor, self.active_world);
+        draw_debug_overlay(
+            ctx,
+            layout,
+            layer.width,
+            layer.height,
+            |point| match &movement {
+                Some(context) => context.is_walkable(point),
+                None => layer.is_walkable(point),
+            },
+            &self.visible_tiles,
+            &monster_sight,
+        );
+    }
+
+    fn dump_verbose_frame(&self, action_taken: bool) {
+        let pos = self.ecs.player_point();
+        println!(
+            "[RR-VERBOSE] frame={} turn={} acted={} world={} floor={} pos=({}, {})",
+            self.frame,
+            self.ecs.turn,
+            action_taken,
+            self.active_world.as_str(),
+            self.active_floor.0,
+            pos.x,
+            pos.y
+        );
+        for entry in &self.message_log {
+            println!("  log> {entry}");
+        }
+        println!("--");
+    }
+
+    fn handle_reset_request(&mut self) {
+        if self.run_over() {
             return;
         }
         let confirmed = self
@@ -557,21 +2234,42 @@ impl RainbowRogueState {
     }
 
     fn cycle_world(&mut self, delta: i32) -> bool {
-        self.active_world = self.active_world.cycle(delta);
-        self.behavior = BehaviorContext::new(self.active_world);
+        self.set_world(self.active_world.cycle(delta))
+    }
+
+    /// Attunes to `target`, paying the same turn and energy surcharge
+    /// whether it was reached by cycling or picked directly.
+    fn set_world(&mut self, target: World) -> bool {
+        self.active_world = target;
         let point = self.ecs.player_point();
         self.ecs
             .set_player_position(point, self.active_floor, self.active_world);
         self.ecs.clear_player_intent();
         self.last_move_attempt = None;
+        let speed = self.apply_world_pace();
+        // Only player-driven shifts pay the surcharge; the starting world is
+        // assigned directly at bootstrap.
+        let cost = self.options.world_switch_cost;
+        self.ecs.charge_player_energy(cost);
         self.push_log_entry(format!(
-            "Shifted attunement to {} on frame {}",
+            "Shifted attunement to {} on frame {} · pace {speed}",
             self.active_world.as_str(),
             self.frame
         ));
+        if cost > 0 {
+            self.push_log_entry(format!(
+                "The phase drains {cost} energy; foes press closer."
+            ));
+        }
         true
     }
 
+    /// Applies the active world's speed modifier to the player and returns the result.
+    fn apply_world_pace(&mut self) -> i32 {
+        let modifier = data::rule_for(self.active_world).speed_modifier;
+        self.ecs.apply_player_speed_modifier(modifier)
+    }
+
     fn shift_floor(&mut self, delta: i32) -> bool {
         if delta == 0 {
             return false;
@@ -603,6 +2301,11 @@ impl RainbowRogueState {
             return false;
         }
 
+        // Count the stair before leaving, in case no turn ended on it.
+        if delta > 0 {
+            self.check_objective();
+        }
+
         let current = self.active_floor.0 as i32;
         let target = current + delta;
         if target < 0 {
@@ -611,344 +2314,3741 @@ impl RainbowRogueState {
         }
 
         let target_floor = FloorId(target as u32);
+        if delta < 0 && self.sealed_above(target_floor) {
+            if self.fragments != ALL_FRAGMENTS {
+                self.push_log_entry("The prism lock has sealed behind you.");
+                return false;
+            }
+            self.push_log_entry("Your fragments pry the sealed prism lock open.");
+        }
         let created = self.dungeon.ensure_floor(target_floor);
         if created {
             self.push_log_entry(format!("Floor {} takes shape.", target_floor.0));
             self.seed_floor_monsters(target_floor);
         }
 
+        let departure = self.ecs.player_point();
         self.active_floor = target_floor;
-        let arrival = self.arrival_point(delta > 0);
+        let arrival = self.arrival_point(delta > 0, departure);
         self.ecs
             .set_player_position(arrival, self.active_floor, self.active_world);
         self.ecs.clear_player_intent();
         self.last_move_attempt = None;
         self.visible_tiles.clear();
         self.update_visibility();
-        self.record_depth(self.active_floor);
         let verb = if delta > 0 { "Descended" } else { "Ascended" };
         self.push_log_entry(format!("{verb} to floor {}", self.active_floor.0));
+        let new_depth = self.record_depth(self.active_floor);
+        self.start_collapse_clock();
+        self.collapse_edges();
+        if delta > 0 && self.sealed_above(FloorId(current as u32)) {
+            self.push_log_entry("The prism lock seals behind you.");
+        }
+        if new_depth {
+            self.apply_descent_bonus();
+        }
+        self.autosave();
         true
     }
 
-    fn try_step(&mut self, dx: i32, dy: i32) -> bool {
-        if dx == 0 && dy == 0 {
+    /// Whether `floor`'s down-stairs are one-way and one-way stairs are on.
+    fn sealed_above(&self, floor: FloorId) -> bool {
+        self.options.one_way_stairs
+            && self
+                .dungeon
+                .active_floor(floor)
+                .is_some_and(|floor| floor.one_way_down)
+    }
+
+    /// Strikes without moving: a reach weapon thrusts along a cardinal
+    /// line, anything else hits only the adjacent tile. A blow that can't
+    /// land costs no turn and never turns into a step.
+    fn attack_in_place(&mut self, dx: i32, dy: i32) -> bool {
+        let Some(layer) = self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+        else {
             return false;
+        };
+        let delta = Point::new(dx, dy);
+        let thrusts = dx.abs() + dy.abs() == 1
+            && self
+                .ecs
+                .player_equipment()
+                .weapon
+                .is_some_and(|weapon| weapon.reach() >= 2);
+        let result = if thrusts {
+            self.ecs
+                .attack_along(delta, layer, self.active_floor, self.active_world)
+        } else {
+            self.ecs
+                .attack_adjacent(delta, self.active_floor, self.active_world)
+        };
+        match result {
+            Ok(report) => {
+                self.push_attack_report(report);
+                self.last_move_attempt = None;
+                true
+            }
+            Err(reason) => {
+                self.push_log_entry(reason);
+                false
+            }
         }
+    }
 
-        let current = self.ecs.player_point();
-        let target = Point::new(current.x + dx, current.y + dy);
-        if let Some(entity) = self
-            .ecs
-            .entity_at(target, self.active_floor, self.active_world)
+    fn start_run(&mut self, dx: i32, dy: i32) -> bool {
+        if self.monster_in_view() {
+            self.push_log_entry("Too dangerous to run with enemies in view.");
+            return false;
+        }
+        let delta = Point::new(dx, dy);
+        let origin = self.ecs.player_point();
+        if !self
+            .dungeon
+            .is_walkable(self.active_floor, self.active_world, origin + delta)
+            || self.lava_at(origin + delta)
         {
-            if entity != self.ecs.player_entity() {
-                if let Some(report) = self.ecs.player_attack(target, self.active_floor, self.active_world) {
-                    self.push_log_entry(report.hit);
-                    if let Some(kill) = report.kill {
-                        self.push_log_entry(kill);
-                        self.ecs.queue_player_step(Point::new(dx, dy));
-                        self.last_move_attempt = Some((current, target));
-                    } else {
-                        self.last_move_attempt = None;
-                    }
-                    return true;
-                }
-            }
+            return self.try_step(dx, dy);
         }
-        self.ecs.queue_player_step(Point::new(dx, dy));
-        self.last_move_attempt = Some((current, target));
-        true
-    }
-
-    fn push_log_entry<S: Into<String>>(&mut self, entry: S) {
-        let entry = entry.into();
-        self.play_history.push(entry.clone());
-        self.message_log.insert(0, entry);
-        self.message_log.truncate(LOG_MAX_ENTRIES);
+        self.auto_run = Some(AutoRun {
+            delta,
+            sides: self.run_sides(origin, delta),
+            last_point: origin,
+            hp: self.player_hp(),
+        });
+        self.run_step()
     }
 
-    fn draw_quickbar(&self, ctx: &mut BTerm) {
-        let entries = self.ecs.player_inventory();
-        if entries.is_empty() {
-            return;
+    /// Takes the next running step, or stops when something interesting is
+    /// in reach: a visible monster, a wall ahead, a stair underfoot, or a
+    /// change in the corridor's shape.
+    fn continue_run(&mut self) -> bool {
+        let Some(run) = self.auto_run else {
+            return false;
+        };
+        if let Some(cause) = self.should_interrupt_auto(run.hp) {
+            self.stop_running(&format!("You stop running: {cause}."));
+            return false;
         }
-        let mut x = 2;
-        for (idx, slot) in entries.iter().take(5) {
-            let label = format!("[{}] {} (x{})", idx + 1, slot.name, slot.uses_remaining);
-            ctx.print_color(x, 5, slot.color, RGB::named(BLACK), &label);
-            x += label.len() as i32 + 2;
+        let here = self.ecs.player_point();
+        let stop_reason = if here == run.last_point {
+            Some("You stop running.")
+        } else if self.stair_cue().is_some() {
+            Some("You stop running at the stairs.")
+        } else if self.lava_at(here + run.delta) {
+            Some("You stop running at the edge of the lava.")
+        } else if !self
+            .dungeon
+            .is_walkable(self.active_floor, self.active_world, here + run.delta)
+            || self
+                .ecs
+                .entity_at(here + run.delta, self.active_floor, self.active_world)
+                .is_some()
+        {
+            Some("You stop running.")
+        } else if self.run_sides(here, run.delta) != run.sides {
+            Some("You stop running at a junction.")
+        } else {
+            None
+        };
+        if let Some(reason) = stop_reason {
+            self.stop_running(reason);
+            return false;
         }
+        self.auto_run = Some(AutoRun {
+            last_point: here,
+            hp: self.player_hp(),
+            ..run
+        });
+        self.run_step()
     }
 
-    fn resolve_move_attempt(&mut self, previous_point: Point) {
-        if let Some((origin, target)) = self.last_move_attempt.take() {
-            let current = self.ecs.player_point();
-            if current == target {
-                self.push_log_entry(format!(
-                    "Stepped to {},{} in {}",
-                    current.x,
-                    current.y,
-                    self.active_world.as_str()
-                ));
-            } else if origin == previous_point {
-                self.push_log_entry(format!("Blocked at {},{}", target.x, target.y));
-            }
+    fn start_rest(&mut self) -> bool {
+        if self.monster_in_view() {
+            self.push_log_entry("Too dangerous to rest with enemies in view.");
+            return false;
         }
+        self.auto_rest = Some(AutoRest {
+            turns_left: REST_TURNS - 1,
+            hp: self.player_hp(),
+        });
+        true
     }
 
-    fn update_visibility(&mut self) {
-        let previous = self.visible_tiles.clone();
-        if let Some(layer) = self
-            .dungeon
-            .active_layer_mut(self.active_floor, self.active_world)
-        {
-            let visible = self.ecs.player_visible_tiles();
-            for point in &visible {
-                layer.reveal_point(*point);
-            }
-            self.visible_tiles = visible.into_iter().collect();
-            let newly_visible = self
-                .visible_tiles
-                .iter()
-                .filter(|point| !previous.contains(point))
-                .count();
-            if newly_visible > 0 {
-self.push_log_entry(format!(
-                    "Glimpsed {newly_visible} new tiles in {}",
-                    self.active_world.as_str()
-                ));
-            }
-        } else {
-            self.visible_tiles.clear();
+    /// Waits out the next turn of a rest, or stops once it is disturbed or
+    /// has run its course.
+    fn continue_rest(&mut self) -> bool {
+        let Some(rest) = self.auto_rest else {
+            return false;
+        };
+        if let Some(cause) = self.should_interrupt_auto(rest.hp) {
+            self.stop_resting(&format!("You stop resting: {cause}."));
+            return false;
         }
-    }
-
-    fn flush_combat_log(&mut self) {
-        for entry in self.ecs.drain_combat_log() {
-            self.push_log_entry(entry);
+        if rest.turns_left == 0 {
+            self.stop_resting("You finish resting.");
+            return false;
         }
+        self.auto_rest = Some(AutoRest {
+            turns_left: rest.turns_left - 1,
+            hp: self.player_hp(),
+        });
+        true
     }
 
-    fn check_health_warning(&mut self) {
-        if let Some(stats) = self.ecs.player_stats() {
-            let ratio = stats.hp as f32 / stats.max_hp as f32;
-            let critical = ratio <= 0.3;
-            if critical && !self.hp_alerted {
-                self.push_log_entry("!! Vitality critical !!");
-                self.hp_alerted = true;
-            } else if !critical && self.hp_alerted && ratio > 0.5 {
-                self.push_log_entry("Vitality stabilizes.");
-                self.hp_alerted = false;
-            }
-            self.hp_ratio = ratio;
-            if stats.hp <= 0 && !self.is_dead {
-                self.on_player_death();
-            }
+    fn stop_resting(&mut self, reason: &str) {
+        if self.auto_rest.take().is_some() {
+            self.push_log_entry(reason);
         }
     }
 
-    fn activate_consumable(&mut self, slot_index: usize) -> bool {
-        if let Some(messages) = self.ecs.use_consumable(
-            slot_index,
-            &mut self.dungeon,
-            self.active_floor,
-            self.active_world,
-        ) {
-            for message in messages {
-                self.push_log_entry(message);
-            }
-            self.last_move_attempt = None;
-            self.update_visibility();
-            true
+    /// Why any automatic action should stop before its next step: a
+    /// monster has come into view, or the player has lost HP since
+    /// `hp_before` (a trap, a burn, a blow from out of sight).
+    fn should_interrupt_auto(&self, hp_before: i32) -> Option<&'static str> {
+        if self.player_hp() < hp_before {
+            Some("you've been hurt")
+        } else if self.monster_in_view() {
+            Some("something moves in view")
         } else {
-            self.push_log_entry(format!("Slot {} is empty.", slot_index + 1));
-            false
+            None
         }
     }
 
-    fn tile_under_player(&self) -> Option<Tile> {
-        let point = self.ecs.player_point();
-        self.dungeon
-            .active_layer(self.active_floor, self.active_world)
-            .and_then(|layer| layer.tile_at(point).cloned())
+    fn player_hp(&self) -> i32 {
+        self.ecs.player_stats().map_or(0, |stats| stats.hp)
     }
 
-    fn stair_cue(&self) -> Option<StairCue> {
-        self.tile_under_player().and_then(|tile| match tile.tag {
-            Tile::TAG_STAIR_UP => Some(StairCue {
-                icon: "^",
-                description: "On < : PageDown to ascend",
-                color: RGB::named(LIGHT_GREEN),
-            }),
-            Tile::TAG_STAIR_DOWN => Some(StairCue {
-                icon: "v",
-                description: "On > : PageUp to descend",
-                color: RGB::named(ORANGE),
-            }),
-            _ => None,
-        })
+    fn run_step(&mut self) -> bool {
+        let Some(run) = self.auto_run else {
+            return false;
+        };
+        let acted = self.try_step(run.delta.x, run.delta.y);
+        // Individual steps would flood the log; the stop message summarizes.
+        self.last_move_attempt = None;
+        acted
     }
 
-    fn expire_reset_prompt(&mut self) {
-        if let Some(frame) = self.reset_prompt_frame {
-            if self.frame.saturating_sub(frame) > RESET_CONFIRM_WINDOW_FRAMES {
-                self.reset_prompt_frame = None;
-            }
+    fn stop_running(&mut self, reason: &str) {
+        if self.auto_run.take().is_some() {
+            self.push_log_entry(reason);
         }
     }
 
-    fn clear_run_stats(&mut self) {
-        let _ = fs::remove_file(RUN_STATS_PATH);
-        self.run_stats = RunStats::default();
-        self.run_max_floor = self.active_floor.0;
-        self.persist_run_stats();
-        self.push_log_entry("Run stats reset. Run counter back to 1.");
+    fn run_sides(&self, point: Point, delta: Point) -> (bool, bool) {
+        let (left, right) = if delta.x == 0 || delta.y == 0 {
+            (Point::new(delta.y, -delta.x), Point::new(-delta.y, delta.x))
+        } else {
+            (Point::new(delta.x, 0), Point::new(0, delta.y))
+        };
+        (
+            self.dungeon
+                .is_walkable(self.active_floor, self.active_world, point + left),
+            self.dungeon
+                .is_walkable(self.active_floor, self.active_world, point + right),
+        )
     }
 
-    fn arrival_point(&self, descending: bool) -> Point {
-        if let Some(floor) = self.dungeon.active_floor(self.active_floor) {
-            let anchor = if descending {
-                floor.stairs_up().first()
-            } else {
-                floor.stairs_down().first()
-            };
-            if let Some(point) = anchor {
-                return *point;
-            }
-            return floor.spawn_point();
-        }
-        Point::new(1, 1)
+    fn monster_in_view(&self) -> bool {
+        self.visible_monster_count() > 0
     }
 
-    fn persist_run_stats(&self) {
-        if let Err(err) = self.run_stats.persist_to_disk() {
-            eprintln!("Failed to persist run stats: {err}");
+    /// Arrows on the viewport's edge toward monsters the player can see or
+    /// remembers but the viewport is too small to show.
+    fn draw_threat_arrows(&self, ctx: &mut BTerm, layout: &Layout) {
+        let player = self.ecs.player_point();
+        let seen = self
+            .ecs
+            .monster_footprints(self.active_floor, self.active_world)
+            .into_iter()
+            .filter_map(|cells| {
+                cells
+                    .into_iter()
+                    .find(|cell| self.visible_tiles.contains(cell))
+            });
+        let remembered = self
+            .monster_memory
+            .ghosts(&self.visible_tiles)
+            .map(|(point, _, _)| point);
+        for threat in seen.chain(remembered) {
+            if let Some((edge, arrow)) = layout.threat_arrow(player, threat) {
+                ctx.set(
+                    edge.x,
+                    edge.y,
+                    RGB::named(ORANGE_RED),
+                    RGB::named(BLACK),
+                    to_cp437(arrow),
+                );
+            }
         }
     }
 
-    fn record_depth(&mut self, floor: FloorId) {
-        self.run_max_floor = self.run_max_floor.max(floor.0);
-        self.run_stats.best_depth = self.run_stats.best_depth.max(self.run_max_floor);
-        self.persist_run_stats();
+    fn visible_monster_count(&self) -> usize {
+        self.ecs
+            .monster_footprints(self.active_floor, self.active_world)
+            .iter()
+            .filter(|cells| cells.iter().any(|cell| self.visible_tiles.contains(cell)))
+            .count()
     }
 
-    fn seed_floor_monsters(&mut self, floor_id: FloorId) {
-        if self.seeded_floors.contains(&floor_id.0) {
-            return;
-        }
-        let mut rng = RandomNumberGenerator::seeded(0xdead_beef ^ floor_id.0 as u64);
-        if let Some(floor) = self.dungeon.active_floor(floor_id) {
-            for &world in SPECTRUM.iter() {
-                let layer = floor.layer(world);
-                let mut walkable = layer.walkable_points();
-                if walkable.is_empty() {
+    /// Pulls the next key from the script, evaluating directives on the way.
+    /// A failed `#assert` or an expired `#waituntil` ends the process with a
+    /// non-zero status.
+    fn next_scripted_key(&mut self) -> Option<VirtualKeyCode> {
+        loop {
+            let probe = self.script_probe();
+            let script = self.scripted_input.as_mut()?;
+            if let Some(wait) = script.pending_wait() {
+                if wait.condition.holds(&probe) {
+                    if self.verbose {
+                        println!(
+                            "[RR-SCRIPT] line {}: {} held after {} wait turns",
+                            wait.line, wait.source, wait.waited
+                        );
+                    }
+                    script.set_pending_wait(None);
                     continue;
                 }
-                let templates = MonsterTemplate::for_world(world);
-                if templates.is_empty() {
-                    continue;
+                if wait.waited >= wait.max_turns {
+                    eprintln!(
+                        "[RR-SCRIPT] line {}: waituntil {} still false after {} turns ({})",
+                        wait.line,
+                        wait.source,
+                        wait.max_turns,
+                        wait.condition.actual(&probe)
+                    );
+                    std::process::exit(1);
                 }
-                let spawn_target = (walkable.len() / 90).max(2).min(6);
-                let mut spawned = 0;
-                while spawned < spawn_target && !walkable.is_empty() {
-                    let idx = rng.range(0, walkable.len() as i32) as usize;
-                    let point = walkable.swap_remove(idx);
-                    if self.ecs.entity_at(point, floor_id, world).is_some()
-                        || (floor_id == self.active_floor
-                            && world == self.active_world
-                            && point == self.ecs.player_point())
-                    {
-                        continue;
+                wait.waited += 1;
+                return Some(VirtualKeyCode::Period);
+            }
+            let step = script.next_step()?;
+            match step {
+                ScriptStep::WaitUntil {
+                    condition,
+                    source,
+                    line,
+                    max_turns,
+                } => script.set_pending_wait(Some(PendingWait {
+                    condition,
+                    source,
+                    line,
+                    max_turns,
+                    waited: 0,
+                })),
+                ScriptStep::Key(key) => return Some(key),
+                ScriptStep::Assert {
+                    condition,
+                    source,
+                    line,
+                } => {
+                    if !condition.holds(&probe) {
+                        eprintln!(
+                            "[RR-SCRIPT] line {line}: assertion failed: {source} ({})",
+                            condition.actual(&probe)
+                        );
+                        std::process::exit(1);
+                    }
+                    if self.verbose {
+                        println!("[RR-SCRIPT] line {line}: assert {source} holds");
                     }
-                    let template_idx = rng.range(0, templates.len() as i32) as usize;
-                    let template = templates[template_idx].clone();
-                    self.ecs.spawn_monster(&template, point, floor_id, world);
-                    spawned += 1;
                 }
             }
         }
-        self.seeded_floors.insert(floor_id.0);
     }
 
-    fn on_player_death(&mut self) {
-        self.is_dead = true;
-        self.ecs.clear_player_intent();
-        self.last_move_attempt = None;
-        self.run_stats.best_depth = self.run_stats.best_depth.max(self.run_max_floor);
-        self.persist_run_stats();
-        self.push_log_entry("Your spectrum shatters. Press R to restart or Esc to quit.");
+    fn script_probe(&self) -> ScriptProbe {
+        let (hp, max_hp) = self
+            .ecs
+            .player_stats()
+            .map(|stats| (stats.hp, stats.max_hp))
+            .unwrap_or_default();
+        ScriptProbe {
+            hp,
+            max_hp,
+            floor: self.active_floor.0,
+            turn: self.ecs.turn,
+            pos: self.ecs.player_point(),
+            monsters_visible: self.visible_monster_count(),
+        }
     }
 
-    fn reset_run(&mut self) {
-        let mut next_stats = self.run_stats.clone();
-        next_stats.best_depth = next_stats.best_depth.max(self.run_max_floor);
-        next_stats.run_number = next_stats.run_number.saturating_add(1);
-        *self = Self::bootstrap(next_stats);
-        self.persist_run_stats();
-        self.push_log_entry(format!(
-                    "Run {} anchors. Best depth {}",
-                    self.run_stats.run_number, self.run_stats.best_depth
-                ));
+    fn try_step(&mut self, dx: i32, dy: i32) -> bool {
+        if dx == 0 && dy == 0 {
+            return false;
+        }
+
+        let current = self.ecs.player_point();
+        let target = Point::new(current.x + dx, current.y + dy);
+        match self.bump_at(target) {
+            Bump::Door => {
+                // Stepping through takes another turn.
+                self.set_door(target, true);
+                self.push_log_entry("You ease the door open.");
+                self.last_move_attempt = None;
+                return true;
+            }
+            Bump::Friend(entity) => {
+                return match self.ecs.swap_player_with(entity) {
+                    Some(name) => {
+                        self.push_log_entry(format!("You slip past {name}."));
+                        self.last_move_attempt = None;
+                        true
+                    }
+                    None => false,
+                };
+            }
+            Bump::Foe => {
+                if let Some(report) =
+                    self.ecs
+                        .player_attack(target, self.active_floor, self.active_world)
+                {
+                    // A killing blow carries the player into the space.
+                    if self.push_attack_report(report) {
+                        self.ecs.queue_player_step(Point::new(dx, dy));
+                        self.last_move_attempt = Some((current, target));
+                    } else {
+                        self.last_move_attempt = None;
+                    }
+                    return true;
+                }
+            }
+            Bump::Open => {}
+        }
+        // What lies on the tile is dealt with on arrival; see `arrive`.
+        self.ecs.queue_player_step(Point::new(dx, dy));
+        self.last_move_attempt = Some((current, target));
+        true
     }
 
-    fn draw_game_over(&self, ctx: &mut BTerm) {
-        let (width_raw, height_raw) = ctx.get_char_size();
-        let screen_h = height_raw as i32;
-        let banner = "S P E C T R U M   S H A T T E R E D";
-        let hint = "Press R to restart or Esc to quit.";
-        let box_top = (MAP_ORIGIN_Y + 6).min(screen_h.saturating_sub(8));
-        let box_height = 6.min(screen_h.saturating_sub(box_top).saturating_sub(1).max(3));
-        ctx.draw_box(
-            1,
-            box_top,
-            width_raw.saturating_sub(2),
-            box_height,
-            RGB::named(DARK_GRAY),
-            RGB::named(BLACK),
-        );
-        let banner_y = (box_top + 2).min(screen_h.saturating_sub(2));
-        let hint_y = (banner_y + 2).min(screen_h.saturating_sub(1));
-        ctx.print_color_centered(banner_y, RGB::named(RED), RGB::named(BLACK), banner);
-        ctx.print_color_centered(hint_y, RGB::named(WHITE), RGB::named(BLACK), hint);
+    /// Sorts a step into `target` by what is there. Every directional
+    /// input that isn't a run or a `Ctrl` strike resolves through this, so
+    /// doors, friends, and foes all answer to the same bump.
+    fn bump_at(&self, target: Point) -> Bump {
+        if self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+            .and_then(|layer| layer.tile_at(target))
+            .is_some_and(|tile| tile.tag == Tile::TAG_DOOR_CLOSED)
+        {
+            return Bump::Door;
+        }
+        match self
+            .ecs
+            .entity_at(target, self.active_floor, self.active_world)
+        {
+            Some(entity) if entity == self.ecs.player_entity() => Bump::Open,
+            // Friendly occupants are never attacked.
+            Some(entity) if self.ecs.is_friendly(entity) => Bump::Friend(entity),
+            Some(_) => Bump::Foe,
+            None => Bump::Open,
+        }
     }
-}
 
-impl Drop for RainbowRogueState {
-    fn drop(&mut self) {
-        if !self.verbose {
-            return;
+    /// Everything stepping onto a new tile sets off: traps, cursed ground,
+    /// corpses, loose items (when auto-pickup is on), and a cue when the
+    /// tile is a stair.
+    fn arrive(&mut self) {
+        self.spring_trap_under_player();
+        self.touch_cursed_tile();
+        self.search_corpse();
+        self.step_onto_items();
+        if let Some(cue) = self.stair_cue() {
+            self.push_log_entry(cue.description);
         }
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
-        let filename = format!("history/play_history_{}.log", timestamp);
-        if let Err(e) = fs::create_dir_all("history") {
-            eprintln!("Failed to create history directory: {}", e);
-            return;
+    }
+
+    /// Logs a player attack and what followed it. Returns whether the
+    /// target died.
+    fn push_attack_report(&mut self, report: AttackReport) -> bool {
+        let name = report.hit.other.clone();
+        self.push_combat_event(report.hit);
+        if report.ignited {
+            self.push_log_entry(format!("{name} catches fire!"));
         }
-        if let Err(e) = fs::write(&filename, self.play_history.join("\n")) {
-            eprintln!("Failed to write play history to {}: {}", filename, e);
-        } else {
-            println!("Play history saved to {}", filename);
+        if report.chilled {
+            self.push_log_entry(format!("{name} is chilled to a crawl."));
         }
+        let Some(kill) = report.kill else {
+            return false;
+        };
+        self.push_log_entry(kill);
+        if let Some(loot) = report.loot {
+            self.push_log_entry(loot);
+        }
+        true
     }
-}
 
-fn main() -> BError {
-    let args: Vec<String> = env::args().collect();
-    let is_scripted = args.iter().any(|arg| arg == "--scripted-input");
+    fn push_log_entry<S: Into<String>>(&mut self, entry: S) {
+        self.push_log_with(entry, LogPriority::Normal);
+    }
 
-    let (console_width, console_height) = console_dimensions(is_scripted);
-    let context = BTermBuilder::simple(console_width, console_height)?
-        .with_title("RainbowRogue · Spectrum Seed")
-        .with_font("vga8x16.png", 8, 16)
-        .with_tile_dimensions(8, 16)
+    /// Logs `entry`, then trims the log back to `--log-history` lines by
+    /// dropping the oldest older line of the lowest priority still present.
+    /// The new line itself always stays, whatever its priority.
+    fn push_log_with<S: Into<String>>(&mut self, entry: S, priority: LogPriority) {
+        let entry = entry.into();
+        self.log_streak = None;
+        self.record_history(&entry);
+        self.message_log.insert(0, entry);
+        self.log_priorities.insert(0, priority);
+        while self.message_log.len() > self.options.log_history {
+            let older = &self.log_priorities[1..];
+            let lowest = older.iter().min().copied().unwrap_or_default();
+            let Some(idx) = older.iter().rposition(|&p| p == lowest) else {
+                break;
+            };
+            let idx = idx + 1;
+            self.message_log.remove(idx);
+            self.log_priorities.remove(idx);
+        }
+    }
 
-        .build()?;
+    fn record_history(&mut self, line: &str) {
+        self.play_history.push(line.to_string());
+        if let Some(file) = self.log_file.as_mut()
+            && let Err(err) = writeln!(file, "{line}")
+        {
+            eprintln!("[RR-ERROR] Log file write failed, disabling it: {err}");
+            self.log_file = None;
+        }
+    }
+
+    /// Logs a combat event, folding it into the newest line when it repeats
+    /// the same kind of hit on the same party ("You strike X ×3 for 15").
+    /// Any other entry in between starts a fresh line. The play history
+    /// still records every hit individually.
+    fn push_combat_event(&mut self, event: CombatEvent) {
+        if let Some(streak) = self.log_streak.as_mut()
+            && streak.event.same_streak(&event)
+            && !self.message_log.is_empty()
+        {
+            streak.count += 1;
+            streak.total += event.amount;
+            self.message_log[0] = streak.event.describe(streak.count, streak.total);
+            self.record_history(&event.describe(1, event.amount));
+            return;
+        }
+        let priority = match event.kind {
+            CombatEventKind::FoeClaw => LogPriority::Alert,
+            CombatEventKind::PlayerStrike => LogPriority::Normal,
+        };
+        self.push_log_with(event.describe(1, event.amount), priority);
+        self.log_streak = Some(LogStreak {
+            total: event.amount,
+            event,
+            count: 1,
+        });
+    }
+
+    fn draw_quickbar(&self, ctx: &mut BTerm, layout: &Layout) {
+        let entries = self.ecs.player_inventory();
+        if entries.is_empty() {
+            return;
+        }
+        let mut x = layout.hud.x1 + 2;
+        let y = layout.hud.y1 + 5;
+        let pages = PACK_SLOTS.div_ceil(QUICKBAR_PAGE_SLOTS);
+        let page = format!("Q {}/{pages}", self.quickbar_page + 1);
+        ctx.print_color(x, y, RGB::named(GRAY), RGB::named(BLACK), &page);
+        x += page.len() as i32 + 2;
+        let first = self.quickbar_page * QUICKBAR_PAGE_SLOTS;
+        for (idx, slot) in entries.iter().skip(first).take(QUICKBAR_PAGE_SLOTS) {
+            let label = format!(
+                "[{}] {} (x{})",
+                idx - first + 1,
+                slot.name,
+                slot.uses_remaining
+            );
+            if x + label.len() as i32 >= layout.hud.x2 {
+                break;
+            }
+            ctx.print_color(x, y, slot.color, RGB::named(BLACK), &label);
+            x += label.len() as i32 + 2;
+        }
+    }
+
+    fn set_door(&mut self, point: Point, open: bool) {
+        let close_after = self.options.door_close_turns;
+        if let Some(floor) = self.dungeon.floor_mut(self.active_floor)
+            && floor.set_door_open(point, open, close_after)
+        {
+            self.ecs.mark_viewsheds_dirty();
+        }
+    }
+
+    /// True when anything, on any world layer of this floor, stands on
+    /// `point`; doors never close on an occupant.
+    fn doorway_occupied(&self, point: Point) -> bool {
+        SPECTRUM.iter().any(|&world| {
+            self.ecs
+                .entity_at(point, self.active_floor, world)
+                .is_some()
+        })
+    }
+
+    /// Yellow's lens-prisms pick out every trap in sight. Detection sticks
+    /// across worlds; traps never seen from Yellow stay hidden.
+    fn detect_visible_traps(&mut self) {
+        let Some(floor) = self.dungeon.floor_mut(self.active_floor) else {
+            return;
+        };
+        let in_sight: Vec<Point> = floor
+            .substrate
+            .traps
+            .iter()
+            .copied()
+            .filter(|p| self.visible_tiles.contains(p))
+            .collect();
+        let found = in_sight
+            .into_iter()
+            .filter(|&p| floor.detect_trap(p))
+            .count();
+        match found {
+            0 => {}
+            1 => self.push_log_entry("Your lens-prism picks out a hidden snare (^)."),
+            n => self.push_log_entry(format!("Your lens-prism picks out {n} hidden snares (^).")),
+        }
+    }
+
+    /// Stepping onto a trap springs it, hurting more on deeper floors, and
+    /// leaves it detected.
+    fn spring_trap_under_player(&mut self) {
+        let point = self.ecs.player_point();
+        if self
+            .tile_under_player()
+            .is_none_or(|tile| tile.tag != Tile::TAG_TRAP)
+        {
+            return;
+        }
+        if let Some(floor) = self.dungeon.floor_mut(self.active_floor) {
+            floor.detect_trap(point);
+        }
+        let damage = self.ecs.damage_player(3 + self.active_floor.0 as i32);
+        if damage > 0 {
+            self.push_log_entry(format!("A prism snare bites you for {damage}!"));
+        } else {
+            self.push_log_entry("A prism snare snaps at you. (godmode) blocked.");
+        }
+    }
+
+    /// Ages the floor's acid clouds (drifting them downwind while attuned to
+    /// Orange), burns whatever stands in one, then releases fresh clouds
+    /// where bursting monsters died.
+    fn tick_acid_clouds(&mut self) {
+        let drift = self.active_world == World::Orange;
+        let Some(floor) = self.dungeon.floor_mut(self.active_floor) else {
+            return;
+        };
+        floor.tick_clouds(drift);
+        let clouds = floor.cloud_points();
+        if clouds.contains(&self.ecs.player_point()) {
+            let damage = self.ecs.damage_player(ACID_CLOUD_DAMAGE);
+            if damage > 0 {
+                self.push_log_entry(format!("The acid cloud burns you for {damage}!"));
+            } else {
+                self.push_log_entry("Acid hisses over you. (godmode) blocked.");
+            }
+        }
+        if !clouds.is_empty() {
+            for line in self.ecs.hurt_monsters_in_clouds(
+                &clouds,
+                ACID_CLOUD_DAMAGE,
+                self.active_floor,
+                self.active_world,
+            ) {
+                self.push_log_entry(line);
+            }
+        }
+        let bursts = self.ecs.take_cloud_bursts();
+        if let Some(floor) = self.dungeon.floor_mut(self.active_floor) {
+            for point in bursts {
+                floor.add_cloud(point, ACID_CLOUD_TURNS);
+            }
+        }
+    }
+
+    /// Starts the active floor's collapse clock the first time you arrive;
+    /// going back to a floor never resets it.
+    fn start_collapse_clock(&mut self) {
+        if self.options.collapse_turns > 0 {
+            self.collapse_clocks
+                .entry(self.active_floor.0)
+                .or_insert(self.ecs.turn);
+        }
+    }
+
+    /// Turns since you first arrived on the active floor, while
+    /// `--collapse` is on; a `--hub` floor 0 never collapses.
+    fn collapse_elapsed(&self) -> Option<u64> {
+        if self.options.collapse_turns == 0 || (self.options.hub && self.active_floor.0 == 0) {
+            return None;
+        }
+        let start = self.collapse_clocks.get(&self.active_floor.0)?;
+        Some(self.ecs.turn.saturating_sub(*start))
+    }
+
+    /// Rings of the active floor's edge that have crumbled so far.
+    fn collapsed_rings(&self) -> i32 {
+        self.collapse_elapsed().map_or(0, |elapsed| {
+            collapse_rings(elapsed, self.options.collapse_turns)
+        })
+    }
+
+    /// Turns the active floor's crumbled rings into void tiles, so
+    /// monsters, pathing, and your own steps all treat them as gone.
+    fn collapse_edges(&mut self) {
+        let rings = self.collapsed_rings();
+        if rings > 0
+            && let Some(floor) = self.dungeon.floor_mut(self.active_floor)
+        {
+            floor.collapse_edges(rings);
+        }
+    }
+
+    /// Counts the active floor's clock down in the log, announces the
+    /// collapse when it runs out, and lets the void gnaw at you while you
+    /// stand on a crumbled tile.
+    fn tick_collapse(&mut self) {
+        let Some(elapsed) = self.collapse_elapsed() else {
+            return;
+        };
+        let budget = u64::from(self.options.collapse_turns);
+        if elapsed < budget {
+            let left = budget - elapsed;
+            if COLLAPSE_WARNINGS.contains(&left) {
+                let turns = if left == 1 { "turn" } else { "turns" };
+                self.push_log_with(
+                    format!("The floor groans: {left} {turns} until its edges give way."),
+                    LogPriority::Alert,
+                );
+            }
+            return;
+        }
+        if elapsed == budget {
+            self.push_log_with(
+                "The floor's edges crumble into the void! Make for the stairs.",
+                LogPriority::Alert,
+            );
+        }
+        self.collapse_edges();
+        let player = self.ecs.player_point();
+        let collapsed = self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+            .and_then(|layer| layer.tile_at(player))
+            .is_some_and(Tile::is_void);
+        if collapsed {
+            let damage = self.ecs.damage_player(COLLAPSE_DAMAGE);
+            if damage > 0 {
+                self.push_log_with(
+                    format!("The void gnaws at you for {damage}!"),
+                    LogPriority::Alert,
+                );
+            } else {
+                self.push_log_entry("The void tugs at you. (godmode) blocked.");
+            }
+        }
+    }
+
+    /// Crumbles old corpses, then lays down the ones this turn's kills left.
+    fn tick_corpses(&mut self) {
+        let fallen = self.ecs.take_fallen();
+        self.ecs.bark_deaths(&fallen, self.active_world);
+        if let Some(floor) = self.dungeon.floor_mut(self.active_floor) {
+            floor.tick_corpses();
+            for corpse in fallen {
+                floor.add_corpse(corpse);
+            }
+        }
+    }
+
+    /// Stepping onto a corpse searches it, using it up.
+    fn search_corpse(&mut self) {
+        let point = self.ecs.player_point();
+        let Some(corpse) = self
+            .dungeon
+            .floor_mut(self.active_floor)
+            .and_then(|floor| floor.take_corpse(self.active_world, point))
+        else {
+            return;
+        };
+        let line = self.ecs.loot_corpse(&corpse);
+        self.push_log_entry(line);
+    }
+
+    fn toggle_auto_pickup(&mut self) {
+        self.auto_pickup = !self.auto_pickup;
+        self.push_log_entry(if self.auto_pickup {
+            "Auto-pickup on."
+        } else {
+            "Auto-pickup off: items stay where they lie."
+        });
+    }
+
+    /// Stepping onto items collects them with auto-pickup on; otherwise
+    /// each one is only pointed out.
+    fn step_onto_items(&mut self) {
+        if self.auto_pickup {
+            self.pick_up_items();
+            return;
+        }
+        let point = self.ecs.player_point();
+        let names: Vec<String> = self
+            .dungeon
+            .active_floor(self.active_floor)
+            .map(|floor| {
+                floor
+                    .items_at(self.active_world, point)
+                    .map(|item| item.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        for name in names {
+            self.push_log_entry(format!("There is a {name} here."));
+        }
+    }
+
+    /// The `,` key: picks up whatever lies underfoot, taking a turn if
+    /// anything was collected.
+    fn pick_up_here(&mut self) -> bool {
+        let point = self.ecs.player_point();
+        let lying = self
+            .dungeon
+            .active_floor(self.active_floor)
+            .is_some_and(|floor| floor.items_at(self.active_world, point).next().is_some());
+        if !lying {
+            self.push_log_entry("There is nothing here to pick up.");
+            return false;
+        }
+        self.pick_up_items() > 0
+    }
+
+    /// HUD hint for items underfoot that auto-pickup left alone.
+    fn pickup_prompt(&self) -> Option<String> {
+        if self.auto_pickup {
+            return None;
+        }
+        let point = self.ecs.player_point();
+        let floor = self.dungeon.active_floor(self.active_floor)?;
+        let mut items = floor.items_at(self.active_world, point);
+        let first = items.next()?;
+        let more = items.count();
+        Some(if more == 0 {
+            format!("Press , to pick up the {}.", first.name)
+        } else {
+            format!("Press , to pick up the {} (+{more} more).", first.name)
+        })
+    }
+
+    /// Moves every item under the player into the pack, leaving behind any
+    /// the quickbar has no room for. Returns how many were picked up.
+    fn pick_up_items(&mut self) -> usize {
+        let point = self.ecs.player_point();
+        let Some(floor) = self.dungeon.floor_mut(self.active_floor) else {
+            return 0;
+        };
+        let mut lying = Vec::new();
+        while let Some(item) = floor.take_item(self.active_world, point) {
+            lying.push(item);
+        }
+        let mut picked = 0;
+        let mut left = Vec::new();
+        for item in lying {
+            match floor_item_template(item.world, &item.name) {
+                Some(template) if self.ecs.give_player_consumable(&template) => {
+                    self.push_log_entry(format!("You pick up the {}.", item.name));
+                    picked += 1;
+                }
+                Some(_) => {
+                    self.push_log_entry(format!("You have no room for the {}.", item.name));
+                    left.push(item);
+                }
+                None => {}
+            }
+        }
+        if let Some(floor) = self.dungeon.floor_mut(self.active_floor) {
+            for item in left {
+                floor.add_item(item);
+            }
+        }
+        picked
+    }
+
+    /// Cursed tiles sting whenever the player steps onto one.
+    fn touch_cursed_tile(&mut self) {
+        let point = self.ecs.player_point();
+        if self
+            .dungeon
+            .active_floor(self.active_floor)
+            .is_none_or(|floor| !floor.is_cursed(point))
+        {
+            return;
+        }
+        let damage = self.ecs.damage_player(CURSE_DAMAGE);
+        if damage > 0 {
+            self.push_log_entry(format!("Cursed resonance stings you for {damage}."));
+        } else {
+            self.push_log_entry("Cursed resonance prickles. (godmode) blocked.");
+        }
+    }
+
+    /// Water slows the step out of it; lava burns on every step in. A
+    /// levitating player floats over both, and touches down the turn the
+    /// effect runs out.
+    fn enter_hazard(&mut self, previous_point: Point) {
+        if self.ecs.levitate_turns() > 0 {
+            return;
+        }
+        let Some(layer) = self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+        else {
+            return;
+        };
+        let tag_at = |point: Point| layer.tile_at(point).map(|tile| tile.tag);
+        let (here, before) = (tag_at(self.ecs.player_point()), tag_at(previous_point));
+        match here {
+            Some(Tile::TAG_WATER) => {
+                self.ecs.charge_player_energy(WATER_SURCHARGE);
+                if before != Some(Tile::TAG_WATER) {
+                    self.push_log_entry("You wade into deep water; it drags at you.");
+                }
+            }
+            Some(Tile::TAG_LAVA) => {
+                let damage = self.ecs.damage_player(LAVA_DAMAGE);
+                if damage > 0 {
+                    self.push_log_entry(format!("The lava sears you for {damage}!"));
+                } else {
+                    self.push_log_entry("Lava laps at your boots. (godmode) blocked.");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn lava_at(&self, point: Point) -> bool {
+        self.dungeon
+            .active_layer(self.active_floor, self.active_world)
+            .and_then(|layer| layer.tile_at(point))
+            .is_some_and(Tile::is_lava)
+    }
+
+    /// Picks up the fragment under the player if it belongs to the world
+    /// they are attuned to; shifting into that world on the spot counts.
+    fn collect_fragment(&mut self) {
+        let player = self.ecs.player_point();
+        let Some(fragment) = self
+            .dungeon
+            .active_floor(self.active_floor)
+            .and_then(|floor| floor.fragment_at(player, self.active_world, self.fragments))
+        else {
+            return;
+        };
+        self.fragments |= fragment_bit(fragment.world);
+        self.push_log_entry(format!(
+            "You gather the {} spectrum fragment ({}/{}).",
+            fragment.world.as_str(),
+            self.fragments.count_ones(),
+            SPECTRUM.len()
+        ));
+        if self.fragments == ALL_FRAGMENTS && self.active_floor.0 > 0 {
+            self.push_log_entry("All seven fragments hum together. Carry them back to floor 0.");
+        }
+    }
+
+    /// Marks the active floor's objective done once it holds, paying out
+    /// the reward exactly once.
+    fn check_objective(&mut self) {
+        let player = self.ecs.player_point();
+        let Some(floor) = self.dungeon.active_floor(self.active_floor) else {
+            return;
+        };
+        let Some(objective) = floor.objective.filter(|objective| !objective.complete) else {
+            return;
+        };
+        let quarry_alive = match objective.kind {
+            ObjectiveKind::Slay { world, name } => {
+                self.ecs.monster_named_on(name, self.active_floor, world)
+            }
+            _ => false,
+        };
+        let probe = ObjectiveProbe {
+            on_down_stair: floor.stairs_down().contains(&player),
+            seeded: self.seeded_floors.contains(&self.active_floor.0),
+            quarry_alive,
+            charted_percent: SPECTRUM
+                .iter()
+                .map(|&world| floor.layer(world).charted_percent())
+                .max()
+                .unwrap_or(0),
+        };
+        if !objective.holds(&probe) {
+            return;
+        }
+        if let Some(objective) = self
+            .dungeon
+            .floor_mut(self.active_floor)
+            .and_then(|floor| floor.objective.as_mut())
+        {
+            objective.complete = true;
+        }
+        self.ecs.grant_shards(OBJECTIVE_REWARD_SHARDS);
+        self.push_log_entry(format!(
+            "Objective complete: {}. +{OBJECTIVE_REWARD_SHARDS} shards.",
+            objective.describe()
+        ));
+    }
+
+    /// The run is won the moment the player stands on floor 0 holding every
+    /// fragment.
+    fn check_victory(&mut self) {
+        if self.options.endless
+            || self.run_over()
+            || self.fragments != ALL_FRAGMENTS
+            || self.active_floor.0 != 0
+        {
+            return;
+        }
+        self.has_won = true;
+        if self.autosave_enabled() {
+            let _ = fs::remove_file(save::autosave_path(&self.save_dir));
+        }
+        self.auto_run = None;
+        self.auto_rest = None;
+        self.ecs.clear_player_intent();
+        self.last_move_attempt = None;
+        self.run_stats.best_depth = self.run_stats.best_depth.max(self.run_max_floor);
+        self.run_stats.wins = self.run_stats.wins.saturating_add(1);
+        self.persist_run_stats();
+        self.flush_run_stats();
+        self.push_log_entry(
+            "The seven fragments blaze into one spectrum. Press R for a new run or Esc to quit.",
+        );
+    }
+
+    /// In endless mode a wave lands every [`ENDLESS_WAVE_TURNS`] turns on
+    /// the player's layer, each bigger and more often elite than the last.
+    /// The hub's wards turn waves away, but the count still rises.
+    fn spawn_endless_waves(&mut self) {
+        if !self.options.endless || self.run_over() {
+            return;
+        }
+        let due = (self.ecs.turn / ENDLESS_WAVE_TURNS) as u32;
+        while self.endless_waves < due {
+            self.endless_waves += 1;
+            let wave = self.endless_waves;
+            if self.options.hub && self.active_floor.0 == 0 {
+                self.push_log_entry(format!("Wave {wave} breaks against the hub's wards."));
+                continue;
+            }
+            let mut rng = RandomNumberGenerator::seeded(
+                RngStream::Spawns.seed(self.master_seed) ^ (wave as u64).rotate_left(32),
+            );
+            let count = (2 + wave as usize).min(ENDLESS_WAVE_CAP);
+            let depth = FloorId(self.active_floor.0 + wave);
+            let player = self.ecs.player_point();
+            let visible = self.visible_tiles.clone();
+            let spawned = self.spawn_monsters(
+                self.active_floor,
+                self.active_world,
+                count,
+                depth,
+                &mut rng,
+                |point| {
+                    !visible.contains(&point)
+                        && DistanceAlg::Pythagoras.distance2d(point, player)
+                            >= ENDLESS_WAVE_MIN_DISTANCE
+                },
+            );
+            self.push_log_entry(format!(
+                "Wave {wave}: {spawned} monsters pour out of the dark."
+            ));
+        }
+    }
+
+    /// Death and victory both freeze the run until `R` starts a new one.
+    fn run_over(&self) -> bool {
+        self.is_dead || self.has_won
+    }
+
+    /// Swings open any closed door a monster stepped into this turn.
+    fn open_occupied_doors(&mut self) {
+        let Some(floor) = self.dungeon.active_floor(self.active_floor) else {
+            return;
+        };
+        let layer = floor.layer(self.active_world);
+        let shut: Vec<Point> = floor
+            .substrate
+            .doors
+            .iter()
+            .copied()
+            .filter(|&p| {
+                layer
+                    .tile_at(p)
+                    .is_some_and(|tile| tile.tag == Tile::TAG_DOOR_CLOSED)
+            })
+            .collect();
+        for point in shut {
+            if self.doorway_occupied(point) {
+                self.set_door(point, true);
+            }
+        }
+    }
+
+    /// Counts down open doors on the active floor and shuts those whose
+    /// doorway has stayed empty long enough. Occupied doorways restart the
+    /// countdown.
+    fn tick_doors(&mut self) {
+        let close_after = self.options.door_close_turns;
+        if close_after == 0 {
+            return;
+        }
+        let Some(floor) = self.dungeon.active_floor(self.active_floor) else {
+            return;
+        };
+        let doors: Vec<(Point, u32)> = floor.open_doors.iter().map(|(p, t)| (*p, *t)).collect();
+        for (point, remaining) in doors {
+            if self.doorway_occupied(point) {
+                if let Some(floor) = self.dungeon.floor_mut(self.active_floor) {
+                    floor.open_doors.insert(point, close_after);
+                }
+            } else if remaining <= 1 {
+                self.set_door(point, false);
+                if self.visible_tiles.contains(&point) {
+                    self.push_log_entry("A door swings shut.");
+                }
+            } else if let Some(floor) = self.dungeon.floor_mut(self.active_floor) {
+                floor.open_doors.insert(point, remaining - 1);
+            }
+        }
+    }
+
+    /// Spends a turn feeling the walls around the player; each adjacent
+    /// secret door has a [`SEARCH_CHANCE`] percent chance to turn up.
+    fn search(&mut self) -> bool {
+        let player = self.ecs.player_point();
+        let hidden: Vec<Point> = self
+            .dungeon
+            .active_floor(self.active_floor)
+            .map(|floor| {
+                let mut adjacent: Vec<Point> = floor
+                    .secret_doors
+                    .iter()
+                    .copied()
+                    .filter(|door| (door.x - player.x).abs() <= 1 && (door.y - player.y).abs() <= 1)
+                    .collect();
+                adjacent.sort_by_key(|door| (door.y, door.x));
+                adjacent
+            })
+            .unwrap_or_default();
+        let mut found = 0;
+        for door in hidden {
+            if self.ecs.roll_search(SEARCH_CHANCE)
+                && let Some(floor) = self.dungeon.floor_mut(self.active_floor)
+                && floor.reveal_secret_door(door)
+            {
+                found += 1;
+            }
+        }
+        if found > 0 {
+            self.push_log_entry("A section of wall gives way: a hidden door!");
+        } else {
+            self.push_log_entry("You search the walls but find nothing.");
+        }
+        true
+    }
+
+    /// `C`: shut an adjacent open door to break line of sight. Costs a turn
+    /// only when a door actually closes.
+    fn close_adjacent_door(&mut self) -> bool {
+        let origin = self.ecs.player_point();
+        let Some(layer) = self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+        else {
+            return false;
+        };
+        let doors: Vec<Point> = (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| Point::new(origin.x + dx, origin.y + dy)))
+            .filter(|&p| p != origin)
+            .filter(|&p| {
+                layer
+                    .tile_at(p)
+                    .is_some_and(|tile| tile.tag == Tile::TAG_DOOR_OPEN)
+            })
+            .collect();
+        if doors.is_empty() {
+            self.push_log_entry("No open door within reach.");
+            return false;
+        }
+        match doors.into_iter().find(|&p| !self.doorway_occupied(p)) {
+            Some(point) => {
+                self.set_door(point, false);
+                self.push_log_entry("You shut the door.");
+                true
+            }
+            None => {
+                self.push_log_entry("Something blocks the doorway.");
+                false
+            }
+        }
+    }
+
+    fn resolve_move_attempt(&mut self, previous_point: Point) {
+        if let Some((origin, target)) = self.last_move_attempt.take() {
+            let current = self.ecs.player_point();
+            if current == target {
+                self.push_log_entry(format!(
+                    "Stepped to {},{} in {}",
+                    current.x,
+                    current.y,
+                    self.active_world.as_str()
+                ));
+            } else if origin == previous_point {
+                if self.corner_blocks(origin, target) {
+                    self.push_log_entry("A wall corner blocks the diagonal.");
+                } else {
+                    self.push_log_entry(format!("Blocked at {},{}", target.x, target.y));
+                }
+            }
+        }
+    }
+
+    /// A diagonal step into the tip of a wall: the target is solid but at
+    /// least one of the two straight steps beside it is open.
+    fn corner_blocks(&self, origin: Point, target: Point) -> bool {
+        let (dx, dy) = (target.x - origin.x, target.y - origin.y);
+        if dx == 0 || dy == 0 {
+            return false;
+        }
+        let Some(layer) = self
+            .dungeon
+            .active_layer(self.active_floor, self.active_world)
+        else {
+            return false;
+        };
+        !layer.is_walkable(target)
+            && (layer.is_walkable(Point::new(origin.x + dx, origin.y))
+                || layer.is_walkable(Point::new(origin.x, origin.y + dy)))
+    }
+
+    fn update_visibility(&mut self) {
+        if let Some(layer) = self
+            .dungeon
+            .active_layer_mut(self.active_floor, self.active_world)
+        {
+            let visible = self.ecs.player_visible_tiles();
+            let newly_visible = layer.reveal_field(&mut self.visible_tiles, visible);
+            let mut seen = Vec::new();
+            self.ecs.each_renderable(
+                self.active_floor,
+                self.active_world,
+                false,
+                |point, renderable| {
+                    if self.visible_tiles.contains(&point) {
+                        seen.push((point, renderable.glyph, renderable.color));
+                    }
+                },
+            );
+            self.monster_memory.observe(
+                self.active_floor,
+                self.active_world,
+                &self.visible_tiles,
+                seen,
+            );
+            if self.active_world == World::Yellow {
+                self.detect_visible_traps();
+            }
+            if newly_visible > 0 {
+                self.push_log_with(
+                    format!(
+                        "Glimpsed {newly_visible} new tiles in {}",
+                        self.active_world.as_str()
+                    ),
+                    LogPriority::Chatter,
+                );
+            }
+        } else {
+            self.visible_tiles.clear();
+        }
+    }
+
+    fn flush_combat_log(&mut self) {
+        for line in self.ecs.drain_combat_log() {
+            match line {
+                LogLine::Text(entry) => self.push_log_entry(entry),
+                LogLine::Bark(entry) => self.push_log_with(entry, LogPriority::Chatter),
+                LogLine::Event(event) => self.push_combat_event(event),
+            }
+        }
+    }
+
+    fn check_health_warning(&mut self) {
+        if let Some(stats) = self.ecs.player_stats() {
+            let ratio = stats.hp as f32 / stats.max_hp as f32;
+            if self.ecs.godmode() {
+                // Godmode runs never warn or die, even if HP was low before toggling.
+                self.hp_ratio = ratio;
+                return;
+            }
+            let critical = ratio <= CRITICAL_HP_RATIO;
+            if critical && !self.hp_alerted {
+                self.push_log_with("!! Vitality critical !!", LogPriority::Alert);
+                self.hp_alerted = true;
+            } else if !critical && self.hp_alerted && ratio > 0.5 {
+                self.push_log_entry("Vitality stabilizes.");
+                self.hp_alerted = false;
+            }
+            self.hp_ratio = ratio;
+            if stats.hp <= 0 && !self.is_dead {
+                self.on_player_death();
+            }
+        }
+    }
+
+    fn activate_consumable(&mut self, slot_index: usize) -> bool {
+        if let Some(messages) = self.ecs.use_consumable(
+            slot_index,
+            &mut self.dungeon,
+            self.active_floor,
+            self.active_world,
+        ) {
+            for message in messages {
+                self.push_log_entry(message);
+            }
+            self.last_move_attempt = None;
+            self.update_visibility();
+            true
+        } else {
+            self.push_log_entry(format!("Slot {} is empty.", slot_index + 1));
+            false
+        }
+    }
+
+    /// How an entity standing at `point` is drawn, if at all. Detect life
+    /// also marks monsters outside FOV at their live positions.
+    fn entity_visibility(&self, point: Point) -> Option<EntityVisibility> {
+        if self.visible_tiles.contains(&point) {
+            Some(EntityVisibility::Visible)
+        } else if self.ecs.detect_life_active() {
+            Some(EntityVisibility::Sensed)
+        } else {
+            None
+        }
+    }
+
+    fn tile_under_player(&self) -> Option<Tile> {
+        let point = self.ecs.player_point();
+        self.dungeon
+            .active_layer(self.active_floor, self.active_world)
+            .and_then(|layer| layer.tile_at(point).cloned())
+    }
+
+    fn stair_cue(&self) -> Option<StairCue> {
+        self.tile_under_player().and_then(|tile| match tile.tag {
+            Tile::TAG_STAIR_UP => Some(StairCue {
+                icon: "^",
+                description: "On < : PageDown to ascend",
+                color: RGB::named(LIGHT_GREEN),
+            }),
+            Tile::TAG_STAIR_DOWN if self.sealed_above(self.active_floor) => Some(StairCue {
+                icon: "v",
+                description: "On > : PageUp to descend (one-way)",
+                color: RGB::named(RED),
+            }),
+            Tile::TAG_STAIR_DOWN => Some(StairCue {
+                icon: "v",
+                description: "On > : PageUp to descend",
+                color: RGB::named(ORANGE),
+            }),
+            _ => None,
+        })
+    }
+
+    fn expire_reset_prompt(&mut self) {
+        if let Some(frame) = self.reset_prompt_frame
+            && self.frame.saturating_sub(frame) > RESET_CONFIRM_WINDOW_FRAMES
+        {
+            self.reset_prompt_frame = None;
+        }
+    }
+
+    fn clear_run_stats(&mut self) {
+        let _ = fs::remove_file(RUN_STATS_PATH);
+        self.run_stats = RunStats::default();
+        self.run_max_floor = self.active_floor.0;
+        self.persist_run_stats();
+        self.flush_run_stats();
+        self.push_log_entry("Run stats reset. Run counter back to 1.");
+    }
+
+    /// Where the player lands on the active floor: the matching stair
+    /// nearest `departure` (the stair just taken, for continuity), else
+    /// the spawn point, else the closest open tile to either. Only tiles
+    /// that are walkable, free of lava and unoccupied qualify.
+    fn arrival_point(&self, descending: bool, departure: Point) -> Point {
+        let (Some(floor), Some(layer)) = (
+            self.dungeon.active_floor(self.active_floor),
+            self.dungeon
+                .active_layer(self.active_floor, self.active_world),
+        ) else {
+            return Point::new(1, 1);
+        };
+        let open = |point: Point| {
+            layer.is_walkable(point)
+                && !self.lava_at(point)
+                && self
+                    .ecs
+                    .entity_at(point, self.active_floor, self.active_world)
+                    .is_none()
+        };
+        let distance = |a: Point, b: Point| (a.x - b.x).pow(2) + (a.y - b.y).pow(2);
+        let mut stairs = if descending {
+            floor.stairs_up()
+        } else {
+            floor.stairs_down()
+        }
+        .to_vec();
+        stairs.sort_by_key(|&stair| distance(stair, departure));
+        let anchor = stairs.first().copied().unwrap_or(floor.spawn_point());
+        stairs
+            .into_iter()
+            .chain([floor.spawn_point()])
+            .find(|&point| open(point))
+            .or_else(|| {
+                layer
+                    .walkable_points()
+                    .into_iter()
+                    .filter(|&point| open(point))
+                    .min_by_key(|&point| distance(point, anchor))
+            })
+            .unwrap_or(anchor)
+    }
+
+    /// Marks the run stats changed; they reach disk once the throttle
+    /// allows (see [`StatsThrottle`]).
+    fn persist_run_stats(&mut self) {
+        self.stats_throttle.dirty = true;
+        self.write_run_stats_if_due();
+    }
+
+    fn write_run_stats_if_due(&mut self) {
+        if self.stats_throttle.due(Instant::now()) {
+            self.write_run_stats();
+        }
+    }
+
+    /// Writes any pending run stats now, whatever the throttle says.
+    fn flush_run_stats(&mut self) {
+        if self.stats_throttle.dirty {
+            self.write_run_stats();
+        }
+    }
+
+    /// Writes `run_stats.json`, retrying once before giving up. A failed
+    /// write stays pending, so the next due write tries again.
+    fn write_run_stats(&mut self) {
+        if !self.persist_stats {
+            self.stats_throttle.dirty = false;
+            return;
+        }
+        let result = self
+            .run_stats
+            .persist_to_disk()
+            .or_else(|_| self.run_stats.persist_to_disk());
+        let now = Instant::now();
+        match result {
+            Ok(()) => self.stats_throttle.wrote(now),
+            Err(err) => {
+                self.stats_throttle.last_write = Some(now);
+                eprintln!("[RR-ERROR] Failed to persist run stats after a retry: {err}");
+                self.push_log_entry("Run stats couldn't be saved; will try again.");
+            }
+        }
+    }
+
+    /// Tracks the deepest floor reached; returns true only when `floor` sets a
+    /// new max for this run, so stair bouncing never counts twice.
+    fn record_depth(&mut self, floor: FloorId) -> bool {
+        let new_max = floor.0 > self.run_max_floor;
+        self.run_max_floor = self.run_max_floor.max(floor.0);
+        self.run_stats.best_depth = self.run_stats.best_depth.max(self.run_max_floor);
+        if new_max || self.depth_turns.is_empty() {
+            let turn = self.ecs.turn;
+            self.depth_turns.push(DepthSplit {
+                floor: floor.0,
+                turn,
+            });
+            if new_max && let Some(target) = self.options.speedrun {
+                self.record_split(floor.0, turn, target);
+            }
+        }
+        self.persist_run_stats();
+        new_max
+    }
+
+    /// Keeps the speed run's best turn count for `floor`, announcing the
+    /// result once the run reaches its `target`.
+    fn record_split(&mut self, floor: u32, turn: u64, target: u32) {
+        let best = self.run_stats.best_splits.get(&floor).copied();
+        self.split_delta = best.map(|best| turn as i64 - best as i64);
+        if best.is_none_or(|best| turn < best) {
+            self.run_stats.best_splits.insert(floor, turn);
+        }
+        if floor != target {
+            return;
+        }
+        match best {
+            Some(best) if turn < best => self.push_log_entry(format!(
+                "Speed run: floor {target} in {turn} turns, a new best (was {best})."
+            )),
+            Some(best) => self.push_log_entry(format!(
+                "Speed run: floor {target} in {turn} turns (best {best})."
+            )),
+            None => self.push_log_entry(format!(
+                "Speed run: floor {target} in {turn} turns, the first on record."
+            )),
+        }
+    }
+
+    /// Header readout for `--speedrun`: the latest split against its best,
+    /// then turns per floor so far and the finish that pace points to.
+    fn speedrun_summary(&self) -> Option<String> {
+        let target = self.options.speedrun?;
+        let start = self.depth_turns.first()?;
+        let last = self.depth_turns.last()?;
+        let mut summary = format!("Race to floor {target}");
+        if last.floor > start.floor {
+            summary.push_str(&format!(" · F{} @{}", last.floor, last.turn));
+            if let Some(delta) = self.split_delta {
+                summary.push_str(&format!(" ({delta:+})"));
+            }
+        }
+        let gained = self.run_max_floor.saturating_sub(start.floor) as u64;
+        if gained > 0 && self.run_max_floor < target {
+            let pace = self.ecs.turn.saturating_sub(start.turn) / gained;
+            let projected = start.turn + pace * target.saturating_sub(start.floor) as u64;
+            summary.push_str(&format!(" · {pace}/floor, ~{projected} to finish"));
+        }
+        Some(summary)
+    }
+
+    /// The reward for a new deepest floor: back some HP and one use to the
+    /// emptiest pack slot.
+    fn apply_descent_bonus(&mut self) {
+        let percent = self.options.descent_heal_percent;
+        if percent <= 0 {
+            return;
+        }
+        self.push_log_entry("Crossing the prism lock restores you.");
+        let gained = self.ecs.restore_player_fraction(percent);
+        if gained > 0 {
+            self.push_log_entry(format!("Recovered {gained} HP."));
+        }
+        if let Some(line) = self.ecs.recharge_emptiest_slot() {
+            self.push_log_entry(line);
+        }
+    }
+
+    /// Seeds the first `floors` floors exactly as a run would and tallies
+    /// what landed on each layer.
+    fn spawn_report(&mut self, floors: u32) -> bench::SpawnReport {
+        if floors == 0 {
+            return bench::SpawnReport::default();
+        }
+        self.dungeon.ensure_floor(FloorId(floors - 1));
+        for floor in 0..floors {
+            self.seed_floor_monsters(FloorId(floor));
+        }
+        let mut report = bench::SpawnReport::new(&self.dungeon, floors);
+        for (floor, world, stats) in self.ecs.monster_census() {
+            report.record(floor, world, stats.max_hp + stats.power);
+        }
+        report
+    }
+
+    /// Generates and populates `floor` if needed, then exports its layers
+    /// and every entity on them for `--export-map`.
+    fn export_map(&mut self, floor: FloorId) -> MapSnapshot {
+        self.dungeon.ensure_floor(floor);
+        self.seed_floor_monsters(floor);
+        let mut snapshot = self
+            .dungeon
+            .export_snapshot(floor)
+            .expect("ensure_floor generated the floor");
+        for &world in SPECTRUM.iter() {
+            self.ecs.each_renderable(floor, world, true, |point, r| {
+                snapshot.entities.push(EntitySnapshot {
+                    world,
+                    x: point.x,
+                    y: point.y,
+                    glyph: r.glyph,
+                    color: [r.color.r, r.color.g, r.color.b].map(|c| (c * 255.0).round() as u8),
+                    order: r.order,
+                });
+            });
+        }
+        snapshot
+    }
+
+    fn seed_floor_monsters(&mut self, floor_id: FloorId) {
+        if self.seeded_floors.contains(&floor_id.0) {
+            return;
+        }
+        if self.options.hub && floor_id.0 == 0 {
+            self.seed_hub();
+            self.seeded_floors.insert(floor_id.0);
+            return;
+        }
+        let mut rng =
+            RandomNumberGenerator::seeded(RngStream::Spawns.floor_seed(self.master_seed, floor_id));
+        for &world in SPECTRUM.iter() {
+            let Some(layer) = self.dungeon.active_layer(floor_id, world) else {
+                continue;
+            };
+            let spawn_target = self
+                .options
+                .spawn_density
+                .target(layer.walkable_points().len());
+            self.spawn_monsters(floor_id, world, spawn_target, floor_id, &mut rng, |_| true);
+        }
+        if let Some(floor) = self.dungeon.active_floor(floor_id) {
+            // A slay objective always has a quarry to hunt, even when the
+            // rolls above never picked its species.
+            if let Some(ObjectiveKind::Slay { world, name }) =
+                floor.objective.map(|objective| objective.kind)
+                && !self.ecs.monster_named_on(name, floor_id, world)
+                && let Some(template) = MonsterTemplate::for_world(world)
+                    .into_iter()
+                    .find(|template| template.name == name)
+            {
+                let layer = floor.layer(world);
+                let mut walkable = layer.walkable_points();
+                let spawn = floor.spawn_point();
+                while !walkable.is_empty() {
+                    let idx = rng.range(0, walkable.len() as i32) as usize;
+                    let point = walkable.swap_remove(idx);
+                    let clear = Size::square(template.size)
+                        .cells(point)
+                        .into_iter()
+                        .all(|cell| {
+                            layer.is_walkable(cell)
+                                && cell != spawn
+                                && self.ecs.entity_at(cell, floor_id, world).is_none()
+                                && !(floor_id == self.active_floor
+                                    && world == self.active_world
+                                    && cell == self.ecs.player_point())
+                        });
+                    if clear {
+                        self.ecs
+                            .spawn_monster(&template, point, floor_id, world, None);
+                        break;
+                    }
+                }
+            }
+        }
+        self.seed_floor_items(floor_id, &mut rng);
+        self.seeded_floors.insert(floor_id.0);
+    }
+
+    /// Leaves [`FLOOR_ITEMS_PER_LAYER`] of each world's consumables on
+    /// random plain floor tiles away from the spawn point. Rolled after the
+    /// monsters so their placement is unchanged.
+    fn seed_floor_items(&mut self, floor_id: FloorId, rng: &mut RandomNumberGenerator) {
+        let Some(floor) = self.dungeon.floor_mut(floor_id) else {
+            return;
+        };
+        let spawn = floor.spawn_point();
+        for &world in SPECTRUM.iter() {
+            let templates = starter_consumables(world);
+            if templates.is_empty() {
+                continue;
+            }
+            let layer = floor.layer(world);
+            let mut candidates: Vec<Point> = layer
+                .walkable_points()
+                .into_iter()
+                .filter(|&point| {
+                    point != spawn
+                        && layer
+                            .tile_at(point)
+                            .is_some_and(|tile| tile.tag == Tile::TAG_FLOOR)
+                })
+                .collect();
+            for _ in 0..FLOOR_ITEMS_PER_LAYER {
+                if candidates.is_empty() {
+                    break;
+                }
+                let point = candidates.swap_remove(rng.range(0, candidates.len() as i32) as usize);
+                let name = if rng.range(0, 100) < RESONANCE_SHARD_CHANCE {
+                    resonance_shard().name
+                } else {
+                    templates[rng.range(0, templates.len() as i32) as usize].name
+                };
+                floor.add_item(FloorItem {
+                    point,
+                    world,
+                    name: name.to_string(),
+                });
+            }
+        }
+    }
+
+    /// Spawns up to `count` of `world`'s monsters on random free tiles of
+    /// the layer that `allowed` accepts. Elite rolls scale with `depth`,
+    /// which endless waves push past the real floor number.
+    fn spawn_monsters(
+        &mut self,
+        floor_id: FloorId,
+        world: World,
+        count: usize,
+        depth: FloorId,
+        rng: &mut RandomNumberGenerator,
+        allowed: impl Fn(Point) -> bool,
+    ) -> usize {
+        let Some(layer) = self.dungeon.active_layer(floor_id, world) else {
+            return 0;
+        };
+        let mut walkable = layer.walkable_points();
+        let templates = MonsterTemplate::for_world(world);
+        if templates.is_empty() {
+            return 0;
+        }
+        let mut spawned = 0;
+        while spawned < count && !walkable.is_empty() {
+            let idx = rng.range(0, walkable.len() as i32) as usize;
+            let point = walkable.swap_remove(idx);
+            let blocked = |cell: Point| {
+                !layer.is_walkable(cell)
+                    || layer.tile_at(cell).is_some_and(Tile::is_lava)
+                    || self.ecs.entity_at(cell, floor_id, world).is_some()
+                    || (floor_id == self.active_floor
+                        && world == self.active_world
+                        && cell == self.ecs.player_point())
+            };
+            if blocked(point) || !allowed(point) {
+                continue;
+            }
+            // Infighting needs mixed layers: some spawns wander in from a
+            // neighbouring world. The extra rolls only happen with the flag,
+            // so seeded runs without it place the same monsters.
+            let home = if self.options.infighting && rng.range(0, 100) < STRAY_SPAWN_PERCENT {
+                world.cycle(if rng.range(0, 2) == 0 { -1 } else { 1 })
+            } else {
+                world
+            };
+            let roster = if home == world {
+                templates.clone()
+            } else {
+                MonsterTemplate::for_world(home)
+            };
+            let template_idx = rng.range(0, roster.len() as i32) as usize;
+            let template = roster[template_idx].clone();
+            // Large monsters need their whole footprint clear.
+            if Size::square(template.size)
+                .cells(point)
+                .into_iter()
+                .any(blocked)
+            {
+                continue;
+            }
+            let modifier = MonsterModifier::roll(rng, depth);
+            self.ecs
+                .spawn_monster_from(&template, point, floor_id, world, home, modifier);
+            spawned += 1;
+        }
+        spawned
+    }
+
+    /// The hub gets no monsters; instead the Prism Merchant keeps a stall
+    /// beside the spawn point on every world layer.
+    fn seed_hub(&mut self) {
+        let floor_id = FloorId(0);
+        let Some(floor) = self.dungeon.active_floor(floor_id) else {
+            return;
+        };
+        let spawn = floor.spawn_point();
+        let template = NpcTemplate::for_role(NpcRole::Shopkeeper);
+        for &world in SPECTRUM.iter() {
+            let layer = floor.layer(world);
+            let stall = (1..=3).find_map(|radius| {
+                (-radius..=radius)
+                    .flat_map(|dy| (-radius..=radius).map(move |dx| Point::new(dx, dy)))
+                    .map(|offset| Point::new(spawn.x + offset.x, spawn.y + offset.y))
+                    .find(|&point| point != spawn && layer.is_walkable(point))
+            });
+            if let Some(point) = stall {
+                self.ecs.spawn_npc(&template, point, floor_id, world);
+            }
+        }
+    }
+
+    fn on_player_death(&mut self) {
+        self.is_dead = true;
+        // A run that ended must not be resumable from the floor before death.
+        if self.autosave_enabled() {
+            let _ = fs::remove_file(save::autosave_path(&self.save_dir));
+        }
+        self.auto_run = None;
+        self.auto_rest = None;
+        self.ecs.clear_player_intent();
+        self.last_move_attempt = None;
+        self.run_stats.best_depth = self.run_stats.best_depth.max(self.run_max_floor);
+        self.persist_run_stats();
+        if self.options.endless {
+            let score = self.ecs.turn;
+            self.run_stats.best_endless_score = self.run_stats.best_endless_score.max(score);
+            self.persist_run_stats();
+            self.push_log_entry(format!(
+                "You held out {score} turns through {} waves.",
+                self.endless_waves
+            ));
+        }
+        self.flush_run_stats();
+        self.push_log_with(
+            "Your spectrum shatters. Press R to restart or Esc to quit.",
+            LogPriority::Alert,
+        );
+    }
+
+    fn reset_run(&mut self) {
+        let mut next_stats = self.run_stats.clone();
+        next_stats.best_depth = next_stats.best_depth.max(self.run_max_floor);
+        next_stats.run_number = next_stats.run_number.saturating_add(1);
+        match Self::bootstrap(next_stats, self.options.clone(), self.persist_stats) {
+            Ok(state) => {
+                let save_dir = std::mem::take(&mut self.save_dir);
+                *self = state;
+                self.save_dir = save_dir;
+            }
+            Err(err) => {
+                eprintln!("[RR-ERROR] Cannot start a new run: {err}");
+                self.push_log_with(
+                    format!("A new run couldn't begin: {err}"),
+                    LogPriority::Alert,
+                );
+                return;
+            }
+        }
+        self.persist_run_stats();
+        self.flush_run_stats();
+        self.push_log_entry(format!(
+            "Run {} anchors. Best depth {}",
+            self.run_stats.run_number, self.run_stats.best_depth
+        ));
+    }
+
+    fn draw_game_over(&self, ctx: &mut BTerm, layout: &Layout) {
+        let (banner, banner_color) = if self.has_won {
+            ("S P E C T R U M   R E S T O R E D", RGB::named(GOLD))
+        } else {
+            ("S P E C T R U M   S H A T T E R E D", RGB::named(RED))
+        };
+        let hint = "Press R to restart or Esc to quit.";
+        let seed_line = format!("Seed code {}", seed::encode_seed_code(self.master_seed));
+        let map = layout.map;
+        let box_height = 6.min(map.height() - 1);
+        if box_height < 3 {
+            return;
+        }
+        let box_top = map.y1 + ((map.height() - box_height) / 2).min(6);
+        ctx.draw_box(
+            1,
+            box_top,
+            layout.width.saturating_sub(2),
+            box_height,
+            RGB::named(DARK_GRAY),
+            RGB::named(BLACK),
+        );
+        let banner_y = box_top + box_height / 2 - 1;
+        let hint_y = banner_y + 2;
+        ctx.print_color_centered(banner_y, banner_color, RGB::named(BLACK), banner);
+        ctx.print_color_centered(hint_y, RGB::named(WHITE), RGB::named(BLACK), hint);
+        if hint_y + 1 < box_top + box_height {
+            ctx.print_color_centered(hint_y + 1, RGB::named(GRAY), RGB::named(BLACK), &seed_line);
+        }
+    }
+
+    /// Lists each worn piece with the cost and odds of its next enchant so
+    /// the player sees the price before committing.
+    fn draw_enchant_menu(&self, ctx: &mut BTerm, layout: &Layout) {
+        let map = layout.map;
+        let box_width = 60.min(map.width() - 2);
+        let box_height = 6;
+        if box_width < 20 || box_height >= map.height() {
+            return;
+        }
+        let left = map.x1 + (map.width() - box_width) / 2;
+        let top = map.y1 + (map.height() - box_height) / 2;
+        let fg = RGB::named(WHITE);
+        let bg = RGB::named(BLACK);
+        ctx.draw_box(left, top, box_width, box_height, RGB::named(LIGHT_CYAN), bg);
+        ctx.print_color(left + 2, top, RGB::named(YELLOW), bg, " Enchant gear ");
+        let equipment = self.ecs.player_equipment();
+        for (row, slot) in [GearSlot::Weapon, GearSlot::Armor].into_iter().enumerate() {
+            let line = match equipment.get(slot) {
+                Some(piece) if piece.enchant >= ENCHANT_CAP => {
+                    format!(
+                        "{}) {} +{} (at the cap)",
+                        row + 1,
+                        piece.name,
+                        piece.enchant
+                    )
+                }
+                Some(piece) => format!(
+                    "{}) {} +{} -> +{} · {} shards · {}%",
+                    row + 1,
+                    piece.name,
+                    piece.enchant,
+                    piece.enchant + 1,
+                    enchant_cost(piece.enchant),
+                    enchant_chance(piece.enchant)
+                ),
+                None => format!("{}) (nothing worn)", row + 1),
+            };
+            let clipped: String = line.chars().take((box_width - 3) as usize).collect();
+            ctx.print_color(left + 2, top + 1 + row as i32, fg, bg, clipped);
+        }
+        let footer = format!(
+            "{} shards held · 1-2 enchant · Esc closes",
+            self.ecs.player_shards()
+        );
+        ctx.print_color(left + 2, top + box_height - 1, RGB::named(GRAY), bg, footer);
+    }
+
+    fn draw_world_menu(&self, ctx: &mut BTerm, layout: &Layout) {
+        let map = layout.map;
+        let box_width = 36.min(map.width() - 2);
+        let box_height = SPECTRUM.len() as i32 + 2;
+        if box_width < 20 || box_height >= map.height() {
+            return;
+        }
+        let left = map.x1 + (map.width() - box_width) / 2;
+        let top = map.y1 + (map.height() - box_height) / 2;
+        let bg = RGB::named(BLACK);
+        ctx.draw_box(left, top, box_width, box_height, RGB::named(LIGHT_CYAN), bg);
+        ctx.print_color(left + 2, top, RGB::named(YELLOW), bg, " Shift attunement ");
+        for (row, &world) in SPECTRUM.iter().enumerate() {
+            let marker = if world == self.active_world {
+                " (here)"
+            } else {
+                ""
+            };
+            let line = format!("{}) {}{marker}", row + 1, world.as_str());
+            ctx.print_color(left + 2, top + 1 + row as i32, world_color(world), bg, line);
+        }
+        ctx.print_color(
+            left + 2,
+            top + box_height - 1,
+            RGB::named(GRAY),
+            bg,
+            "1-7 shift · Esc closes",
+        );
+    }
+
+    fn draw_dialog(&self, ctx: &mut BTerm, layout: &Layout, dialog: &Dialog) {
+        let map = layout.map;
+        let rows = dialog.shop.as_ref().map_or(3, |stock| stock.len() as i32);
+        let box_width = 64.min(map.width() - 2);
+        let box_height = rows + 3;
+        if box_width < 20 || box_height >= map.height() {
+            return;
+        }
+        let left = map.x1 + (map.width() - box_width) / 2;
+        let top = map.y1 + (map.height() - box_height) / 2;
+        let fg = RGB::named(WHITE);
+        let bg = RGB::named(BLACK);
+        let clip =
+            |line: String| -> String { line.chars().take((box_width - 3) as usize).collect() };
+        ctx.draw_box(left, top, box_width, box_height, RGB::named(GOLD), bg);
+        ctx.print_color(
+            left + 2,
+            top,
+            RGB::named(YELLOW),
+            bg,
+            format!(" {} ", dialog.speaker),
+        );
+        let footer = match &dialog.shop {
+            Some(stock) => {
+                for (row, offer) in stock.iter().enumerate() {
+                    let line = format!(
+                        "{}) {} · {} shards · {}",
+                        row + 1,
+                        offer.ware.name(),
+                        offer.price,
+                        offer.ware.description()
+                    );
+                    ctx.print_color(left + 2, top + 1 + row as i32, fg, bg, clip(line));
+                }
+                format!(
+                    "{} shards held · 1-{} buy · Esc back",
+                    self.ecs.player_shards(),
+                    stock.len()
+                )
+            }
+            None => {
+                let lines = NpcTemplate::for_role(dialog.role).lines;
+                let quote = format!("\"{}\"", lines[dialog.line % lines.len()]);
+                ctx.print_color(left + 2, top + 1, RGB::named(LIGHT_CYAN), bg, clip(quote));
+                let browse = if dialog.role == NpcRole::Shopkeeper {
+                    "1) Browse wares"
+                } else {
+                    ""
+                };
+                ctx.print_color(left + 2, top + 2, fg, bg, browse);
+                ctx.print_color(left + 2, top + 3, fg, bg, "2) Chat");
+                "Esc leaves".to_string()
+            }
+        };
+        ctx.print_color(left + 2, top + box_height - 1, RGB::named(GRAY), bg, footer);
+    }
+
+    fn draw_slot_menu(ctx: &mut BTerm, layout: &Layout, menu: &SlotMenu) {
+        let title = match menu.mode {
+            SlotMenuMode::Save => "Save to slot",
+            SlotMenuMode::Load => "Load from slot",
+        };
+        let map = layout.map;
+        let box_width = 60.min(map.width() - 2);
+        let box_height = SAVE_SLOTS as i32 + 4;
+        if box_width < 20 || box_height >= map.height() {
+            return;
+        }
+        let left = map.x1 + (map.width() - box_width) / 2;
+        let top = map.y1 + (map.height() - box_height) / 2;
+        let fg = RGB::named(WHITE);
+        let bg = RGB::named(BLACK);
+        ctx.draw_box(left, top, box_width, box_height, RGB::named(LIGHT_CYAN), bg);
+        ctx.print_color(left + 2, top, RGB::named(YELLOW), bg, format!(" {title} "));
+        for (slot, summary) in menu.summaries.iter().enumerate() {
+            let line = match summary {
+                Some(info) => format!(
+                    "{}) Run {} · Floor {} {} · turn {} · {}",
+                    slot + 1,
+                    info.run_number,
+                    info.floor,
+                    info.world.as_str(),
+                    info.turn,
+                    info.saved_at
+                ),
+                None => format!("{}) (empty)", slot + 1),
+            };
+            let clipped: String = line.chars().take((box_width - 3) as usize).collect();
+            ctx.print_color(left + 2, top + 1 + slot as i32, fg, bg, clipped);
+        }
+        let footer = match menu.confirm {
+            Some(slot) => format!("Overwrite slot {}? Y/N", slot + 1),
+            None => "1-3 pick a slot · Esc closes".to_string(),
+        };
+        let footer_color = if menu.confirm.is_some() {
+            RGB::named(ORANGE)
+        } else {
+            RGB::named(GRAY)
+        };
+        ctx.print_color(left + 2, top + box_height - 1, footer_color, bg, footer);
+    }
+}
+
+impl Drop for RainbowRogueState {
+    fn drop(&mut self) {
+        self.flush_run_stats();
+        if !self.verbose {
+            return;
+        }
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+        let filename = format!("history/play_history_{}.log", timestamp);
+        if let Err(e) = fs::create_dir_all("history") {
+            eprintln!("Failed to create history directory: {}", e);
+            return;
+        }
+        if let Err(e) = fs::write(&filename, self.play_history.join("\n")) {
+            eprintln!("Failed to write play history to {}: {}", filename, e);
+        } else {
+            println!("Play history saved to {}", filename);
+        }
+    }
+}
+
+/// The map step a movement key stands for, diagonals included.
+fn direction_of(key: VirtualKeyCode) -> Option<Point> {
+    let (dx, dy) = match key {
+        VirtualKeyCode::Left | VirtualKeyCode::A | VirtualKeyCode::H | VirtualKeyCode::Numpad4 => {
+            (-1, 0)
+        }
+        VirtualKeyCode::Right | VirtualKeyCode::D | VirtualKeyCode::L | VirtualKeyCode::Numpad6 => {
+            (1, 0)
+        }
+        VirtualKeyCode::Up | VirtualKeyCode::W | VirtualKeyCode::K | VirtualKeyCode::Numpad8 => {
+            (0, -1)
+        }
+        VirtualKeyCode::Down | VirtualKeyCode::S | VirtualKeyCode::J | VirtualKeyCode::Numpad2 => {
+            (0, 1)
+        }
+        VirtualKeyCode::Y | VirtualKeyCode::Numpad7 => (-1, -1),
+        VirtualKeyCode::U | VirtualKeyCode::Numpad9 => (1, -1),
+        VirtualKeyCode::B | VirtualKeyCode::Numpad1 => (-1, 1),
+        VirtualKeyCode::N | VirtualKeyCode::Numpad3 => (1, 1),
+        _ => return None,
+    };
+    Some(Point::new(dx, dy))
+}
+
+fn main() -> BError {
+    let args: Vec<String> = env::args().collect();
+    let is_scripted = args.iter().any(|arg| arg == "--scripted-input");
+
+    let options = GameOptions::from_args(&args)?;
+    if let Some(raw) = config::flag_value(&args, "--bench-mapgen") {
+        return run_mapgen_bench(raw, &options);
+    }
+    if let Some(raw) = config::flag_value(&args, "--bench-spawns") {
+        return run_spawn_bench(raw, options);
+    }
+    if let Some(idx) = args.iter().position(|arg| arg == "--export-map") {
+        return run_map_export(&args[idx + 1..], options);
+    }
+    // Strict scripts must fail the process, not fall back to the keyboard.
+    if options.script_limits.strict
+        && let Some(path) = config::flag_value(&args, "--scripted-input")
+    {
+        ScriptedInput::from_file(path, options.script_limits)
+            .map_err(|err| format!("script {path} rejected: {err}"))?;
+    }
+
+    let (console_width, console_height) = config::console_dimensions(&args, is_scripted)?;
+    let game_state = RainbowRogueState::new(options)?;
+    let context = BTermBuilder::simple(console_width, console_height)?
+        .with_title("RainbowRogue · Spectrum Seed")
+        .with_font("vga8x16.png", 8, 16)
+        .with_tile_dimensions(8, 16)
+        .with_automatic_console_resize(true)
+        .build()?;
 
-    let game_state = RainbowRogueState::default();
     main_loop(context, game_state)
 }
 
-fn console_dimensions(_is_scripted: bool) -> (i32, i32) {
-    (132, 43)
-}
\ No newline at end of file
+/// Where `--bench-spawns` writes its per-layer CSV.
+const SPAWN_REPORT_PATH: &str = "spawn_density.csv";
+
+/// `--debug --bench-spawns <count>`: seed monsters onto `count` floors
+/// headlessly, print per-floor totals, and write the per-layer CSV.
+fn run_spawn_bench(raw: &str, options: GameOptions) -> BError {
+    if !options.debug {
+        return Err("--bench-spawns is a debug flag; pass --debug as well".into());
+    }
+    let count: u32 = raw
+        .trim()
+        .parse()
+        .map_err(|_| format!("--bench-spawns expects a floor count, got '{raw}'"))?;
+    let master_seed = options.seed.unwrap_or_else(seed::random_master_seed);
+    println!(
+        "[RR-BENCH] Seed code: {}",
+        seed::encode_seed_code(master_seed)
+    );
+    let mut state = RainbowRogueState::headless(GameOptions {
+        seed: Some(master_seed),
+        ..options
+    })?;
+    let report = state.spawn_report(count);
+    for line in report.summary() {
+        println!("{line}");
+    }
+    std::fs::write(SPAWN_REPORT_PATH, report.to_csv())
+        .map_err(|err| format!("cannot write {SPAWN_REPORT_PATH}: {err}"))?;
+    println!("[RR-BENCH] Wrote {SPAWN_REPORT_PATH}");
+    Ok(())
+}
+
+/// `--export-map <floor> <path>`: generate the run's `floor` headlessly
+/// and write all seven layers and their entities to `path` as JSON.
+fn run_map_export(params: &[String], options: GameOptions) -> BError {
+    let [raw_floor, path, ..] = params else {
+        return Err("--export-map expects a floor and an output path".into());
+    };
+    let floor: u32 = raw_floor
+        .trim()
+        .parse()
+        .map_err(|_| format!("--export-map expects a floor number, got '{raw_floor}'"))?;
+    let master_seed = options.seed.unwrap_or_else(seed::random_master_seed);
+    let mut state = RainbowRogueState::headless(GameOptions {
+        seed: Some(master_seed),
+        ..options
+    })?;
+    let snapshot = state.export_map(FloorId(floor));
+    let json = serde_json::to_vec_pretty(&snapshot)
+        .map_err(|err| format!("cannot encode map snapshot: {err}"))?;
+    std::fs::write(path, json).map_err(|err| format!("cannot write {path}: {err}"))?;
+    println!(
+        "Exported floor {floor} of seed code {} to {path}",
+        seed::encode_seed_code(master_seed)
+    );
+    Ok(())
+}
+
+/// `--debug --bench-mapgen <count>`: generate floors headlessly, print timing
+/// and layout stats, and fail if any floor is disconnected.
+fn run_mapgen_bench(raw: &str, options: &GameOptions) -> BError {
+    if !options.debug {
+        return Err("--bench-mapgen is a debug flag; pass --debug as well".into());
+    }
+    let count: u32 = raw
+        .trim()
+        .parse()
+        .map_err(|_| format!("--bench-mapgen expects a floor count, got '{raw}'"))?;
+    let master_seed = options.seed.unwrap_or_else(seed::random_master_seed);
+    println!(
+        "[RR-BENCH] Seed code: {}",
+        seed::encode_seed_code(master_seed)
+    );
+    let report =
+        bench::run_mapgen_bench(count, master_seed, options.map_width, options.map_height)?;
+    for line in report.summary() {
+        println!("{line}");
+    }
+    if report.disconnected.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} disconnected floor(s)", report.disconnected.len()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::SpawnDensity,
+        map::{CORPSE_TURNS, Fragment, WorldFloor},
+    };
+
+    #[test]
+    fn debug_start_floor_leaves_run_stats_unwritten() {
+        let mut state = RainbowRogueState::bootstrap(
+            RunStats::default(),
+            GameOptions {
+                debug: true,
+                start_floor: FloorId(3),
+                seed: Some(7),
+                ..GameOptions::default()
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!(state.active_floor, FloorId(3));
+        assert!(!state.persist_stats);
+        state.flush_run_stats();
+        assert!(!state.stats_throttle.dirty);
+        assert_eq!(state.stats_throttle.last_write, None);
+    }
+
+    #[test]
+    fn headless_runs_leave_run_stats_unwritten() {
+        let mut state = RainbowRogueState::headless(GameOptions {
+            seed: Some(7),
+            ..GameOptions::default()
+        })
+        .unwrap();
+        assert_eq!(state.active_floor, FloorId(0));
+        assert!(!state.persist_stats);
+        state.persist_run_stats();
+        state.flush_run_stats();
+        assert!(!state.stats_throttle.dirty);
+        assert_eq!(state.stats_throttle.last_write, None);
+    }
+
+    #[test]
+    fn advanced_turns_move_monsters_and_tick_timers_once_per_turn() {
+        let mut state = RainbowRogueState::headless(GameOptions {
+            seed: Some(11),
+            ..GameOptions::default()
+        })
+        .unwrap();
+        let (floor, world) = (state.active_floor, state.active_world);
+        let footprints = state.ecs.monster_footprints(floor, world);
+        assert!(!footprints.is_empty());
+        // Keep the monsters from ending the run mid-measurement.
+        state.ecs.set_godmode(true);
+        let prism = pack_slot_with(&mut state, "Updraft Prism");
+        assert!(state.activate_consumable(prism));
+        let (turn, levitation) = (state.ecs.turn, state.ecs.levitate_turns());
+        assert!(levitation > 5);
+
+        state.advance_turns(5);
+
+        assert_eq!(state.ecs.turn, turn + 5);
+        assert_eq!(state.ecs.levitate_turns(), levitation - 5);
+        assert_ne!(state.ecs.monster_footprints(floor, world), footprints);
+        assert_eq!(state.run_state, RunState::AwaitingInput);
+    }
+
+    #[test]
+    fn bootstrap_rejects_a_map_size_the_dungeon_cannot_hold() {
+        for (map_width, map_height) in [(0, 48), (80, -1), (4096, 4096)] {
+            let state = RainbowRogueState::headless(GameOptions {
+                seed: Some(7),
+                map_width,
+                map_height,
+                ..GameOptions::default()
+            });
+            assert!(state.is_err(), "{map_width}x{map_height} was accepted");
+        }
+    }
+
+    /// A window-less terminal holding `key` as this frame's keypress.
+    fn ctx_with_key(key: Option<VirtualKeyCode>) -> BTerm {
+        BTerm {
+            width_pixels: 0,
+            height_pixels: 0,
+            original_height_pixels: 0,
+            original_width_pixels: 0,
+            fps: 0.0,
+            frame_time_ms: 0.0,
+            active_console: 0,
+            key,
+            mouse_pos: (0, 0),
+            left_click: false,
+            shift: false,
+            control: false,
+            alt: false,
+            web_button: None,
+            quitting: false,
+            post_scanlines: false,
+            post_screenburn: false,
+            screen_burn_color: RGB::named(BLACK),
+            mouse_visible: false,
+        }
+    }
+
+    fn seeded_run() -> RainbowRogueState {
+        RainbowRogueState::headless(GameOptions {
+            seed: Some(7),
+            ..GameOptions::default()
+        })
+        .unwrap()
+    }
+
+    /// A seeded run that seeds no monsters on any floor it generates.
+    fn quiet_run() -> RainbowRogueState {
+        RainbowRogueState::headless(GameOptions {
+            seed: Some(7),
+            spawn_density: SpawnDensity {
+                min: 0,
+                max: 0,
+                ..SpawnDensity::default()
+            },
+            ..GameOptions::default()
+        })
+        .unwrap()
+    }
+
+    /// A quiet run whose floor 0 is redrawn on every layer from `rows`,
+    /// with wall everywhere the rows don't reach: `.` floor, `+` closed
+    /// door, `>` down-stair, `~` water, `^` lava, `!` a hidden trap, and
+    /// `@` the player on plain floor. The ECS is rebuilt, so only the
+    /// player stands on it.
+    fn sandbox(rows: &[&str]) -> RainbowRogueState {
+        let mut state = quiet_run();
+        let (width, height) = state.dungeon.size();
+        let mut floor = WorldFloor::empty(FloorId(0), width, height);
+        let mut player = None;
+        for (y, row) in rows.iter().enumerate() {
+            for (x, glyph) in row.chars().enumerate() {
+                let point = Point::new(x as i32, y as i32);
+                match glyph {
+                    '@' => player = Some(point),
+                    '+' => floor.substrate.doors.push(point),
+                    '>' => floor.substrate.stairs_down.push(point),
+                    '!' => floor.substrate.traps.push(point),
+                    _ => {}
+                }
+                for layer in floor.layers.iter_mut() {
+                    let tile = match glyph {
+                        '.' | '@' => Tile::floor(layer.world),
+                        '+' => Tile::door(layer.world, false),
+                        '>' => Tile::stair_down(layer.world),
+                        '~' => Tile::water(),
+                        '^' => Tile::lava(),
+                        '!' => Tile::trap(layer.world),
+                        _ => Tile::wall(),
+                    };
+                    layer.set_tile(point, tile);
+                }
+            }
+        }
+        let player = player.expect("the sandbox marks the player with @");
+        floor.substrate.spawn = player;
+        state.dungeon.floors[0] = floor;
+        state.ecs = EcsWorld::new(
+            player,
+            FloorId(0),
+            state.active_world,
+            state.master_seed,
+            &state.options.loadout,
+        );
+        state.visible_tiles.clear();
+        state.update_visibility();
+        state
+    }
+
+    #[test]
+    fn bumping_a_friend_swaps_places_instead_of_attacking() {
+        let mut state = sandbox(&["#####", "#@..#", "#####"]);
+        let (floor, world) = (state.active_floor, state.active_world);
+        let shopkeeper = NpcTemplate::for_role(NpcRole::Shopkeeper);
+        state
+            .ecs
+            .spawn_npc(&shopkeeper, Point::new(2, 1), floor, world);
+        let npc = state.ecs.entity_at(Point::new(2, 1), floor, world).unwrap();
+
+        assert!(state.try_step(1, 0));
+
+        assert_eq!(state.ecs.player_point(), Point::new(2, 1));
+        assert_eq!(
+            state.ecs.entity_at(Point::new(1, 1), floor, world),
+            Some(npc)
+        );
+        assert_eq!(
+            state.message_log[0],
+            format!("You slip past {}.", shopkeeper.name)
+        );
+    }
+
+    #[test]
+    fn a_run_down_a_corridor_stops_at_the_first_junction() {
+        let mut state = sandbox(&[
+            "##########",
+            "#@.......#",
+            "#####.####",
+            "#####.####",
+            "##########",
+        ]);
+
+        let mut acted = state.start_run(1, 0);
+        for _ in 0..20 {
+            if !acted {
+                break;
+            }
+            state.advance_turns(1);
+            acted = state.continue_run();
+        }
+
+        assert_eq!(state.ecs.player_point(), Point::new(5, 1));
+        assert!(state.auto_run.is_none());
+        assert_eq!(state.message_log[0], "You stop running at a junction.");
+    }
+
+    #[test]
+    fn cycling_worlds_takes_a_turn_and_drains_the_configured_energy() {
+        let mut state = quiet_run();
+        let cost = state.options.world_switch_cost;
+        assert!(cost > 0);
+        let (world, turn) = (state.active_world, state.ecs.turn);
+        let mut ctx = ctx_with_key(None);
+
+        assert!(state.apply_action(&mut ctx, PlayerAction::CycleWorld(1)));
+        state.advance_turns(1);
+
+        assert_eq!(state.active_world, world.cycle(1));
+        assert_eq!(state.ecs.turn, turn + 1);
+        let drain = format!("The phase drains {cost} energy; foes press closer.");
+        assert!(state.message_log.contains(&drain));
+
+        state.options.world_switch_cost = 0;
+        assert!(state.apply_action(&mut ctx, PlayerAction::CycleWorld(-1)));
+        assert!(state.message_log[0].starts_with("Shifted attunement to"));
+    }
+
+    #[test]
+    fn the_hud_rule_line_follows_each_world_shift() {
+        let mut state = quiet_run();
+        state.options.world_switch_cost = 0;
+        let mut shown = vec![world_rule_line(state.active_world)];
+
+        for _ in 1..SPECTRUM.len() {
+            let mut ctx = ctx_with_key(Some(VirtualKeyCode::Tab));
+            state.handle_input(&mut ctx);
+            state.advance_turns(1);
+            let line = world_rule_line(state.active_world);
+            assert_eq!(
+                line,
+                format!(
+                    "{} rule: {}",
+                    state.active_world.as_str(),
+                    data::rule_for(state.active_world).notes
+                )
+            );
+            assert_ne!(shown.last(), Some(&line));
+            shown.push(line);
+        }
+
+        shown.sort();
+        shown.dedup();
+        assert_eq!(shown.len(), SPECTRUM.len());
+    }
+
+    #[test]
+    fn a_rift_shade_follows_the_player_across_a_world_shift() {
+        let mut state = sandbox(&["#########", "#@......#", "#########"]);
+        state.options.world_switch_cost = 0;
+        state.ecs.set_godmode(true);
+        let (floor, from) = (state.active_floor, state.active_world);
+        let shade = MonsterTemplate::for_world(World::Violet)
+            .into_iter()
+            .find(|template| template.name == "Rift Shade")
+            .unwrap();
+        let cadence = shade.phase_shifter.unwrap().cadence;
+        state
+            .ecs
+            .spawn_monster(&shade, Point::new(5, 1), floor, from, None);
+
+        let mut ctx = ctx_with_key(Some(VirtualKeyCode::Tab));
+        state.handle_input(&mut ctx);
+        state.advance_turns(1);
+        let to = state.active_world;
+        assert_ne!(to, from);
+        assert!(state.ecs.monster_footprints(floor, to).is_empty());
+
+        let mut waited = 0;
+        while state.ecs.monster_footprints(floor, to).is_empty() {
+            assert!(waited < cadence, "the shade never phased in");
+            state.advance_turns(1);
+            waited += 1;
+        }
+        assert!(state.ecs.monster_footprints(floor, from).is_empty());
+        assert!(state.ecs.turn.is_multiple_of(u64::from(cadence)));
+        let arrival = format!("Rift Shade phases in from {}!", from.as_str());
+        assert!(state.message_log.contains(&arrival));
+    }
+
+    #[test]
+    fn hp_carries_across_a_world_switch_and_monsters_stay_behind() {
+        let mut state = sandbox(&["#######", "#@....#", "#######"]);
+        let (floor, from) = (state.active_floor, state.active_world);
+        let imp = &MonsterTemplate::for_world(from)[0];
+        state
+            .ecs
+            .spawn_monster(imp, Point::new(5, 1), floor, from, None);
+        state.ecs.damage_player(4);
+        let hp = state.player_hp();
+
+        let to = from.cycle(3);
+        assert!(state.set_world(to));
+        state.advance_turns(1);
+
+        assert_eq!(state.player_hp(), hp);
+        assert_eq!(state.ecs.monster_footprints(floor, from).len(), 1);
+        assert!(state.ecs.monster_footprints(floor, to).is_empty());
+        let mut drawn = 0;
+        state
+            .ecs
+            .each_renderable(floor, to, false, |_, _| drawn += 1);
+        assert_eq!(drawn, 0);
+    }
+
+    /// An empty directory of its own under the system temp dir, so saves
+    /// written by a test never land in the repo's `saves/`.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rainbowrogue-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn saving_to_slot_two_leaves_slot_one_as_it_was() {
+        let mut state = quiet_run();
+        state.save_dir = scratch_dir("slots");
+        state.save_to_slot(0);
+        let slot_one = fs::read(save::slot_path(&state.save_dir, 0)).unwrap();
+        let (first_turn, first_hp) = (state.ecs.turn, state.player_hp());
+
+        state.ecs.damage_player(3);
+        state.advance_turns(2);
+        state.save_to_slot(1);
+        let (second_turn, second_hp) = (state.ecs.turn, state.player_hp());
+
+        assert_eq!(
+            fs::read(save::slot_path(&state.save_dir, 0)).unwrap(),
+            slot_one
+        );
+        let summaries = save::slot_summaries(&state.save_dir);
+        assert_eq!(summaries[0].as_ref().unwrap().turn, first_turn);
+        assert_eq!(summaries[1].as_ref().unwrap().turn, second_turn);
+        assert!(summaries[2].is_none());
+
+        state.load_from_slot(0);
+        assert_eq!((state.ecs.turn, state.player_hp()), (first_turn, first_hp));
+        state.load_from_slot(1);
+        assert_eq!(
+            (state.ecs.turn, state.player_hp()),
+            (second_turn, second_hp)
+        );
+        fs::remove_dir_all(&state.save_dir).unwrap();
+    }
+
+    /// Uses left across the whole pack.
+    fn pack_uses(state: &RainbowRogueState) -> i32 {
+        state
+            .ecs
+            .player_inventory()
+            .iter()
+            .map(|(_, slot)| slot.uses_remaining)
+            .sum()
+    }
+
+    /// Puts one use of the starter consumable `name` in the pack and
+    /// returns the slot holding it.
+    fn pack_slot_with(state: &mut RainbowRogueState, name: &str) -> usize {
+        let template = SPECTRUM
+            .iter()
+            .flat_map(|&world| starter_consumables(world))
+            .find(|template| template.name == name)
+            .unwrap();
+        assert!(state.ecs.give_player_consumable(&template));
+        state
+            .ecs
+            .player_inventory()
+            .into_iter()
+            .find(|(_, slot)| slot.name == name)
+            .map(|(idx, _)| idx)
+            .unwrap()
+    }
+
+    #[test]
+    fn only_a_first_descent_heals_and_recharges() {
+        let mut state = quiet_run();
+        state.save_dir = scratch_dir("descent");
+        let (floor, world) = (state.active_floor, state.active_world);
+        let down = state.dungeon.active_floor(floor).unwrap().stairs_down()[0];
+        state.ecs.set_player_position(down, floor, world);
+        state.ecs.damage_player(10);
+        let (hp, uses) = (state.player_hp(), pack_uses(&state));
+
+        assert!(state.shift_floor(1));
+        assert!(state.player_hp() > hp);
+        assert_eq!(pack_uses(&state), uses + 1);
+        assert!(
+            state
+                .message_log
+                .contains(&"Crossing the prism lock restores you.".to_string())
+        );
+
+        assert!(state.shift_floor(-1));
+        state.ecs.damage_player(10);
+        let (hp, uses) = (state.player_hp(), pack_uses(&state));
+        assert!(state.shift_floor(1));
+        assert_eq!(state.player_hp(), hp);
+        assert_eq!(pack_uses(&state), uses);
+        assert_eq!(state.message_log[0], "Descended to floor 1");
+        fs::remove_dir_all(&state.save_dir).unwrap();
+    }
+
+    #[test]
+    fn godmode_shrugs_off_lethal_damage() {
+        let mut state = quiet_run();
+        state.ecs.set_godmode(true);
+        let hp = state.player_hp();
+
+        assert_eq!(state.ecs.damage_player(hp * 10), 0);
+        state.check_health_warning();
+
+        assert_eq!(state.player_hp(), hp);
+        assert!(!state.is_dead);
+        assert!(!state.hp_alerted);
+    }
+
+    #[test]
+    fn a_floor_change_writes_a_loadable_autosave() {
+        let mut state = quiet_run();
+        state.save_dir = scratch_dir("autosave");
+        let (floor, world) = (state.active_floor, state.active_world);
+        let down = state.dungeon.active_floor(floor).unwrap().stairs_down()[0];
+        state.ecs.set_player_position(down, floor, world);
+
+        assert!(state.shift_floor(1));
+
+        let saved = save::read_save(&save::autosave_path(&state.save_dir)).unwrap();
+        assert_eq!(saved.active_floor, FloorId(1));
+        let arrival = state.ecs.player_point();
+        let mut resumed = quiet_run();
+        resumed.restore_save(saved).unwrap();
+        assert_eq!(resumed.active_floor, FloorId(1));
+        assert_eq!(resumed.ecs.player_point(), arrival);
+        fs::remove_dir_all(&state.save_dir).unwrap();
+    }
+
+    #[test]
+    fn repeated_hits_fold_into_one_line_until_something_else_is_logged() {
+        let mut state = quiet_run();
+        let strike = |amount| CombatEvent {
+            kind: CombatEventKind::PlayerStrike,
+            other: "Cinder Wolf".to_string(),
+            amount,
+        };
+        let latest = state.message_log[0].clone();
+        let history = state.play_history.len();
+
+        for _ in 0..3 {
+            state.push_combat_event(strike(5));
+        }
+        assert_eq!(state.message_log[1], latest);
+        assert_eq!(state.message_log[0], "You strike Cinder Wolf ×3 for 15");
+        assert_eq!(state.play_history.len(), history + 3);
+
+        state.push_log_entry("The wolf howls.");
+        state.push_combat_event(strike(4));
+        assert_eq!(state.message_log[0], "You strike Cinder Wolf for 4");
+        assert_eq!(state.message_log[2], "You strike Cinder Wolf ×3 for 15");
+    }
+
+    #[test]
+    fn log_lines_stream_into_the_log_file() {
+        let dir = scratch_dir("log-file");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("play.log");
+        let mut state = RainbowRogueState::headless(GameOptions {
+            seed: Some(7),
+            log_file: Some(path.clone()),
+            ..GameOptions::default()
+        })
+        .unwrap();
+
+        state.push_log_entry("First line.");
+        state.push_log_with("Second line.", LogPriority::Alert);
+
+        let written = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(lines[lines.len() - 2..], ["First line.", "Second line."]);
+        assert_eq!(lines, state.play_history);
+        drop(state);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn an_open_door_shuts_once_its_doorway_stays_empty() {
+        let mut state = sandbox(&["#####", "#@+.#", "#####"]);
+        let door = Point::new(2, 1);
+        let close_after = state.options.door_close_turns;
+        let door_tag = |state: &RainbowRogueState| {
+            state
+                .dungeon
+                .active_layer(state.active_floor, state.active_world)
+                .and_then(|layer| layer.tile_at(door))
+                .map(|tile| tile.tag)
+        };
+
+        state.set_door(door, true);
+        state
+            .ecs
+            .set_player_position(door, FloorId(0), state.active_world);
+        state.advance_turns(close_after * 2);
+        assert_eq!(door_tag(&state), Some(Tile::TAG_DOOR_OPEN));
+
+        state
+            .ecs
+            .set_player_position(Point::new(3, 1), FloorId(0), state.active_world);
+        state.advance_turns(close_after - 1);
+        assert_eq!(door_tag(&state), Some(Tile::TAG_DOOR_OPEN));
+        state.advance_turns(1);
+        assert_eq!(door_tag(&state), Some(Tile::TAG_DOOR_CLOSED));
+    }
+
+    #[test]
+    fn detect_life_marks_monsters_beyond_sight_until_it_fades() {
+        let mut state = sandbox(&["#########", "#@..#...#", "#########"]);
+        let hidden = Point::new(6, 1);
+        let imp = &MonsterTemplate::for_world(state.active_world)[0];
+        state
+            .ecs
+            .spawn_monster(imp, hidden, FloorId(0), state.active_world, None);
+        state.advance_turns(1);
+        assert!(!state.visible_tiles.contains(&hidden));
+        assert_eq!(state.entity_visibility(hidden), None);
+
+        let lens = pack_slot_with(&mut state, "Lifesense Lens");
+        assert!(state.activate_consumable(lens));
+        assert_eq!(
+            state.entity_visibility(hidden),
+            Some(EntityVisibility::Sensed)
+        );
+
+        state.advance_turns(20);
+        assert_eq!(state.entity_visibility(hidden), None);
+    }
+
+    #[test]
+    fn only_yellow_picks_out_a_trap_in_view() {
+        let mut state = sandbox(&["#######", "#@..!.#", "#######"]);
+        let trap = Point::new(4, 1);
+        let detected =
+            |state: &RainbowRogueState| state.dungeon.floors[0].detected_traps().contains(&trap);
+
+        state.set_world(World::Red);
+        state.advance_turns(1);
+        assert!(state.visible_tiles.contains(&trap));
+        assert!(!detected(&state));
+
+        state.set_world(World::Yellow);
+        state.advance_turns(1);
+        assert!(detected(&state));
+    }
+
+    #[test]
+    fn a_twenty_line_log_keeps_twenty_entries_and_draws_them_all() {
+        let args: Vec<String> = ["--seed", "7", "--log-history", "20", "--log-lines", "20"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let options = GameOptions::from_args(&args).unwrap();
+        let mut state = RainbowRogueState::headless(options).unwrap();
+        for n in 0..30 {
+            state.push_log_entry(format!("line {n}"));
+        }
+        assert_eq!(state.message_log.len(), 20);
+        assert_eq!(state.message_log[0], "line 29");
+        assert_eq!(state.message_log[19], "line 10");
+
+        let layout = Layout::compute(120, 60, state.options.log_lines);
+        assert_eq!(layout.log_lines(), 20);
+        assert!(layout.map.y2 <= layout.log.y1);
+    }
+
+    #[test]
+    fn the_hub_seeds_no_monsters_and_keeps_a_shopkeeper() {
+        let floor_zero_monsters = |state: &RainbowRogueState| {
+            state
+                .ecs
+                .monster_census()
+                .iter()
+                .filter(|(floor, _, _)| *floor == FloorId(0))
+                .count()
+        };
+        assert!(floor_zero_monsters(&seeded_run()) > 0);
+
+        let state = RainbowRogueState::headless(GameOptions {
+            seed: Some(7),
+            hub: true,
+            ..GameOptions::default()
+        })
+        .unwrap();
+        assert_eq!(floor_zero_monsters(&state), 0);
+        let shopkeeper = state.ecs.adjacent_npc().expect("a stall beside the spawn");
+        assert_eq!(
+            shopkeeper.name,
+            NpcTemplate::for_role(NpcRole::Shopkeeper).name
+        );
+    }
+
+    #[test]
+    fn gathering_every_fragment_and_surfacing_wins_the_run() {
+        let mut state = sandbox(&["#####", "#@..#", "#####"]);
+        let world = state.active_world;
+        state.dungeon.floors[0].fragment = Some(Fragment {
+            world,
+            point: Point::new(2, 1),
+        });
+        assert!(state.try_step(1, 0));
+        state.advance_turns(1);
+        assert_eq!(state.fragments, fragment_bit(world));
+        assert!(!state.has_won);
+
+        let mut state = quiet_run();
+        state.save_dir = scratch_dir("surfacing");
+        let down = state
+            .dungeon
+            .active_floor(FloorId(0))
+            .unwrap()
+            .stairs_down()[0];
+        state
+            .ecs
+            .set_player_position(down, FloorId(0), state.active_world);
+        assert!(state.shift_floor(1));
+        state.fragments = ALL_FRAGMENTS;
+        state.advance_turns(1);
+        assert!(!state.has_won, "the fragments must come home first");
+        assert!(state.shift_floor(-1));
+        state.advance_turns(1);
+        assert!(state.has_won);
+        fs::remove_dir_all(&state.save_dir).unwrap();
+    }
+
+    #[test]
+    fn reaching_the_stair_completes_the_objective_and_pays_once() {
+        let mut state = quiet_run();
+        let floor = state.dungeon.active_floor(FloorId(0)).unwrap();
+        assert!(matches!(
+            floor.objective.map(|objective| objective.kind),
+            Some(ObjectiveKind::ReachDownStair)
+        ));
+        let down = floor.stairs_down()[0];
+        let shards = state.ecs.player_shards();
+        state
+            .ecs
+            .set_player_position(down, FloorId(0), state.active_world);
+
+        state.advance_turns(1);
+        let paid = shards + OBJECTIVE_REWARD_SHARDS;
+        assert_eq!(state.ecs.player_shards(), paid);
+        assert_eq!(state.capture_save().completed_objectives, vec![0]);
+
+        state.advance_turns(3);
+        assert_eq!(state.ecs.player_shards(), paid);
+    }
+
+    #[test]
+    fn an_endless_wave_lands_on_its_cadence_turn_and_not_before() {
+        let mut state = RainbowRogueState::headless(GameOptions {
+            seed: Some(7),
+            endless: true,
+            spawn_density: SpawnDensity {
+                min: 0,
+                max: 0,
+                ..SpawnDensity::default()
+            },
+            ..GameOptions::default()
+        })
+        .unwrap();
+        state.ecs.set_godmode(true);
+        while state.ecs.turn + 1 < ENDLESS_WAVE_TURNS {
+            state.advance_turns(1);
+        }
+        assert_eq!(state.endless_waves, 0);
+        assert!(state.ecs.monster_census().is_empty());
+
+        state.advance_turns(1);
+        assert_eq!(state.ecs.turn, ENDLESS_WAVE_TURNS);
+        assert_eq!(state.endless_waves, 1);
+        assert!(!state.ecs.monster_census().is_empty());
+    }
+
+    #[test]
+    fn a_one_way_descent_seals_the_way_back_up() {
+        let mut state = RainbowRogueState::headless(GameOptions {
+            seed: Some(7),
+            one_way_stairs: true,
+            ..GameOptions::default()
+        })
+        .unwrap();
+        state.save_dir = scratch_dir("one-way");
+        state.dungeon.floors[0].one_way_down = true;
+        let down = state
+            .dungeon
+            .active_floor(FloorId(0))
+            .unwrap()
+            .stairs_down()[0];
+        state
+            .ecs
+            .set_player_position(down, FloorId(0), state.active_world);
+
+        assert!(state.shift_floor(1));
+        assert!(
+            state
+                .message_log
+                .contains(&"The prism lock seals behind you.".to_string())
+        );
+        assert!(!state.shift_floor(-1));
+        assert_eq!(state.active_floor, FloorId(1));
+        assert_eq!(
+            state.message_log[0],
+            "The prism lock has sealed behind you."
+        );
+        fs::remove_dir_all(&state.save_dir).unwrap();
+    }
+
+    #[test]
+    fn a_lucky_search_turns_up_the_adjacent_secret_door() {
+        let mut state = sandbox(&["#####", "#@..#", "#####"]);
+        let door = Point::new(1, 0);
+        let floor = &mut state.dungeon.floors[0];
+        floor.substrate.secret_doors.push(door);
+        floor.secret_doors.insert(door);
+
+        state
+            .ecs
+            .pin_combat_rng(|rng| rng.range(0, 100) < SEARCH_CHANCE);
+        assert!(state.search());
+
+        let tile = state.dungeon.floors[0].layers[0].tile_at(door).cloned();
+        assert_eq!(tile.map(|tile| tile.tag), Some(Tile::TAG_DOOR_CLOSED));
+        assert_eq!(
+            state.message_log[0],
+            "A section of wall gives way: a hidden door!"
+        );
+        let found = state.capture_save().found_secret_doors;
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].floor, found[0].x, found[0].y), (0, 1, 0));
+    }
+
+    #[test]
+    fn water_drags_at_the_next_turn_and_lava_sears() {
+        let mut state = sandbox(&["######", "#@~^.#", "######"]);
+        assert!(state.try_step(1, 0));
+        state.advance_turns(1);
+        assert_eq!(state.ecs.player_point(), Point::new(2, 1));
+        assert_eq!(
+            state.ecs.snapshot().pending_player_surcharge,
+            WATER_SURCHARGE
+        );
+        assert!(
+            state
+                .message_log
+                .contains(&"You wade into deep water; it drags at you.".to_string())
+        );
+
+        let hp = state.player_hp();
+        assert!(state.try_step(1, 0));
+        state.advance_turns(1);
+        assert_eq!(state.ecs.player_point(), Point::new(3, 1));
+        assert_eq!(state.ecs.snapshot().pending_player_surcharge, 0);
+        assert_eq!(state.player_hp(), hp - LAVA_DAMAGE);
+    }
+
+    #[test]
+    fn levitation_floats_over_lava_until_it_wears_off() {
+        let mut state = sandbox(&["#####", "#@^.#", "#####"]);
+        let hp = state.player_hp();
+        let prism = pack_slot_with(&mut state, "Updraft Prism");
+        assert!(state.activate_consumable(prism));
+        state.advance_turns(1);
+
+        assert!(state.try_step(1, 0));
+        state.advance_turns(1);
+        assert_eq!(state.ecs.player_point(), Point::new(2, 1));
+        assert_eq!(state.player_hp(), hp);
+
+        while state.ecs.levitate_turns() > 0 {
+            assert_eq!(state.player_hp(), hp);
+            state.advance_turns(1);
+        }
+        assert_eq!(state.player_hp(), hp - LAVA_DAMAGE);
+        assert!(
+            state
+                .message_log
+                .contains(&"You drift back down.".to_string())
+        );
+        state.advance_turns(2);
+        assert_eq!(
+            state.player_hp(),
+            hp - LAVA_DAMAGE,
+            "standing still doesn't re-sear"
+        );
+    }
+
+    #[test]
+    fn arrival_steps_aside_when_every_stair_is_occupied() {
+        let mut state = quiet_run();
+        state.save_dir = scratch_dir("arrival");
+        let world = state.active_world;
+        state.dungeon.ensure_floor(FloorId(1));
+        let below = state.dungeon.active_floor(FloorId(1)).unwrap();
+        let mut blocked = below.stairs_up().to_vec();
+        blocked.push(below.spawn_point());
+        let imp = &MonsterTemplate::for_world(world)[0];
+        for &point in &blocked {
+            state.ecs.spawn_monster(imp, point, FloorId(1), world, None);
+        }
+        let down = state
+            .dungeon
+            .active_floor(FloorId(0))
+            .unwrap()
+            .stairs_down()[0];
+        state.ecs.set_player_position(down, FloorId(0), world);
+
+        assert!(state.shift_floor(1));
+        let arrival = state.ecs.player_point();
+        assert!(!blocked.contains(&arrival));
+        let layer = state.dungeon.active_layer(FloorId(1), world).unwrap();
+        assert!(layer.is_walkable(arrival));
+        assert!(!state.lava_at(arrival));
+        assert!(
+            state
+                .ecs
+                .monster_footprints(FloorId(1), world)
+                .iter()
+                .all(|cells| !cells.contains(&arrival))
+        );
+        fs::remove_dir_all(&state.save_dir).unwrap();
+    }
+
+    #[test]
+    fn a_kill_leaves_a_corpse_that_crumbles_on_schedule() {
+        let mut state = sandbox(&["####", "#@.#", "####"]);
+        state.ecs.set_godmode(true);
+        let world = state.active_world;
+        let imp = &MonsterTemplate::for_world(world)[0];
+        let spot = Point::new(2, 1);
+        state.ecs.spawn_monster(imp, spot, FloorId(0), world, None);
+        let corpses = |state: &RainbowRogueState| state.dungeon.floors[0].corpses.clone();
+
+        for _ in 0..50 {
+            if state.ecs.monster_census().is_empty() {
+                break;
+            }
+            assert!(state.attack_in_place(1, 0));
+            state.advance_turns(1);
+        }
+        let fresh = corpses(&state);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!((fresh[0].point, fresh[0].name.as_str()), (spot, imp.name));
+
+        state.advance_turns(CORPSE_TURNS - 1);
+        assert_eq!(corpses(&state).len(), 1);
+        state.advance_turns(1);
+        assert!(corpses(&state).is_empty());
+    }
+
+    #[test]
+    fn a_diagonal_into_a_wall_corner_gets_its_own_message() {
+        let mut state = sandbox(&["#####", "#@..#", "#.#.#", "#####"]);
+        let logged = |state: &RainbowRogueState, line: &str| {
+            state.message_log.iter().take(3).any(|entry| entry == line)
+        };
+
+        assert!(state.try_step(1, 1));
+        state.advance_turns(1);
+        assert_eq!(state.ecs.player_point(), Point::new(1, 1));
+        assert!(logged(&state, "A wall corner blocks the diagonal."));
+
+        assert!(state.try_step(-1, 0));
+        state.advance_turns(1);
+        assert!(logged(&state, "Blocked at 0,1"));
+        assert!(!state.corner_blocks(Point::new(1, 1), Point::new(0, 0)));
+    }
+
+    #[test]
+    fn a_stalled_turn_loop_drops_its_intents_and_waits_for_input() {
+        let mut state = quiet_run();
+        let here = state.ecs.player_point();
+        state.ecs.queue_player_step(Point::new(1, 0));
+        state.last_move_attempt = Some((here, Point::new(here.x + 1, here.y)));
+        state.run_state = RunState::PlayerTurn;
+
+        state.recover_stalled_turn();
+        assert!(matches!(state.run_state, RunState::AwaitingInput));
+        assert!(!state.ecs.has_player_intent());
+        assert_eq!(state.last_move_attempt, None);
+        assert_eq!(state.ecs.player_point(), here);
+
+        // With nothing pending the loop settles at once instead of spinning.
+        let mut ctx = ctx_with_key(None);
+        assert_eq!(state.resolve_turns(&mut ctx), (false, false));
+        assert!(matches!(state.run_state, RunState::AwaitingInput));
+    }
+
+    #[test]
+    fn a_collapsing_floor_crumbles_its_border_on_schedule_and_walls_it_off() {
+        let mut state = sandbox(&["#######", "#.....#", "#.@...#", "#.....#", "#######"]);
+        state.options.collapse_turns = 3;
+        state.start_collapse_clock();
+        let (floor, world) = (state.active_floor, state.active_world);
+        let void_at = |state: &RainbowRogueState, point| {
+            let layer = state.dungeon.active_layer(floor, world).unwrap();
+            layer.tile_at(point).unwrap().is_void()
+        };
+        let (border, inner, player) = (Point::new(0, 2), Point::new(1, 1), Point::new(2, 2));
+        let hp = state.player_hp();
+
+        state.advance_turns(2);
+        assert!(!void_at(&state, border));
+        assert!(
+            state
+                .message_log
+                .contains(&"The floor groans: 1 turn until its edges give way.".to_string())
+        );
+
+        state.advance_turns(1);
+        assert!(void_at(&state, border) && !void_at(&state, inner));
+        assert!(
+            state.message_log[0].starts_with("The floor's edges crumble"),
+            "{:?}",
+            state.message_log[0]
+        );
+
+        state.advance_turns(1);
+        assert!(void_at(&state, inner) && !void_at(&state, player));
+        assert!(state.try_step(-1, -1));
+        state.advance_turns(1);
+        let context = state.ecs.movement_context(floor, world).unwrap();
+        assert!(
+            !context.is_walkable(inner),
+            "monsters can't path through it"
+        );
+        drop(context);
+        assert_eq!(state.ecs.player_point(), player, "nor can you step in");
+
+        assert!(void_at(&state, player));
+        assert_eq!(state.player_hp(), hp - COLLAPSE_DAMAGE);
+    }
+
+    #[test]
+    fn a_floor_that_no_longer_exists_snaps_back_to_floor_zeros_spawn() {
+        let mut state = quiet_run();
+        assert_eq!(state.dungeon.floors.len(), 1);
+        // What a load pointing past the generated floors leaves behind.
+        state.active_floor = FloorId(40);
+        assert!(
+            state
+                .dungeon
+                .active_layer(FloorId(40), state.active_world)
+                .is_none()
+        );
+
+        state.recover_missing_layer();
+
+        assert_eq!(state.active_floor, FloorId(0));
+        assert_eq!(
+            state.ecs.player_point(),
+            state.dungeon.spawn_point(FloorId(0))
+        );
+        assert!(
+            state
+                .dungeon
+                .active_layer(state.active_floor, state.active_world)
+                .is_some()
+        );
+        assert_eq!(
+            state.message_log[0],
+            "Floor 40 couldn't be found; you are back at floor 0's spawn."
+        );
+        assert_eq!(state.log_priorities[0], LogPriority::Alert);
+    }
+
+    #[test]
+    fn a_save_and_load_round_trip_keeps_every_revealed_tile() {
+        let mut state = quiet_run();
+        state.save_dir = scratch_dir("fog");
+        state.advance_turns(1);
+        let floor = state.active_floor;
+        let far_corner = state
+            .dungeon
+            .active_layer_mut(floor, World::Blue)
+            .unwrap()
+            .tiles
+            .len()
+            - 1;
+        state
+            .dungeon
+            .active_layer_mut(floor, World::Blue)
+            .unwrap()
+            .tiles[far_corner]
+            .revealed = true;
+        let fog = |state: &RainbowRogueState| {
+            SPECTRUM.map(|world| {
+                state
+                    .dungeon
+                    .active_layer(floor, world)
+                    .unwrap()
+                    .revealed_indices()
+            })
+        };
+        let explored = fog(&state);
+        let active = SPECTRUM
+            .iter()
+            .position(|&world| world == state.active_world);
+        assert!(
+            !explored[active.unwrap()].is_empty(),
+            "sight charted the start"
+        );
+
+        let path = save::slot_path(&state.save_dir, 0);
+        save::write_save(&path, &state.capture_save()).unwrap();
+        let mut resumed = quiet_run();
+        assert_ne!(fog(&resumed), explored);
+        resumed
+            .restore_save(save::read_save(&path).unwrap())
+            .unwrap();
+        assert_eq!(fog(&resumed), explored);
+        fs::remove_dir_all(&state.save_dir).unwrap();
+    }
+
+    #[test]
+    fn stepping_onto_an_item_collects_it_only_with_auto_pickup_on() {
+        for auto_pickup in [false, true] {
+            let mut state = sandbox(&["#####", "#@..#", "#####"]);
+            state.auto_pickup = auto_pickup;
+            let world = state.active_world;
+            let name = starter_consumables(world)[0].name.to_string();
+            let spot = Point::new(2, 1);
+            state
+                .dungeon
+                .floor_mut(state.active_floor)
+                .unwrap()
+                .add_item(FloorItem {
+                    point: spot,
+                    world,
+                    name: name.clone(),
+                });
+            let uses = pack_uses(&state);
+
+            assert!(state.try_step(1, 0));
+            state.advance_turns(1);
+            assert_eq!(state.ecs.player_point(), spot);
+            let lying = state
+                .dungeon
+                .active_floor(state.active_floor)
+                .unwrap()
+                .items_at(world, spot)
+                .count();
+            if auto_pickup {
+                assert_eq!(pack_uses(&state), uses + 1);
+                assert_eq!(lying, 0);
+            } else {
+                assert_eq!(pack_uses(&state), uses, "the pack is left alone");
+                assert_eq!(lying, 1);
+                assert!(
+                    state
+                        .message_log
+                        .contains(&format!("There is a {name} here."))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn the_pickup_key_collects_what_lies_underfoot_or_says_there_is_nothing() {
+        let mut state = sandbox(&["####", "#@.#", "####"]);
+        state.auto_pickup = false;
+        let uses = pack_uses(&state);
+        let mut ctx = ctx_with_key(Some(VirtualKeyCode::Comma));
+        assert!(!state.handle_input(&mut ctx), "an empty tile costs no turn");
+        assert_eq!(state.message_log[0], "There is nothing here to pick up.");
+        assert_eq!(state.pickup_prompt(), None);
+
+        let world = state.active_world;
+        let name = starter_consumables(world)[0].name.to_string();
+        let point = state.ecs.player_point();
+        state
+            .dungeon
+            .floor_mut(state.active_floor)
+            .unwrap()
+            .add_item(FloorItem {
+                point,
+                world,
+                name: name.clone(),
+            });
+        assert_eq!(
+            state.pickup_prompt(),
+            Some(format!("Press , to pick up the {name}."))
+        );
+        let mut ctx = ctx_with_key(Some(VirtualKeyCode::Comma));
+        assert!(state.handle_input(&mut ctx));
+        assert_eq!(pack_uses(&state), uses + 1);
+        assert_eq!(state.message_log[0], format!("You pick up the {name}."));
+        assert_eq!(state.pickup_prompt(), None);
+    }
+
+    #[test]
+    fn picking_violet_directly_matches_six_forward_cycles() {
+        let mut picked = quiet_run();
+        let mut cycled = quiet_run();
+        assert_eq!(picked.active_world, World::Red);
+        for state in [&mut picked, &mut cycled] {
+            state.options.world_switch_cost = 0;
+        }
+
+        for key in [VirtualKeyCode::V, VirtualKeyCode::Key7] {
+            let mut ctx = ctx_with_key(Some(key));
+            picked.handle_input(&mut ctx);
+        }
+        picked.advance_turns(1);
+        for _ in 0..6 {
+            assert!(cycled.cycle_world(1));
+        }
+        cycled.advance_turns(1);
+
+        assert_eq!(picked.active_world, World::Violet);
+        assert_eq!(cycled.active_world, World::Violet);
+        assert_eq!(picked.ecs.player_point(), cycled.ecs.player_point());
+        assert_eq!(picked.ecs.player_speed(), cycled.ecs.player_speed());
+        assert_eq!(picked.ecs.turn, cycled.ecs.turn);
+        let (direct, stepped) = (picked.ecs.snapshot(), cycled.ecs.snapshot());
+        assert_eq!(direct.attunement_turns, stepped.attunement_turns);
+        assert_eq!(
+            direct.pending_player_surcharge,
+            stepped.pending_player_surcharge
+        );
+        assert_eq!(picked.visible_tiles, cycled.visible_tiles);
+        assert_eq!(picked.message_log[..2], cycled.message_log[..2]);
+    }
+
+    #[test]
+    fn a_new_depth_records_its_split_once_and_a_revisit_does_not() {
+        let mut state = quiet_run();
+        state.save_dir = scratch_dir("splits");
+        let stand_on = |state: &mut RainbowRogueState, down: bool| {
+            let floor = state.dungeon.active_floor(state.active_floor).unwrap();
+            let stair = if down {
+                floor.stairs_down()[0]
+            } else {
+                floor.stairs_up()[0]
+            };
+            let (floor, world) = (state.active_floor, state.active_world);
+            state.ecs.set_player_position(stair, floor, world);
+        };
+        let splits = |state: &RainbowRogueState| -> Vec<(u32, u64)> {
+            state
+                .depth_turns
+                .iter()
+                .map(|split| (split.floor, split.turn))
+                .collect()
+        };
+        assert_eq!(splits(&state), [(0, 0)]);
+
+        state.advance_turns(3);
+        let reached = state.ecs.turn;
+        stand_on(&mut state, true);
+        assert!(state.shift_floor(1));
+        assert_eq!(splits(&state), [(0, 0), (1, reached)]);
+
+        stand_on(&mut state, false);
+        assert!(state.shift_floor(-1));
+        state.advance_turns(2);
+        stand_on(&mut state, true);
+        assert!(state.shift_floor(1));
+        assert_eq!(state.active_floor, FloorId(1));
+        assert_eq!(splits(&state), [(0, 0), (1, reached)], "no second split");
+        fs::remove_dir_all(&state.save_dir).unwrap();
+    }
+
+    #[test]
+    fn page_two_slot_one_uses_the_sixth_pack_item() {
+        let mut state = quiet_run();
+        let names: Vec<&str> = SPECTRUM
+            .iter()
+            .flat_map(|&world| starter_consumables(world))
+            .map(|template| template.name)
+            .collect();
+        for name in names {
+            if state.ecs.player_inventory().len() > QUICKBAR_PAGE_SLOTS {
+                break;
+            }
+            pack_slot_with(&mut state, name);
+        }
+        let uses_of = |state: &RainbowRogueState, idx| {
+            state
+                .ecs
+                .player_inventory()
+                .into_iter()
+                .find(|(slot, _)| *slot == idx)
+                .map(|(_, slot)| (slot.name, slot.uses_remaining))
+        };
+        let first = uses_of(&state, 0).unwrap();
+        let (sixth, uses) = uses_of(&state, QUICKBAR_PAGE_SLOTS).unwrap();
+
+        for key in [VirtualKeyCode::Q, VirtualKeyCode::Key1] {
+            let mut ctx = ctx_with_key(Some(key));
+            state.handle_input(&mut ctx);
+        }
+        assert_eq!(state.quickbar_page, 1);
+        assert_eq!(uses_of(&state, 0), Some(first), "page one is untouched");
+        let left = state
+            .ecs
+            .player_inventory()
+            .into_iter()
+            .find(|(_, slot)| slot.name == sixth)
+            .map_or(0, |(_, slot)| slot.uses_remaining);
+        assert_eq!(left, uses - 1);
+    }
+
+    #[test]
+    fn an_idle_key_always_leaves_a_last_action_behind() {
+        let mut state = quiet_run();
+        let press = |state: &mut RainbowRogueState, key| {
+            let mut ctx = ctx_with_key(Some(key));
+            state.handle_input(&mut ctx)
+        };
+        assert_eq!(state.last_action, None);
+
+        assert!(!press(&mut state, VirtualKeyCode::P));
+        let dump = "Nothing to dump (needs --verbose).";
+        assert_eq!(state.last_action.as_deref(), Some(dump));
+
+        // Opening the world picker logs nothing of its own, so it is echoed.
+        assert!(!press(&mut state, VirtualKeyCode::V));
+        assert_eq!(state.last_action.as_deref(), Some("V: nothing to do."));
+        assert!(!press(&mut state, VirtualKeyCode::Escape));
+
+        assert!(press(&mut state, VirtualKeyCode::Period));
+        assert_eq!(
+            state.last_action.as_deref(),
+            Some("V: nothing to do."),
+            "a key that takes a turn isn't an idle one"
+        );
+    }
+
+    #[test]
+    fn a_critical_line_outlasts_a_flood_of_chatter() {
+        let mut state = quiet_run();
+        let limit = state.options.log_history;
+        state.push_log_with("The Hex Bat shrieks!", LogPriority::Chatter);
+        state.push_log_with("!! Vitality critical !!", LogPriority::Alert);
+        for n in 0..limit * 3 {
+            state.push_log_with(format!("Glimpsed {n} new tiles."), LogPriority::Chatter);
+        }
+
+        assert_eq!(state.message_log.len(), limit);
+        assert_eq!(state.log_priorities.len(), limit);
+        let critical = "!! Vitality critical !!".to_string();
+        assert!(state.message_log.contains(&critical));
+        assert!(
+            !state
+                .message_log
+                .contains(&"The Hex Bat shrieks!".to_string()),
+            "chatter as old as the alert is gone"
+        );
+        let newest = format!("Glimpsed {} new tiles.", limit * 3 - 1);
+        assert_eq!(state.message_log[0], newest);
+    }
+
+    #[test]
+    fn striking_an_empty_tile_neither_moves_nor_queues_a_step() {
+        let mut state = sandbox(&["#####", "#@..#", "#####"]);
+        let start = state.ecs.player_point();
+        let mut ctx = ctx_with_key(None);
+
+        assert!(!state.apply_action(&mut ctx, PlayerAction::Strike(1, 0)));
+        assert_eq!(state.message_log[0], "You strike at empty air.");
+        assert!(!state.ecs.has_player_intent());
+        state.advance_turns(1);
+        assert_eq!(state.ecs.player_point(), start);
+    }
+
+    #[test]
+    fn a_hit_from_out_of_sight_breaks_off_a_rest() {
+        let mut state = sandbox(&["#######", "#@....#", "#######"]);
+        let mut ctx = ctx_with_key(None);
+        assert_eq!(
+            state.action_for_key(VirtualKeyCode::Period, true, false),
+            Some(PlayerAction::Rest)
+        );
+        assert!(state.apply_action(&mut ctx, PlayerAction::Rest));
+        state.advance_turns(1);
+        for _ in 0..3 {
+            assert!(state.handle_input(&mut ctx), "a quiet rest keeps waiting");
+            state.advance_turns(1);
+        }
+
+        assert!(state.ecs.damage_player(2) > 0);
+        assert!(!state.handle_input(&mut ctx));
+        assert!(state.auto_rest.is_none());
+        assert_eq!(state.message_log[0], "You stop resting: you've been hurt.");
+    }
+
+    #[test]
+    fn the_stats_throttle_folds_a_burst_of_changes_into_one_write() {
+        let interval = Duration::from_secs(30);
+        let mut throttle = StatsThrottle::new(interval);
+        let start = Instant::now();
+        let mut writes = Vec::new();
+        let mut change = |throttle: &mut StatsThrottle, now: Instant| {
+            throttle.dirty = true;
+            if throttle.due(now) {
+                throttle.wrote(now);
+                writes.push(now);
+            }
+        };
+
+        for step in 0..6 {
+            change(&mut throttle, start + Duration::from_millis(step * 200));
+        }
+        // The first change writes at once; the rest wait for the interval.
+        assert!(throttle.dirty);
+        assert!(!throttle.due(start + interval / 2));
+        assert!(throttle.due(start + interval));
+        change(&mut throttle, start + interval);
+        assert!(!throttle.dirty);
+        assert_eq!(writes, [start, start + interval]);
+        assert!(!throttle.due(start + interval * 3), "nothing left to write");
+    }
+
+    #[test]
+    fn one_directional_bump_attacks_swaps_opens_or_stops() {
+        let fresh = || {
+            let mut state = sandbox(&["######", "#.+..#", "#.@..#", "######"]);
+            state.ecs.set_godmode(true);
+            state
+        };
+        let bump = |state: &mut RainbowRogueState, dx, dy| {
+            let mut ctx = ctx_with_key(None);
+            let acted = state.apply_action(&mut ctx, PlayerAction::Move(dx, dy));
+            state.advance_turns(1);
+            acted
+        };
+        let start = Point::new(2, 2);
+
+        let mut state = fresh();
+        let (floor, world) = (state.active_floor, state.active_world);
+        let brute = MonsterTemplate::for_world(world)
+            .into_iter()
+            .filter(|template| template.size == 1)
+            .max_by_key(|template| template.hp)
+            .unwrap();
+        state
+            .ecs
+            .spawn_monster(&brute, Point::new(3, 2), floor, world, None);
+        assert!(bump(&mut state, 1, 0));
+        assert!(
+            state
+                .message_log
+                .iter()
+                .any(|line| line.starts_with(&format!("You strike {}", brute.name))),
+            "{:?}",
+            state.message_log
+        );
+
+        let mut state = fresh();
+        let shopkeeper = NpcTemplate::for_role(NpcRole::Shopkeeper);
+        state
+            .ecs
+            .spawn_npc(&shopkeeper, Point::new(1, 2), floor, world);
+        assert!(bump(&mut state, -1, 0));
+        assert_eq!(state.ecs.player_point(), Point::new(1, 2));
+        assert!(state.ecs.entity_at(start, floor, world).is_some());
+
+        let mut state = fresh();
+        assert!(bump(&mut state, 0, -1));
+        assert_eq!(state.ecs.player_point(), start, "opening doesn't step");
+        let door = state
+            .dungeon
+            .active_layer(floor, world)
+            .and_then(|layer| layer.tile_at(Point::new(2, 1)))
+            .map(|tile| tile.tag);
+        assert_eq!(door, Some(Tile::TAG_DOOR_OPEN));
+        assert!(
+            state
+                .message_log
+                .contains(&"You ease the door open.".to_string())
+        );
+
+        let mut state = fresh();
+        assert!(bump(&mut state, 0, 1));
+        assert_eq!(state.ecs.player_point(), start);
+        assert!(state.message_log.contains(&"Blocked at 2,3".to_string()));
+    }
+
+    #[test]
+    fn keys_map_onto_player_actions() {
+        let mut state = seeded_run();
+        let action = |state: &RainbowRogueState, key, shift, control| {
+            state.action_for_key(key, shift, control)
+        };
+        assert_eq!(
+            action(&state, VirtualKeyCode::Left, false, false),
+            Some(PlayerAction::Move(-1, 0))
+        );
+        assert_eq!(
+            action(&state, VirtualKeyCode::L, true, false),
+            Some(PlayerAction::Run(1, 0))
+        );
+        assert_eq!(
+            action(&state, VirtualKeyCode::Numpad8, false, true),
+            Some(PlayerAction::Strike(0, -1))
+        );
+        assert_eq!(
+            action(&state, VirtualKeyCode::N, false, false),
+            Some(PlayerAction::Move(1, 1))
+        );
+        assert_eq!(
+            action(&state, VirtualKeyCode::Tab, false, false),
+            Some(PlayerAction::CycleWorld(1))
+        );
+        assert_eq!(
+            action(&state, VirtualKeyCode::PageDown, false, false),
+            Some(PlayerAction::ShiftFloor(-1))
+        );
+        assert_eq!(
+            action(&state, VirtualKeyCode::Period, false, false),
+            Some(PlayerAction::Wait)
+        );
+        assert_eq!(
+            action(&state, VirtualKeyCode::F6, false, false),
+            Some(PlayerAction::OpenSlotMenu(SlotMenuMode::Save))
+        );
+        assert_eq!(action(&state, VirtualKeyCode::M, false, false), None);
+
+        assert_eq!(
+            action(&state, VirtualKeyCode::Key3, false, false),
+            Some(PlayerAction::UseSlot(2))
+        );
+        state.quickbar_page = 1;
+        assert_eq!(
+            action(&state, VirtualKeyCode::Key3, false, false),
+            Some(PlayerAction::UseSlot(QUICKBAR_PAGE_SLOTS + 2))
+        );
+    }
+
+    #[test]
+    fn a_move_action_matches_its_keypress() {
+        let probe = seeded_run();
+        let start = probe.ecs.player_point();
+        let (key, dx, dy) = [
+            (VirtualKeyCode::Right, 1, 0),
+            (VirtualKeyCode::Left, -1, 0),
+            (VirtualKeyCode::Down, 0, 1),
+            (VirtualKeyCode::Up, 0, -1),
+        ]
+        .into_iter()
+        .find(|&(_, dx, dy)| {
+            probe.dungeon.is_walkable(
+                probe.active_floor,
+                probe.active_world,
+                start + Point::new(dx, dy),
+            )
+        })
+        .expect("spawn has an open neighbour");
+
+        let mut pressed = seeded_run();
+        let mut ctx = ctx_with_key(Some(key));
+        assert!(pressed.handle_input(&mut ctx));
+        pressed.advance_turns(1);
+
+        let mut applied = seeded_run();
+        let mut ctx = ctx_with_key(None);
+        assert!(applied.apply_action(&mut ctx, PlayerAction::Move(dx, dy)));
+        applied.advance_turns(1);
+
+        assert_eq!(pressed.ecs.player_point(), start + Point::new(dx, dy));
+        assert_eq!(pressed.ecs.player_point(), applied.ecs.player_point());
+        assert_eq!(pressed.ecs.turn, applied.ecs.turn);
+        assert_eq!(pressed.ecs.rng_fingerprint(), applied.ecs.rng_fingerprint());
+        assert_eq!(pressed.message_log, applied.message_log);
+    }
+
+    #[test]
+    fn exported_maps_carry_every_layer_and_match_the_live_tiles() {
+        let mut state = seeded_run();
+        let floor = FloorId(1);
+        let json = serde_json::to_value(state.export_map(floor)).unwrap();
+
+        let live = state.dungeon.active_floor(floor).unwrap();
+        let (width, height) = state.dungeon.size();
+        assert_eq!(json["floor"], 1);
+        assert_eq!(json["width"], width);
+        assert_eq!(json["height"], height);
+        let layers = json["layers"].as_array().unwrap();
+        assert_eq!(layers.len(), SPECTRUM.len());
+        for (layer, &world) in layers.iter().zip(SPECTRUM.iter()) {
+            assert_eq!(layer["world"], serde_json::to_value(world).unwrap());
+            let tiles = layer["tiles"].as_array().unwrap();
+            assert_eq!(tiles.len(), (width * height) as usize);
+            let live_layer = live.layer(world);
+            for point in [Point::new(0, 0), live.spawn_point(), live.stairs_down()[0]] {
+                let tile = live_layer.tile_at(point).unwrap();
+                let exported = &tiles[(point.y * width + point.x) as usize];
+                assert_eq!(exported["glyph"], tile.glyph);
+                assert_eq!(exported["walkable"], !tile.blocks_move);
+                assert_eq!(exported["revealed"], tile.revealed);
+                assert_eq!(exported["tag"], tile.tag);
+            }
+        }
+
+        let entities = json["entities"].as_array().unwrap();
+        assert!(!entities.is_empty());
+        for entity in entities {
+            for key in ["world", "x", "y", "glyph", "color", "order"] {
+                assert!(entity.get(key).is_some(), "entity lacks {key}");
+            }
+            assert_eq!(entity["color"].as_array().unwrap().len(), 3);
+        }
+    }
+}
@@ -1,14 +1,40 @@
 #![allow(dead_code)]
 
+use std::collections::{HashMap, HashSet};
+
 use bracket_geometry::prelude::{Point, Rect};
 use bracket_random::prelude::RandomNumberGenerator;
-use bracket_terminal::prelude::{BLACK, RGB};
+use bracket_terminal::prelude::{BLACK, MAGENTA, RGB};
+use serde::{Deserialize, Serialize};
+
+use crate::{objective::FloorObjective, seed::RngStream};
 
 pub const DEFAULT_MAP_WIDTH: i32 = 80;
 pub const DEFAULT_MAP_HEIGHT: i32 = 48;
+/// Most tiles a single layer may hold; every floor allocates seven layers
+/// of this, so it bounds memory as well as keeping tile indices in `i32`.
+pub const MAX_MAP_TILES: usize = 1 << 20;
+const _: () = assert!((DEFAULT_MAP_WIDTH * DEFAULT_MAP_HEIGHT) as usize <= MAX_MAP_TILES);
+
+/// Tiles in a `width` by `height` layer, or why that size can't be built:
+/// both sides must be positive and the area at most [`MAX_MAP_TILES`].
+pub fn checked_tile_count(width: i32, height: i32) -> Result<usize, String> {
+    if width <= 0 || height <= 0 {
+        return Err(format!(
+            "map dimensions must be positive, got {width}x{height}"
+        ));
+    }
+    match (width as usize).checked_mul(height as usize) {
+        Some(tiles) if tiles <= MAX_MAP_TILES => Ok(tiles),
+        _ => Err(format!(
+            "a {width}x{height} map exceeds the {MAX_MAP_TILES}-tile limit"
+        )),
+    }
+}
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum World {
+    #[default]
     Red,
     Orange,
     Yellow,
@@ -18,12 +44,6 @@ pub enum World {
     Violet,
 }
 
-impl Default for World {
-    fn default() -> Self {
-        World::Red
-    }
-}
-
 pub fn world_color(world: World) -> RGB {
     match world {
         World::Red => RGB::from_u8(255, 95, 86),
@@ -49,6 +69,14 @@ impl World {
         }
     }
 
+    /// Case-insensitive lookup by display name (`"red"`, `"Indigo"`, ...).
+    pub fn from_name(name: &str) -> Option<Self> {
+        SPECTRUM
+            .iter()
+            .copied()
+            .find(|world| world.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+
     pub fn spectrum_index(&self) -> usize {
         match self {
             World::Red => 0,
@@ -61,6 +89,20 @@ impl World {
         }
     }
 
+    /// The world across the colour wheel: complementary pairs Red/Green,
+    /// Orange/Blue, and Yellow/Violet, with Indigo set against Yellow.
+    pub fn opposite(self) -> Self {
+        match self {
+            World::Red => World::Green,
+            World::Orange => World::Blue,
+            World::Yellow => World::Violet,
+            World::Green => World::Red,
+            World::Blue => World::Orange,
+            World::Indigo => World::Yellow,
+            World::Violet => World::Yellow,
+        }
+    }
+
     pub fn cycle(self, delta: i32) -> Self {
         let idx = self.spectrum_index() as i32;
         let next = (idx + delta).rem_euclid(SPECTRUM.len() as i32) as usize;
@@ -78,6 +120,37 @@ pub const SPECTRUM: [World; 7] = [
     World::Violet,
 ];
 
+/// Orthogonal steps from `from` to `to` through `open`, if connected.
+fn walk_distance(open: &HashSet<Point>, from: Point, to: Point) -> Option<usize> {
+    let mut seen = HashSet::from([from]);
+    let mut frontier = std::collections::VecDeque::from([(from, 0)]);
+    while let Some((point, steps)) = frontier.pop_front() {
+        if point == to {
+            return Some(steps);
+        }
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let next = Point::new(point.x + dx, point.y + dy);
+            if open.contains(&next) && seen.insert(next) {
+                frontier.push_back((next, steps + 1));
+            }
+        }
+    }
+    None
+}
+
+/// The up-stair's tile: a room tile next to `spawn`, so arriving from
+/// above doesn't land on the exact generation spawn. Falls back to `spawn`
+/// itself only in a one-tile room.
+fn up_stair_beside(room: Rect, spawn: Point) -> Point {
+    [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .into_iter()
+        .map(|(dx, dy)| Point::new(spawn.x + dx, spawn.y + dy))
+        .find(|&point| {
+            point.x >= room.x1 && point.x < room.x2 && point.y >= room.y1 && point.y < room.y2
+        })
+        .unwrap_or(spawn)
+}
+
 fn corridor_path(start: Point, end: Point) -> Vec<Point> {
     let mut path = Vec::new();
     let mut cursor = start;
@@ -100,7 +173,7 @@ fn corridor_path(start: Point, end: Point) -> Vec<Point> {
     path
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct FloorId(pub u32);
 
 #[derive(Clone, Debug)]
@@ -111,6 +184,17 @@ pub struct Substrate {
     pub corridors: Vec<Vec<Point>>,
     pub stairs_up: Vec<Point>,
     pub stairs_down: Vec<Point>,
+    /// Corridor tiles where a one-wide passage meets a room; carved as
+    /// closed doors on every world layer.
+    pub doors: Vec<Point>,
+    /// Hidden snares inside rooms, shared by every world layer.
+    pub traps: Vec<Point>,
+    /// Wall tiles that are really doors, short-cutting a long way round.
+    /// They stay walls until searched out.
+    pub secret_doors: Vec<Point>,
+    /// Sunken room tiles: water on the Blue layer, lava on the Red one,
+    /// plain floor everywhere else.
+    pub pools: Vec<Point>,
     pub spawn: Point,
 }
 
@@ -123,10 +207,25 @@ impl Substrate {
             corridors: Vec::new(),
             stairs_up: Vec::new(),
             stairs_down: Vec::new(),
+            doors: Vec::new(),
+            traps: Vec::new(),
+            secret_doors: Vec::new(),
+            pools: Vec::new(),
             spawn: Point::new(width / 2, height / 2),
         }
     }
 
+    /// Tiles between `point` and the nearest map edge; 0 on the border
+    /// itself. A collapsing floor has crumbled every tile closer to the
+    /// edge than its ring count.
+    pub fn edge_distance(&self, point: Point) -> i32 {
+        point
+            .x
+            .min(point.y)
+            .min(self.width - 1 - point.x)
+            .min(self.height - 1 - point.y)
+    }
+
     pub fn procedural(width: i32, height: i32, seed: u64) -> Self {
         const MAX_ROOMS: usize = 24;
         const MIN_ROOM_W: i32 = 6;
@@ -146,7 +245,9 @@ impl Substrate {
 
             let x_max = width - room_w - 2;
             let y_max = height - room_h - 2;
-            if x_max <= 2 || y_max <= 2 {
+            // Rooms start at column 2 and row 4; leave room for at least
+            // one roll past each.
+            if x_max <= 2 || y_max <= 4 {
                 continue;
             }
 
@@ -169,7 +270,7 @@ impl Substrate {
                     .push(corridor_path(prev_center, candidate_center));
             } else {
                 substrate.spawn = candidate_center;
-                substrate.stairs_up = vec![candidate_center];
+                substrate.stairs_up = vec![up_stair_beside(candidate, candidate_center)];
             }
 
             substrate.rooms.push(candidate);
@@ -178,6 +279,12 @@ impl Substrate {
         if let Some(last_room) = substrate.rooms.last() {
             substrate.stairs_down = vec![last_room.center()];
         }
+        substrate.separate_stairs();
+        substrate.place_doors();
+        substrate.place_traps(&mut rng);
+        // Own stream, so floors keep the layouts they had before secret doors.
+        substrate.place_secret_doors(&mut RandomNumberGenerator::seeded(seed ^ SECRET_DOOR_SALT));
+        substrate.place_pools(&mut RandomNumberGenerator::seeded(seed ^ POOL_SALT));
 
         if substrate.rooms.is_empty() {
             Self::demo_layout(width, height)
@@ -186,31 +293,213 @@ impl Substrate {
         }
     }
 
+    /// With a single room the down-stair lands on its centre, on top of the
+    /// spawn point or beside the up-stair. Moves it to the room tile
+    /// farthest from the spawn point instead.
+    fn separate_stairs(&mut self) {
+        let Some(room) = self.rooms.last().copied() else {
+            return;
+        };
+        if !self
+            .stairs_down
+            .iter()
+            .any(|down| *down == self.spawn || self.stairs_up.contains(down))
+        {
+            return;
+        }
+        let spawn = self.spawn;
+        let mut farthest = None;
+        room.for_each(|point| {
+            let distance = (point.x - spawn.x).pow(2) + (point.y - spawn.y).pow(2);
+            if distance > 0
+                && !self.stairs_up.contains(&point)
+                && farthest.is_none_or(|(best, _)| distance > best)
+            {
+                farthest = Some((distance, point));
+            }
+        });
+        if let Some((_, point)) = farthest {
+            self.stairs_down = vec![point];
+        }
+    }
+
+    /// Marks doorways: corridor tiles outside every room that touch a room
+    /// tile and are walled in on both sides across the passage.
+    fn place_doors(&mut self) {
+        let in_room = |p: Point| {
+            self.rooms
+                .iter()
+                .any(|room| p.x >= room.x1 && p.x < room.x2 && p.y >= room.y1 && p.y < room.y2)
+        };
+        let open: std::collections::HashSet<Point> = self
+            .corridors
+            .iter()
+            .flatten()
+            .copied()
+            .chain(self.rooms.iter().flat_map(|room| room.point_set()))
+            .collect();
+        let mut doors = Vec::new();
+        for &point in self.corridors.iter().flatten() {
+            let crowded = doors
+                .iter()
+                .any(|door: &Point| (door.x - point.x).abs() <= 1 && (door.y - point.y).abs() <= 1);
+            if in_room(point) || crowded {
+                continue;
+            }
+            let at = |dx: i32, dy: i32| Point::new(point.x + dx, point.y + dy);
+            let touches_room = [at(1, 0), at(-1, 0), at(0, 1), at(0, -1)]
+                .into_iter()
+                .any(in_room);
+            if !touches_room {
+                continue;
+            }
+            let horizontal_passage = !open.contains(&at(0, 1)) && !open.contains(&at(0, -1));
+            let vertical_passage = !open.contains(&at(1, 0)) && !open.contains(&at(-1, 0));
+            if horizontal_passage || vertical_passage {
+                doors.push(point);
+            }
+        }
+        self.doors = doors;
+    }
+
+    /// Hides a trap in about half the rooms past the first, never on a room
+    /// centre (where stairs sit) or a doorway.
+    fn place_traps(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut traps = Vec::new();
+        for room in self.rooms.iter().skip(1) {
+            if rng.range(0, 2) == 0 || room.width() < 3 || room.height() < 3 {
+                continue;
+            }
+            let point = Point::new(
+                rng.range(room.x1 + 1, room.x2 - 1),
+                rng.range(room.y1 + 1, room.y2 - 1),
+            );
+            if point != room.center() && !self.doors.contains(&point) {
+                traps.push(point);
+            }
+        }
+        self.traps = traps;
+    }
+
+    /// Hides up to [`SECRET_DOORS_PER_FLOOR`] doors in single walls between
+    /// a room and open ground that is at least [`SECRET_DOOR_MIN_DETOUR`]
+    /// steps away the long way round, so each one guards a real shortcut
+    /// and never the only way in.
+    fn place_secret_doors(&mut self, rng: &mut RandomNumberGenerator) {
+        let open: HashSet<Point> = self
+            .corridors
+            .iter()
+            .flatten()
+            .copied()
+            .chain(self.rooms.iter().flat_map(|room| room.point_set()))
+            .collect();
+        let in_room = |p: Point| {
+            self.rooms
+                .iter()
+                .any(|room| p.x >= room.x1 && p.x < room.x2 && p.y >= room.y1 && p.y < room.y2)
+        };
+        let mut candidates = Vec::new();
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let point = Point::new(x, y);
+                if open.contains(&point) {
+                    continue;
+                }
+                let at = |dx: i32, dy: i32| Point::new(x + dx, y + dy);
+                for (a, b, side_a, side_b) in [
+                    (at(-1, 0), at(1, 0), at(0, -1), at(0, 1)),
+                    (at(0, -1), at(0, 1), at(-1, 0), at(1, 0)),
+                ] {
+                    if open.contains(&a)
+                        && open.contains(&b)
+                        && !open.contains(&side_a)
+                        && !open.contains(&side_b)
+                        && (in_room(a) || in_room(b))
+                    {
+                        candidates.push((point, a, b));
+                    }
+                }
+            }
+        }
+        let mut secret: Vec<Point> = Vec::new();
+        while secret.len() < SECRET_DOORS_PER_FLOOR && !candidates.is_empty() {
+            let idx = rng.range(0, candidates.len() as i32) as usize;
+            let (point, a, b) = candidates.swap_remove(idx);
+            let crowded = self
+                .doors
+                .iter()
+                .chain(&secret)
+                .any(|door| (door.x - point.x).abs() <= 2 && (door.y - point.y).abs() <= 2);
+            if crowded {
+                continue;
+            }
+            if walk_distance(&open, a, b).is_none_or(|steps| steps >= SECRET_DOOR_MIN_DETOUR) {
+                secret.push(point);
+            }
+        }
+        self.secret_doors = secret;
+    }
+
+    /// Sinks a small pool into about one room in [`POOL_ODDS`] past the
+    /// first. Pools keep off the room's edge ring, so a way round always
+    /// stays dry, and never cover a centre, door, or trap.
+    fn place_pools(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut pools = Vec::new();
+        for room in self.rooms.iter().skip(1) {
+            if rng.range(0, POOL_ODDS) != 0 || room.width() < 5 || room.height() < 5 {
+                continue;
+            }
+            let pool_w = rng.range(1, (room.width() - 3).min(4));
+            let pool_h = rng.range(1, (room.height() - 3).min(3));
+            let x = rng.range(room.x1 + 1, room.x2 - 1 - pool_w);
+            let y = rng.range(room.y1 + 1, room.y2 - 1 - pool_h);
+            Rect::with_size(x, y, pool_w, pool_h).for_each(|point| {
+                if point != room.center()
+                    && !self.doors.contains(&point)
+                    && !self.traps.contains(&point)
+                {
+                    pools.push(point);
+                }
+            });
+        }
+        self.pools = pools;
+    }
+
+    /// A row of rooms joined end to end; the fallback when procedural
+    /// generation places none. On a map too small for the row it is a
+    /// single room filling whatever lies inside the outer wall, so there is
+    /// always somewhere to spawn and two distinct stairs.
     pub fn demo_layout(width: i32, height: i32) -> Self {
         let mut substrate = Self::new(width, height);
         let room_width = 12;
-        let room_height = 8;
+        let room_height = 8.min(height - 2).max(1);
+        let room_y = 8.min((height - room_height) / 2).max(1);
         let mut x = 2;
         while x + room_width < width - 2 {
-            let room = Rect::with_size(x, 8, room_width, room_height);
+            let room = Rect::with_size(x, room_y, room_width, room_height);
             substrate.rooms.push(room);
             x += room_width + 3;
         }
 
-        if substrate.rooms.is_empty() {
-            substrate
-                .rooms
-                .push(Rect::with_size(2, 8, width.saturating_sub(4), room_height));
-        }
-
-        substrate.spawn = substrate.rooms[0].center();
-        substrate.stairs_up.push(substrate.rooms[0].center());
+        let first = match substrate.rooms.first() {
+            Some(room) => *room,
+            None => {
+                let room = Rect::with_size(1, room_y, (width - 2).max(1), room_height);
+                substrate.rooms.push(room);
+                room
+            }
+        };
+        substrate.spawn = first.center();
+        substrate
+            .stairs_up
+            .push(up_stair_beside(first, substrate.spawn));
         let exit_point = substrate
             .rooms
             .last()
             .map(|rect| rect.center())
             .unwrap_or(substrate.spawn);
         substrate.stairs_down.push(exit_point);
+        substrate.separate_stairs();
 
         for window in substrate.rooms.windows(2) {
             let start = window[0].center();
@@ -231,6 +520,9 @@ pub struct Tile {
     pub blocks_sight: bool,
     pub tag: u32,
     pub revealed: bool,
+    /// A hidden trap the player has spotted; separate from `revealed`,
+    /// which only means the tile has been seen.
+    pub detected: bool,
 }
 
 impl Default for Tile {
@@ -244,6 +536,12 @@ impl Tile {
     pub const TAG_FLOOR: u32 = 1;
     pub const TAG_STAIR_UP: u32 = 2;
     pub const TAG_STAIR_DOWN: u32 = 3;
+    pub const TAG_DOOR_CLOSED: u32 = 4;
+    pub const TAG_DOOR_OPEN: u32 = 5;
+    pub const TAG_TRAP: u32 = 6;
+    pub const TAG_WATER: u32 = 7;
+    pub const TAG_LAVA: u32 = 8;
+    pub const TAG_VOID: u32 = 9;
 
     pub fn wall() -> Self {
         Self {
@@ -254,6 +552,7 @@ impl Tile {
             blocks_sight: true,
             tag: Self::TAG_WALL,
             revealed: false,
+            detected: false,
         }
     }
 
@@ -266,6 +565,7 @@ impl Tile {
             blocks_sight: false,
             tag: Self::TAG_FLOOR,
             revealed: false,
+            detected: false,
         }
     }
 
@@ -278,6 +578,7 @@ impl Tile {
             blocks_sight: false,
             tag: Self::TAG_STAIR_UP,
             revealed: false,
+            detected: false,
         }
     }
 
@@ -290,8 +591,92 @@ impl Tile {
             blocks_sight: false,
             tag: Self::TAG_STAIR_DOWN,
             revealed: false,
+            detected: false,
+        }
+    }
+
+    pub fn door(world: World, open: bool) -> Self {
+        Self {
+            glyph: if open { b'\'' } else { b'+' } as u16,
+            fg: world_color(world),
+            bg: RGB::named(BLACK),
+            blocks_move: !open,
+            blocks_sight: !open,
+            tag: if open {
+                Self::TAG_DOOR_OPEN
+            } else {
+                Self::TAG_DOOR_CLOSED
+            },
+            revealed: false,
+            detected: false,
+        }
+    }
+
+    /// A snare disguised as ordinary floor until detected.
+    pub fn trap(world: World) -> Self {
+        Self {
+            tag: Self::TAG_TRAP,
+            ..Self::floor(world)
+        }
+    }
+
+    /// Deep water: walkable, but wading through it is slow.
+    pub fn water() -> Self {
+        Self {
+            glyph: b'~' as u16,
+            fg: RGB::from_u8(80, 150, 255),
+            bg: RGB::from_u8(0, 20, 60),
+            tag: Self::TAG_WATER,
+            ..Self::floor(World::Blue)
+        }
+    }
+
+    /// Molten rock: walkable, but it burns whoever steps in.
+    pub fn lava() -> Self {
+        Self {
+            glyph: b'~' as u16,
+            fg: RGB::from_u8(255, 140, 40),
+            bg: RGB::from_u8(90, 20, 0),
+            tag: Self::TAG_LAVA,
+            ..Self::floor(World::Red)
+        }
+    }
+
+    pub fn is_lava(&self) -> bool {
+        self.tag == Self::TAG_LAVA
+    }
+
+    /// A crumbled edge of a collapsing floor: nothing can cross it, and it
+    /// gnaws at whoever it opened up under.
+    pub fn void() -> Self {
+        Self {
+            glyph: 176,
+            fg: RGB::from_u8(90, 40, 120),
+            bg: RGB::from_u8(20, 0, 30),
+            blocks_move: true,
+            blocks_sight: false,
+            tag: Self::TAG_VOID,
+            revealed: false,
+            detected: false,
+        }
+    }
+
+    pub fn is_void(&self) -> bool {
+        self.tag == Self::TAG_VOID
+    }
+
+    /// Shows a trap for what it is; its glyph changes but it stays walkable.
+    pub fn detect_trap(&mut self) {
+        if self.tag == Self::TAG_TRAP {
+            self.detected = true;
+            self.glyph = b'^' as u16;
+            self.fg = RGB::named(MAGENTA);
         }
     }
+
+    pub fn is_door(&self) -> bool {
+        self.tag == Self::TAG_DOOR_CLOSED || self.tag == Self::TAG_DOOR_OPEN
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -303,6 +688,8 @@ pub struct MapLayer {
 }
 
 impl MapLayer {
+    /// A layer of default tiles. The size is trusted; [`Dungeon::new`] is
+    /// where it gets checked.
     pub fn empty(world: World, width: i32, height: i32) -> Self {
         let size = (width * height) as usize;
         Self {
@@ -335,9 +722,44 @@ impl MapLayer {
             layer.set_tile(stair, Tile::stair_down(world));
         }
 
+        for &door in &substrate.doors {
+            layer.set_tile(door, Tile::door(world, false));
+        }
+
+        for &trap in &substrate.traps {
+            layer.set_tile(trap, Tile::trap(world));
+        }
+
+        let pool = match world {
+            World::Blue => Some(Tile::water()),
+            World::Red => Some(Tile::lava()),
+            _ => None,
+        };
+        if let Some(pool) = pool {
+            for &point in &substrate.pools {
+                layer.set_tile(point, pool.clone());
+            }
+        }
+
         layer
     }
 
+    /// Swings the door at `point`, keeping whether it was revealed. Returns
+    /// false when there is no door there.
+    pub fn set_door_open(&mut self, point: Point, open: bool) -> bool {
+        let world = self.world;
+        let Some(tile) = self.tile_at_mut(point) else {
+            return false;
+        };
+        if !tile.is_door() {
+            return false;
+        }
+        let revealed = tile.revealed;
+        *tile = Tile::door(world, open);
+        tile.revealed = revealed;
+        true
+    }
+
     fn idx(&self, x: i32, y: i32) -> Option<usize> {
         if self.in_bounds(Point::new(x, y)) {
             Some((y * self.width + x) as usize)
@@ -374,8 +796,46 @@ impl MapLayer {
         }
     }
 
+    /// Replaces `field` with `visible` in one pass, revealing each of its
+    /// tiles. Returns how many visible points `field` didn't already hold.
+    pub fn reveal_field(&mut self, field: &mut HashSet<Point>, visible: Vec<Point>) -> usize {
+        let mut next = HashSet::with_capacity(visible.len());
+        let mut newly_visible = 0;
+        for point in visible {
+            if !next.insert(point) {
+                continue;
+            }
+            if !field.contains(&point) {
+                newly_visible += 1;
+            }
+            if let Some(tile) = self.tile_at_mut(point) {
+                tile.revealed = true;
+            }
+        }
+        *field = next;
+        newly_visible
+    }
+
+    /// Tile indices the player has revealed, for saving explored state.
+    pub fn revealed_indices(&self) -> Vec<u32> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| tile.revealed)
+            .map(|(idx, _)| idx as u32)
+            .collect()
+    }
+
+    pub fn reveal_indices(&mut self, indices: &[u32]) {
+        for &idx in indices {
+            if let Some(tile) = self.tiles.get_mut(idx as usize) {
+                tile.revealed = true;
+            }
+        }
+    }
+
     pub fn is_walkable(&self, point: Point) -> bool {
-        self.tile_at(point).map_or(false, |tile| !tile.blocks_move)
+        self.tile_at(point).is_some_and(|tile| !tile.blocks_move)
     }
 
     pub fn first_walkable(&self) -> Point {
@@ -390,6 +850,53 @@ impl MapLayer {
         Point::new(0, 0)
     }
 
+    /// Counts walkable tiles that can't be reached from `start` by orthogonal
+    /// steps; zero means the layer is fully connected. Doors count as
+    /// passable whether open or closed, since any actor can open them.
+    pub fn unreachable_from(&self, start: Point) -> usize {
+        let passable = |tile: &Tile| !tile.blocks_move || tile.is_door();
+        let Some(start_idx) = self.idx(start.x, start.y) else {
+            return self.walkable_points().len();
+        };
+        let mut seen = vec![false; self.tiles.len()];
+        let mut frontier = Vec::new();
+        if passable(&self.tiles[start_idx]) {
+            seen[start_idx] = true;
+            frontier.push(start);
+        }
+        while let Some(point) = frontier.pop() {
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let next = Point::new(point.x + dx, point.y + dy);
+                if let Some(idx) = self.idx(next.x, next.y)
+                    && !seen[idx]
+                    && passable(&self.tiles[idx])
+                {
+                    seen[idx] = true;
+                    frontier.push(next);
+                }
+            }
+        }
+        self.tiles
+            .iter()
+            .zip(&seen)
+            .filter(|(tile, reached)| passable(tile) && !**reached)
+            .count()
+    }
+
+    /// `(revealed, total)` counts of the layer's walkable tiles.
+    pub fn revealed_walkable(&self) -> (u32, u32) {
+        let walkable = self.tiles.iter().filter(|tile| !tile.blocks_move);
+        walkable.fold((0, 0), |(revealed, total), tile| {
+            (revealed + tile.revealed as u32, total + 1)
+        })
+    }
+
+    /// Share of walkable tiles the player has revealed, in whole percent.
+    pub fn charted_percent(&self) -> u32 {
+        let (revealed, total) = self.revealed_walkable();
+        (revealed * 100).checked_div(total).unwrap_or(0)
+    }
+
     pub fn walkable_points(&self) -> Vec<Point> {
         let mut points = Vec::new();
         for y in 0..self.height {
@@ -404,11 +911,173 @@ impl MapLayer {
     }
 }
 
+/// Turns an acid cloud lingers after it is released.
+pub const ACID_CLOUD_TURNS: u32 = 6;
+/// Damage an acid cloud deals each turn to whatever stands in it.
+pub const ACID_CLOUD_DAMAGE: i32 = 2;
+
+/// Turns a corpse lies where its monster fell before it crumbles away.
+pub const CORPSE_TURNS: u32 = 12;
+/// Percent chance that searching a corpse turns up a consumable.
+pub const CORPSE_LOOT_CHANCE: i32 = 35;
+
+/// Consumables left lying on each world layer of a freshly seeded floor.
+pub const FLOOR_ITEMS_PER_LAYER: usize = 1;
+
+/// Most tiles a Violet curse can claim on one floor.
+pub const CURSE_TILE_CAP: usize = 24;
+/// Damage a cursed tile deals to the player stepping onto it.
+pub const CURSE_DAMAGE: i32 = 1;
+
+/// Wind directions a floor can roll, in the order [`wind_for`] picks them.
+const WINDS: [Point; 4] = [
+    Point { x: 1, y: 0 },
+    Point { x: 0, y: 1 },
+    Point { x: -1, y: 0 },
+    Point { x: 0, y: -1 },
+];
+
+/// The floor-wide wind that pushes acid clouds while attuned to Orange.
+pub fn wind_for(floor: FloorId) -> Point {
+    WINDS[floor.0 as usize % WINDS.len()]
+}
+
+/// Compass name for a wind direction, for the HUD.
+pub fn wind_name(wind: Point) -> &'static str {
+    match (wind.x, wind.y) {
+        (1, 0) => "east",
+        (0, 1) => "south",
+        (-1, 0) => "west",
+        (0, -1) => "north",
+        _ => "still",
+    }
+}
+
+/// Floor holding Red's spectrum fragment; each later world's lies one
+/// floor deeper, so Violet's is on floor 9.
+pub const FRAGMENT_FIRST_FLOOR: u32 = 3;
+/// Bitmask with every world's fragment collected.
+pub const ALL_FRAGMENTS: u8 = (1 << SPECTRUM.len()) - 1;
+/// Mixed into the floor seed when placing a fragment, so placement never
+/// disturbs the layout rolls.
+const FRAGMENT_SALT: u64 = 0xf4a6_3e47;
+
+/// The bit `world`'s fragment sets in a collected-fragments mask.
+pub fn fragment_bit(world: World) -> u8 {
+    1 << world.spectrum_index()
+}
+
+/// Whose fragment, if any, lies on `floor`.
+pub fn fragment_world(floor: FloorId) -> Option<World> {
+    let idx = floor.0.checked_sub(FRAGMENT_FIRST_FLOOR)?;
+    SPECTRUM.get(idx as usize).copied()
+}
+
+/// Most secret doors hidden on one floor.
+const SECRET_DOORS_PER_FLOOR: usize = 2;
+/// How much longer the way round must be for a wall to hide a secret door.
+const SECRET_DOOR_MIN_DETOUR: usize = 12;
+/// Mixed into the floor seed when placing secret doors.
+const SECRET_DOOR_SALT: u64 = 0x5ec2_e7d0;
+/// Percent chance each turn of searching finds an adjacent secret door.
+pub const SEARCH_CHANCE: i32 = 35;
+
+/// One room in this many (past the first) holds a pool.
+const POOL_ODDS: i32 = 3;
+/// Mixed into the floor seed when sinking pools.
+const POOL_SALT: u64 = 0x9001_5a1d;
+/// Extra energy wading into water costs, on top of the step itself.
+pub const WATER_SURCHARGE: i32 = 60;
+/// Damage lava deals each time the player steps onto it.
+pub const LAVA_DAMAGE: i32 = 4;
+/// Damage the void deals each turn to a player standing on a collapsed
+/// tile (`--collapse`).
+pub const COLLAPSE_DAMAGE: i32 = 5;
+
+/// How many rings of the map's edge have crumbled once `elapsed` turns
+/// have passed on a floor that holds for `budget` turns: one at the
+/// deadline, and one more every turn after.
+pub fn collapse_rings(elapsed: u64, budget: u32) -> i32 {
+    if budget == 0 || elapsed < u64::from(budget) {
+        return 0;
+    }
+    (elapsed - u64::from(budget) + 1).min(i32::MAX as u64) as i32
+}
+
+/// One floor in this many (below the surface) has one-way down-stairs.
+const ONE_WAY_ODDS: i32 = 3;
+/// Mixed into the floor seed when rolling one-way stairs.
+const ONE_WAY_SALT: u64 = 0x0e_3a71;
+
+/// A spectrum fragment waiting on its world's layer. It only exists there;
+/// the same tile in other worlds is empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fragment {
+    pub world: World,
+    pub point: Point,
+}
+
+/// A patch of acid hanging over one tile. Clouds overlay the shared
+/// substrate rather than replacing tiles, so they show in every world.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AcidCloud {
+    pub point: Point,
+    pub turns_left: u32,
+}
+
+/// What's left of a slain monster (`%`). Unlike a cloud it stays on the
+/// layer its monster died on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Corpse {
+    pub point: Point,
+    pub world: World,
+    pub name: String,
+    pub turns_left: u32,
+}
+
+impl Corpse {
+    /// A fresh corpse, good for [`CORPSE_TURNS`].
+    pub fn fresh(point: Point, world: World, name: &str) -> Self {
+        Self {
+            point,
+            world,
+            name: name.to_string(),
+            turns_left: CORPSE_TURNS,
+        }
+    }
+}
+
+/// A consumable lying loose on one world's layer (`!`). It is always one of
+/// that world's starter consumables, named so it can be rebuilt on pickup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FloorItem {
+    pub point: Point,
+    pub world: World,
+    pub name: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct WorldFloor {
     pub id: FloorId,
     pub substrate: Substrate,
     pub layers: [MapLayer; 7],
+    /// Open doors and the turns left before each swings shut.
+    pub open_doors: HashMap<Point, u32>,
+    pub clouds: Vec<AcidCloud>,
+    pub corpses: Vec<Corpse>,
+    pub items: Vec<FloorItem>,
+    pub wind: Point,
+    /// Tiles claimed by a spreading Violet curse; they stay cursed after
+    /// the curse itself runs out, until a Cleanse clears them.
+    pub cursed: HashSet<Point>,
+    pub fragment: Option<Fragment>,
+    /// This floor's optional objective, rolled when the floor is generated.
+    pub objective: Option<FloorObjective>,
+    /// Every down-stair here seals behind whoever takes it, so the floor
+    /// can't be re-entered from below. Only enforced with `--one-way-stairs`.
+    pub one_way_down: bool,
+    /// Secret doors not yet found; a found one becomes an ordinary door.
+    pub secret_doors: HashSet<Point>,
 }
 
 impl WorldFloor {
@@ -419,6 +1088,16 @@ impl WorldFloor {
             id,
             substrate,
             layers,
+            open_doors: HashMap::new(),
+            clouds: Vec::new(),
+            corpses: Vec::new(),
+            items: Vec::new(),
+            wind: wind_for(id),
+            cursed: HashSet::new(),
+            fragment: None,
+            objective: None,
+            one_way_down: false,
+            secret_doors: HashSet::new(),
         }
     }
 
@@ -430,15 +1109,65 @@ impl WorldFloor {
 
     pub fn from_seed(id: FloorId, width: i32, height: i32, seed: u64) -> Self {
         let substrate = Substrate::procedural(width, height, seed);
-        Self::from_substrate(id, substrate)
+        let mut floor = Self::from_substrate(id, substrate);
+        floor.place_fragment(seed);
+        floor.one_way_down = id.0 > 0
+            && RandomNumberGenerator::seeded(seed ^ ONE_WAY_SALT).range(0, ONE_WAY_ODDS) == 0;
+        floor
+    }
+
+    /// Drops this floor's fragment on a random plain floor tile of its
+    /// world's layer, away from the spawn point.
+    fn place_fragment(&mut self, seed: u64) {
+        let Some(world) = fragment_world(self.id) else {
+            return;
+        };
+        let spawn = self.spawn_point();
+        let layer = self.layer(world);
+        let candidates: Vec<Point> = layer
+            .walkable_points()
+            .into_iter()
+            .filter(|&point| {
+                point != spawn
+                    && layer
+                        .tile_at(point)
+                        .is_some_and(|tile| tile.tag == Tile::TAG_FLOOR)
+            })
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+        let mut rng = RandomNumberGenerator::seeded(seed ^ FRAGMENT_SALT);
+        let point = candidates[rng.range(0, candidates.len() as i32) as usize];
+        self.fragment = Some(Fragment { world, point });
+    }
+
+    /// The uncollected fragment at `point` on `world`'s layer, if any.
+    pub fn fragment_at(&self, point: Point, world: World, collected: u8) -> Option<Fragment> {
+        self.fragment.filter(|fragment| {
+            fragment.world == world
+                && fragment.point == point
+                && collected & fragment_bit(world) == 0
+        })
     }
 
     fn from_substrate(id: FloorId, substrate: Substrate) -> Self {
         let layers = std::array::from_fn(|idx| MapLayer::from_substrate(SPECTRUM[idx], &substrate));
+        let secret_doors = substrate.secret_doors.iter().copied().collect();
         Self {
             id,
             substrate,
             layers,
+            open_doors: HashMap::new(),
+            clouds: Vec::new(),
+            corpses: Vec::new(),
+            items: Vec::new(),
+            wind: wind_for(id),
+            cursed: HashSet::new(),
+            fragment: None,
+            objective: None,
+            one_way_down: false,
+            secret_doors,
         }
     }
 
@@ -456,6 +1185,251 @@ impl WorldFloor {
         self.substrate.spawn
     }
 
+    /// Opens or closes a door on every world layer at once, since doors are
+    /// part of the shared substrate. An opened door starts its close timer.
+    pub fn set_door_open(&mut self, point: Point, open: bool, close_after: u32) -> bool {
+        let mut changed = false;
+        for layer in self.layers.iter_mut() {
+            changed |= layer.set_door_open(point, open);
+        }
+        if changed {
+            if open {
+                self.open_doors.insert(point, close_after);
+            } else {
+                self.open_doors.remove(&point);
+            }
+        }
+        changed
+    }
+
+    /// Crumbles every tile closer to the map edge than `rings` into the
+    /// void on all seven layers, keeping whether each was seen. Returns how
+    /// many tiles fell this time.
+    pub fn collapse_edges(&mut self, rings: i32) -> usize {
+        let mut fallen = 0;
+        for layer in self.layers.iter_mut() {
+            for y in 0..layer.height {
+                for x in 0..layer.width {
+                    let point = Point::new(x, y);
+                    if self.substrate.edge_distance(point) >= rings {
+                        continue;
+                    }
+                    if let Some(tile) = layer.tile_at_mut(point)
+                        && !tile.is_void()
+                    {
+                        let revealed = tile.revealed;
+                        *tile = Tile::void();
+                        tile.revealed = revealed;
+                        fallen += 1;
+                    }
+                }
+            }
+        }
+        self.open_doors
+            .retain(|point, _| self.substrate.edge_distance(*point) >= rings);
+        fallen
+    }
+
+    /// Turns the secret door at `point` into a closed door on every layer.
+    /// Returns false when no hidden door is there.
+    pub fn reveal_secret_door(&mut self, point: Point) -> bool {
+        if !self.secret_doors.remove(&point) {
+            return false;
+        }
+        for layer in self.layers.iter_mut() {
+            let world = layer.world;
+            if let Some(tile) = layer.tile_at_mut(point)
+                && !tile.is_void()
+            {
+                let revealed = tile.revealed;
+                *tile = Tile::door(world, false);
+                tile.revealed = revealed;
+            }
+        }
+        self.substrate.doors.push(point);
+        true
+    }
+
+    /// Secret doors that have been found, for saving.
+    pub fn found_secret_doors(&self) -> Vec<Point> {
+        self.substrate
+            .secret_doors
+            .iter()
+            .copied()
+            .filter(|point| !self.secret_doors.contains(point))
+            .collect()
+    }
+
+    /// Marks the trap at `point` detected on every layer. Returns false when
+    /// there is no undetected trap there.
+    pub fn detect_trap(&mut self, point: Point) -> bool {
+        let mut changed = false;
+        for layer in self.layers.iter_mut() {
+            if let Some(tile) = layer.tile_at_mut(point)
+                && tile.tag == Tile::TAG_TRAP
+                && !tile.detected
+            {
+                tile.detect_trap();
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    pub fn detected_traps(&self) -> Vec<Point> {
+        self.substrate
+            .traps
+            .iter()
+            .copied()
+            .filter(|&p| self.layers[0].tile_at(p).is_some_and(|tile| tile.detected))
+            .collect()
+    }
+
+    /// Releases a cloud over `point`, or tops up the one already there.
+    /// Walls and shut doors can't hold a cloud.
+    pub fn add_cloud(&mut self, point: Point, turns: u32) -> bool {
+        if !self.is_open(point) {
+            return false;
+        }
+        match self.clouds.iter_mut().find(|cloud| cloud.point == point) {
+            Some(cloud) => cloud.turns_left = cloud.turns_left.max(turns),
+            None => self.clouds.push(AcidCloud {
+                point,
+                turns_left: turns,
+            }),
+        }
+        true
+    }
+
+    pub fn cloud_at(&self, point: Point) -> bool {
+        self.clouds.iter().any(|cloud| cloud.point == point)
+    }
+
+    pub fn cloud_points(&self) -> Vec<Point> {
+        self.clouds.iter().map(|cloud| cloud.point).collect()
+    }
+
+    /// True for tiles air can move through: not a wall or a shut door.
+    fn is_open(&self, point: Point) -> bool {
+        self.layers[0]
+            .tile_at(point)
+            .is_some_and(|tile| !tile.blocks_move)
+    }
+
+    /// Ages every cloud a turn, dropping spent ones. With `drift`, the
+    /// survivors then move one tile downwind unless a wall or shut door is
+    /// in the way; clouds that meet merge, keeping the longer lifetime.
+    pub fn tick_clouds(&mut self, drift: bool) {
+        let mut moved: Vec<AcidCloud> = Vec::with_capacity(self.clouds.len());
+        for mut cloud in std::mem::take(&mut self.clouds) {
+            cloud.turns_left = cloud.turns_left.saturating_sub(1);
+            if cloud.turns_left == 0 {
+                continue;
+            }
+            if drift {
+                let next = cloud.point + self.wind;
+                if self.is_open(next) {
+                    cloud.point = next;
+                }
+            }
+            match moved.iter_mut().find(|other| other.point == cloud.point) {
+                Some(other) => other.turns_left = other.turns_left.max(cloud.turns_left),
+                None => moved.push(cloud),
+            }
+        }
+        self.clouds = moved;
+    }
+
+    /// Lays `corpse` down, replacing any older one on the same tile. Corpses
+    /// need open ground to lie on.
+    pub fn add_corpse(&mut self, corpse: Corpse) -> bool {
+        if !self.is_open(corpse.point) {
+            return false;
+        }
+        self.corpses
+            .retain(|other| other.point != corpse.point || other.world != corpse.world);
+        self.corpses.push(corpse);
+        true
+    }
+
+    /// Removes and returns the corpse on `world`'s layer at `point`, if any.
+    pub fn take_corpse(&mut self, world: World, point: Point) -> Option<Corpse> {
+        let idx = self
+            .corpses
+            .iter()
+            .position(|corpse| corpse.world == world && corpse.point == point)?;
+        Some(self.corpses.remove(idx))
+    }
+
+    /// Sets `item` down. Items, like corpses, need open ground to lie on.
+    pub fn add_item(&mut self, item: FloorItem) -> bool {
+        if !self.is_open(item.point) {
+            return false;
+        }
+        self.items.push(item);
+        true
+    }
+
+    /// Items lying on `world`'s layer at `point`.
+    pub fn items_at(&self, world: World, point: Point) -> impl Iterator<Item = &FloorItem> {
+        self.items
+            .iter()
+            .filter(move |item| item.world == world && item.point == point)
+    }
+
+    /// Removes and returns one item on `world`'s layer at `point`, if any.
+    pub fn take_item(&mut self, world: World, point: Point) -> Option<FloorItem> {
+        let idx = self
+            .items
+            .iter()
+            .position(|item| item.world == world && item.point == point)?;
+        Some(self.items.remove(idx))
+    }
+
+    /// Ages every corpse a turn, dropping the ones that have crumbled.
+    pub fn tick_corpses(&mut self) {
+        for corpse in &mut self.corpses {
+            corpse.turns_left = corpse.turns_left.saturating_sub(1);
+        }
+        self.corpses.retain(|corpse| corpse.turns_left > 0);
+    }
+
+    /// Tiles a spreading curse may claim next: open floor out of `visible`
+    /// sight, bordering an already cursed tile once the curse has a
+    /// foothold. Empty once the floor holds [`CURSE_TILE_CAP`] cursed tiles.
+    pub fn curse_frontier(&self, visible: &HashSet<Point>) -> Vec<Point> {
+        if self.cursed.len() >= CURSE_TILE_CAP {
+            return Vec::new();
+        }
+        let open = |point: &Point| {
+            !visible.contains(point) && !self.cursed.contains(point) && self.is_open(*point)
+        };
+        if self.cursed.is_empty() {
+            return self.layers[0]
+                .walkable_points()
+                .into_iter()
+                .filter(open)
+                .collect();
+        }
+        let mut frontier: Vec<Point> = self
+            .cursed
+            .iter()
+            .flat_map(|&p| {
+                [(1, 0), (-1, 0), (0, 1), (0, -1)].map(|(dx, dy)| Point::new(p.x + dx, p.y + dy))
+            })
+            .filter(open)
+            .collect();
+        // HashSet order isn't stable across runs; sort so seeded rolls
+        // pick the same tile every time.
+        frontier.sort_by_key(|p| (p.y, p.x));
+        frontier.dedup();
+        frontier
+    }
+
+    pub fn is_cursed(&self, point: Point) -> bool {
+        self.cursed.contains(&point)
+    }
+
     pub fn stairs_up(&self) -> &[Point] {
         &self.substrate.stairs_up
     }
@@ -474,7 +1448,21 @@ pub struct Dungeon {
 }
 
 impl Dungeon {
-    pub fn new(width: i32, height: i32, seed: u64) -> Self {
+    /// A dungeon of `width` by `height` floors with floor 0 generated;
+    /// fails for sizes [`checked_tile_count`] rejects. This is the one
+    /// place map sizes are checked: every floor and layer built afterwards
+    /// takes the dungeon's size on trust.
+    pub fn new(width: i32, height: i32, seed: u64) -> Result<Self, String> {
+        checked_tile_count(width, height)?;
+        Ok(Self::generate(width, height, seed))
+    }
+
+    pub fn scaffolding_demo() -> Self {
+        // The default size is checked at compile time, next to MAX_MAP_TILES.
+        Self::generate(DEFAULT_MAP_WIDTH, DEFAULT_MAP_HEIGHT, 0x51eccafe)
+    }
+
+    fn generate(width: i32, height: i32, seed: u64) -> Self {
         let mut dungeon = Self {
             floors: Vec::new(),
             width,
@@ -485,8 +1473,9 @@ impl Dungeon {
         dungeon
     }
 
-    pub fn scaffolding_demo() -> Self {
-        Self::new(DEFAULT_MAP_WIDTH, DEFAULT_MAP_HEIGHT, 0x51eccafe)
+    /// Width and height of every layer on every floor.
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
     }
 
     pub fn ensure_floor(&mut self, floor: FloorId) -> bool {
@@ -494,7 +1483,8 @@ impl Dungeon {
         while self.floors.len() <= floor.0 as usize {
             let id = FloorId(self.floors.len() as u32);
             let floor_seed = self.floor_seed(id);
-            let new_floor = WorldFloor::from_seed(id, self.width, self.height, floor_seed);
+            let mut new_floor = WorldFloor::from_seed(id, self.width, self.height, floor_seed);
+            new_floor.objective = Some(FloorObjective::roll(id, floor_seed));
             self.floors.push(new_floor);
             created = true;
         }
@@ -502,8 +1492,7 @@ impl Dungeon {
     }
 
     fn floor_seed(&self, floor: FloorId) -> u64 {
-        let spread = 0x9e37_79b9_7f4a_7c15u64;
-        self.seed ^ ((floor.0 as u64 + 1).wrapping_mul(spread))
+        RngStream::MapGen.floor_seed(self.seed, floor)
     }
 
     pub fn active_floor(&self, floor: FloorId) -> Option<&WorldFloor> {
@@ -520,6 +1509,10 @@ impl Dungeon {
             .map(|wf| wf.layer_mut(world))
     }
 
+    pub fn floor_mut(&mut self, floor: FloorId) -> Option<&mut WorldFloor> {
+        self.floors.get_mut(floor.0 as usize)
+    }
+
     pub fn spawn_point(&self, floor: FloorId) -> Point {
         self.active_floor(floor)
             .map(|wf| wf.spawn_point())
@@ -531,6 +1524,80 @@ impl Dungeon {
             .map(|layer| layer.is_walkable(point))
             .unwrap_or(false)
     }
+
+    /// All seven layers of `floor` as plain data for external renderers,
+    /// or `None` if the floor hasn't been generated. Entities live in the
+    /// ECS, so the caller fills in [`MapSnapshot::entities`].
+    pub fn export_snapshot(&self, floor: FloorId) -> Option<MapSnapshot> {
+        let world_floor = self.active_floor(floor)?;
+        let layers = world_floor
+            .layers
+            .iter()
+            .map(|layer| LayerSnapshot {
+                world: layer.world,
+                tiles: layer
+                    .tiles
+                    .iter()
+                    .map(|tile| TileSnapshot {
+                        glyph: tile.glyph,
+                        walkable: !tile.blocks_move,
+                        revealed: tile.revealed,
+                        tag: tile.tag,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Some(MapSnapshot {
+            floor: floor.0,
+            width: world_floor.substrate.width,
+            height: world_floor.substrate.height,
+            layers,
+            entities: Vec::new(),
+        })
+    }
+}
+
+/// One floor exported by [`Dungeon::export_snapshot`]: every world layer's
+/// tiles, row-major from the top-left, plus the entities standing on them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MapSnapshot {
+    pub floor: u32,
+    pub width: i32,
+    pub height: i32,
+    /// One per world, in spectrum order.
+    pub layers: Vec<LayerSnapshot>,
+    pub entities: Vec<EntitySnapshot>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LayerSnapshot {
+    pub world: World,
+    /// `width * height` tiles; tile `(x, y)` sits at `y * width + x`.
+    pub tiles: Vec<TileSnapshot>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileSnapshot {
+    /// CP437 code point, as drawn.
+    pub glyph: u16,
+    pub walkable: bool,
+    /// The player has seen this tile.
+    pub revealed: bool,
+    /// One of the `Tile::TAG_*` kinds.
+    pub tag: u32,
+}
+
+/// A cell an entity covers; large entities export one per cell.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EntitySnapshot {
+    pub world: World,
+    pub x: i32,
+    pub y: i32,
+    pub glyph: u16,
+    /// Foreground colour as 0–255 RGB.
+    pub color: [u8; 3],
+    /// Draw order; higher draws over lower on a shared cell.
+    pub order: i32,
 }
 
 impl Default for Dungeon {
@@ -538,3 +1605,161 @@ impl Default for Dungeon {
         Self::scaffolding_demo()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_tile_count_rejects_empty_negative_and_oversized_maps() {
+        assert_eq!(checked_tile_count(80, 48), Ok(80 * 48));
+        assert_eq!(checked_tile_count(1024, 1024), Ok(MAX_MAP_TILES));
+        assert!(checked_tile_count(0, 48).is_err());
+        assert!(checked_tile_count(80, -1).is_err());
+        assert!(checked_tile_count(1025, 1024).is_err());
+        assert!(checked_tile_count(i32::MAX, i32::MAX).is_err());
+    }
+
+    #[test]
+    fn dungeon_checks_its_size_once_and_keeps_it_for_every_floor() {
+        assert!(Dungeon::new(0, 48, 7).is_err());
+        assert!(Dungeon::new(2048, 1024, 7).is_err());
+
+        let mut dungeon = Dungeon::new(40, 30, 7).unwrap();
+        dungeon.ensure_floor(FloorId(2));
+        assert_eq!(dungeon.size(), (40, 30));
+        for floor in &dungeon.floors {
+            let layer = floor.layer(World::Red);
+            assert_eq!((layer.width, layer.height), (40, 30));
+            assert_eq!(layer.tiles.len(), 40 * 30);
+        }
+    }
+
+    #[test]
+    fn charted_percent_tracks_revealed_walkable_tiles() {
+        let mut dungeon = Dungeon::new(40, 30, 7).unwrap();
+        dungeon.ensure_floor(FloorId(0));
+        let mut layer = dungeon.floors[0].layer(World::Red).clone();
+        let walkable = layer.walkable_points();
+        assert_eq!(layer.charted_percent(), 0);
+
+        for point in &walkable[..walkable.len() / 2] {
+            layer.tile_at_mut(*point).unwrap().revealed = true;
+        }
+        assert!((49..=50).contains(&layer.charted_percent()));
+
+        for point in &walkable {
+            layer.tile_at_mut(*point).unwrap().revealed = true;
+        }
+        assert_eq!(layer.charted_percent(), 100);
+    }
+
+    #[test]
+    fn reveal_field_counts_only_points_new_to_the_field() {
+        let mut layer = MapLayer::empty(World::Red, 6, 6);
+        let points = |coords: &[(i32, i32)]| -> Vec<Point> {
+            coords.iter().map(|&(x, y)| Point::new(x, y)).collect()
+        };
+        let mut field = HashSet::new();
+
+        assert_eq!(
+            layer.reveal_field(&mut field, points(&[(1, 1), (2, 1), (2, 1)])),
+            2
+        );
+        // (2,1) stays in view and (1,1) drops out; only (3,1) and (3,2)
+        // count as new.
+        let next = points(&[(2, 1), (3, 1), (3, 2)]);
+        assert_eq!(layer.reveal_field(&mut field, next.clone()), 2);
+        assert_eq!(field, next.into_iter().collect::<HashSet<_>>());
+        assert!(layer.tile_at(Point::new(1, 1)).unwrap().revealed);
+        assert_eq!(layer.revealed_indices().len(), 4);
+        assert_eq!(layer.reveal_field(&mut field, Vec::new()), 0);
+        assert!(field.is_empty());
+    }
+
+    #[test]
+    fn a_map_too_cramped_for_rooms_still_gets_a_playable_floor() {
+        for (width, height) in [(12, 9), (10, 6), (30, 8)] {
+            let substrate = Substrate::procedural(width, height, 7);
+            assert!(!substrate.rooms.is_empty(), "{width}x{height} fell back");
+            for seed in [0, 7, 0x51eccafe] {
+                let dungeon = Dungeon::new(width, height, seed).unwrap();
+                let floor = dungeon.active_floor(FloorId(0)).unwrap();
+                let spawn = floor.spawn_point();
+                let (up, down) = (floor.stairs_up(), floor.stairs_down());
+                let size = format!("{width}x{height} seed {seed}");
+                assert!(!up.is_empty() && !down.is_empty(), "{size}");
+                assert!(!down.iter().any(|stair| up.contains(stair)), "{size}");
+                for world in SPECTRUM {
+                    let layer = floor.layer(world);
+                    assert!(layer.is_walkable(spawn), "{size}");
+                    assert!(down.iter().all(|&stair| layer.is_walkable(stair)), "{size}");
+                    assert_eq!(layer.unreachable_from(spawn), 0, "{size}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_up_stair_sits_beside_the_spawn_not_on_it() {
+        for seed in [0, 7, 42, 0x51eccafe] {
+            let dungeon = Dungeon::new(DEFAULT_MAP_WIDTH, DEFAULT_MAP_HEIGHT, seed).unwrap();
+            let floor = dungeon.active_floor(FloorId(0)).unwrap();
+            let spawn = floor.spawn_point();
+            let up = floor.stairs_up()[0];
+            assert_ne!(up, spawn, "seed {seed}");
+            for world in SPECTRUM {
+                let layer = floor.layer(world);
+                assert!(layer.is_walkable(spawn), "seed {seed} {world:?}");
+                assert!(layer.is_walkable(up), "seed {seed} {world:?}");
+                assert_eq!(layer.unreachable_from(up), 0, "seed {seed} {world:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn collapsing_rings_turn_the_edges_to_void_one_ring_a_turn() {
+        assert_eq!(collapse_rings(9, 10), 0);
+        assert_eq!(collapse_rings(10, 10), 1);
+        assert_eq!(collapse_rings(12, 10), 3);
+        assert_eq!(collapse_rings(500, 0), 0);
+
+        let mut floor = WorldFloor::empty(FloorId(0), 9, 7);
+        for layer in floor.layers.iter_mut() {
+            for y in 1..6 {
+                for x in 1..8 {
+                    layer.set_tile(Point::new(x, y), Tile::floor(layer.world));
+                }
+            }
+        }
+        floor.layers[0]
+            .tile_at_mut(Point::new(1, 1))
+            .unwrap()
+            .revealed = true;
+        let ring = |floor: &WorldFloor, depth: i32| {
+            (0..floor.substrate.height)
+                .flat_map(|y| (0..floor.substrate.width).map(move |x| Point::new(x, y)))
+                .filter(move |&point| floor.substrate.edge_distance(point) == depth)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(floor.collapse_edges(1), 7 * ring(&floor, 0).len());
+        assert_eq!(floor.collapse_edges(1), 0, "a ring only falls once");
+        for layer in &floor.layers {
+            assert!(
+                ring(&floor, 0)
+                    .iter()
+                    .all(|&p| layer.tile_at(p).unwrap().is_void())
+            );
+            assert!(ring(&floor, 1).iter().all(|&p| layer.is_walkable(p)));
+        }
+
+        assert_eq!(floor.collapse_edges(2), 7 * ring(&floor, 1).len());
+        let corner = floor.layers[0].tile_at(Point::new(1, 1)).unwrap();
+        assert!(corner.is_void() && corner.revealed);
+        for layer in &floor.layers {
+            assert!(ring(&floor, 1).iter().all(|&p| !layer.is_walkable(p)));
+            assert!(ring(&floor, 2).iter().all(|&p| layer.is_walkable(p)));
+        }
+    }
+}
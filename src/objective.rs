@@ -0,0 +1,89 @@
+use bracket_random::prelude::RandomNumberGenerator;
+
+use crate::{
+    data::monsters::MonsterTemplate,
+    map::{FloorId, SPECTRUM, World},
+};
+
+/// Shards granted when a floor's objective is completed.
+pub const OBJECTIVE_REWARD_SHARDS: u32 = 2;
+/// Mixed into the floor seed when rolling an objective, so the roll never
+/// disturbs the layout.
+const OBJECTIVE_SALT: u64 = 0x0b1e_c71e;
+
+/// What a floor asks of the player. Objectives are optional: none of them
+/// gates the stairs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectiveKind {
+    /// Stand on one of the floor's down-stairs.
+    ReachDownStair,
+    /// Kill every monster of one species; seeding makes sure at least one
+    /// spawns on `world`'s layer.
+    Slay { world: World, name: &'static str },
+    /// Reveal `percent` of the floor's walkable tiles on any single layer.
+    Chart { percent: u32 },
+}
+
+/// Live state an objective is checked against.
+#[derive(Clone, Copy, Debug)]
+pub struct ObjectiveProbe {
+    pub on_down_stair: bool,
+    /// The floor has been seeded with monsters (and its quarry).
+    pub seeded: bool,
+    pub quarry_alive: bool,
+    /// Best revealed share of walkable tiles across the floor's layers.
+    pub charted_percent: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FloorObjective {
+    pub kind: ObjectiveKind,
+    pub complete: bool,
+}
+
+impl FloorObjective {
+    /// Rolls the objective for `floor`. Floor 0 always asks for the way
+    /// down, since it may be a monster-free hub.
+    pub fn roll(floor: FloorId, seed: u64) -> Self {
+        let mut rng = RandomNumberGenerator::seeded(seed ^ OBJECTIVE_SALT);
+        let kind = match (floor.0, rng.range(0, 3)) {
+            (0, _) | (_, 0) => ObjectiveKind::ReachDownStair,
+            (_, 1) => {
+                let world = SPECTRUM[rng.range(0, SPECTRUM.len() as i32) as usize];
+                let templates = MonsterTemplate::for_world(world);
+                match templates.get(rng.range(0, templates.len().max(1) as i32) as usize) {
+                    Some(template) => ObjectiveKind::Slay {
+                        world,
+                        name: template.name,
+                    },
+                    None => ObjectiveKind::ReachDownStair,
+                }
+            }
+            _ => ObjectiveKind::Chart {
+                percent: 50 + 5 * rng.range(0, 5) as u32,
+            },
+        };
+        Self {
+            kind,
+            complete: false,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.kind {
+            ObjectiveKind::ReachDownStair => "Find the down-stair".to_string(),
+            ObjectiveKind::Slay { world, name } => {
+                format!("Slay every {name} ({})", world.as_str())
+            }
+            ObjectiveKind::Chart { percent } => format!("Chart {percent}% of the floor"),
+        }
+    }
+
+    pub fn holds(&self, probe: &ObjectiveProbe) -> bool {
+        match self.kind {
+            ObjectiveKind::ReachDownStair => probe.on_down_stair,
+            ObjectiveKind::Slay { .. } => probe.seeded && !probe.quarry_alive,
+            ObjectiveKind::Chart { percent } => probe.charted_percent >= percent,
+        }
+    }
+}
@@ -1,11 +1,416 @@
 #![allow(dead_code)]
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use bracket_geometry::prelude::Point;
+use bracket_geometry::prelude::{Point, Rect};
 use bracket_terminal::prelude::*;
 
-use crate::map::{FloorId, MapLayer, SPECTRUM, World};
+use crate::{
+    data::rule_for,
+    ecs::components::StatusEffects,
+    map::{FloorId, MapLayer, SPECTRUM, World, fragment_bit, world_color},
+};
+
+pub const HUD_ROWS: i32 = 7;
+const MAP_MARGIN_X: i32 = 2;
+const MIN_MAP_ROWS: i32 = 3;
+
+/// Screen regions for one frame, recomputed from the console size so every
+/// draw call agrees on where the HUD, map viewport, and log live. All rects
+/// are half-open (`x2`/`y2` exclusive) and never overlap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    pub width: i32,
+    pub height: i32,
+    pub hud: Rect,
+    pub map: Rect,
+    pub log: Rect,
+    /// Map point shown in the viewport's top-left cell; see [`Layout::follow`].
+    pub camera: Point,
+}
+
+impl Layout {
+    /// `log_lines` is how many entries the log panel should show; the map
+    /// keeps its minimum rows first, so a tall request on a short console
+    /// shrinks the panel instead of covering the map.
+    pub fn compute(width: i32, height: i32, log_lines: usize) -> Self {
+        let width = width.max(1);
+        let height = height.max(1);
+        let hud_rows = HUD_ROWS.min(height);
+        let hud = Rect::with_size(0, 0, width, hud_rows);
+
+        // Title row plus entries plus the two box borders; give rows back to
+        // the map when the console is too short to fit everything.
+        let spare = (height - hud_rows - MIN_MAP_ROWS).max(0);
+        let log_rows = (log_lines as i32).saturating_add(3).min(spare);
+        let log = Rect::with_size(0, height - log_rows, width, log_rows);
+
+        let map_w = (width - MAP_MARGIN_X * 2).max(0);
+        let map_h = (log.y1 - hud.y2).max(0);
+        let map = Rect::with_size(MAP_MARGIN_X, hud.y2, map_w, map_h);
+
+        Self {
+            width,
+            height,
+            hud,
+            map,
+            log,
+            camera: Point::zero(),
+        }
+    }
+
+    /// Scrolls the viewport to centre on `focus` over a `map_width` ×
+    /// `map_height` map; see [`camera_origin`].
+    pub fn follow(&mut self, focus: Point, map_width: i32, map_height: i32) {
+        self.camera = camera_origin(
+            focus,
+            (self.map.width(), self.map.height()),
+            (map_width, map_height),
+        );
+    }
+
+    /// Number of log entries that fit inside the log panel.
+    pub fn log_lines(&self) -> usize {
+        (self.log.height() - 3).max(0) as usize
+    }
+
+    /// Screen cell for a map point, or `None` when it falls outside the viewport.
+    pub fn map_to_screen(&self, point: Point) -> Option<Point> {
+        let screen = self.unclipped_screen(point);
+        let inside = screen.x >= self.map.x1
+            && screen.x < self.map.x2
+            && screen.y >= self.map.y1
+            && screen.y < self.map.y2;
+        inside.then_some(screen)
+    }
+
+    /// Where to point at a known threat the viewport can't show: the edge
+    /// cell closest to it and an arrow facing it. `None` while the threat
+    /// is on screen. Past a corner the arrow follows whichever axis the
+    /// threat lies furthest along from `player`.
+    pub fn threat_arrow(&self, player: Point, threat: Point) -> Option<(Point, char)> {
+        if self.map.width() <= 0 || self.map.height() <= 0 || self.map_to_screen(threat).is_some() {
+            return None;
+        }
+        let screen = self.unclipped_screen(threat);
+        let edge = Point::new(
+            screen.x.clamp(self.map.x1, self.map.x2 - 1),
+            screen.y.clamp(self.map.y1, self.map.y2 - 1),
+        );
+        let horizontal = if screen.x < self.map.x1 {
+            Some('←')
+        } else if screen.x >= self.map.x2 {
+            Some('→')
+        } else {
+            None
+        };
+        let vertical = if screen.y < self.map.y1 {
+            Some('↑')
+        } else if screen.y >= self.map.y2 {
+            Some('↓')
+        } else {
+            None
+        };
+        let arrow = match (horizontal, vertical) {
+            (Some(h), Some(v)) => {
+                if (threat.x - player.x).abs() >= (threat.y - player.y).abs() {
+                    h
+                } else {
+                    v
+                }
+            }
+            (Some(arrow), None) | (None, Some(arrow)) => arrow,
+            (None, None) => return None,
+        };
+        Some((edge, arrow))
+    }
+
+    fn unclipped_screen(&self, point: Point) -> Point {
+        Point::new(
+            self.map.x1 + point.x - self.camera.x,
+            self.map.y1 + point.y - self.camera.y,
+        )
+    }
+}
+
+/// Top-left map point of a `view`-sized viewport centred on `focus`,
+/// clamped so it never scrolls past a `map`-sized map's edges. Along an
+/// axis where the whole map fits, the viewport stays pinned at 0.
+pub fn camera_origin(focus: Point, view: (i32, i32), map: (i32, i32)) -> Point {
+    let axis = |focus: i32, view: i32, map: i32| (focus - view / 2).clamp(0, (map - view).max(0));
+    Point::new(axis(focus.x, view.0, map.0), axis(focus.y, view.1, map.1))
+}
+
+/// Eases the camera's look-ahead one tile per axis toward `reach` tiles
+/// along `heading`, or back toward centre when there is no heading (the
+/// player stood still).
+pub fn ease_look_ahead(lead: Point, heading: Option<Point>, reach: i32) -> Point {
+    let target = heading.map_or(Point::zero(), |heading| {
+        Point::new(heading.x.signum() * reach, heading.y.signum() * reach)
+    });
+    Point::new(
+        lead.x + (target.x - lead.x).signum(),
+        lead.y + (target.y - lead.y).signum(),
+    )
+}
+
+/// What the player knows about an entity being drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntityVisibility {
+    /// In the player's sight right now.
+    Visible,
+    /// Out of sight; drawn where the player last saw it.
+    Remembered,
+    /// Out of sight but picked up by detect life at its live position.
+    Sensed,
+}
+
+/// One status as shown beside the vitality line or over a monster: a
+/// coloured letter and the turns it has left.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatusIndicator {
+    pub symbol: char,
+    pub color: RGB,
+    pub turns: u32,
+}
+
+impl StatusIndicator {
+    pub fn label(&self) -> String {
+        format!("{}{}", self.symbol, self.turns)
+    }
+}
+
+/// Indicators for every active status, in a fixed order so the row doesn't
+/// shuffle as timers run down.
+pub fn status_indicators(effects: &StatusEffects) -> Vec<StatusIndicator> {
+    [
+        (effects.burning, 'B', RGB::named(ORANGE)),
+        (effects.slowed, 'S', RGB::named(LIGHT_BLUE)),
+        (effects.cursed, 'C', RGB::named(MAGENTA)),
+        (effects.levitating, 'L', RGB::named(LAVENDER)),
+    ]
+    .into_iter()
+    .filter(|(turns, _, _)| *turns > 0)
+    .map(|(turns, symbol, color)| StatusIndicator {
+        symbol,
+        color,
+        turns,
+    })
+    .collect()
+}
+
+/// Columns [`draw_status_row`] needs, separators included.
+pub fn status_row_width(indicators: &[StatusIndicator]) -> i32 {
+    indicators
+        .iter()
+        .map(|indicator| indicator.label().len() as i32 + 1)
+        .sum::<i32>()
+        .saturating_sub(1)
+}
+
+/// Prints indicators as `B3 S2` starting at `x`.
+pub fn draw_status_row(ctx: &mut BTerm, x: i32, y: i32, indicators: &[StatusIndicator]) {
+    let mut cursor = x;
+    for indicator in indicators {
+        let label = indicator.label();
+        ctx.print_color(cursor, y, indicator.color, RGB::named(BLACK), &label);
+        cursor += label.len() as i32 + 1;
+    }
+}
+
+/// HP share at or below which vitality counts as critical.
+pub const CRITICAL_HP_RATIO: f32 = 0.3;
+/// Frames one swell-and-fade of the critical-HP border takes.
+const CRITICAL_PULSE_FRAMES: u64 = 8;
+
+/// How strongly the map border glows red for `hp_ratio` at `frame`: 0 once
+/// HP is above [`CRITICAL_HP_RATIO`], otherwise a pulse that swells and
+/// fades every [`CRITICAL_PULSE_FRAMES`] and runs hotter the closer HP is
+/// to zero. Never more than 0.6, so the edge tiles stay readable.
+pub fn critical_pulse(frame: u64, hp_ratio: f32) -> f32 {
+    if hp_ratio <= 0.0 || hp_ratio > CRITICAL_HP_RATIO {
+        return 0.0;
+    }
+    let phase = (frame % CRITICAL_PULSE_FRAMES) as f32 / CRITICAL_PULSE_FRAMES as f32;
+    let swell = 1.0 - (2.0 * phase - 1.0).abs();
+    let severity = 1.0 - hp_ratio / CRITICAL_HP_RATIO;
+    (0.15 + 0.25 * swell) * (1.0 + 0.5 * severity)
+}
+
+/// Tints the background of the map's outermost ring of cells toward red,
+/// leaving the glyphs (and everything inside the ring) untouched.
+pub fn draw_critical_border(ctx: &mut BTerm, layout: &Layout, intensity: f32) {
+    if intensity <= 0.0 || layout.map.width() <= 0 || layout.map.height() <= 0 {
+        return;
+    }
+    let color = RGB::named(BLACK).lerp(RGB::named(RED), intensity);
+    let map = layout.map;
+    for x in map.x1..map.x2 {
+        ctx.set_bg(x, map.y1, color);
+        ctx.set_bg(x, map.y2 - 1, color);
+    }
+    for y in map.y1..map.y2 {
+        ctx.set_bg(map.x1, y, color);
+        ctx.set_bg(map.x2 - 1, y, color);
+    }
+}
+
+/// Glyph for a spectrum fragment, on the map and in the HUD tracker (♦).
+pub const FRAGMENT_GLYPH: u16 = 4;
+
+/// Glyph for a sensed heartbeat: detect life shows where something is,
+/// not what it is.
+const SENSED_GLYPH: u16 = 3;
+
+/// Background drawn behind a visible entity that is on fire.
+pub const BURNING_TINT: RGB = RGB {
+    r: 0.45,
+    g: 0.12,
+    b: 0.0,
+};
+
+/// Background drawn behind a visible entity that is slowed.
+pub const CHILLED_TINT: RGB = RGB {
+    r: 0.05,
+    g: 0.2,
+    b: 0.45,
+};
+
+/// Foreground colour and optional glyph override for an entity whose own
+/// colour is `base`, so every map pass draws each state the same way.
+pub fn entity_draw_style(state: EntityVisibility, base: RGB) -> (RGB, Option<u16>) {
+    match state {
+        EntityVisibility::Visible => (base, None),
+        EntityVisibility::Remembered => (base.desaturate().lerp(RGB::named(BLACK), 0.45), None),
+        EntityVisibility::Sensed => (
+            RGB::named(GOLD).lerp(RGB::named(BLACK), 0.3),
+            Some(SENSED_GLYPH),
+        ),
+    }
+}
+
+/// Where monsters on one layer were last seen, for drawing ghosts. Seeing
+/// a tile again clears whatever ghost stood on it.
+#[derive(Clone, Debug, Default)]
+pub struct MonsterMemory {
+    layer: Option<(FloorId, World)>,
+    ghosts: HashMap<Point, (u16, RGB)>,
+}
+
+impl MonsterMemory {
+    /// Updates the memory after the player's sight changed; switching to a
+    /// different layer forgets the old one. `seen` lists every monster
+    /// cell currently in view.
+    pub fn observe(
+        &mut self,
+        floor: FloorId,
+        world: World,
+        visible: &HashSet<Point>,
+        seen: impl IntoIterator<Item = (Point, u16, RGB)>,
+    ) {
+        if self.layer != Some((floor, world)) {
+            self.layer = Some((floor, world));
+            self.ghosts.clear();
+        }
+        self.ghosts.retain(|point, _| !visible.contains(point));
+        for (point, glyph, color) in seen {
+            self.ghosts.insert(point, (glyph, color));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.layer = None;
+        self.ghosts.clear();
+    }
+
+    /// Ghosts out of sight right now.
+    pub fn ghosts<'a>(
+        &'a self,
+        visible: &'a HashSet<Point>,
+    ) -> impl Iterator<Item = (Point, u16, RGB)> + 'a {
+        self.ghosts
+            .iter()
+            .filter(move |(point, _)| !visible.contains(point))
+            .map(|(point, (glyph, color))| (*point, *glyph, *color))
+    }
+}
+
+/// How the debug overlay tints a map cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverlayCell {
+    Blocked,
+    Walkable,
+    PlayerSight,
+    MonsterSight,
+    SharedSight,
+}
+
+pub fn classify_overlay_cell(walkable: bool, player_sees: bool, monster_sees: bool) -> OverlayCell {
+    match (player_sees, monster_sees) {
+        (true, true) => OverlayCell::SharedSight,
+        (true, false) => OverlayCell::PlayerSight,
+        (false, true) => OverlayCell::MonsterSight,
+        (false, false) if walkable => OverlayCell::Walkable,
+        (false, false) => OverlayCell::Blocked,
+    }
+}
+
+fn overlay_tint(cell: OverlayCell) -> Option<RGB> {
+    match cell {
+        OverlayCell::Blocked => None,
+        OverlayCell::Walkable => Some(RGB::from_u8(0, 48, 0)),
+        OverlayCell::PlayerSight => Some(RGB::from_u8(0, 70, 90)),
+        OverlayCell::MonsterSight => Some(RGB::from_u8(90, 20, 20)),
+        OverlayCell::SharedSight => Some(RGB::from_u8(90, 30, 90)),
+    }
+}
+
+/// Tints cell backgrounds over the already-drawn scene so glyphs stay legible.
+pub fn draw_debug_overlay(
+    ctx: &mut BTerm,
+    layout: &Layout,
+    width: i32,
+    height: i32,
+    is_walkable: impl Fn(Point) -> bool,
+    player_sight: &HashSet<Point>,
+    monster_sight: &HashSet<Point>,
+) {
+    for y in 0..height {
+        for x in 0..width {
+            let point = Point::new(x, y);
+            let Some(screen) = layout.map_to_screen(point) else {
+                continue;
+            };
+            let cell = classify_overlay_cell(
+                is_walkable(point),
+                player_sight.contains(&point),
+                monster_sight.contains(&point),
+            );
+            if let Some(tint) = overlay_tint(cell) {
+                ctx.set_bg(screen.x, screen.y, tint);
+            }
+        }
+    }
+}
+
+fn draw_frame(ctx: &mut BTerm, rect: Rect, fg: RGB) {
+    if rect.width() < 2 || rect.height() < 2 {
+        return;
+    }
+    ctx.draw_box(
+        rect.x1,
+        rect.y1,
+        rect.width() - 1,
+        rect.height() - 1,
+        fg,
+        RGB::named(BLACK),
+    );
+}
+
+/// The HUD's reminder of the rule the active world plays by.
+pub fn world_rule_line(world: World) -> String {
+    format!("{} rule: {}", world.as_str(), rule_for(world).notes)
+}
 
 pub struct HudRing;
 
@@ -14,124 +419,137 @@ impl HudRing {
         Self
     }
 
-    pub fn draw(&self, ctx: &mut BTerm, active_world: World, active_floor: FloorId, frame: u64) {
-        let (width, _) = ctx.get_char_size();
-        ctx.draw_box(0, 0, width - 1, 6, RGB::named(GRAY), RGB::named(BLACK));
+    pub fn draw(
+        &self,
+        ctx: &mut BTerm,
+        area: Rect,
+        active_world: World,
+        active_floor: FloorId,
+        frame: u64,
+        fragments: u8,
+    ) {
+        draw_frame(ctx, area, RGB::named(GRAY));
         ctx.print_color(
-            2,
-            1,
+            area.x1 + 2,
+            area.y1 + 1,
             RGB::named(WHITE),
             RGB::named(BLACK),
-            format!("Spectrum HUD · Floor {}", active_floor.0),
+            format!(
+                "Spectrum HUD · Floor {} · Fragments {}/{}",
+                active_floor.0,
+                fragments.count_ones(),
+                SPECTRUM.len()
+            ),
         );
         ctx.print_color(
-            2,
-            2,
+            area.x1 + 2,
+            area.y1 + 2,
             RGB::named(LIGHT_BLUE),
             RGB::named(BLACK),
             format!("Frame {}", frame),
         );
+        let rule: String = world_rule_line(active_world)
+            .chars()
+            .take((area.width() - 3).max(0) as usize)
+            .collect();
+        ctx.print_color(
+            area.x1 + 2,
+            area.y1 + 3,
+            world_color(active_world),
+            RGB::named(BLACK),
+            rule,
+        );
 
         for (idx, world) in SPECTRUM.iter().enumerate() {
-            let x = 2 + (idx as i32 * 10);
+            let x = area.x1 + 2 + (idx as i32 * 10);
+            let y = area.y1 + 4;
+            if y >= area.y2 {
+                break;
+            }
             let (fg, glyph) = if *world == active_world {
                 (RGB::named(LIGHT_GREEN), '*')
             } else {
                 (RGB::named(DARK_GRAY), '·')
             };
-            ctx.set(x, 4, fg, RGB::named(BLACK), glyph as u16);
-            ctx.print_color(x + 2, 4, fg, RGB::named(BLACK), world.as_str());
+            ctx.set(x, y, fg, RGB::named(BLACK), glyph as u16);
+            ctx.print_color(x + 2, y, fg, RGB::named(BLACK), world.as_str());
+            // Each world's fragment sits just after its name: lit once
+            // collected, dim while still out there.
+            let fragment_fg = if fragments & fragment_bit(*world) != 0 {
+                world_color(*world)
+            } else {
+                RGB::named(DARK_GRAY)
+            };
+            ctx.set(x + 8, y, fragment_fg, RGB::named(BLACK), FRAGMENT_GLYPH);
         }
     }
 }
 
-pub fn draw_log(ctx: &mut BTerm, log: &[String], start_y: i32) {
-    let (width_raw, height_raw) = ctx.get_char_size();
-    let width = width_raw.saturating_sub(1);
-    let screen_h = height_raw as i32;
-    if screen_h <= 0 || width <= 0 {
+pub fn draw_log(ctx: &mut BTerm, log: &[String], area: Rect) {
+    if area.height() < 3 {
         return;
     }
-
-    let mut top = (start_y - 1).max(0);
-    if top >= screen_h {
-        top = screen_h.saturating_sub(2);
-    }
-
-    let max_height = screen_h.saturating_sub(top).max(1);
-    let mut box_height = (log.len() as i32).min(5) + 2;
-    if box_height > max_height {
-        box_height = max_height;
-    }
-    if box_height < 2 {
-        return;
-    }
-
-    ctx.draw_box(
-        0,
-        top,
-        width,
-        box_height,
-        RGB::named(DARK_GRAY),
-        RGB::named(BLACK),
-    );
+    draw_frame(ctx, area, RGB::named(DARK_GRAY));
     ctx.print_color(
-        2,
-        top + 1,
+        area.x1 + 2,
+        area.y1 + 1,
         RGB::named(WHITE),
         RGB::named(BLACK),
         "Event Log",
     );
 
-    let rows_available = box_height.saturating_sub(2) as usize;
+    let rows_available = (area.height() - 3).max(0) as usize;
+    let max_chars = (area.width() - 4).max(0) as usize;
     for (row, entry) in log.iter().take(rows_available).enumerate() {
-        let y = top + 2 + row as i32;
-        if y >= screen_h {
-            break;
-        }
-        ctx.print(2, y, entry);
+        let y = area.y1 + 2 + row as i32;
+        let clipped: String = entry.chars().take(max_chars).collect();
+        ctx.print(area.x1 + 2, y, clipped);
     }
 }
 
-pub fn draw_map(
-    ctx: &mut BTerm,
-    layer: &MapLayer,
-    map_origin: Point,
-    reserved_rows: i32,
-    visible: &HashSet<Point>,
-) {
-    let (screen_w, screen_h) = ctx.get_char_size();
-    let screen_w = screen_w as i32;
-    let screen_h = screen_h as i32;
-    let max_draw_y = screen_h - reserved_rows;
-    let max_draw_x = screen_w - 2;
+/// Stands in for the map when there is no layer to draw, so the screen
+/// says why it is empty instead of going blank.
+pub fn draw_layer_unavailable(ctx: &mut BTerm, layout: &Layout, floor: u32, world: &str) {
+    let map = layout.map;
+    let lines = [
+        format!("Floor {floor} ({world}) is unavailable."),
+        "Returning you to floor 0...".to_string(),
+    ];
+    let top = map.y1 + map.height() / 2 - 1;
+    for (row, line) in lines.iter().enumerate() {
+        let x = map.x1 + (map.width() - line.chars().count() as i32).max(0) / 2;
+        ctx.print_color(
+            x,
+            top + row as i32,
+            RGB::named(RED),
+            RGB::named(BLACK),
+            line,
+        );
+    }
+}
 
+pub fn draw_map(ctx: &mut BTerm, layer: &MapLayer, layout: &Layout, visible: &HashSet<Point>) {
     for y in 0..layer.height {
-        let screen_y = map_origin.y + y;
-        if screen_y >= max_draw_y {
-            break;
-        }
         for x in 0..layer.width {
-            let screen_x = map_origin.x + x;
-            if screen_x >= max_draw_x {
-                break;
-            }
             let point = Point::new(x, y);
+            let Some(screen) = layout.map_to_screen(point) else {
+                continue;
+            };
             if let Some(tile) = layer.tile_at(point) {
                 if visible.contains(&point) {
-                    ctx.set(screen_x, screen_y, tile.fg, tile.bg, tile.glyph);
+                    ctx.set(screen.x, screen.y, tile.fg, tile.bg, tile.glyph);
                 } else if tile.revealed {
                     ctx.set(
-                        screen_x,
-                        screen_y,
+                        screen.x,
+                        screen.y,
                         RGB::named(DARK_GRAY),
                         RGB::named(BLACK),
                         tile.glyph,
                     );
                 } else {
                     ctx.set(
-                        screen_x,
-                        screen_y,
+                        screen.x,
+                        screen.y,
                         RGB::named(BLACK),
                         RGB::named(BLACK),
                         b' ' as u16,
@@ -141,3 +559,196 @@ pub fn draw_map(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_regions_never_overlap_at_any_console_size() {
+        let overlap = |a: Rect, b: Rect| {
+            a.width() > 0
+                && a.height() > 0
+                && b.width() > 0
+                && b.height() > 0
+                && a.x1 < b.x2
+                && b.x1 < a.x2
+                && a.y1 < b.y2
+                && b.y1 < a.y2
+        };
+        for width in [1, 5, 20, 41, 80, 133] {
+            for height in [1, 4, 9, 13, 30, 50, 71] {
+                for log_lines in [0, 4, 12, 40] {
+                    let layout = Layout::compute(width, height, log_lines);
+                    let (hud, map, log) = (layout.hud, layout.map, layout.log);
+                    let size = format!("{width}x{height} with {log_lines} log lines");
+                    assert!(!overlap(hud, map), "HUD covers the map at {size}");
+                    assert!(!overlap(hud, log), "HUD covers the log at {size}");
+                    assert!(!overlap(map, log), "map covers the log at {size}");
+                    for rect in [hud, map, log] {
+                        if rect.width() <= 0 || rect.height() <= 0 {
+                            continue;
+                        }
+                        assert!(rect.x1 >= 0 && rect.x2 <= width, "clipped at {size}");
+                        assert!(rect.y1 >= 0 && rect.y2 <= height, "clipped at {size}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn overlay_sight_outranks_walkability() {
+        assert_eq!(
+            classify_overlay_cell(true, true, true),
+            OverlayCell::SharedSight
+        );
+        assert_eq!(
+            classify_overlay_cell(false, true, false),
+            OverlayCell::PlayerSight
+        );
+        assert_eq!(
+            classify_overlay_cell(false, false, true),
+            OverlayCell::MonsterSight
+        );
+        assert_eq!(
+            classify_overlay_cell(true, false, false),
+            OverlayCell::Walkable
+        );
+        assert_eq!(
+            classify_overlay_cell(false, false, false),
+            OverlayCell::Blocked
+        );
+        assert!(overlay_tint(OverlayCell::Blocked).is_none());
+    }
+
+    #[test]
+    fn each_entity_state_draws_in_its_own_style() {
+        let base = RGB::named(RED);
+        let visible = entity_draw_style(EntityVisibility::Visible, base);
+        let remembered = entity_draw_style(EntityVisibility::Remembered, base);
+        let sensed = entity_draw_style(EntityVisibility::Sensed, base);
+
+        assert_eq!(visible, (base, None));
+        assert_eq!(remembered.1, None, "ghosts keep the monster's glyph");
+        assert_ne!(remembered.0, base);
+        assert!(remembered.0.r < base.r, "ghosts are dimmed");
+        assert_eq!(sensed.1, Some(SENSED_GLYPH));
+        assert_eq!(
+            sensed.0,
+            entity_draw_style(EntityVisibility::Sensed, RGB::named(BLUE)).0,
+            "a heartbeat doesn't give away what it is"
+        );
+    }
+
+    #[test]
+    fn active_statuses_map_to_lettered_countdowns_in_a_fixed_order() {
+        assert!(status_indicators(&StatusEffects::default()).is_empty());
+
+        let effects = StatusEffects {
+            burning: 0,
+            slowed: 2,
+            cursed: 17,
+            levitating: 5,
+        };
+        let indicators = status_indicators(&effects);
+        let labels: Vec<String> = indicators.iter().map(StatusIndicator::label).collect();
+        assert_eq!(labels, ["S2", "C17", "L5"]);
+        assert_eq!(indicators[0].color, RGB::named(LIGHT_BLUE));
+        assert_eq!(status_row_width(&indicators), "S2 C17 L5".len() as i32);
+
+        let burning = StatusEffects {
+            burning: 3,
+            ..effects
+        };
+        assert_eq!(status_indicators(&burning)[0].label(), "B3");
+    }
+
+    #[test]
+    fn off_screen_threats_get_an_edge_arrow_facing_them() {
+        let mut layout = Layout::compute(40, 30, 4);
+        layout.follow(Point::new(100, 100), 200, 200);
+        let (w, h) = (layout.map.width(), layout.map.height());
+        let player = Point::new(100, 100);
+        let left = layout.camera.x - 5;
+        let right = layout.camera.x + w + 5;
+        let top = layout.camera.y - 5;
+        let bottom = layout.camera.y + h + 5;
+
+        assert_eq!(layout.threat_arrow(player, Point::new(101, 100)), None);
+        let arrow = |threat| layout.threat_arrow(player, threat).map(|(_, arrow)| arrow);
+        assert_eq!(arrow(Point::new(left, 100)), Some('←'));
+        assert_eq!(arrow(Point::new(right, 100)), Some('→'));
+        assert_eq!(arrow(Point::new(100, top)), Some('↑'));
+        assert_eq!(arrow(Point::new(100, bottom)), Some('↓'));
+
+        // Past a corner the axis the threat lies furthest along wins.
+        assert_eq!(arrow(Point::new(left - 50, top)), Some('←'));
+        assert_eq!(arrow(Point::new(right, bottom + 50)), Some('↓'));
+
+        let (edge, _) = layout.threat_arrow(player, Point::new(right, 100)).unwrap();
+        assert_eq!(edge.x, layout.map.x2 - 1, "the arrow sits on the edge cell");
+        assert_eq!(
+            layout.map_to_screen(Point::new(100, 100)).unwrap().y,
+            edge.y
+        );
+    }
+
+    #[test]
+    fn the_critical_pulse_swells_with_the_frame_and_runs_hotter_near_death() {
+        for frame in 0..CRITICAL_PULSE_FRAMES {
+            assert_eq!(critical_pulse(frame, 0.31), 0.0, "off above the threshold");
+            assert_eq!(critical_pulse(frame, 1.0), 0.0);
+            assert_eq!(critical_pulse(frame, 0.0), 0.0, "off once dead");
+            assert!(critical_pulse(frame, CRITICAL_HP_RATIO) > 0.0);
+            assert!(critical_pulse(frame, 0.05) > critical_pulse(frame, 0.25));
+            assert!(critical_pulse(frame, 0.001) <= 0.6);
+        }
+        let half = CRITICAL_PULSE_FRAMES / 2;
+        assert!(
+            critical_pulse(half, 0.2) > critical_pulse(0, 0.2),
+            "it swells"
+        );
+        assert!(
+            critical_pulse(half, 0.2) > critical_pulse(half + 2, 0.2),
+            "and fades"
+        );
+        assert_eq!(
+            critical_pulse(half, 0.2),
+            critical_pulse(half + CRITICAL_PULSE_FRAMES, 0.2)
+        );
+    }
+
+    #[test]
+    fn the_look_ahead_eases_toward_the_heading_and_stays_inside_the_map() {
+        let east = Some(Point::new(1, 0));
+        let mut lead = Point::zero();
+        let mut steps = Vec::new();
+        for _ in 0..4 {
+            lead = ease_look_ahead(lead, east, 3);
+            steps.push(lead.x);
+        }
+        assert_eq!(steps, [1, 2, 3, 3]);
+        assert_eq!(lead.y, 0);
+
+        let north_west = Some(Point::new(-1, -1));
+        assert_eq!(ease_look_ahead(lead, north_west, 3), Point::new(2, -1));
+        assert_eq!(ease_look_ahead(lead, None, 3), Point::new(2, 0));
+        assert_eq!(
+            ease_look_ahead(Point::zero(), east, 0),
+            Point::zero(),
+            "off"
+        );
+
+        let (view, map) = ((20, 10), (60, 30));
+        let player = Point::new(30, 15);
+        let centred = camera_origin(player, view, map);
+        assert_eq!(camera_origin(player + lead, view, map).x, centred.x + 3);
+        let at_edge = Point::new(58, 15);
+        assert_eq!(
+            camera_origin(at_edge + lead, view, map),
+            camera_origin(at_edge, view, map),
+            "the lead never scrolls past the map's edge"
+        );
+    }
+}
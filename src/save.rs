@@ -0,0 +1,433 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    ecs::snapshot::EcsSnapshot,
+    map::{DEFAULT_MAP_HEIGHT, DEFAULT_MAP_WIDTH, FloorId, World},
+};
+
+/// Version written into new saves. Bump it whenever the layout changes and
+/// add a step to [`migrate`] that upgrades the previous version.
+pub const SAVE_VERSION: u32 = 15;
+/// Oldest version [`migrate`] can still upgrade.
+const OLDEST_SUPPORTED_VERSION: u32 = 1;
+/// Number of manual save slots offered by the F6/F7 pickers.
+pub const SAVE_SLOTS: usize = 3;
+
+/// Where a live run keeps its autosave and slots.
+pub const SAVE_DIR: &str = "saves";
+
+/// A full run snapshot. Floor geometry is not stored: it is regenerated from
+/// `master_seed`, and only what the player changed (revealed tiles, entities)
+/// is written out.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub saved_at: String,
+    /// Run counter when the save was made; added in version 2.
+    pub run_number: u32,
+    pub master_seed: u64,
+    /// Map size the floors were generated at (`--map-width`,
+    /// `--map-height`); added in version 15.
+    pub map_width: i32,
+    pub map_height: i32,
+    pub floors_generated: u32,
+    pub active_floor: FloorId,
+    pub active_world: World,
+    pub frame: u64,
+    pub run_max_floor: u32,
+    pub seeded_floors: Vec<u32>,
+    pub revealed: Vec<RevealedLayer>,
+    /// Doors standing open and their close timers; added in version 3.
+    pub open_doors: Vec<OpenDoor>,
+    /// Traps the player has spotted; added in version 4.
+    pub detected_traps: Vec<TileMark>,
+    /// Acid clouds still hanging in the air; added in version 5.
+    pub acid_clouds: Vec<CloudMark>,
+    /// Tiles claimed by Violet curses; added in version 6.
+    pub cursed_tiles: Vec<TileMark>,
+    /// Spectrum fragments collected, one bit per world; added in version 7.
+    pub fragments: u8,
+    /// Floors whose objective is done; added in version 8.
+    pub completed_objectives: Vec<u32>,
+    /// Endless-mode waves already spawned; added in version 9.
+    pub endless_waves: u32,
+    /// Secret doors the player has found; added in version 10.
+    pub found_secret_doors: Vec<TileMark>,
+    /// Corpses still lying where monsters fell; added in version 11.
+    pub corpses: Vec<CorpseMark>,
+    /// Consumables lying on the floor; added in version 12.
+    pub floor_items: Vec<ItemMark>,
+    /// Turn each new depth was first reached; added in version 13.
+    pub depth_turns: Vec<DepthSplit>,
+    /// Turn each floor's collapse clock started, for `--collapse`; added
+    /// in version 14.
+    pub collapse_clocks: Vec<DepthSplit>,
+    pub message_log: Vec<String>,
+    pub ecs: EcsSnapshot,
+}
+
+/// Revealed tile indices for one floor/world layer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RevealedLayer {
+    pub floor: u32,
+    pub world: World,
+    pub tiles: Vec<u32>,
+}
+
+/// A door left open when the save was made.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpenDoor {
+    pub floor: u32,
+    pub x: i32,
+    pub y: i32,
+    pub turns_left: u32,
+}
+
+/// A single marked tile: a trap the player knows about, a cursed tile, or
+/// a secret door that has been found.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TileMark {
+    pub floor: u32,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// An acid cloud and the turns it has left.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CloudMark {
+    pub floor: u32,
+    pub x: i32,
+    pub y: i32,
+    pub turns_left: u32,
+}
+
+/// A corpse, the layer it lies on, and the turns before it crumbles.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CorpseMark {
+    pub floor: u32,
+    pub world: World,
+    pub x: i32,
+    pub y: i32,
+    pub name: String,
+    pub turns_left: u32,
+}
+
+/// A consumable left lying on one floor/world layer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemMark {
+    pub floor: u32,
+    pub world: World,
+    pub x: i32,
+    pub y: i32,
+    pub name: String,
+}
+
+/// The turn a run first reached `floor`, or first arrived on it for a
+/// collapse clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepthSplit {
+    pub floor: u32,
+    pub turn: u64,
+}
+
+/// What the slot picker shows for an occupied slot.
+#[derive(Clone, Debug)]
+pub struct SlotSummary {
+    pub run_number: u32,
+    pub floor: u32,
+    pub world: World,
+    pub turn: u64,
+    pub saved_at: String,
+}
+
+impl SaveGame {
+    pub fn summary(&self) -> SlotSummary {
+        SlotSummary {
+            run_number: self.run_number,
+            floor: self.active_floor.0,
+            world: self.active_world,
+            turn: self.ecs.turn,
+            saved_at: self.saved_at.clone(),
+        }
+    }
+}
+
+/// File in `dir` written on every floor transition and on quit.
+pub fn autosave_path(dir: &Path) -> PathBuf {
+    dir.join("autosave.json")
+}
+
+/// File in `dir` backing manual slot `slot` (0-based), e.g.
+/// `saves/slot1.json`.
+pub fn slot_path(dir: &Path, slot: usize) -> PathBuf {
+    dir.join(format!("slot{}.json", slot + 1))
+}
+
+pub fn write_save(path: &Path, save: &SaveGame) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| format!("cannot create {}: {err}", dir.display()))?;
+    }
+    let bytes = serde_json::to_vec(save).map_err(|err| format!("cannot encode save: {err}"))?;
+    fs::write(path, bytes).map_err(|err| format!("cannot write {}: {err}", path.display()))
+}
+
+pub fn read_save(path: &Path) -> Result<SaveGame, String> {
+    let bytes = fs::read(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    parse_save(&bytes).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// Decodes save bytes of any supported version, migrating older layouts up
+/// to [`SAVE_VERSION`] before the typed decode so field changes never turn
+/// into opaque serde errors.
+pub fn parse_save(bytes: &[u8]) -> Result<SaveGame, String> {
+    let raw: Value =
+        serde_json::from_slice(bytes).map_err(|err| format!("not a valid save: {err}"))?;
+    let version = raw
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| "save has no version number".to_string())? as u32;
+    if version > SAVE_VERSION {
+        return Err(format!(
+            "save version {version} is newer than this build understands ({SAVE_VERSION}); update the game"
+        ));
+    }
+    if version < OLDEST_SUPPORTED_VERSION {
+        return Err(format!(
+            "save version {version} is too old to migrate (oldest supported is {OLDEST_SUPPORTED_VERSION})"
+        ));
+    }
+    let current = migrate(version, raw)?;
+    serde_json::from_value(current).map_err(|err| format!("save is damaged: {err}"))
+}
+
+/// Upgrades a raw save one version at a time until it matches
+/// [`SAVE_VERSION`]. Each arm only touches what changed in that step.
+pub fn migrate(mut version: u32, mut save: Value) -> Result<Value, String> {
+    while version < SAVE_VERSION {
+        let Some(fields) = save.as_object_mut() else {
+            return Err("save is not a JSON object".to_string());
+        };
+        match version {
+            // v2 added `run_number`; older saves predate it, so show run 0.
+            1 => {
+                fields.entry("run_number").or_insert(Value::from(0u32));
+            }
+            // v3 added `open_doors`; older floors had no doors, so none are open.
+            2 => {
+                fields
+                    .entry("open_doors")
+                    .or_insert(Value::Array(Vec::new()));
+            }
+            // v4 added `detected_traps`; older runs had spotted none.
+            3 => {
+                fields
+                    .entry("detected_traps")
+                    .or_insert(Value::Array(Vec::new()));
+            }
+            // v5 added `acid_clouds`; older floors never held any.
+            4 => {
+                fields
+                    .entry("acid_clouds")
+                    .or_insert(Value::Array(Vec::new()));
+            }
+            // v6 added `cursed_tiles`; curses didn't spread before it.
+            5 => {
+                fields
+                    .entry("cursed_tiles")
+                    .or_insert(Value::Array(Vec::new()));
+            }
+            // v7 added `fragments`; older runs had gathered none.
+            6 => {
+                fields.entry("fragments").or_insert(Value::from(0u8));
+            }
+            // v8 added `completed_objectives`; floors had none before it.
+            7 => {
+                fields
+                    .entry("completed_objectives")
+                    .or_insert(Value::Array(Vec::new()));
+            }
+            // v9 added `endless_waves`; endless mode didn't exist before it.
+            8 => {
+                fields.entry("endless_waves").or_insert(Value::from(0u32));
+            }
+            // v10 added `found_secret_doors`; floors had no secret doors before it.
+            9 => {
+                fields
+                    .entry("found_secret_doors")
+                    .or_insert(Value::Array(Vec::new()));
+            }
+            // v11 added `corpses`; monsters left none before it.
+            10 => {
+                fields.entry("corpses").or_insert(Value::Array(Vec::new()));
+            }
+            // v12 added `floor_items`; floors held no loose items before it.
+            11 => {
+                fields
+                    .entry("floor_items")
+                    .or_insert(Value::Array(Vec::new()));
+            }
+            // v13 added `depth_turns`; older runs kept no splits.
+            12 => {
+                fields
+                    .entry("depth_turns")
+                    .or_insert(Value::Array(Vec::new()));
+            }
+            // v14 added `collapse_clocks`; floors never collapsed before it.
+            13 => {
+                fields
+                    .entry("collapse_clocks")
+                    .or_insert(Value::Array(Vec::new()));
+            }
+            // v15 added `map_width`/`map_height`; every older run used the default size.
+            14 => {
+                fields
+                    .entry("map_width")
+                    .or_insert(Value::from(DEFAULT_MAP_WIDTH));
+                fields
+                    .entry("map_height")
+                    .or_insert(Value::from(DEFAULT_MAP_HEIGHT));
+            }
+            other => return Err(format!("no migration from save version {other}")),
+        }
+        version += 1;
+        fields.insert("version".to_string(), Value::from(version));
+    }
+    Ok(save)
+}
+
+/// Summaries for every manual slot in `dir`; `None` marks an empty or
+/// unreadable slot.
+pub fn slot_summaries(dir: &Path) -> Vec<Option<SlotSummary>> {
+    (0..SAVE_SLOTS)
+        .map(|slot| {
+            read_save(&slot_path(dir, slot))
+                .ok()
+                .map(|save| save.summary())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn upgraded(from: u32, save: Value) -> Value {
+        migrate(from, save).expect("migration succeeds")
+    }
+
+    #[test]
+    fn migration_stamps_the_current_version() {
+        let save = upgraded(1, json!({ "version": 1 }));
+        assert_eq!(save["version"], json!(SAVE_VERSION));
+        assert_eq!(
+            upgraded(SAVE_VERSION, json!({ "kept": true })),
+            json!({ "kept": true })
+        );
+    }
+
+    #[test]
+    fn v1_gains_run_number() {
+        assert_eq!(upgraded(1, json!({}))["run_number"], json!(0));
+        assert_eq!(
+            upgraded(1, json!({ "run_number": 4 }))["run_number"],
+            json!(4)
+        );
+    }
+
+    #[test]
+    fn migration_rejects_non_objects() {
+        assert!(migrate(1, json!([1, 2, 3])).is_err());
+    }
+
+    #[test]
+    fn parse_rejects_missing_future_and_ancient_versions() {
+        assert!(parse_save(b"{}").unwrap_err().contains("no version"));
+        let future = json!({ "version": SAVE_VERSION + 1 }).to_string();
+        assert!(parse_save(future.as_bytes()).unwrap_err().contains("newer"));
+        assert!(
+            parse_save(br#"{"version":0}"#)
+                .unwrap_err()
+                .contains("too old")
+        );
+        assert!(
+            parse_save(b"not json")
+                .unwrap_err()
+                .contains("not a valid save")
+        );
+    }
+
+    #[test]
+    fn v2_gains_no_open_doors() {
+        assert_eq!(upgraded(2, json!({}))["open_doors"], json!([]));
+    }
+
+    #[test]
+    fn v3_gains_no_detected_traps() {
+        assert_eq!(upgraded(3, json!({}))["detected_traps"], json!([]));
+    }
+
+    #[test]
+    fn v4_gains_no_acid_clouds() {
+        assert_eq!(upgraded(4, json!({}))["acid_clouds"], json!([]));
+    }
+
+    #[test]
+    fn v5_gains_no_cursed_tiles() {
+        assert_eq!(upgraded(5, json!({}))["cursed_tiles"], json!([]));
+    }
+
+    #[test]
+    fn v6_gains_no_fragments() {
+        assert_eq!(upgraded(6, json!({}))["fragments"], json!(0));
+    }
+
+    #[test]
+    fn v7_gains_no_completed_objectives() {
+        assert_eq!(upgraded(7, json!({}))["completed_objectives"], json!([]));
+    }
+
+    #[test]
+    fn v8_gains_no_endless_waves() {
+        assert_eq!(upgraded(8, json!({}))["endless_waves"], json!(0));
+    }
+
+    #[test]
+    fn v9_gains_no_found_secret_doors() {
+        assert_eq!(upgraded(9, json!({}))["found_secret_doors"], json!([]));
+    }
+
+    #[test]
+    fn v10_gains_no_corpses() {
+        assert_eq!(upgraded(10, json!({}))["corpses"], json!([]));
+    }
+
+    #[test]
+    fn v11_gains_no_floor_items() {
+        assert_eq!(upgraded(11, json!({}))["floor_items"], json!([]));
+    }
+
+    #[test]
+    fn v12_gains_no_depth_turns() {
+        assert_eq!(upgraded(12, json!({}))["depth_turns"], json!([]));
+    }
+
+    #[test]
+    fn v13_gains_no_collapse_clocks() {
+        assert_eq!(upgraded(13, json!({}))["collapse_clocks"], json!([]));
+    }
+
+    #[test]
+    fn v14_gains_the_default_map_size() {
+        let save = upgraded(14, json!({}));
+        assert_eq!(save["map_width"], json!(DEFAULT_MAP_WIDTH));
+        assert_eq!(save["map_height"], json!(DEFAULT_MAP_HEIGHT));
+    }
+}
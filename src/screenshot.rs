@@ -0,0 +1,172 @@
+use std::{collections::HashSet, fs, path::PathBuf};
+
+use bracket_geometry::prelude::Point;
+use bracket_terminal::prelude::to_char;
+use serde::Serialize;
+
+use crate::map::MapLayer;
+
+const SCREENSHOT_DIR: &str = "screenshots";
+
+/// An entity as seen in a dump: where it stands and what it looks like.
+#[derive(Clone, Debug, Serialize)]
+pub struct EntityGlyph {
+    pub x: i32,
+    pub y: i32,
+    pub glyph: char,
+    pub order: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TileDump {
+    pub x: i32,
+    pub y: i32,
+    pub glyph: char,
+    pub visible: bool,
+    pub blocks_move: bool,
+    pub tag: u32,
+}
+
+/// Machine-readable companion to the ASCII dump; only revealed tiles are
+/// listed so dumps stay small and don't leak unexplored layout.
+#[derive(Debug, Serialize)]
+pub struct ScreenDump {
+    pub floor: u32,
+    pub world: String,
+    pub turn: u64,
+    pub frame: u64,
+    pub hud: Vec<String>,
+    pub log: Vec<String>,
+    pub tiles: Vec<TileDump>,
+    pub entities: Vec<EntityGlyph>,
+}
+
+/// Renders `layer` as one string per row, the way the map panel shows it:
+/// unexplored tiles are blank, and entities (highest `order` on top)
+/// replace the tile glyph only where the player can currently see.
+pub fn ascii_map(
+    layer: &MapLayer,
+    visible: &HashSet<Point>,
+    entities: &[EntityGlyph],
+) -> Vec<String> {
+    let mut rows: Vec<Vec<char>> = (0..layer.height)
+        .map(|y| {
+            (0..layer.width)
+                .map(|x| match layer.tile_at(Point::new(x, y)) {
+                    Some(tile) if tile.revealed || visible.contains(&Point::new(x, y)) => {
+                        to_char(tile.glyph as u8)
+                    }
+                    _ => ' ',
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut ordered: Vec<&EntityGlyph> = entities
+        .iter()
+        .filter(|entity| visible.contains(&Point::new(entity.x, entity.y)))
+        .collect();
+    ordered.sort_by_key(|entity| entity.order);
+    for entity in ordered {
+        if let Some(cell) = rows
+            .get_mut(entity.y as usize)
+            .and_then(|row| row.get_mut(entity.x as usize))
+        {
+            *cell = entity.glyph;
+        }
+    }
+
+    rows.into_iter()
+        .map(|row| row.into_iter().collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+pub fn tile_dump(layer: &MapLayer, visible: &HashSet<Point>) -> Vec<TileDump> {
+    let mut tiles = Vec::new();
+    for y in 0..layer.height {
+        for x in 0..layer.width {
+            let point = Point::new(x, y);
+            let Some(tile) = layer.tile_at(point) else {
+                continue;
+            };
+            let seen = visible.contains(&point);
+            if !tile.revealed && !seen {
+                continue;
+            }
+            tiles.push(TileDump {
+                x,
+                y,
+                glyph: to_char(tile.glyph as u8),
+                visible: seen,
+                blocks_move: tile.blocks_move,
+                tag: tile.tag,
+            });
+        }
+    }
+    tiles
+}
+
+/// Writes `<stem>.txt` (HUD, map, log as plain text) and `<stem>.json` into
+/// `screenshots/`, returning the text file's path.
+pub fn write_screenshot(stem: &str, map: &[String], dump: &ScreenDump) -> Result<PathBuf, String> {
+    let dir = PathBuf::from(SCREENSHOT_DIR);
+    fs::create_dir_all(&dir).map_err(|err| format!("cannot create {}: {err}", dir.display()))?;
+
+    let mut text = dump.hud.join("\n");
+    text.push_str("\n\n");
+    text.push_str(&map.join("\n"));
+    text.push_str("\n\n");
+    text.push_str(&dump.log.join("\n"));
+    text.push('\n');
+    let txt_path = dir.join(format!("{stem}.txt"));
+    fs::write(&txt_path, text)
+        .map_err(|err| format!("cannot write {}: {err}", txt_path.display()))?;
+
+    let json_path = dir.join(format!("{stem}.json"));
+    let json =
+        serde_json::to_vec_pretty(dump).map_err(|err| format!("cannot encode dump: {err}"))?;
+    fs::write(&json_path, json)
+        .map_err(|err| format!("cannot write {}: {err}", json_path.display()))?;
+    Ok(txt_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{Tile, World};
+
+    #[test]
+    fn ascii_map_shows_what_the_player_knows_of_a_tiny_map() {
+        let mut layer = MapLayer::empty(World::Red, 5, 4);
+        for point in [Point::new(1, 1), Point::new(2, 1), Point::new(1, 2)] {
+            layer.set_tile(point, Tile::floor(World::Red));
+        }
+        layer.set_tile(Point::new(3, 1), Tile::stair_down(World::Red));
+        layer.set_tile(Point::new(3, 2), Tile::floor(World::Red));
+        layer.reveal_point(Point::new(3, 1));
+        let visible: HashSet<Point> = [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (0, 1),
+            (1, 1),
+            (2, 1),
+            (0, 2),
+            (1, 2),
+        ]
+        .into_iter()
+        .map(|(x, y)| Point::new(x, y))
+        .collect();
+        let glyph = |x, y, glyph, order| EntityGlyph { x, y, glyph, order };
+        let entities = [
+            glyph(1, 1, '@', 2),
+            glyph(1, 1, '!', 0),
+            // Remembered ground, but nobody can see who stands on it.
+            glyph(3, 1, 'g', 1),
+        ];
+
+        let rows = ascii_map(&layer, &visible, &entities);
+
+        assert_eq!(rows, ["###", "#@.>", "#.", ""]);
+    }
+}
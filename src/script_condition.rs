@@ -0,0 +1,306 @@
+use std::fmt;
+
+use bracket_geometry::prelude::Point;
+
+use crate::map::{DEFAULT_MAP_HEIGHT, DEFAULT_MAP_WIDTH};
+
+/// Live state a script condition is checked against.
+#[derive(Clone, Copy, Debug)]
+pub struct ScriptProbe {
+    pub hp: i32,
+    pub max_hp: i32,
+    pub floor: u32,
+    pub turn: u64,
+    pub pos: Point,
+    pub monsters_visible: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptStat {
+    Hp,
+    MaxHp,
+    Floor,
+    Turn,
+    X,
+    Y,
+    MonstersVisible,
+}
+
+impl ScriptStat {
+    fn parse(token: &str) -> Option<Self> {
+        match token {
+            "hp" => Some(Self::Hp),
+            "max" | "max_hp" => Some(Self::MaxHp),
+            "floor" => Some(Self::Floor),
+            "turn" => Some(Self::Turn),
+            "x" => Some(Self::X),
+            "y" => Some(Self::Y),
+            "monsters" | "monsters_visible" => Some(Self::MonstersVisible),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Hp => "hp",
+            Self::MaxHp => "max_hp",
+            Self::Floor => "floor",
+            Self::Turn => "turn",
+            Self::X => "x",
+            Self::Y => "y",
+            Self::MonstersVisible => "monsters_visible",
+        }
+    }
+
+    fn read(self, probe: &ScriptProbe) -> i64 {
+        match self {
+            Self::Hp => probe.hp as i64,
+            Self::MaxHp => probe.max_hp as i64,
+            Self::Floor => probe.floor as i64,
+            Self::Turn => probe.turn as i64,
+            Self::X => probe.pos.x as i64,
+            Self::Y => probe.pos.y as i64,
+            Self::MonstersVisible => probe.monsters_visible as i64,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn parse(token: &str) -> Option<Self> {
+        match token {
+            "==" => Some(Self::Eq),
+            "!=" => Some(Self::Ne),
+            "<" => Some(Self::Lt),
+            "<=" => Some(Self::Le),
+            ">" => Some(Self::Gt),
+            ">=" => Some(Self::Ge),
+            _ => None,
+        }
+    }
+
+    fn apply(self, lhs: i64, rhs: i64) -> bool {
+        match self {
+            Self::Eq => lhs == rhs,
+            Self::Ne => lhs != rhs,
+            Self::Lt => lhs < rhs,
+            Self::Le => lhs <= rhs,
+            Self::Gt => lhs > rhs,
+            Self::Ge => lhs >= rhs,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Number(i64),
+    Stat(ScriptStat),
+}
+
+impl Operand {
+    fn read(self, probe: &ScriptProbe) -> i64 {
+        match self {
+            Self::Number(value) => value,
+            Self::Stat(stat) => stat.read(probe),
+        }
+    }
+}
+
+/// A condition from an `#assert` or `#waituntil` directive, e.g.
+/// `hp >= 10`, `hp == max`, `floor == 2`, `pos 5,7`, `no_monsters_visible`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptCondition {
+    Compare {
+        stat: ScriptStat,
+        op: CompareOp,
+        rhs: Operand,
+    },
+    Pos(Point),
+    NoMonstersVisible,
+}
+
+impl ScriptCondition {
+    pub fn parse(source: &str) -> Result<Self, ConditionError> {
+        let tokens: Vec<&str> = source.split_whitespace().collect();
+        let is = |token: &str, keyword: &str| token.eq_ignore_ascii_case(keyword);
+        match tokens.as_slice() {
+            [] => Err(ConditionError::at(source, source, "missing condition")),
+            [flag] if is(flag, "no_monsters_visible") => Ok(Self::NoMonstersVisible),
+            [keyword, coords @ ..] if is(keyword, "pos") => parse_pos(source, keyword, coords),
+            [stat, op, rhs] => {
+                let stat = ScriptStat::parse(&stat.to_lowercase()).ok_or_else(|| {
+                    ConditionError::at(source, stat, format!("unknown stat '{stat}'"))
+                })?;
+                let op = CompareOp::parse(op).ok_or_else(|| {
+                    ConditionError::at(source, op, format!("unknown comparison '{op}'"))
+                })?;
+                let rhs = match rhs.parse::<i64>() {
+                    Ok(value) => Operand::Number(value),
+                    Err(_) => {
+                        Operand::Stat(ScriptStat::parse(&rhs.to_lowercase()).ok_or_else(|| {
+                            ConditionError::at(
+                                source,
+                                rhs,
+                                format!("'{rhs}' is neither a number nor a stat"),
+                            )
+                        })?)
+                    }
+                };
+                Ok(Self::Compare { stat, op, rhs })
+            }
+            [_, _, _, extra, ..] => Err(ConditionError::at(
+                source,
+                extra,
+                format!("unexpected '{extra}' after the comparison"),
+            )),
+            [first, ..] => Err(ConditionError::at(
+                source,
+                first,
+                format!("cannot parse condition '{source}'"),
+            )),
+        }
+    }
+
+    pub fn holds(&self, probe: &ScriptProbe) -> bool {
+        match *self {
+            Self::Compare { stat, op, rhs } => op.apply(stat.read(probe), rhs.read(probe)),
+            Self::Pos(point) => probe.pos == point,
+            Self::NoMonstersVisible => probe.monsters_visible == 0,
+        }
+    }
+
+    /// The live values the condition looked at, for failure diagnostics.
+    pub fn actual(&self, probe: &ScriptProbe) -> String {
+        match *self {
+            Self::Compare { stat, rhs, .. } => match rhs {
+                Operand::Number(_) => format!("{} is {}", stat.name(), stat.read(probe)),
+                Operand::Stat(other) => format!(
+                    "{} is {}, {} is {}",
+                    stat.name(),
+                    stat.read(probe),
+                    other.name(),
+                    other.read(probe)
+                ),
+            },
+            Self::Pos(_) => format!("player is at {},{}", probe.pos.x, probe.pos.y),
+            Self::NoMonstersVisible => format!("{} monsters visible", probe.monsters_visible),
+        }
+    }
+}
+
+/// Why a condition didn't parse, and where in its source the fault lies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConditionError {
+    pub message: String,
+    /// Byte offset into the parsed source of the offending token.
+    pub offset: usize,
+}
+
+impl ConditionError {
+    /// An error pointing at `token`, which must be a slice of `source`.
+    fn at(source: &str, token: &str, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            offset: token.as_ptr() as usize - source.as_ptr() as usize,
+        }
+    }
+}
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// `pos X,Y` (spaces around the comma allowed): exactly one comma between
+/// two coordinates, and the target must lie on the map.
+fn parse_pos(
+    source: &str,
+    keyword: &str,
+    coords: &[&str],
+) -> Result<ScriptCondition, ConditionError> {
+    let commas = coords
+        .iter()
+        .map(|token| token.matches(',').count())
+        .sum::<usize>();
+    let parts: Vec<&str> = coords
+        .iter()
+        .flat_map(|token| token.split(','))
+        .filter(|part| !part.is_empty())
+        .collect();
+    let [x, y] = parts.as_slice() else {
+        let at = coords.first().copied().unwrap_or(keyword);
+        return Err(ConditionError::at(
+            source,
+            at,
+            format!("expected 'pos X,Y', got '{}'", source.trim()),
+        ));
+    };
+    if commas != 1 {
+        return Err(ConditionError::at(
+            source,
+            coords[0],
+            "'pos' takes exactly one comma between X and Y",
+        ));
+    }
+    let coordinate = |raw: &str, axis: &str, limit: i32| {
+        let value = raw
+            .parse::<i32>()
+            .map_err(|_| ConditionError::at(source, raw, format!("'{raw}' is not a coordinate")))?;
+        if !(0..limit).contains(&value) {
+            return Err(ConditionError::at(
+                source,
+                raw,
+                format!("{axis} {value} is off the map (0-{})", limit - 1),
+            ));
+        }
+        Ok(value)
+    };
+    Ok(ScriptCondition::Pos(Point::new(
+        coordinate(x, "x", DEFAULT_MAP_WIDTH)?,
+        coordinate(y, "y", DEFAULT_MAP_HEIGHT)?,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe() -> ScriptProbe {
+        ScriptProbe {
+            hp: 12,
+            max_hp: 20,
+            floor: 2,
+            turn: 40,
+            pos: Point::new(5, 7),
+            monsters_visible: 0,
+        }
+    }
+
+    #[test]
+    fn conditions_check_live_values() {
+        let holds = |source: &str| ScriptCondition::parse(source).unwrap().holds(&probe());
+        assert!(holds("hp >= 10"));
+        assert!(!holds("hp == max"));
+        assert!(holds("HP < MAX_HP"));
+        assert!(holds("floor == 2"));
+        assert!(holds("pos 5,7"));
+        assert!(!holds("pos 7,5"));
+        assert!(holds("no_monsters_visible"));
+    }
+
+    #[test]
+    fn failures_report_what_was_seen() {
+        let condition = ScriptCondition::parse("hp == max").unwrap();
+        assert_eq!(condition.actual(&probe()), "hp is 12, max_hp is 20");
+    }
+}
@@ -1,52 +1,283 @@
 use bracket_terminal::prelude::VirtualKeyCode;
+
+use crate::script_condition::{ConditionError, ScriptCondition};
 use std::{
     fs::File,
     io::{self, BufRead, BufReader},
     path::Path,
 };
 
+/// Default cap on commands per script; far beyond any hand-written playtest.
+pub const DEFAULT_MAX_SCRIPT_COMMANDS: usize = 100_000;
+
+/// Bounds applied while parsing a script.
+#[derive(Clone, Copy, Debug)]
+pub struct ScriptLimits {
+    /// Scripts with more commands than this are rejected outright.
+    pub max_commands: usize,
+    /// Reject unknown tokens instead of warning and skipping them.
+    pub strict: bool,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            max_commands: DEFAULT_MAX_SCRIPT_COMMANDS,
+            strict: false,
+        }
+    }
+}
+
+/// How fast a script feeds keys to the game (`--script-speed`).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ScriptSpeed {
+    /// One key per frame.
+    #[default]
+    PerFrame,
+    /// This many keys per second of frame time; slower than the frame rate
+    /// for watching a run, faster to catch up several keys a frame.
+    PerSecond(u32),
+    /// The whole script in a single frame, for headless checks.
+    Instant,
+}
+
+impl ScriptSpeed {
+    pub fn from_arg(raw: &str) -> Result<Self, String> {
+        if raw.trim().eq_ignore_ascii_case("instant") {
+            return Ok(Self::Instant);
+        }
+        match raw.trim().parse::<u32>() {
+            Ok(rate) if rate > 0 => Ok(Self::PerSecond(rate)),
+            _ => Err(format!(
+                "--script-speed expects keys per second (at least 1) or 'instant', got '{raw}'"
+            )),
+        }
+    }
+}
+
+/// One parsed script line: a key to feed the game, or a directive checked
+/// against live state when the script reaches it.
+#[derive(Clone, Debug)]
+pub enum ScriptStep {
+    Key(VirtualKeyCode),
+    /// `#assert <condition>`: stop the run with an error if it doesn't hold.
+    Assert {
+        condition: ScriptCondition,
+        source: String,
+        line: usize,
+    },
+    /// `#waituntil <condition> [within N]`: issue wait turns until the
+    /// condition holds, failing after `max_turns`.
+    WaitUntil {
+        condition: ScriptCondition,
+        source: String,
+        line: usize,
+        max_turns: u32,
+    },
+}
+
+/// Default turn cap for `#waituntil` when no `within N` is given.
+pub const DEFAULT_WAIT_TURNS: u32 = 200;
+
+/// A `#waituntil` in progress and how many wait turns it has spent.
+#[derive(Clone, Debug)]
+pub struct PendingWait {
+    pub condition: ScriptCondition,
+    pub source: String,
+    pub line: usize,
+    pub max_turns: u32,
+    pub waited: u32,
+}
+
 pub struct ScriptedInput {
-    script_commands: Vec<VirtualKeyCode>,
+    script_commands: Vec<ScriptStep>,
     current_command_index: usize,
+    pending_wait: Option<PendingWait>,
+    speed: ScriptSpeed,
+    /// Keys earned but not yet fed under [`ScriptSpeed::PerSecond`].
+    key_budget: f32,
 }
 
 impl ScriptedInput {
-    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    pub fn from_file<P: AsRef<Path>>(path: P, limits: ScriptLimits) -> io::Result<Self> {
         let file = File::open(path)?;
-        let reader = BufReader::new(file);
+        Self::from_reader(BufReader::new(file), limits)
+    }
+
+    /// Parses one command per line. Errors carry the 1-based line and column
+    /// of the offending token so typos are easy to find.
+    pub fn from_reader<R: BufRead>(reader: R, limits: ScriptLimits) -> io::Result<Self> {
         let mut script_commands = Vec::new();
 
-        for line in reader.lines() {
+        for (line_idx, line) in reader.lines().enumerate() {
             let line = line?;
             let trimmed_line = line.trim();
-            if trimmed_line.is_empty() || trimmed_line.starts_with('#') {
-                continue; // Skip empty lines and comments
-            }
+            let line_no = line_idx + 1;
+            let token_column = column_of(&line, trimmed_line);
+            let step = match parse_directive(trimmed_line, line_no) {
+                Some(Ok(step)) => Some(step),
+                Some(Err(err)) => {
+                    let column = column_of(&line, &trimmed_line[err.offset..]);
+                    let message = format!("line {line_no}, column {column}: {err}");
+                    if limits.strict {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                    }
+                    eprintln!("Warning: Skipping script directive at {message}");
+                    continue;
+                }
+                None if trimmed_line.is_empty() || trimmed_line.starts_with('#') => {
+                    continue; // Skip empty lines and comments
+                }
+                None => string_to_virtualkeycode(trimmed_line).map(ScriptStep::Key),
+            };
 
-            if let Some(key) = string_to_virtualkeycode(trimmed_line) {
-                script_commands.push(key);
+            if let Some(step) = step {
+                if script_commands.len() >= limits.max_commands {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "line {line_no}, column {token_column}: script exceeds the {} command limit",
+                            limits.max_commands
+                        ),
+                    ));
+                }
+                script_commands.push(step);
+            } else if limits.strict {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "line {line_no}, column {token_column}: unknown command '{trimmed_line}'"
+                    ),
+                ));
             } else {
-                eprintln!("Warning: Unknown command in script: {}", trimmed_line);
+                eprintln!(
+                    "Warning: Unknown command in script at line {line_no}, column {token_column}: {trimmed_line}"
+                );
             }
         }
 
         Ok(Self {
             script_commands,
             current_command_index: 0,
+            pending_wait: None,
+            speed: ScriptSpeed::default(),
+            key_budget: 0.0,
         })
     }
 
-    pub fn next_key(&mut self) -> Option<VirtualKeyCode> {
-        if self.current_command_index < self.script_commands.len() {
-            let key = self.script_commands[self.current_command_index];
+    pub fn with_speed(mut self, speed: ScriptSpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Earns this frame's keys. A per-second budget never banks more than
+    /// a second's worth, so a stalled frame doesn't release a burst.
+    pub fn start_frame(&mut self, frame_time_ms: f32) {
+        self.key_budget = match self.speed {
+            ScriptSpeed::PerFrame => 1.0,
+            ScriptSpeed::PerSecond(rate) => {
+                (self.key_budget + rate as f32 * frame_time_ms / 1000.0).min(rate as f32)
+            }
+            ScriptSpeed::Instant => 0.0,
+        };
+    }
+
+    /// True while this frame may still feed a key.
+    pub fn key_due(&self) -> bool {
+        self.speed == ScriptSpeed::Instant || self.key_budget >= 1.0
+    }
+
+    /// Spends one key of this frame's budget; false when it is used up.
+    pub fn take_key(&mut self) -> bool {
+        if !self.key_due() {
+            return false;
+        }
+        if self.speed != ScriptSpeed::Instant {
+            self.key_budget -= 1.0;
+        }
+        true
+    }
+
+    /// The `#waituntil` currently holding the script, if any.
+    pub fn pending_wait(&mut self) -> Option<&mut PendingWait> {
+        self.pending_wait.as_mut()
+    }
+
+    pub fn set_pending_wait(&mut self, wait: Option<PendingWait>) {
+        self.pending_wait = wait;
+    }
+
+    pub fn next_step(&mut self) -> Option<ScriptStep> {
+        let step = self
+            .script_commands
+            .get(self.current_command_index)
+            .cloned();
+        if step.is_some() {
             self.current_command_index += 1;
-            Some(key)
-        } else {
-            None
         }
+        step
     }
 }
 
+/// 1-based character column where `token`, a slice of `line`, starts.
+fn column_of(line: &str, token: &str) -> usize {
+    let byte = token.as_ptr() as usize - line.as_ptr() as usize;
+    line[..byte].chars().count() + 1
+}
+
+/// Recognises `#assert ...` and `#waituntil ...` lines. Returns `None` for
+/// anything that isn't a directive, including ordinary `#` comments. Error
+/// offsets are bytes into `line`.
+fn parse_directive(line: &str, line_no: usize) -> Option<Result<ScriptStep, ConditionError>> {
+    let (name, rest) = ["#assert", "#waituntil"]
+        .into_iter()
+        .find_map(|name| line.strip_prefix(name).map(|rest| (name, rest)))?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let source = rest.trim();
+    let source_offset = line.len() - rest.trim_start().len();
+    let step = if name == "#assert" {
+        ScriptCondition::parse(source).map(|condition| ScriptStep::Assert {
+            condition,
+            source: source.to_string(),
+            line: line_no,
+        })
+    } else {
+        parse_wait_until(source, line_no)
+    };
+    Some(step.map_err(|err| ConditionError {
+        offset: source_offset + err.offset,
+        ..err
+    }))
+}
+
+fn parse_wait_until(source: &str, line_no: usize) -> Result<ScriptStep, ConditionError> {
+    let (condition_src, max_turns) = match source.rsplit_once(" within ") {
+        Some((condition, turns)) => {
+            let turns = turns.trim();
+            let max_turns = turns.parse::<u32>().map_err(|_| ConditionError {
+                message: format!("'within {turns}' needs a turn count"),
+                offset: turns.as_ptr() as usize - source.as_ptr() as usize,
+            })?;
+            (condition.trim(), max_turns)
+        }
+        None => (source, DEFAULT_WAIT_TURNS),
+    };
+    let condition_offset = condition_src.as_ptr() as usize - source.as_ptr() as usize;
+    ScriptCondition::parse(condition_src)
+        .map(|condition| ScriptStep::WaitUntil {
+            condition,
+            source: condition_src.to_string(),
+            line: line_no,
+            max_turns,
+        })
+        .map_err(|err| ConditionError {
+            offset: condition_offset + err.offset,
+            ..err
+        })
+}
+
 fn string_to_virtualkeycode(s: &str) -> Option<VirtualKeyCode> {
     match s.to_lowercase().as_str() {
         "up" | "k" | "w" => Some(VirtualKeyCode::Up),
@@ -57,15 +288,164 @@ fn string_to_virtualkeycode(s: &str) -> Option<VirtualKeyCode> {
         "descend" | ">" => Some(VirtualKeyCode::PageUp),
         "cycle" | "tab" => Some(VirtualKeyCode::Tab),
         "cycle_rev" | "backtab" => Some(VirtualKeyCode::Back),
+        "worlds" | "v" => Some(VirtualKeyCode::V),
         "item1" | "1" => Some(VirtualKeyCode::Key1),
         "item2" | "2" => Some(VirtualKeyCode::Key2),
         "item3" | "3" => Some(VirtualKeyCode::Key3),
         "item4" | "4" => Some(VirtualKeyCode::Key4),
+        "item5" | "5" => Some(VirtualKeyCode::Key5),
+        "page" => Some(VirtualKeyCode::Q),
+        "search" | "z" => Some(VirtualKeyCode::Z),
         "reset" | "r" => Some(VirtualKeyCode::R),
         "quit" | "q" | "escape" => Some(VirtualKeyCode::Escape),
         "wait" | "." => Some(VirtualKeyCode::Period),
+        "close" | "c" => Some(VirtualKeyCode::C),
+        "craft" | "x" => Some(VirtualKeyCode::X),
+        "enchant" | "e" => Some(VirtualKeyCode::E),
+        "pickup" | "," | "g" => Some(VirtualKeyCode::Comma),
+        "autopickup" | "o" => Some(VirtualKeyCode::O),
         "turn" | "t" => Some(VirtualKeyCode::T),
         "dump" | "p" => Some(VirtualKeyCode::P),
+        "overlay" | "f3" => Some(VirtualKeyCode::F3),
+        "godmode" | "f4" => Some(VirtualKeyCode::F4),
+        "save" | "f6" => Some(VirtualKeyCode::F6),
+        "load" | "f7" => Some(VirtualKeyCode::F7),
+        "screenshot" | "f12" => Some(VirtualKeyCode::F12),
+        "yes" | "confirm" => Some(VirtualKeyCode::Y),
+        "no" => Some(VirtualKeyCode::N),
         _ => None,
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strict() -> ScriptLimits {
+        ScriptLimits {
+            strict: true,
+            ..ScriptLimits::default()
+        }
+    }
+
+    fn parse_error(script: &str) -> String {
+        match ScriptedInput::from_reader(script.as_bytes(), strict()) {
+            Ok(_) => panic!("script should be rejected: {script:?}"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn command_cap_rejects_long_scripts() {
+        let limits = ScriptLimits {
+            max_commands: 2,
+            ..ScriptLimits::default()
+        };
+        assert!(ScriptedInput::from_reader("up\ndown\n".as_bytes(), limits).is_ok());
+        let err = ScriptedInput::from_reader("up\ndown\nleft\n".as_bytes(), limits)
+            .err()
+            .expect("third command is over the cap");
+        assert!(err.to_string().contains("line 3, column 1"), "{err}");
+    }
+
+    #[test]
+    fn strict_mode_rejects_unknown_tokens_that_lenient_mode_skips() {
+        let script = "up\n  jump\ndown\n";
+        let mut lenient = ScriptedInput::from_reader(script.as_bytes(), ScriptLimits::default())
+            .expect("lenient parse skips the typo");
+        assert!(matches!(
+            lenient.next_step(),
+            Some(ScriptStep::Key(VirtualKeyCode::Up))
+        ));
+        assert!(matches!(
+            lenient.next_step(),
+            Some(ScriptStep::Key(VirtualKeyCode::Down))
+        ));
+        assert_eq!(
+            parse_error(script),
+            "line 2, column 3: unknown command 'jump'"
+        );
+    }
+
+    #[test]
+    fn directive_errors_point_at_the_offending_token() {
+        assert_eq!(
+            parse_error("#assert hp >= ten"),
+            "line 1, column 15: 'ten' is neither a number nor a stat"
+        );
+        assert_eq!(
+            parse_error("up\n  #waituntil hp == max within soon"),
+            "line 2, column 31: 'within soon' needs a turn count"
+        );
+    }
+
+    #[test]
+    fn columns_count_characters_not_bytes() {
+        assert_eq!(
+            parse_error("\u{a0}#assert ünknown == 1"),
+            "line 1, column 10: unknown stat 'ünknown'"
+        );
+        assert_eq!(
+            parse_error("#assert hp ≥ 5"),
+            "line 1, column 12: unknown comparison '≥'"
+        );
+    }
+
+    #[test]
+    fn pos_targets_must_be_balanced_and_on_the_map() {
+        assert!(ScriptedInput::from_reader("#assert pos 5, 7".as_bytes(), strict()).is_ok());
+        assert!(parse_error("#assert pos 5,7,9").contains("expected 'pos X,Y'"));
+        assert!(parse_error("#assert pos 5 7").contains("column 13"));
+        assert_eq!(
+            parse_error("#assert pos 5,,7"),
+            "line 1, column 13: 'pos' takes exactly one comma between X and Y"
+        );
+        assert_eq!(
+            parse_error("#assert pos 5,999"),
+            "line 1, column 15: y 999 is off the map (0-47)"
+        );
+        assert!(parse_error("#waituntil pos -1,3").contains("x -1 is off the map"));
+    }
+
+    #[test]
+    fn waituntil_defaults_its_turn_cap() {
+        let mut script = ScriptedInput::from_reader(
+            "#waituntil hp == max\n#waituntil no_monsters_visible within 50".as_bytes(),
+            strict(),
+        )
+        .unwrap();
+        assert!(matches!(
+            script.next_step(),
+            Some(ScriptStep::WaitUntil {
+                max_turns: DEFAULT_WAIT_TURNS,
+                line: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            script.next_step(),
+            Some(ScriptStep::WaitUntil { max_turns: 50, line: 2, ref source, .. })
+                if source == "no_monsters_visible"
+        ));
+    }
+
+    #[test]
+    fn instant_mode_feeds_the_whole_script_in_one_frame() {
+        let keys_in_one_frame = |speed| {
+            let mut script =
+                ScriptedInput::from_reader("up\ndown\nleft\nright\nwait\n".as_bytes(), strict())
+                    .unwrap()
+                    .with_speed(speed);
+            script.start_frame(16.0);
+            let mut fed = 0;
+            while script.take_key() && script.next_step().is_some() {
+                fed += 1;
+            }
+            fed
+        };
+        assert_eq!(keys_in_one_frame(ScriptSpeed::Instant), 5);
+        assert_eq!(keys_in_one_frame(ScriptSpeed::PerFrame), 1);
+        assert_eq!(keys_in_one_frame(ScriptSpeed::PerSecond(10)), 0);
+        assert_eq!(keys_in_one_frame(ScriptSpeed::PerSecond(250)), 4);
+    }
+}
@@ -0,0 +1,202 @@
+use bracket_random::prelude::RandomNumberGenerator;
+
+use crate::map::FloorId;
+
+/// Digits used by seed codes; uppercase base-36 so codes read cleanly aloud.
+const ALPHABET: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+/// `u64::MAX` needs 13 base-36 digits; codes are zero-padded to this width.
+const SEED_DIGITS: usize = 13;
+/// Digits per dash-separated group when printing a code.
+const GROUP_LEN: usize = 5;
+/// Odd multiplier that spreads consecutive floor ids across the seed's bits.
+const FLOOR_SPREAD: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Independent random streams derived from one master seed. Each
+/// subsystem draws only from its own stream, so adding or removing a roll
+/// in one never shifts the sequence another sees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngStream {
+    /// Floor layouts: rooms, corridors, doors, traps.
+    MapGen,
+    /// Which monsters a floor starts with, where, and whether they're elite.
+    Spawns,
+    /// Rolls riding on player and monster actions: curses, blink landings,
+    /// enchanting.
+    Combat,
+    /// Monster decisions: wandering and phasing.
+    Ai,
+}
+
+impl RngStream {
+    /// Mixed into the master seed to derive each stream. Map gen, spawns,
+    /// and AI keep the salts they used before streams were split, so old
+    /// seed codes and saves still build the same floors.
+    const fn salt(self) -> u64 {
+        match self {
+            Self::MapGen => 0,
+            Self::Spawns => 0xdead_beef,
+            Self::Combat => 0xc0ba_7000_5eed,
+            Self::Ai => 0x51ec_5ead,
+        }
+    }
+
+    /// This stream's seed under `master`.
+    pub const fn seed(self, master: u64) -> u64 {
+        master ^ self.salt()
+    }
+
+    /// This stream's seed for one floor under `master`: the same master seed
+    /// and floor always give the same rolls, and different runs differ.
+    pub const fn floor_seed(self, master: u64, floor: FloorId) -> u64 {
+        self.seed(master) ^ (floor.0 as u64 + 1).wrapping_mul(FLOOR_SPREAD)
+    }
+}
+
+/// Picks a fresh master seed for a run that wasn't given a seed code.
+pub fn random_master_seed() -> u64 {
+    RandomNumberGenerator::new().next_u64()
+}
+
+/// Encodes a master seed as a shareable code like `00017-RF9KM-92XY`: 13
+/// base-36 digits plus a trailing check digit, grouped with dashes.
+pub fn encode_seed_code(seed: u64) -> String {
+    let mut digits = [b'0'; SEED_DIGITS];
+    let mut rest = seed;
+    for slot in digits.iter_mut().rev() {
+        *slot = ALPHABET[(rest % 36) as usize];
+        rest /= 36;
+    }
+    let mut raw: Vec<u8> = digits.to_vec();
+    raw.push(ALPHABET[check_digit(&digits)]);
+    raw.chunks(GROUP_LEN)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Parses a code produced by [`encode_seed_code`]. Dashes, spaces, and case
+/// are ignored; wrong lengths, stray characters, and typos caught by the
+/// check digit are rejected with a message suitable for the command line.
+pub fn decode_seed_code(code: &str) -> Result<u64, String> {
+    let cleaned: Vec<u8> = code
+        .bytes()
+        .filter(|b| *b != b'-' && !b.is_ascii_whitespace())
+        .map(|b| b.to_ascii_uppercase())
+        .collect();
+    if cleaned.len() != SEED_DIGITS + 1 {
+        return Err(format!(
+            "seed code '{code}' should have {} characters (ignoring dashes), found {}",
+            SEED_DIGITS + 1,
+            cleaned.len()
+        ));
+    }
+    let mut values = Vec::with_capacity(cleaned.len());
+    for &byte in &cleaned {
+        match ALPHABET.iter().position(|&c| c == byte) {
+            Some(value) => values.push(value),
+            None => {
+                return Err(format!(
+                    "seed code '{code}' contains '{}'; only 0-9 and A-Z are allowed",
+                    byte as char
+                ));
+            }
+        }
+    }
+    let (body, check) = values.split_at(SEED_DIGITS);
+    if check_digit_values(body) != check[0] {
+        return Err(format!(
+            "seed code '{code}' failed its check digit; it may be mistyped"
+        ));
+    }
+    let mut seed: u64 = 0;
+    for &value in body {
+        seed = seed
+            .checked_mul(36)
+            .and_then(|s| s.checked_add(value as u64))
+            .ok_or_else(|| format!("seed code '{code}' is out of range"))?;
+    }
+    Ok(seed)
+}
+
+fn check_digit(digits: &[u8]) -> usize {
+    let values: Vec<usize> = digits
+        .iter()
+        .filter_map(|d| ALPHABET.iter().position(|c| c == d))
+        .collect();
+    check_digit_values(&values)
+}
+
+/// Position-weighted sum so swapped neighbours change the check digit too.
+fn check_digit_values(values: &[usize]) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| (idx + 1) * value)
+        .sum::<usize>()
+        % 36
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_codes_round_trip() {
+        for seed in [0, 1, 36, 0xdead_beef, u64::MAX - 1, u64::MAX] {
+            let code = encode_seed_code(seed);
+            assert_eq!(decode_seed_code(&code), Ok(seed), "code {code}");
+        }
+    }
+
+    #[test]
+    fn decoding_ignores_dashes_spaces_and_case() {
+        let code = encode_seed_code(123_456_789);
+        let sloppy = code.replace('-', " ").to_lowercase();
+        assert_eq!(decode_seed_code(&sloppy), Ok(123_456_789));
+    }
+
+    #[test]
+    fn decoding_rejects_bad_codes() {
+        let code = encode_seed_code(42);
+        assert!(decode_seed_code(&code[1..]).is_err());
+        assert!(decode_seed_code(&code.replacen('0', "!", 1)).is_err());
+
+        let mut typo: Vec<u8> = code.into_bytes();
+        let last = typo.len() - 1;
+        typo[last] = if typo[last] == b'Z' { b'Y' } else { b'Z' };
+        let typo = String::from_utf8(typo).unwrap();
+        assert!(decode_seed_code(&typo).unwrap_err().contains("check digit"));
+    }
+
+    #[test]
+    fn streams_are_distinct_and_map_gen_keeps_the_master_seed() {
+        let master = 0x1234_5678_9abc_def0;
+        let streams = [
+            RngStream::MapGen,
+            RngStream::Spawns,
+            RngStream::Combat,
+            RngStream::Ai,
+        ];
+        let seeds: Vec<u64> = streams.iter().map(|stream| stream.seed(master)).collect();
+        for (idx, seed) in seeds.iter().enumerate() {
+            assert!(
+                !seeds[idx + 1..].contains(seed),
+                "{:?} collides",
+                streams[idx]
+            );
+        }
+        assert_eq!(RngStream::MapGen.seed(master), master);
+    }
+
+    #[test]
+    fn floor_seeds_are_stable_per_run_and_differ_across_floors_and_runs() {
+        let seed = |master, floor| RngStream::Spawns.floor_seed(master, FloorId(floor));
+        assert_eq!(seed(42, 3), seed(42, 3));
+        assert_ne!(seed(42, 3), seed(42, 4));
+        assert_ne!(seed(42, 3), seed(43, 3));
+        assert_ne!(
+            RngStream::Spawns.floor_seed(42, FloorId(3)),
+            RngStream::MapGen.floor_seed(42, FloorId(3))
+        );
+    }
+}